@@ -9,7 +9,8 @@ pub fn compile(schema_json: &str, dialect: &str, input: String) -> Result<String
     };
     let options = Options {
         dialect,
-        identifier_resolution: IdentifierResolution::Flexible,
+        identifier_resolver: Box::new(IdentifierResolution::Flexible),
+        ..Default::default()
     };
     let compiler = Compiler::new(schema_json, options)?;
     compiler.compile(input.to_owned())
@@ -23,6 +23,15 @@ struct CompileArgs {
     /// Path to the schema JSON file
     #[arg(short, long)]
     schema: String,
+    /// The maximum number of rows to return
+    #[arg(short, long)]
+    limit: Option<u64>,
+    /// The number of rows to skip before returning any rows
+    #[arg(short, long)]
+    offset: Option<u64>,
+    /// Precede each CTE and join with a comment explaining where it came from
+    #[arg(short, long)]
+    annotate: bool,
     /// The querydown query to execute. If empty, stdin will be used.
     query: Option<String>,
 }
@@ -38,7 +47,11 @@ fn compile(args: CompileArgs) {
     let schema_json = std::fs::read_to_string(args.schema).unwrap();
     let options = Options {
         dialect: Box::new(Postgres()),
-        identifier_resolution: IdentifierResolution::Flexible,
+        identifier_resolver: Box::new(IdentifierResolution::Flexible),
+        limit: args.limit,
+        offset: args.offset,
+        annotate: args.annotate,
+        ..Default::default()
     };
     let compiler = Compiler::new(&schema_json, options).unwrap();
     let sql_code = compiler.compile(querydown_code).unwrap();
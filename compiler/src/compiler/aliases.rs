@@ -0,0 +1,68 @@
+use std::hash::{Hash, Hasher};
+
+use super::constants::{MAX_ALIAS_LENGTH, MAX_NUMERIC_ALIAS_SUFFIX};
+
+/// A 64-bit FNV-1a hasher. Unlike `DefaultHasher`, its output is guaranteed not to change between
+/// Rust versions, so the aliases derived from it are stable.
+struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+/// Truncate the alias so that it fits within `MAX_ALIAS_LENGTH` bytes along with the suffix
+fn with_suffix(ideal_alias: &str, suffix: Option<&str>) -> String {
+    let max_length = match suffix {
+        Some(suffix) => MAX_ALIAS_LENGTH - suffix.len() - 1,
+        None => MAX_ALIAS_LENGTH,
+    };
+    let base: String = ideal_alias
+        .char_indices()
+        .take_while(|(i, c)| i + c.len_utf8() <= max_length)
+        .map(|(_, c)| c)
+        .collect();
+    match suffix {
+        Some(suffix) => format!("{base}_{suffix}"),
+        None => base,
+    }
+}
+
+/// Return the first alias accepted by `try_alias`, beginning with `ideal_alias` and then adding
+/// numeric suffixes like `_1`. After a few numeric suffixes, we switch to suffixes hashed from
+/// `seed` instead so that many collisions on the same ideal alias still resolve quickly. Callers
+/// should vary the seed between calls, e.g. with the number of aliases already taken, so that the
+/// first hashed suffix is rarely taken too. Aliases never exceed `MAX_ALIAS_LENGTH`.
+pub fn find_alias(
+    ideal_alias: &str,
+    seed: impl Hash,
+    mut try_alias: impl FnMut(&str) -> bool,
+) -> String {
+    let numeric_suffixes =
+        (0..=MAX_NUMERIC_ALIAS_SUFFIX).map(|index| (index > 0).then(|| index.to_string()));
+    let hashed_suffixes = (0_u64..).map(|attempt| {
+        let mut hasher = FnvHasher::default();
+        seed.hash(&mut hasher);
+        attempt.hash(&mut hasher);
+        Some(format!("{:08x}", hasher.finish() as u32))
+    });
+    numeric_suffixes
+        .chain(hashed_suffixes)
+        .map(|suffix| with_suffix(ideal_alias, suffix.as_deref()))
+        .find(|alias| try_alias(alias))
+        .expect("there are endless suffixes to try")
+}
@@ -3,6 +3,8 @@ use querydown_parser::ast::*;
 use crate::{
     compiler::expr::convert_expr,
     errors::msg,
+    options::BooleanRendering,
+    schema::DataType,
     sql::{
         expr::build::*,
         tree::{CtePurpose, SqlExpr},
@@ -11,13 +13,17 @@ use crate::{
 };
 
 use super::{
+    constants::{VAR_FALSE, VAR_NULL, VAR_TRUE},
     paths::{clarify_path, ClarifiedPathTail},
     scope::Scope,
+    type_inference::infer_type,
 };
 
 pub fn convert_comparison(c: Comparison, scope: &mut Scope) -> Result<SqlExpr, String> {
     use ComparisonSide::{Expansion as CmpExpansion, Expr as CmpExpr, Range as CmpRange};
 
+    let boolean_rendering = scope.options.boolean_rendering;
+    let expansion = |exprs, conjunction| expansion_set(exprs, conjunction, boolean_rendering);
     let mut simple = |l: &Expr, r: &Expr| convert_simple_comparison(l, c.operator, r, scope);
 
     match (c.left, c.right) {
@@ -30,13 +36,13 @@ pub fn convert_comparison(c: Comparison, scope: &mut Scope) -> Result<SqlExpr, S
             .iter()
             .map(|right| simple(left, right))
             .collect::<Result<Vec<_>, _>>()
-            .map(|exprs| cmp::condition_set(exprs, &conditions.conjunction)),
+            .map(|exprs| expansion(exprs, &conditions.conjunction)),
         (CmpExpansion(conditions), CmpExpr(ref right)) => conditions
             .entries
             .iter()
             .map(|left| simple(left, right))
             .collect::<Result<Vec<_>, _>>()
-            .map(|exprs| cmp::condition_set(exprs, &conditions.conjunction)),
+            .map(|exprs| expansion(exprs, &conditions.conjunction)),
 
         // Dual expansion
         (CmpExpansion(left_conditions), CmpExpansion(right_conditions)) => {
@@ -46,15 +52,9 @@ pub fn convert_comparison(c: Comparison, scope: &mut Scope) -> Result<SqlExpr, S
                 for right in right_conditions.entries.iter() {
                     inner_entries.push(simple(left, right)?);
                 }
-                outer_entries.push(cmp::condition_set(
-                    inner_entries,
-                    &right_conditions.conjunction,
-                ));
+                outer_entries.push(expansion(inner_entries, &right_conditions.conjunction));
             }
-            Ok(cmp::condition_set(
-                outer_entries,
-                &left_conditions.conjunction,
-            ))
+            Ok(expansion(outer_entries, &left_conditions.conjunction))
         }
 
         // Range vs Expr
@@ -75,7 +75,7 @@ pub fn convert_comparison(c: Comparison, scope: &mut Scope) -> Result<SqlExpr, S
                 .iter()
                 .map(|expr| convert_range_comparison(expr, &r, scope))
                 .collect::<Result<Vec<_>, _>>()
-                .map(|exprs| cmp::condition_set(exprs, &conditions.conjunction))
+                .map(|exprs| expansion(exprs, &conditions.conjunction))
         }
 
         // Two ranges
@@ -83,6 +83,25 @@ pub fn convert_comparison(c: Comparison, scope: &mut Scope) -> Result<SqlExpr, S
     }
 }
 
+/// Join the comparisons produced by expanding a condition set. An empty expansion is like an empty
+/// `IN` list: no value equals any of no values, but every value differs from all of them. So an
+/// empty OR set is always false and an empty AND set is always true, instead of vanishing.
+fn expansion_set(
+    exprs: Vec<SqlExpr>,
+    conjunction: &Conjunction,
+    boolean_rendering: BooleanRendering,
+) -> SqlExpr {
+    if !exprs.is_empty() {
+        return cmp::condition_set(exprs, conjunction);
+    }
+    match (conjunction, boolean_rendering) {
+        (Conjunction::And, BooleanRendering::Native) => value::true_(),
+        (Conjunction::Or, BooleanRendering::Native) => value::false_(),
+        (Conjunction::And, BooleanRendering::Integer) => cmp::eq(value::one(), value::one()),
+        (Conjunction::Or, BooleanRendering::Integer) => cmp::eq(value::one(), value::zero()),
+    }
+}
+
 fn convert_simple_comparison(
     left: &Expr,
     operator: Operator,
@@ -104,6 +123,10 @@ fn convert_simple_comparison(
         return convert_expression_vs_zero(&left, ComparisonVsZero::Gt, scope);
     }
 
+    if matches!(operator, Is | IsNot) {
+        return convert_truth_test(left, operator == IsNot, right, scope);
+    }
+
     if right.is_null() && operator == Eq {
         return convert_expr(left.to_owned(), scope).map(cmp::is_null);
     }
@@ -117,9 +140,22 @@ fn convert_simple_comparison(
         return convert_expr(right.to_owned(), scope).map(cmp::is_not_null);
     }
 
+    if matches!(operator, IEq | NIEq)
+        && !matches!(infer_type(right, scope), DataType::Text | DataType::Unknown)
+    {
+        return Err(msg::non_text_case_insensitive_operand());
+    }
+
     let left_converted = convert_expr(left.to_owned(), scope)?;
     let right_converted = convert_expr(right.to_owned(), scope)?;
 
+    let like_escape = match scope.options.like_escape_char {
+        Some(_) if matches!(operator, Like | NLike) => {
+            let escape_char = scope.options.checked_like_escape_char()?.to_string();
+            Some(scope.options.dialect.quote_string(&escape_char))
+        }
+        _ => None,
+    };
     let match_regex = |a: SqlExpr, b: SqlExpr, is_positive: bool, scope: &mut Scope| {
         let flags = RegExFlags {
             is_case_sensitive: false,
@@ -133,14 +169,57 @@ fn convert_simple_comparison(
         Gte => Ok(cmp::gte(left_converted, right_converted)),
         Lt => Ok(cmp::lt(left_converted, right_converted)),
         Lte => Ok(cmp::lte(left_converted, right_converted)),
-        Like => Ok(cmp::like(left_converted, right_converted)),
+        Like => Ok(match like_escape {
+            Some(escape) => cmp::like_escaped(left_converted, right_converted, &escape),
+            None => cmp::like(left_converted, right_converted),
+        }),
         Neq => Ok(cmp::neq(left_converted, right_converted)),
-        NLike => Ok(cmp::nlike(left_converted, right_converted)),
+        NLike => Ok(match like_escape {
+            Some(escape) => cmp::nlike_escaped(left_converted, right_converted, &escape),
+            None => cmp::nlike(left_converted, right_converted),
+        }),
         Match => Ok(match_regex(left_converted, right_converted, true, scope)),
         NMatch => Ok(match_regex(left_converted, right_converted, false, scope)),
+        IEq => Ok(cmp::eq(
+            strings::lower(left_converted),
+            strings::lower(right_converted),
+        )),
+        NIEq => Ok(cmp::neq(
+            strings::lower(left_converted),
+            strings::lower(right_converted),
+        )),
+        Is | IsNot => unreachable!(),
     }
 }
 
+/// `IS TRUE`, `IS NOT FALSE`, etc. One side must be `@true`, `@false`, or `@null`. Testing for
+/// `@null` is the same as `IS UNKNOWN`, which we render as `IS NULL` because more databases
+/// support it.
+fn convert_truth_test(
+    left: &Expr,
+    is_negated: bool,
+    right: &Expr,
+    scope: &mut Scope,
+) -> Result<SqlExpr, String> {
+    let truth_value = |expr: &Expr| match expr {
+        Expr::Variable(name) if name == VAR_TRUE => Some(Some(true)),
+        Expr::Variable(name) if name == VAR_FALSE => Some(Some(false)),
+        Expr::Variable(name) if name == VAR_NULL => Some(None),
+        _ => None,
+    };
+    let (expr, value) = match (truth_value(right), truth_value(left)) {
+        (Some(value), _) => (left, value),
+        (None, Some(value)) => (right, value),
+        (None, None) => return Err(msg::truth_test_without_truth_value()),
+    };
+    let expr = convert_expr(expr.to_owned(), scope)?;
+    Ok(match (value, is_negated) {
+        (Some(value), _) => scope.options.dialect.truth_test(expr, value, is_negated),
+        (None, false) => cmp::is_null(expr),
+        (None, true) => cmp::is_not_null(expr),
+    })
+}
+
 fn convert_range_comparison(
     expr: &Expr,
     range: &Range,
@@ -180,11 +259,37 @@ impl From<ComparisonVsZero> for CtePurpose {
     fn from(cmp: ComparisonVsZero) -> Self {
         match cmp {
             ComparisonVsZero::Eq => CtePurpose::Exclusion,
-            ComparisonVsZero::Gt => CtePurpose::Inclusion,
+            ComparisonVsZero::Gt => CtePurpose::ConditionalInclusion,
         }
     }
 }
 
+/// Convert a "has some" condition which every row of the query must satisfy. Instead of testing
+/// for a related record within the WHERE clause, we inner join the CTE, which leaves no condition
+/// behind.
+pub fn convert_required_presence(
+    path_parts: Vec<PathPart>,
+    scope: &mut Scope,
+) -> Result<SqlExpr, String> {
+    let expr = Expr::path(path_parts.clone());
+    let fallback =
+        |scope: &mut Scope| convert_expression_vs_zero(&expr, ComparisonVsZero::Gt, scope);
+    let Ok(clarified_path) = clarify_path(path_parts, scope) else {
+        return fallback(scope);
+    };
+    let Some(ClarifiedPathTail::ChainToMany((chain, None))) = clarified_path.tail else {
+        return fallback(scope);
+    };
+    if scope.uses_correlated_subqueries() {
+        return fallback(scope);
+    }
+    let purpose = CtePurpose::Inclusion;
+    match scope.join_chain_to_many(&clarified_path.head, chain, None, purpose) {
+        Ok(_) => Ok(SqlExpr::empty()),
+        Err(_) => fallback(scope),
+    }
+}
+
 fn convert_expression_vs_zero(
     expr: &Expr,
     cmp: ComparisonVsZero,
@@ -197,7 +302,7 @@ fn convert_expression_vs_zero(
         };
         Ok(op(convert_expr(expr.to_owned(), scope)?, value::zero()))
     };
-    let Expr::Path(path_parts) = &expr else { return fallback(scope) };
+    let Expr::Path(path_parts, _) = &expr else { return fallback(scope) };
     let Ok(clarified_path) = clarify_path(path_parts.to_owned(), scope) else {
         return fallback(scope);
     };
@@ -206,6 +311,10 @@ fn convert_expression_vs_zero(
     };
     let join_result = scope.join_chain_to_many(&clarified_path.head, chain, None, cmp.into());
     let Ok(pk) = join_result else { return fallback(scope) };
+    if scope.uses_correlated_subqueries() {
+        // Already an `EXISTS` or `NOT EXISTS` condition
+        return Ok(pk);
+    }
     match cmp {
         ComparisonVsZero::Eq => Ok(cmp::is_null(pk)),
         ComparisonVsZero::Gt => Ok(cmp::is_not_null(pk)),
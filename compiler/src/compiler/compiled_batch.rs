@@ -0,0 +1,24 @@
+use super::compiled_query::CompiledQuery;
+
+/// The output of compiling several queries together. CTEs which more than one of the queries need
+/// are defined only once, as temporary views, instead of being repeated within each query.
+#[derive(Debug)]
+pub struct CompiledBatch {
+    /// The statements defining the shared CTEs. These must run before any of the queries, within
+    /// the same database session. Empty when the queries don't share any CTEs.
+    pub prelude: String,
+    /// The compiled queries, in the same order as their inputs. Each query's `ctes` lists only the
+    /// CTEs that remain within its own WITH clause.
+    pub queries: Vec<CompiledQuery>,
+}
+
+impl CompiledBatch {
+    /// The prelude followed by all of the queries, as one script
+    pub fn sql(&self) -> String {
+        std::iter::once(self.prelude.as_str())
+            .chain(self.queries.iter().map(|query| query.sql.as_str()))
+            .filter(|sql| !sql.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
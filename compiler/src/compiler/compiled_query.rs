@@ -0,0 +1,174 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::{
+    schema::DataType,
+    sql::tree::{CtePurpose, JoinType, Select},
+};
+
+/// The output of the compiler, along with metadata about the query
+#[derive(Debug)]
+pub struct CompiledQuery {
+    /// Statements to run before `sql` within the same transaction, e.g. to set the statement
+    /// timeout. Empty when there are none.
+    pub prelude: String,
+    pub sql: String,
+    /// Metadata about each result column, in order
+    pub columns: Vec<ColumnMetadata>,
+    /// Metadata about each CTE, in the order they appear within the SQL
+    pub ctes: Vec<CteMetadata>,
+    /// The tables which the main query reads and how they're joined
+    pub join_plan: JoinPlan,
+    /// Present only when `Options::collect_stats` is set
+    pub stats: Option<CompileStats>,
+    pub(crate) aliases: HashSet<String>,
+    pub(crate) parameters: Vec<ParamInfo>,
+}
+
+impl CompiledQuery {
+    /// The number of CTEs which serve each purpose. Purposes without any CTEs are omitted.
+    pub fn cte_count_by_purpose(&self) -> HashMap<CtePurpose, usize> {
+        let mut counts = HashMap::new();
+        for cte in &self.ctes {
+            *counts.entry(cte.purpose).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Every name by which the query refers to a table or CTE: the base table, each joined table,
+    /// and each CTE. SQL appended to the query should avoid these to prevent collisions.
+    pub fn aliases(&self) -> &HashSet<String> {
+        &self.aliases
+    }
+
+    /// The parameters to bind when running the query, one for each distinct placeholder, in order
+    /// of their numbering
+    pub fn parameters(&self) -> &[ParamInfo] {
+        &self.parameters
+    }
+}
+
+/// What the compiler was able to determine about one parameter of the query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamInfo {
+    /// The position of the parameter, starting from 1, as in `$1` for Postgres
+    pub index: usize,
+    /// The name of the placeholder, without its `:`
+    pub name: String,
+    pub data_type: DataType,
+    /// The name of the database type given by the placeholder's annotation, or else the one which
+    /// matches its inferred type. `None` when neither is known.
+    pub type_name: Option<String>,
+}
+
+/// What the compiler was able to determine about one result column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMetadata {
+    pub name: String,
+    pub data_type: DataType,
+    /// False only when we can be sure that the column will never contain NULL
+    pub nullable: bool,
+}
+
+/// What the compiler produced for one CTE
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CteMetadata {
+    pub alias: String,
+    pub purpose: CtePurpose,
+}
+
+/// The FROM clause of a query as structured data, e.g. to draw a diagram of the joins. This
+/// describes the same tables and conditions as the SQL, without needing to parse it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JoinPlan {
+    /// `None` when the query has no FROM clause
+    pub base_table: Option<String>,
+    /// The database schema of the base table, if it's qualified with one
+    pub base_table_schema: Option<String>,
+    /// `None` when the base table is referenced by its name
+    pub base_table_alias: Option<String>,
+    /// Each table joined to the base table, in the order they appear within the SQL
+    pub joins: Vec<JoinPlanNode>,
+    /// The plan of each CTE, in the order they appear within the SQL
+    pub ctes: Vec<CtePlan>,
+}
+
+/// One table joined within a `JoinPlan`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JoinPlanNode {
+    /// The name of the table or CTE
+    pub table: String,
+    pub table_schema: Option<String>,
+    pub alias: String,
+    pub join_type: JoinType,
+    /// The SQL of the ON condition. Empty for cross joins.
+    pub condition: String,
+    /// The plan of the subquery joined via LATERAL in place of the table, if any
+    pub lateral_subquery: Option<Box<JoinPlan>>,
+    /// The plan of the grouped subquery joined in place of a CTE, if any
+    pub subquery: Option<Box<JoinPlan>>,
+}
+
+/// The plan of the query within one CTE
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CtePlan {
+    pub alias: String,
+    pub purpose: CtePurpose,
+    pub plan: JoinPlan,
+}
+
+impl From<&Select> for JoinPlan {
+    fn from(select: &Select) -> Self {
+        let joins = select
+            .joins
+            .iter()
+            .map(|join| JoinPlanNode {
+                table: join.table.clone(),
+                table_schema: join.table_schema.clone(),
+                alias: join.alias.clone(),
+                join_type: join.join_type,
+                condition: join.conditions.to_string(),
+                lateral_subquery: join.lateral_subquery.as_deref().map(|s| Box::new(s.into())),
+                subquery: join.subquery.as_deref().map(|s| Box::new(s.into())),
+            })
+            .collect();
+        let ctes = select
+            .ctes
+            .iter()
+            .map(|cte| CtePlan {
+                alias: cte.alias.clone(),
+                purpose: cte.purpose,
+                plan: (&cte.select).into(),
+            })
+            .collect();
+        Self {
+            base_table: select.base_table.clone(),
+            base_table_schema: select.base_table_schema.clone(),
+            base_table_alias: select.base_table_alias.clone(),
+            joins,
+            ctes,
+        }
+    }
+}
+
+/// Measurements of the work the compiler did, for tuning the compiler itself
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompileStats {
+    /// Time spent parsing the Querydown code. Zero when compiling a query which was already
+    /// parsed.
+    pub parse_time: Duration,
+    /// Time spent resolving paths against the schema and building the SELECT, including any
+    /// rebuild needed to put the CTE aliases in canonical order
+    pub resolution_time: Duration,
+    /// Time spent rendering the SQL
+    pub generation_time: Duration,
+    /// The number of joins in the main query, including joins to CTEs
+    pub join_count: usize,
+    pub cte_count: usize,
+    /// The number of aliases allocated for tables and CTEs within the main query
+    pub alias_count: usize,
+}
@@ -1,13 +1,41 @@
-use querydown_parser::parse;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use itertools::Itertools;
+use querydown_parser::{
+    ast::{ConditionSet, Expr, PathPart, Placeholder, Query, SortDirection, Transformation},
+    parse_conditions_with_options, parse_expr, parse_expr_with_options, parse_with_options,
+};
 
 use crate::{
-    schema::{primitive_schema::PrimitiveSchema, Schema},
-    sql::tree::Select,
-    Options,
+    errors::msg,
+    schema::{
+        chain::{Chain, ChainIntersecting},
+        links::{FilteredLink, Link, LinkToOne, MultiLink},
+        primitive_schema::PrimitiveSchema,
+        Schema, Table,
+    },
+    sql::{
+        expr::build::{agg, cmp, value},
+        tree::{Column, Delete, Join, JoinType, Select, SortEntry, SqlExpr, Update},
+    },
+    BooleanRendering, Options, PointInTime, PreparedStatement, SafeMode, View,
 };
 
 use super::{
-    expr::convert_condition_set, rendering::Render, result_columns::convert_result_columns,
+    compiled_batch::CompiledBatch,
+    compiled_query::{CompileStats, CompiledQuery, CteMetadata, ParamInfo},
+    condition_fragment::ConditionFragment,
+    constant_folding::{fold_condition_set, Folded},
+    constants::{COUNTED_GROUP_COLUMN_ALIAS, COUNTED_SUBQUERY_ALIAS, CTE_ALIAS_PREFIX},
+    cte_inlining::inline_small_ctes,
+    cte_ordering::{plan_cte_aliases, sort_ctes},
+    expr::{convert_condition_set, convert_expr, convert_top_level_condition_set},
+    parameters::{find_expr_parameters, find_parameters, Parameter},
+    rendering::Render,
+    result_columns::{convert_result_columns, ResultColumnTypes},
     scope::Scope,
 };
 
@@ -24,10 +52,478 @@ impl Compiler {
         Ok(Self { options, schema })
     }
 
+    /// Like `new`, but with tables and links described across several schema JSON documents. A
+    /// table may appear in more than one of them only if each defines it with the same columns.
+    pub fn from_schemas(schema_jsons: &[&str], options: Options) -> Result<Self, String> {
+        let mut schema: Option<Schema> = None;
+        for schema_json in schema_jsons {
+            let primitive_schema = serde_json::from_str::<PrimitiveSchema>(schema_json)
+                .map_err(|_| "Schema input is not valid JSON.")?;
+            let next = Schema::try_from(primitive_schema)?;
+            schema = Some(match schema {
+                Some(schema) => schema.merge(next)?,
+                None => next,
+            });
+        }
+        let schema = schema.ok_or("At least one schema is required.")?;
+        Ok(Self { options, schema })
+    }
+
     pub fn compile(&self, input: String) -> Result<String, String> {
-        let query = parse(&input)?;
-        let mut scope = Scope::build(&self.options, &self.schema, &query.base_table)?;
-        let mut select = Select::from(scope.get_base_table().name.clone());
+        self.compile_query(input)
+            .map(|compiled_query| compiled_query.sql)
+    }
+
+    /// Compile the query, also returning metadata about the result columns
+    pub fn compile_query(&self, input: String) -> Result<CompiledQuery, String> {
+        let start = self.start_timer();
+        let query = parse_with_options(&input, self.options.parse_options())?;
+        let parse_time = start.map(|s| s.elapsed()).unwrap_or_default();
+        let mut compiled_query = self.compile_ast(query)?;
+        if let Some(stats) = &mut compiled_query.stats {
+            stats.parse_time = parse_time;
+        }
+        Ok(compiled_query)
+    }
+
+    /// Check the query against the schema without rendering any SQL, e.g. to report mistakes when
+    /// a query is saved. When the query is invalid, each condition and result column is checked in
+    /// turn alongside the ones before it which are valid, so that one mistake doesn't hide the
+    /// others. The errors are returned in order of appearance.
+    pub fn validate(&self, input: &str) -> Result<(), Vec<String>> {
+        let query = parse_with_options(input, self.options.parse_options()).map_err(|e| vec![e])?;
+        let check = |query: &Query| {
+            self.resolve_select(query.clone(), &HashMap::new(), true)
+                .err()
+        };
+        let Some(query_error) = check(&query) else {
+            return Ok(());
+        };
+        let mut errors = Vec::<String>::new();
+        let mut checked_query = Query {
+            transformations: vec![],
+            ..query.clone()
+        };
+        for transformation in query.transformations {
+            checked_query.transformations.push(Transformation {
+                conditions: ConditionSet {
+                    entries: vec![],
+                    ..transformation.conditions
+                },
+                result_columns: vec![],
+            });
+            if let Some(e) = check(&checked_query) {
+                // Nothing within the transformation can be valid without the rest of the query
+                errors.push(e);
+                return Err(errors);
+            }
+            for entry in transformation.conditions.entries {
+                let checked = checked_query.transformations.last_mut().unwrap();
+                checked.conditions.entries.push(entry);
+                if let Some(e) = check(&checked_query) {
+                    let checked = checked_query.transformations.last_mut().unwrap();
+                    checked.conditions.entries.pop();
+                    errors.push(e);
+                }
+            }
+            for column_statement in transformation.result_columns {
+                let checked = checked_query.transformations.last_mut().unwrap();
+                checked.result_columns.push(column_statement);
+                if let Some(e) = check(&checked_query) {
+                    let checked = checked_query.transformations.last_mut().unwrap();
+                    checked.result_columns.pop();
+                    errors.push(e);
+                }
+            }
+        }
+        // Some mistakes only arise from the combination of otherwise valid parts
+        if !errors.contains(&query_error) {
+            errors.push(query_error);
+        }
+        Err(errors.into_iter().unique().collect())
+    }
+
+    /// Every path through links to one which starts at the given table and has at most
+    /// `max_depth` links, written as it would be within a query, e.g. `author.team` from `issues`.
+    /// Useful to offer the related records that a user could filter by. Paths never visit the
+    /// same table twice. Shorter paths come first.
+    pub fn reachable_paths(
+        &self,
+        table_name: &str,
+        max_depth: usize,
+    ) -> Result<Vec<String>, String> {
+        let table_id = self
+            .options
+            .resolve_identifier(&self.schema.table_lookup, table_name)
+            .ok_or_else(|| msg::table_not_found(table_name))?;
+        let part = |link: &LinkToOne| match link {
+            LinkToOne::ForwardLinkToOne(_) => {
+                let start = link.get_start();
+                let table = self.schema.tables.get(&start.table_id).unwrap();
+                table.columns.get(&start.column_id).unwrap().name.clone()
+            }
+            LinkToOne::ReverseLinkToOne(_) => {
+                let table = self.schema.tables.get(&link.get_end().table_id).unwrap();
+                format!(">>{}", table.name)
+            }
+        };
+        let paths = self
+            .schema
+            .reachable_chains(*table_id, max_depth)
+            .iter()
+            .map(|chain| chain.get_links().iter().map(part).join("."))
+            .collect();
+        Ok(paths)
+    }
+
+    /// Reverse a path through foreign keys from the given table, e.g. `customer` from `orders`,
+    /// into the path which travels the same foreign keys back from the table where it ends, e.g.
+    /// `#orders(customer)` from `customers`. Useful to build the complementary query. Fails if the
+    /// schema lacks the reverse of any link.
+    pub fn reverse_path(&self, table_name: &str, path: &str) -> Result<String, String> {
+        let mut table = self
+            .options
+            .resolve_identifier(&self.schema.table_lookup, table_name)
+            .and_then(|id| self.schema.tables.get(id))
+            .ok_or_else(|| msg::table_not_found(table_name))?;
+        let mut chain: Option<Chain<LinkToOne>> = None;
+        for column_name in path.split('.') {
+            let column_id = self
+                .options
+                .resolve_identifier(&table.column_lookup, column_name)
+                .ok_or_else(|| msg::col_not_in_table(column_name, &table.name))?;
+            let link = table
+                .forward_links_to_one
+                .get(column_id)
+                .map(|link| LinkToOne::ForwardLinkToOne(*link))
+                .ok_or_else(|| msg::not_a_foreign_key(column_name, &table.name))?;
+            table = self.schema.tables.get(&link.get_end().table_id).unwrap();
+            match chain.as_mut() {
+                Some(chain) => chain.try_append(link)?,
+                None => chain = Some(Chain::try_new(link, ChainIntersecting::Disallowed)?),
+            }
+        }
+        // unwrap is safe here because splitting always gives at least one part
+        let reversed = chain.unwrap().try_reverse(&self.schema)?;
+        let part = |link: &FilteredLink| {
+            let start = link.link.get_start();
+            let end = link.link.get_end();
+            let table = self.schema.tables.get(&end.table_id).unwrap();
+            match link.link {
+                MultiLink::ReverseLinkToMany(_) => {
+                    let column = table.columns.get(&end.column_id).unwrap();
+                    format!("#{}({})", table.name, column.name)
+                }
+                MultiLink::ReverseLinkToOne(_) => format!(">>{}", table.name),
+                MultiLink::ForwardLinkToOne(_) => {
+                    let table = self.schema.tables.get(&start.table_id).unwrap();
+                    table.columns.get(&start.column_id).unwrap().name.clone()
+                }
+            }
+        };
+        Ok(reversed.get_links().iter().map(part).join("."))
+    }
+
+    /// Timing is opt-in because `Instant` isn't available on every platform, e.g. WebAssembly
+    fn start_timer(&self) -> Option<Instant> {
+        self.options.collect_stats.then(Instant::now)
+    }
+
+    /// Compile a query which has already been parsed, or which was built via the methods on
+    /// `Query`
+    pub fn compile_ast(&self, query: Query) -> Result<CompiledQuery, String> {
+        let start = self.start_timer();
+        let (select, columns, scope) = self.resolve_select(query, &HashMap::new(), true)?;
+        let resolution_time = start.map(|s| s.elapsed()).unwrap_or_default();
+        self.render_query(select, columns, scope, resolution_time)
+    }
+
+    /// Compile a query into one which counts the rows it would return, e.g. to report the number
+    /// of pages. The count ignores the query's result columns, sorting, limit, and offset. A
+    /// grouped query is counted by its groups, and so is a query with distinct rows.
+    pub fn compile_count(&self, input: &str) -> Result<String, String> {
+        let query = parse_with_options(input, self.options.parse_options())?;
+        let (mut select, _, mut scope) = self.resolve_select(query, &HashMap::new(), false)?;
+        select.sorting = vec![];
+        select.lock = None;
+        let count = Column::new(agg::count_star(), None);
+        let is_grouped = !select.grouping.is_empty() || !select.distinct_on.is_empty();
+        if !is_grouped {
+            select.columns = vec![count];
+            let sql = format!("{};", select.render(&mut scope));
+            return Ok(self.options.case_keywords(sql));
+        }
+        // Every column of a subquery needs a name in SQL Server, and the values don't matter
+        select.columns = vec![Column::new(
+            value::one(),
+            Some(COUNTED_GROUP_COLUMN_ALIAS.to_owned()),
+        )];
+        let ctes = std::mem::take(&mut select.ctes);
+        let counting = Select {
+            base_table_alias: Some(COUNTED_SUBQUERY_ALIAS.to_owned()),
+            base_subquery: Some(Box::new(select)),
+            columns: vec![count],
+            ctes,
+            ..Default::default()
+        };
+        let sql = format!("{};", counting.render(&mut scope));
+        Ok(self.options.case_keywords(sql))
+    }
+
+    /// Compile several queries which are meant to run together, e.g. to populate a dashboard. When
+    /// more than one of the queries needs the same CTE, that CTE is defined once within the
+    /// batch's prelude as a temporary view, and each query refers to the view instead. The names of
+    /// those views don't collide with any alias in any of the queries.
+    pub fn compile_batch(&self, inputs: &[&str]) -> Result<CompiledBatch, String> {
+        if self.options.view.is_some() {
+            return Err(msg::view_in_batch());
+        }
+        let queries = inputs
+            .iter()
+            .map(|input| parse_with_options(input, self.options.parse_options()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Build every query once to learn which CTEs they have in common
+        let mut all_aliases = HashSet::new();
+        let mut cte_users = HashMap::<String, Vec<(usize, String)>>::new();
+        for (index, query) in queries.iter().enumerate() {
+            let (select, _, mut scope) =
+                self.resolve_select(query.clone(), &HashMap::new(), true)?;
+            all_aliases.extend(scope.get_aliases());
+            for cte in &select.ctes {
+                let body = cte.select.render(&mut scope);
+                cte_users
+                    .entry(body)
+                    .or_default()
+                    .push((index, cte.alias.clone()));
+            }
+        }
+        let mut shared_ctes = cte_users
+            .into_values()
+            .filter(|users| users.iter().map(|(index, _)| index).unique().count() > 1)
+            .collect_vec();
+        if shared_ctes.is_empty() {
+            let queries = queries
+                .into_iter()
+                .map(|query| self.compile_ast(query))
+                .collect::<Result<_, _>>()?;
+            return Ok(CompiledBatch {
+                prelude: String::new(),
+                queries,
+            });
+        }
+        if !self.options.dialect.supports_temporary_views() {
+            return Err(msg::temporary_views_unsupported());
+        }
+        // Name the views in order of their first use so that the output is stable
+        shared_ctes.sort();
+        let mut view_names = HashMap::<(usize, String), String>::new();
+        let mut naming_index = 0;
+        for users in &shared_ctes {
+            let name = loop {
+                let name = format!("{CTE_ALIAS_PREFIX}{naming_index}");
+                naming_index += 1;
+                if !all_aliases.contains(&name) {
+                    break name;
+                }
+            };
+            for user in users {
+                view_names.insert(user.clone(), name.clone());
+            }
+        }
+
+        // Build every query again, this time giving the shared CTEs the names of their views
+        let mut prelude = Vec::new();
+        let mut defined_views = HashSet::new();
+        let mut compiled_queries = Vec::with_capacity(queries.len());
+        for (index, query) in queries.into_iter().enumerate() {
+            let start = self.start_timer();
+            let renamed_ctes = view_names
+                .iter()
+                .filter(|((user, _), _)| *user == index)
+                .map(|((_, alias), name)| (alias.clone(), name.clone()))
+                .collect::<HashMap<_, _>>();
+            let (mut select, columns, mut scope) =
+                self.resolve_select(query, &renamed_ctes, true)?;
+            let (shared, own): (Vec<_>, Vec<_>) = std::mem::take(&mut select.ctes)
+                .into_iter()
+                .partition(|cte| renamed_ctes.values().contains(&cte.alias));
+            select.ctes = own;
+            for cte in shared {
+                if defined_views.insert(cte.alias.clone()) {
+                    let create_view = self.options.dialect.create_temporary_view(&cte.alias);
+                    prelude.push(format!("{create_view}\n{};", cte.select.render(&mut scope)));
+                }
+            }
+            let resolution_time = start.map(|s| s.elapsed()).unwrap_or_default();
+            compiled_queries.push(self.render_query(select, columns, scope, resolution_time)?);
+        }
+        Ok(CompiledBatch {
+            prelude: self.options.case_keywords(prelude.join("\n\n")),
+            queries: compiled_queries,
+        })
+    }
+
+    /// Build the SELECT for a query with its CTEs in canonical order. CTEs are normally named by
+    /// their position within that order, but any alias in `renamed_ctes` is swapped for the name
+    /// it maps to. The limit and offset from the options are only applied when `paginate` is true.
+    fn resolve_select(
+        &self,
+        query: Query,
+        renamed_ctes: &HashMap<String, String>,
+        paginate: bool,
+    ) -> Result<(Select, ResultColumnTypes, Scope<'_, '_>), String> {
+        let (mut select, mut columns, mut scope) =
+            self.build_select(query.clone(), vec![], paginate)?;
+
+        // If the CTEs weren't created in canonical order, we build the query again so that the
+        // aliases line up with the canonical order too.
+        let rename = |alias: &String| renamed_ctes.get(alias).unwrap_or(alias).clone();
+        let cte_aliases = scope.get_cte_aliases().iter().map(rename).collect_vec();
+        let cte_alias_plan = plan_cte_aliases(&select.ctes, &mut scope)
+            .iter()
+            .map(rename)
+            .collect_vec();
+        if cte_alias_plan != scope.get_cte_aliases() {
+            (select, columns, scope) = self.build_select(query, cte_alias_plan, paginate)?;
+        }
+        sort_ctes(&mut select.ctes, &cte_aliases);
+        if let Some(max_complexity) = self.options.inline_cte_max_complexity {
+            inline_small_ctes(&mut select, max_complexity);
+        }
+        for transform in &self.options.sql_tree_transforms {
+            transform.transform(&mut select)?;
+        }
+        reject_unconditioned_joins(&select)?;
+        if let Some(safe_mode) = &self.options.safe_mode {
+            reject_unsafe_select(&select, safe_mode)?;
+        }
+        Ok((select, columns, scope))
+    }
+
+    fn render_query(
+        &self,
+        select: Select,
+        columns: ResultColumnTypes,
+        mut scope: Scope,
+        resolution_time: Duration,
+    ) -> Result<CompiledQuery, String> {
+        let start = self.start_timer();
+        let mut sql = select.render(&mut scope);
+        if let Some(view) = &self.options.view {
+            sql = format!("{}\n{}", self.create_view(view)?, sql);
+        }
+        if let Some(prepared_statement) = &self.options.prepared_statement {
+            sql = format!(
+                "{}\n{}",
+                self.prepare(prepared_statement, &sql, &scope)?,
+                sql
+            );
+        }
+        let sql = self.options.case_keywords(format!("{sql};"));
+        let prelude = match self.options.checked_statement_timeout()? {
+            Some(milliseconds) => self
+                .options
+                .dialect
+                .statement_timeout(milliseconds)
+                .ok_or_else(msg::statement_timeout_unsupported)?,
+            None => String::new(),
+        };
+        let prelude = self.options.case_keywords(prelude);
+        let ctes = select
+            .ctes
+            .iter()
+            .map(|cte| CteMetadata {
+                alias: cte.alias.clone(),
+                purpose: cte.purpose,
+            })
+            .collect();
+        let stats = start.map(|start| CompileStats {
+            parse_time: Duration::ZERO,
+            resolution_time,
+            generation_time: start.elapsed(),
+            join_count: select.joins.len(),
+            cte_count: select.ctes.len(),
+            alias_count: scope.get_alias_count(),
+        });
+        let parameters = scope
+            .get_parameters()
+            .iter()
+            .enumerate()
+            .map(|(position, parameter)| {
+                let data_type = parameter.get_data_type(&scope);
+                let type_name = parameter
+                    .type_name
+                    .clone()
+                    .or_else(|| self.options.dialect.type_name(&data_type));
+                ParamInfo {
+                    index: position + 1,
+                    name: parameter.name.clone(),
+                    data_type,
+                    type_name,
+                }
+            })
+            .collect();
+        Ok(CompiledQuery {
+            prelude,
+            sql,
+            columns,
+            ctes,
+            join_plan: (&select).into(),
+            stats,
+            aliases: scope.get_aliases(),
+            parameters,
+        })
+    }
+
+    fn build_select(
+        &self,
+        mut query: Query,
+        cte_alias_plan: Vec<String>,
+        paginate: bool,
+    ) -> Result<(Select, ResultColumnTypes, Scope<'_, '_>), String> {
+        let mut scope = Scope::build(&self.options, &self.schema, query.base_table.as_deref())?;
+        scope.set_cte_alias_plan(cte_alias_plan);
+        for cte in &self.options.external_ctes {
+            scope.register_external_cte(cte.clone());
+        }
+        // Unsatisfiable conditions are dropped before anything else sees them, so that they need
+        // no joins or parameters
+        let mut is_unsatisfiable = false;
+        if let Some(transformation) = query.transformations.first_mut() {
+            if self.options.fold_constant_conditions {
+                let conditions = std::mem::take(&mut transformation.conditions);
+                match fold_condition_set(conditions) {
+                    Folded::Constant(value) => is_unsatisfiable = !value,
+                    Folded::Remaining(conditions) => transformation.conditions = conditions,
+                }
+            }
+        }
+        let mut parameters = find_parameters(&query)?;
+        // The base table comes before anything else which could refer to a parameter
+        if let Some(PointInTime::Placeholder(name)) = &self.options.system_time_as_of {
+            if !parameters.iter().any(|p| &p.name == name) {
+                let parameter = Parameter {
+                    name: name.clone(),
+                    counterpart: None,
+                    type_name: None,
+                };
+                parameters.insert(0, parameter);
+            }
+        }
+        scope.set_parameters(parameters);
+        for table_name in &query.cross_joined_tables {
+            scope.cross_join_table(table_name)?;
+        }
+        let mut select = Select {
+            base_table: scope.get_base_table().ok().map(|t| t.name.clone()),
+            base_table_schema: scope
+                .get_base_table()
+                .ok()
+                .and_then(|t| scope.get_table_schema(t)),
+            base_table_alias: scope.get_base_table_alias().map(str::to_owned),
+            ..Default::default()
+        };
 
         let mut transformations_iter = query.transformations.into_iter();
         let first_transformation = transformations_iter.next().unwrap_or_default();
@@ -36,13 +532,462 @@ impl Compiler {
             return Err("Pipelines not yet supported".to_string());
         }
 
-        select.conditions = convert_condition_set(first_transformation.conditions, &mut scope)?;
+        select.conditions = if is_unsatisfiable {
+            match self.options.boolean_rendering {
+                BooleanRendering::Native => value::false_(),
+                BooleanRendering::Integer => cmp::eq(value::one(), value::zero()),
+            }
+        } else {
+            convert_top_level_condition_set(first_transformation.conditions, &mut scope)?
+        };
 
         let result_columns = first_transformation.result_columns;
-        (select.columns, select.sorting) = convert_result_columns(result_columns, &mut scope)?;
+        if result_columns.is_empty() && !scope.has_base_table() {
+            return Err(msg::no_result_columns_without_base_table());
+        }
+        let columns;
+        let grouping;
+        (
+            select.columns,
+            select.sorting,
+            select.distinct_on,
+            grouping,
+            columns,
+        ) = convert_result_columns(result_columns, &mut scope)?;
+        (select.grouping, select.grouping_extension) = grouping;
+        if select.grouping_extension.is_some()
+            && !self.options.dialect.supports_grouping_extensions()
+        {
+            return Err(msg::grouping_extension_unsupported());
+        }
+
+        if self.options.default_sort
+            && select.sorting.is_empty()
+            && select.grouping.is_empty()
+            && select.distinct_on.is_empty()
+        {
+            select.sorting = default_sorting(&scope);
+        }
 
         (select.joins, select.ctes) = scope.decompose_join_tree();
+        select.joins.extend(scope.take_lateral_joins());
+        select.unnests = scope.take_unnests();
+        let has_full_outer_join = has_full_outer_join(&select.joins);
+        if has_full_outer_join && !self.options.dialect.supports_full_outer_joins() {
+            return Err(msg::full_outer_join_unsupported());
+        }
 
-        Ok(format!("{};", select.render(&mut scope)))
+        if !self.options.cte_materialization.is_empty()
+            && !self.options.dialect.supports_cte_materialization()
+        {
+            return Err(msg::cte_materialization_unsupported());
+        }
+
+        if paginate {
+            select.limit = match (self.options.limit, self.options.max_limit) {
+                (Some(limit), Some(max_limit)) => Some(limit.min(max_limit)),
+                (limit, max_limit) => limit.or(max_limit),
+            };
+            if let (None, Some(safe_mode)) = (select.limit, &self.options.safe_mode) {
+                let default_limit = safe_mode.default_limit;
+                select.limit = Some(default_limit.ok_or_else(msg::unbounded_query_in_safe_mode)?);
+            }
+            select.offset = self.options.offset;
+        }
+        let is_paginated = select.limit.is_some() || select.offset.is_some();
+        if is_paginated
+            && select.sorting.is_empty()
+            && self.options.dialect.pagination_requires_sorting()
+        {
+            return Err(msg::pagination_without_sorting());
+        }
+
+        if self.options.lock.is_some() {
+            if !self.options.dialect.supports_locking() {
+                return Err(msg::locking_unsupported());
+            }
+            if !select.grouping.is_empty() || !select.ctes.is_empty() {
+                return Err(msg::locking_with_aggregation());
+            }
+            // Postgres can't lock the nullable side of an outer join, which includes the base
+            // table here
+            if has_full_outer_join {
+                return Err(msg::locking_with_full_outer_join());
+            }
+            select.lock = self.options.lock;
+        }
+
+        if let Some(sample) = self.options.table_sample {
+            if !sample.percentage.is_finite() || !(0.0..=100.0).contains(&sample.percentage) {
+                return Err(msg::invalid_sample_percentage(sample.percentage));
+            }
+            if !self.options.dialect.supports_table_sample(sample.method) {
+                return Err(msg::table_sample_unsupported());
+            }
+            if select.base_table.is_none() {
+                return Err(msg::table_sample_without_base_table());
+            }
+            select.table_sample = Some(sample);
+        }
+
+        if let Some(point) = &self.options.system_time_as_of {
+            if !self.options.dialect.supports_system_time() {
+                return Err(msg::system_time_unsupported());
+            }
+            if !scope.get_base_table().is_ok_and(|t| t.values.is_none()) {
+                return Err(msg::system_time_without_versioned_base_table());
+            }
+            let point = match point {
+                PointInTime::Timestamp(timestamp) => {
+                    if !is_timestamp(timestamp) {
+                        return Err(msg::invalid_point_in_time(timestamp));
+                    }
+                    SqlExpr::atom(self.options.dialect.timestamp(timestamp))
+                }
+                PointInTime::Placeholder(name) => {
+                    let placeholder = Placeholder {
+                        name: name.clone(),
+                        type_name: None,
+                    };
+                    convert_expr(Expr::Placeholder(placeholder), &mut scope)?
+                }
+            };
+            select.system_time_as_of = Some(point);
+        }
+
+        Ok((select, columns, scope))
+    }
+
+    fn create_view(&self, view: &View) -> Result<String, String> {
+        let dialect = &self.options.dialect;
+        if view.name.split('.').any(|part| part.trim().is_empty()) {
+            return Err(msg::invalid_view_name(&view.name));
+        }
+        if view.materialized {
+            if !dialect.supports_materialized_views() {
+                return Err(msg::materialized_views_unsupported());
+            }
+            if view.or_replace {
+                return Err(msg::replacing_materialized_view());
+            }
+        }
+        if view.or_replace && !dialect.supports_replacing_views() {
+            return Err(msg::replacing_views_unsupported());
+        }
+        Ok(dialect.create_view(view))
+    }
+
+    fn prepare(
+        &self,
+        prepared_statement: &PreparedStatement,
+        sql: &str,
+        scope: &Scope,
+    ) -> Result<String, String> {
+        let dialect = &self.options.dialect;
+        if !dialect.supports_prepared_statements() {
+            return Err(msg::prepared_statements_unsupported());
+        }
+        if self.options.view.is_some() {
+            return Err(msg::prepared_view());
+        }
+        let name = match &prepared_statement.name {
+            Some(name) if name.trim().is_empty() => return Err(msg::invalid_statement_name(name)),
+            Some(name) => name.clone(),
+            None => generate_statement_name(sql),
+        };
+        let parameter_types = scope
+            .get_parameters()
+            .iter()
+            .map(|parameter| parameter.get_type_name(scope))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(dialect.prepare(&name, &parameter_types))
+    }
+
+    /// Compile a set of conditions into a SQL fragment that can be spliced into the WHERE clause
+    /// of another query on the same base table. Aliases in `reserved_aliases` will not be used
+    /// for any tables or CTEs that the fragment needs.
+    pub fn compile_condition(
+        &self,
+        base_table: &str,
+        input: &str,
+        reserved_aliases: &[String],
+    ) -> Result<ConditionFragment, String> {
+        let conditions = parse_conditions_with_options(input, self.options.parse_options())?;
+        let mut scope = Scope::build(&self.options, &self.schema, Some(base_table))?;
+        scope.reserve_aliases(reserved_aliases.iter().cloned());
+        scope.set_parameters(find_expr_parameters(&conditions.entries)?);
+        let sql = convert_condition_set(conditions, &mut scope)?.render(&mut scope);
+        if !scope.take_unnests().is_empty() {
+            return Err(msg::unnest_outside_query());
+        }
+        let (joins, ctes) = scope.decompose_join_tree();
+        if has_full_outer_join(&joins) && !self.options.dialect.supports_full_outer_joins() {
+            return Err(msg::full_outer_join_unsupported());
+        }
+        let aliases = ctes
+            .iter()
+            .map(|cte| cte.alias.clone())
+            .chain(joins.iter().map(|join| join.alias.clone()))
+            .unique()
+            .collect();
+        let render = |sql: String| self.options.case_keywords(sql);
+        Ok(ConditionFragment {
+            sql: render(sql),
+            ctes: ctes
+                .iter()
+                .map(|cte| render(cte.render(&mut scope)))
+                .collect(),
+            joins: joins
+                .iter()
+                .map(|join| render(join.render(&mut scope)))
+                .collect(),
+            aliases,
+        })
+    }
+
+    /// Compile a set of conditions into a single boolean SQL expression on the columns of the base
+    /// table, e.g. for a CHECK constraint or a validation query. There's nowhere to join other
+    /// tables, so conditions on related records become `EXISTS` subqueries and conditions on
+    /// single related records are rejected.
+    pub fn compile_boolean_expression(
+        &self,
+        base_table: &str,
+        input: &str,
+    ) -> Result<String, String> {
+        let conditions = parse_conditions_with_options(input, self.options.parse_options())?;
+        let mut scope = Scope::build(&self.options, &self.schema, Some(base_table))?
+            .with_correlated_subqueries();
+        scope.set_parameters(find_expr_parameters(&conditions.entries)?);
+        let mut expr = convert_condition_set(conditions, &mut scope)?;
+        reject_joins(&mut scope)?;
+        if expr.is_empty() {
+            expr = match self.options.boolean_rendering {
+                BooleanRendering::Native => value::true_(),
+                BooleanRendering::Integer => cmp::eq(value::one(), value::one()),
+            };
+        }
+        Ok(self.options.case_keywords(expr.render(&mut scope)))
+    }
+
+    /// Compile a set of conditions into a boolean SQL expression for the `USING` or `WITH CHECK`
+    /// clause of a row-level security policy on the base table. As with
+    /// `compile_boolean_expression`, conditions on related records become `EXISTS` subqueries.
+    /// The policy may also refer to the database session via `@current_user` and the `setting`
+    /// function, e.g. `tenant_id:"app.tenant_id"|setting|to_integer`. Placeholders are rejected.
+    pub fn compile_policy_expression(
+        &self,
+        base_table: &str,
+        input: &str,
+    ) -> Result<String, String> {
+        if !self.options.dialect.supports_row_level_security() {
+            return Err(msg::row_level_security_unsupported());
+        }
+        let conditions = parse_conditions_with_options(input, self.options.parse_options())?;
+        if !find_expr_parameters(&conditions.entries)?.is_empty() {
+            return Err(msg::placeholder_in_policy());
+        }
+        let mut scope = Scope::build(&self.options, &self.schema, Some(base_table))?
+            .with_correlated_subqueries()
+            .with_session_values();
+        let mut expr = convert_condition_set(conditions, &mut scope)?;
+        reject_joins(&mut scope)?;
+        if !scope.take_unnests().is_empty() {
+            return Err(msg::unnest_outside_query());
+        }
+        if expr.is_empty() {
+            expr = value::true_();
+        }
+        Ok(self.options.case_keywords(expr.render(&mut scope)))
     }
+
+    /// Compile a set of conditions into a DELETE statement which removes the matching rows of the
+    /// base table. DELETE can't join other tables, so conditions on related records become
+    /// correlated subqueries.
+    pub fn compile_delete(&self, base_table: &str, input: &str) -> Result<String, String> {
+        let conditions = parse_conditions_with_options(input, self.options.parse_options())?;
+        let mut scope = Scope::build(&self.options, &self.schema, Some(base_table))?
+            .with_correlated_subqueries();
+        scope.set_parameters(find_expr_parameters(&conditions.entries)?);
+        let conditions = convert_condition_set(conditions, &mut scope)?;
+        reject_joins(&mut scope)?;
+        let table = scope.get_base_table()?;
+        let delete = Delete {
+            base_table: table.name.clone(),
+            base_table_schema: scope.get_table_schema(table),
+            base_table_alias: scope.get_base_table_alias().map(str::to_owned),
+            conditions,
+        };
+        Ok(self
+            .options
+            .case_keywords(format!("{};", delete.render(&mut scope))))
+    }
+
+    /// Compile an UPDATE statement which assigns values to columns of the base table within the
+    /// rows matching the conditions. Each assignment pairs a column name with an expression for its
+    /// new value. As with `compile_delete`, related records are referenced via correlated
+    /// subqueries.
+    pub fn compile_update(
+        &self,
+        base_table: &str,
+        assignments: &[(&str, &str)],
+        conditions: &str,
+    ) -> Result<String, String> {
+        if assignments.is_empty() {
+            return Err(msg::update_without_assignments());
+        }
+        let conditions = parse_conditions_with_options(conditions, self.options.parse_options())?;
+        let mut scope = Scope::build(&self.options, &self.schema, Some(base_table))?
+            .with_correlated_subqueries();
+        let table = scope.get_base_table()?;
+        let columns = assignments
+            .iter()
+            .map(|(target, _)| self.resolve_assignment_target(target, table))
+            .collect::<Result<Vec<_>, _>>()?;
+        let values = assignments
+            .iter()
+            .map(|(_, value)| parse_expr_with_options(value, self.options.parse_options()))
+            .collect::<Result<Vec<_>, _>>()?;
+        scope.set_parameters(find_expr_parameters(
+            values.iter().chain(&conditions.entries),
+        )?);
+        let values = values
+            .into_iter()
+            .map(|value| convert_expr(value, &mut scope))
+            .collect::<Result<Vec<_>, _>>()?;
+        let conditions = convert_condition_set(conditions, &mut scope)?;
+        reject_joins(&mut scope)?;
+        let table = scope.get_base_table()?;
+        let update = Update {
+            base_table: table.name.clone(),
+            base_table_schema: scope.get_table_schema(table),
+            base_table_alias: scope.get_base_table_alias().map(str::to_owned),
+            assignments: columns.into_iter().zip(values).collect(),
+            conditions,
+        };
+        Ok(self
+            .options
+            .case_keywords(format!("{};", update.render(&mut scope))))
+    }
+
+    /// The name of the base table column to which an UPDATE assigns a value
+    fn resolve_assignment_target(&self, target: &str, table: &Table) -> Result<String, String> {
+        let Ok(Expr::Path(path, _)) = parse_expr(target) else {
+            return Err(msg::invalid_assignment_target(target));
+        };
+        let [PathPart::Column(name)] = path.as_slice() else {
+            return match path.len() {
+                0 | 1 => Err(msg::invalid_assignment_target(target)),
+                _ => Err(msg::assignment_to_related_column(target)),
+            };
+        };
+        let column_id = self
+            .options
+            .resolve_identifier(&table.column_lookup, name)
+            .ok_or_else(|| msg::col_not_in_table(name, &table.name))?;
+        Ok(table.columns.get(column_id).unwrap().name.clone())
+    }
+}
+
+/// Statements which can't join other tables can only refer to related records via the correlated
+/// subqueries of their scope
+fn reject_joins(scope: &mut Scope) -> Result<(), String> {
+    let (joins, _) = scope.decompose_join_tree();
+    if !joins.is_empty() {
+        return Err(msg::single_related_record_without_join());
+    }
+    Ok(())
+}
+
+/// A join without conditions silently pairs every row with every row of the joined table, which is
+/// only what we want for explicit cross joins
+fn reject_unconditioned_joins(select: &Select) -> Result<(), String> {
+    let cte_joins = select.ctes.iter().flat_map(|cte| &cte.select.joins);
+    for join in select.joins.iter().chain(cte_joins) {
+        if join.conditions.is_empty() && !matches!(join.join_type, JoinType::Cross) {
+            return Err(msg::unconditioned_join(&join.alias));
+        }
+    }
+    Ok(())
+}
+
+/// Enforce the join, CTE, and cross join restrictions of safe mode. Lateral joins are rendered as
+/// cross joins too, but they're correlated with the rows before them, so they're allowed.
+fn reject_unsafe_select(select: &Select, safe_mode: &SafeMode) -> Result<(), String> {
+    let cte_joins = select.ctes.iter().flat_map(|cte| &cte.select.joins);
+    for join in select.joins.iter().chain(cte_joins) {
+        if matches!(join.join_type, JoinType::Cross) && join.lateral_subquery.is_none() {
+            return Err(msg::cross_join_in_safe_mode(&join.alias));
+        }
+    }
+    let (join_count, cte_count) = count_joins_and_ctes(select);
+    if join_count > safe_mode.max_joins {
+        return Err(msg::too_many_joins(safe_mode.max_joins));
+    }
+    if cte_count > safe_mode.max_ctes {
+        return Err(msg::too_many_ctes(safe_mode.max_ctes));
+    }
+    Ok(())
+}
+
+/// The total numbers of joins and CTEs within the select, including those nested within its CTEs
+/// and the subqueries of its joins
+fn count_joins_and_ctes(select: &Select) -> (usize, usize) {
+    let join_subqueries = select.joins.iter().filter_map(|join| {
+        join.subquery
+            .as_deref()
+            .or(join.lateral_subquery.as_deref())
+    });
+    let nested = select
+        .ctes
+        .iter()
+        .map(|cte| &cte.select)
+        .chain(join_subqueries);
+    nested.map(count_joins_and_ctes).fold(
+        (select.joins.len(), select.ctes.len()),
+        |(joins, ctes), (nested_joins, nested_ctes)| (joins + nested_joins, ctes + nested_ctes),
+    )
+}
+
+/// A name which is stable for a given query and unlikely to be shared with any other query. This
+/// is the FNV-1a hash of the SQL, since the hashers in the standard library may change between
+/// releases.
+fn generate_statement_name(sql: &str) -> String {
+    let hash = sql.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    format!("querydown_{hash:016x}")
+}
+
+/// The sorting declared by the schema for the base table of the query, if any
+fn default_sorting(scope: &Scope) -> Vec<SortEntry> {
+    let (Ok(table), Ok(alias)) = (scope.get_base_table(), scope.alias_for_base_table()) else {
+        return vec![];
+    };
+    table
+        .default_sort
+        .iter()
+        .filter_map(|column_id| table.columns.get(column_id))
+        .map(|column| SortEntry {
+            expr: scope.table_column_expr(alias, &column.name),
+            direction: SortDirection::Asc,
+            nulls_sort: scope.options.nulls_sort.clone(),
+        })
+        .collect()
+}
+
+/// True when the text is a timestamp written as `YYYY-MM-DD`, optionally followed by `T` or a space
+/// and then a time of day as `HH:MM` or `HH:MM:SS`
+fn is_timestamp(text: &str) -> bool {
+    let shape = text
+        .chars()
+        .map(|c| if c.is_ascii_digit() { '9' } else { c })
+        .collect::<String>()
+        .replacen('T', " ", 1);
+    ["9999-99-99", "9999-99-99 99:99", "9999-99-99 99:99:99"].contains(&shape.as_str())
+}
+
+fn has_full_outer_join(joins: &[Join]) -> bool {
+    joins
+        .iter()
+        .any(|join| matches!(join.join_type, JoinType::FullOuter))
 }
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use querydown_parser::ast::{Expr, FunctionDimension};
+
+use crate::{errors::msg, schema::DataType, sql::expr::SqlExpr};
+
+use super::{expr::convert_expr, scope::Scope};
+
+pub type CompositionMap = HashMap<String, Composition>;
+
+/// Converts the Querydown arguments of a function call into an SQL expression
+pub type Func = fn(Vec<Expr>, &mut Scope) -> Result<SqlExpr, String>;
+
+/// Renders SQL for a function call, given the SQL for each of its arguments
+pub type RenderFunc = fn(Vec<String>) -> String;
+
+/// The number of arguments a function accepts. For piped calls, this includes the piped value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exactly(usize),
+    AtLeast(usize),
+    /// At least the first number of arguments and at most the second
+    Between(usize, usize),
+}
+
+impl Arity {
+    fn accepts(&self, count: usize) -> bool {
+        match self {
+            Arity::Exactly(n) => count == *n,
+            Arity::AtLeast(n) => count >= *n,
+            Arity::Between(min, max) => (*min..=*max).contains(&count),
+        }
+    }
+}
+
+/// How to determine the type of the value returned by a function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReturnType {
+    Fixed(DataType),
+    /// The same type as the first argument
+    FirstArg,
+    /// The same type as the second argument
+    SecondArg,
+    /// The type of the elements of the array given as the first argument
+    FirstArgElement,
+    /// The type of the column that an aggregate function is applied to
+    AggregatedColumn,
+    /// An array of the type of the column that an aggregate function is applied to
+    AggregatedColumnArray,
+    /// The first argument's timestamp type with its time zone added or removed, as happens when
+    /// converting it to another zone
+    TimeZoneFlipped,
+    /// The common type of the values to which `map` maps its keys, i.e. every second argument
+    /// after the first, along with the default value if there is one
+    MappedValues,
+    /// The common type of the labels which `bucket` gives its buckets, i.e. the entries of the
+    /// third argument
+    BucketLabels,
+    /// The type resulting from adding the two arguments
+    Sum,
+    /// The type resulting from subtracting the second argument from the first
+    Difference,
+    /// The type resulting from multiplying or dividing the two arguments
+    Product,
+}
+
+/// What a function accepts and returns, for tooling such as editors which suggest functions
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompositionSignature {
+    pub name: String,
+    pub dimension: FunctionDimension,
+    pub arity: Arity,
+    pub return_type: ReturnType,
+    /// True for functions from `Options::custom_functions`
+    pub is_custom: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Conversion {
+    Func(Func),
+    Render(RenderFunc),
+    /// Render a call to the SQL function of this name
+    SqlFunction(String),
+}
+
+/// A function which can be called from Querydown code, along with its signature
+#[derive(Debug, Clone)]
+pub struct Composition {
+    pub arity: Arity,
+    pub return_type: ReturnType,
+    conversion: Conversion,
+}
+
+impl Composition {
+    /// Define a scalar function which converts each argument to SQL and then renders the call
+    /// with `render`.
+    pub fn new(arity: Arity, return_type: DataType, render: RenderFunc) -> Self {
+        Self {
+            arity,
+            return_type: ReturnType::Fixed(return_type),
+            conversion: Conversion::Render(render),
+        }
+    }
+
+    /// Define a scalar function which calls an SQL function of the given name, passing all the
+    /// arguments through in order.
+    pub fn sql_function(sql_name: &str, arity: Arity, return_type: DataType) -> Self {
+        Self {
+            arity,
+            return_type: ReturnType::Fixed(return_type),
+            conversion: Conversion::SqlFunction(sql_name.to_owned()),
+        }
+    }
+
+    pub(crate) fn from_func(arity: Arity, return_type: ReturnType, func: Func) -> Self {
+        Self {
+            arity,
+            return_type,
+            conversion: Conversion::Func(func),
+        }
+    }
+
+    pub(crate) fn convert(
+        &self,
+        name: &str,
+        args: Vec<Expr>,
+        scope: &mut Scope,
+    ) -> Result<SqlExpr, String> {
+        if !self.arity.accepts(args.len()) {
+            return Err(msg::wrong_number_of_args(name, &self.arity));
+        }
+        match &self.conversion {
+            Conversion::Func(func) => func(args, scope),
+            Conversion::Render(render) => Ok(SqlExpr::atom(render(convert_args(args, scope)?))),
+            Conversion::SqlFunction(sql_name) => {
+                let sql_args = convert_args(args, scope)?.join(", ");
+                Ok(SqlExpr::atom(format!("{}({})", sql_name, sql_args)))
+            }
+        }
+    }
+}
+
+fn convert_args(args: Vec<Expr>, scope: &mut Scope) -> Result<Vec<String>, String> {
+    let mut sql_args = Vec::<String>::new();
+    for arg in args {
+        sql_args.push(convert_expr(arg, scope)?.content);
+    }
+    Ok(sql_args)
+}
@@ -0,0 +1,16 @@
+/// A set of conditions compiled on their own so that they can be spliced into another query. The
+/// host query is expected to have the same base table (un-aliased) as the one the fragment was
+/// compiled against.
+#[derive(Debug)]
+pub struct ConditionFragment {
+    /// The SQL expression to be added (via `AND`) to the WHERE clause of the host query. Empty
+    /// when there are no conditions.
+    pub sql: String,
+    /// The CTEs needed by the conditions, each rendered as `"alias" AS (...)`
+    pub ctes: Vec<String>,
+    /// The joins needed by the conditions, each rendered as a full `JOIN` clause
+    pub joins: Vec<String>,
+    /// All table and CTE aliases introduced by the fragment. Pass these as reserved aliases when
+    /// compiling another fragment for the same host query to avoid collisions.
+    pub aliases: Vec<String>,
+}
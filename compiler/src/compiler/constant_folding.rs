@@ -0,0 +1,135 @@
+use std::cmp::Ordering;
+
+use querydown_parser::ast::{
+    Comparison, ComparisonSide, ConditionSet, Conjunction, Expr, Operator,
+};
+
+use super::constants::{VAR_FALSE, VAR_TRUE};
+
+/// The outcome of simplifying a condition
+pub enum Folded<T> {
+    /// The condition is the same for every row
+    Constant(bool),
+    /// What remains of the condition once its constant parts are removed
+    Remaining(T),
+}
+
+/// Simplify the conditions whose truth we can tell from their literals alone, e.g. `1:2` or an
+/// expansion with no entries like `id:[]`. Constant entries fold into the sets containing them.
+/// Comparisons of unequal strings are left alone, since a collation may consider them equal.
+pub fn fold_condition_set(condition_set: ConditionSet) -> Folded<ConditionSet> {
+    if condition_set.is_empty() {
+        return Folded::Remaining(condition_set);
+    }
+    // The value of an entry which decides the whole set, e.g. a false entry within an AND set
+    let decisive = condition_set.conjunction == Conjunction::Or;
+    let mut entries = Vec::new();
+    for entry in condition_set.entries {
+        match fold_expr(entry) {
+            Folded::Constant(value) if value == decisive => return Folded::Constant(decisive),
+            Folded::Constant(_) => {}
+            Folded::Remaining(expr) => entries.push(expr),
+        }
+    }
+    if entries.is_empty() {
+        return Folded::Constant(!decisive);
+    }
+    Folded::Remaining(ConditionSet {
+        conjunction: condition_set.conjunction,
+        entries,
+    })
+}
+
+fn fold_expr(expr: Expr) -> Folded<Expr> {
+    match expr {
+        Expr::Variable(ref v) if v == VAR_TRUE => Folded::Constant(true),
+        Expr::Variable(ref v) if v == VAR_FALSE => Folded::Constant(false),
+        Expr::ConditionSet(condition_set) => match fold_condition_set(condition_set) {
+            Folded::Constant(value) => Folded::Constant(value),
+            Folded::Remaining(condition_set) => {
+                Folded::Remaining(Expr::ConditionSet(condition_set))
+            }
+        },
+        Expr::Comparison(comparison) => match comparison_truth(&comparison) {
+            Some(value) => Folded::Constant(value),
+            None => Folded::Remaining(Expr::Comparison(comparison)),
+        },
+        expr => Folded::Remaining(expr),
+    }
+}
+
+fn comparison_truth(comparison: &Comparison) -> Option<bool> {
+    let operator = comparison.operator;
+    match (&comparison.left, &comparison.right) {
+        (ComparisonSide::Expansion(set), _) | (_, ComparisonSide::Expansion(set))
+            if set.entries.is_empty() =>
+        {
+            // An expansion combines the comparison with each of its entries
+            Some(set.conjunction == Conjunction::And)
+        }
+        (ComparisonSide::Expr(Expr::Number(a, _)), ComparisonSide::Expr(Expr::Number(b, _))) => {
+            let ordering = compare_numbers(a, b)?;
+            match operator {
+                Operator::Eq => Some(ordering == Ordering::Equal),
+                Operator::Neq => Some(ordering != Ordering::Equal),
+                Operator::Gt => Some(ordering == Ordering::Greater),
+                Operator::Gte => Some(ordering != Ordering::Less),
+                Operator::Lt => Some(ordering == Ordering::Less),
+                Operator::Lte => Some(ordering != Ordering::Greater),
+                _ => None,
+            }
+        }
+        (ComparisonSide::Expr(Expr::String(a, _)), ComparisonSide::Expr(Expr::String(b, _)))
+            if a == b =>
+        {
+            match operator {
+                Operator::Eq | Operator::IEq => Some(true),
+                Operator::Neq | Operator::NIEq => Some(false),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Compare two number literals exactly, since converting them to floats could make different
+/// literals equal, e.g. `10000000000000000001` and `10000000000000000000`
+fn compare_numbers(a: &str, b: &str) -> Option<Ordering> {
+    let (a_sign, a_exponent, a_digits) = normalize_number(a)?;
+    let (b_sign, b_exponent, b_digits) = normalize_number(b)?;
+    let magnitude = || {
+        a_exponent
+            .cmp(&b_exponent)
+            .then_with(|| a_digits.cmp(&b_digits))
+    };
+    Some(match a_sign.cmp(&b_sign) {
+        Ordering::Equal if a_sign > 0 => magnitude(),
+        Ordering::Equal if a_sign < 0 => magnitude().reverse(),
+        ordering => ordering,
+    })
+}
+
+/// The sign, the position of the decimal point relative to the first significant digit, and the
+/// significant digits of a number literal like `-1.5e10`. Zero has no digits.
+fn normalize_number(number: &str) -> Option<(i8, i64, String)> {
+    let (sign, unsigned) = match number.strip_prefix('-') {
+        Some(unsigned) => (-1, unsigned),
+        None => (1, number.strip_prefix('+').unwrap_or(number)),
+    };
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+        None => (unsigned, 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{integer}{fraction}");
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let leading_zeros = digits.len() - digits.trim_start_matches('0').len();
+    let significant = digits.trim_matches('0');
+    if significant.is_empty() {
+        return Some((0, 0, String::new()));
+    }
+    let exponent = exponent + integer.len() as i64 - leading_zeros as i64;
+    Some((sign, exponent, significant.to_owned()))
+}
@@ -1,6 +1,20 @@
+/// Used when a long base table name has no letters or digits from which to abbreviate an alias
+pub const BASE_TABLE_ALIAS_FALLBACK: &str = "t";
 pub const CTE_ALIAS_PREFIX: &str = "cte";
+/// The longest alias we generate, matching the limit on identifier length in Postgres
+pub const MAX_ALIAS_LENGTH: usize = 63;
+/// Colliding aliases get numeric suffixes up to this one before we fall back to hashed suffixes
+pub const MAX_NUMERIC_ALIAS_SUFFIX: usize = 9;
 pub const CTE_PK_COLUMN_ALIAS: &str = "pk";
 pub const CTE_VALUE_COLUMN_PREFIX: &str = "v";
+pub const COUNTED_SUBQUERY_ALIAS: &str = "counted";
+pub const COUNTED_GROUP_COLUMN_ALIAS: &str = "one";
+pub const LIMITED_CTE_ALIAS: &str = "limited";
+pub const RANKED_CTE_ALIAS: &str = "ranked";
+pub const ROW_NUMBER_COLUMN_ALIAS: &str = "rn";
+pub const UNNEST_ALIAS: &str = "unnest";
+pub const UNNEST_VALUE_COLUMN_ALIAS: &str = "value";
+pub const UNNEST_ORDINALITY_COLUMN_ALIAS: &str = "ordinality";
 
 /// We may eventually make this configurable
 pub const INDENT_SPACER: &str = "  ";
@@ -10,3 +24,5 @@ pub const VAR_NOW: &str = "now";
 pub const VAR_TRUE: &str = "true";
 pub const VAR_FALSE: &str = "false";
 pub const VAR_NULL: &str = "null";
+/// Only available within row-level security policies
+pub const VAR_CURRENT_USER: &str = "current_user";
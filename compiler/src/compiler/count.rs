@@ -0,0 +1,9 @@
+use crate::Options;
+
+use super::compiler::Compiler;
+
+/// Compile a query into one which counts the rows it would return, for paginating its results.
+/// See `Compiler::compile_count`.
+pub fn compile_count(source: &str, schema_json: &str, options: Options) -> Result<String, String> {
+    Compiler::new(schema_json, options)?.compile_count(source)
+}
@@ -0,0 +1,62 @@
+use crate::sql::tree::Select;
+
+/// Join each CTE whose query is no more complex than `max_complexity` as a subquery in its place,
+/// both within the select and within the queries nested in it. Nested CTEs are inlined first, so
+/// that a CTE whose nested CTEs were all inlined may be inlined too. A CTE which keeps any nested
+/// CTEs stays in the WITH clause, since not every database accepts a WITH clause within a
+/// subquery.
+pub fn inline_small_ctes(select: &mut Select, max_complexity: usize) {
+    for cte in &mut select.ctes {
+        inline_small_ctes(&mut cte.select, max_complexity);
+    }
+    for join in &mut select.joins {
+        let subqueries = join.subquery.iter_mut().chain(&mut join.lateral_subquery);
+        for subquery in subqueries {
+            inline_small_ctes(subquery, max_complexity);
+        }
+    }
+    if let Some(base_subquery) = &mut select.base_subquery {
+        inline_small_ctes(base_subquery, max_complexity);
+    }
+    let mut kept = Vec::new();
+    for cte in std::mem::take(&mut select.ctes) {
+        let is_small = cte.select.ctes.is_empty() && complexity(&cte.select) <= max_complexity;
+        // CTEs never refer to their siblings, so the join is the only reference to the CTE
+        let join = select
+            .joins
+            .iter_mut()
+            .find(|join| join.is_cte && join.table == cte.alias);
+        match join {
+            Some(join) if is_small => {
+                join.is_cte = false;
+                join.subquery = Some(Box::new(cte.select));
+            }
+            _ => kept.push(cte),
+        }
+    }
+    select.ctes = kept;
+}
+
+/// A rough measure of how much a query does: one for each column, join, unnested array, grouping
+/// or sorting expression, and for the conditions if there are any, plus the complexity of each
+/// query nested within it
+fn complexity(select: &Select) -> usize {
+    let joins = select.joins.iter().map(|join| {
+        let subqueries = join.subquery.iter().chain(&join.lateral_subquery);
+        1 + subqueries.map(|s| complexity(s)).sum::<usize>()
+    });
+    let nested = select
+        .ctes
+        .iter()
+        .map(|cte| &cte.select)
+        .chain(select.base_subquery.as_deref())
+        .map(complexity);
+    select.columns.len()
+        + select.unnests.len()
+        + select.grouping.len()
+        + select.sorting.len()
+        + select.distinct_on.len()
+        + usize::from(!select.conditions.is_empty())
+        + joins.sum::<usize>()
+        + nested.sum::<usize>()
+}
@@ -0,0 +1,38 @@
+use itertools::Itertools;
+
+use crate::sql::tree::Cte;
+
+use super::{rendering::Render, scope::Scope};
+
+/// CTE aliases are handed out in the order that paths to many are encountered, so two queries
+/// which differ only in the order of their conditions would otherwise produce different CTEs.
+/// To keep the output stable, we order the CTEs by their rendered SQL and then hand out the same
+/// set of aliases again in that order.
+///
+/// CTEs never refer to their siblings. When a CTE depends on another, the dependency is nested
+/// inside of it, so any order of the top-level CTEs preserves the dependencies between them.
+///
+/// Returns the alias that each CTE should have, in order of creation.
+pub fn plan_cte_aliases(ctes: &[Cte], scope: &mut Scope) -> Vec<String> {
+    let aliases = scope.get_cte_aliases().to_vec();
+    let rendered_ctes = aliases
+        .iter()
+        .map(|alias| {
+            ctes.iter()
+                .find(|cte| &cte.alias == alias)
+                .map(|cte| cte.select.render(scope))
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    let canonical_order = (0..aliases.len()).sorted_by_key(|&i| &rendered_ctes[i]);
+    let mut plan = aliases.clone();
+    for (index, alias) in canonical_order.zip(aliases) {
+        plan[index] = alias;
+    }
+    plan
+}
+
+/// Sort CTEs to match the order of the given aliases
+pub fn sort_ctes(ctes: &mut [Cte], aliases: &[String]) {
+    ctes.sort_by_key(|cte| aliases.iter().position(|alias| alias == &cte.alias));
+}
@@ -2,28 +2,33 @@ use querydown_parser::ast::*;
 
 use crate::{
     errors::msg,
-    schema::links::Link,
+    options::{BooleanRendering, DivisionByZero},
+    schema::{links::Link, DataType},
     sql::expr::build::*,
     sql::tree::{CtePurpose, SqlExpr},
 };
 
 use super::{
-    comparisons::convert_comparison,
-    constants::{VAR_FALSE, VAR_INFINITY, VAR_NOW, VAR_NULL, VAR_TRUE},
+    comparisons::{convert_comparison, convert_required_presence},
+    constants::{VAR_CURRENT_USER, VAR_FALSE, VAR_INFINITY, VAR_NOW, VAR_NULL, VAR_TRUE},
     functions::convert_call,
-    paths::{clarify_path, ClarifiedPathTail},
+    paths::{clarify_cross_joined_column, clarify_path, ClarifiedPathTail},
     scope::Scope,
+    type_inference::{infer_type, is_decimal},
 };
 
 /// Convert a Querydown expression to an SQL expression
 pub fn convert_expr(expr: Expr, scope: &mut Scope) -> Result<SqlExpr, String> {
     match expr {
-        Expr::Number(n) => Ok(SqlExpr::atom(n)),
-        Expr::Date(d) => Ok(SqlExpr::atom(scope.options.dialect.date(&d))),
-        Expr::Duration(d) => Ok(SqlExpr::atom(scope.options.dialect.duration(&d))),
-        Expr::String(s) => Ok(SqlExpr::atom(scope.options.dialect.quote_string(&s))),
+        Expr::Number(n, _) => Ok(convert_number(n, scope)),
+        Expr::Date(d, _) => Ok(SqlExpr::atom(scope.options.dialect.date(&d))),
+        Expr::Duration(d, _) => Ok(SqlExpr::atom(scope.options.dialect.duration(&d))),
+        Expr::String(s, _) => Ok(SqlExpr::atom(scope.options.dialect.quote_string(&s))),
+        Expr::Bytes(b, _) => Ok(SqlExpr::atom(scope.options.dialect.bytes(&b))),
+        Expr::Uuid(u, _) => Ok(SqlExpr::atom(scope.options.dialect.uuid(&u))),
         Expr::Variable(v) => convert_variable(&v, scope),
-        Expr::Path(p) => convert_path(p, scope),
+        Expr::Placeholder(placeholder) => convert_placeholder(&placeholder.name, scope),
+        Expr::Path(p, _) => convert_path(p, scope),
         Expr::ConditionSet(cs) => convert_condition_set(cs, scope),
         Expr::HasQuantity(h) => convert_has_quantity(h, scope),
         Expr::Call(c) => convert_call(c, scope),
@@ -31,10 +36,8 @@ pub fn convert_expr(expr: Expr, scope: &mut Scope) -> Result<SqlExpr, String> {
             convert_expr(*a, scope)?,
             convert_expr(*b, scope)?,
         )),
-        Expr::Quotient(a, b) => Ok(math::divide(
-            convert_expr(*a, scope)?,
-            convert_expr(*b, scope)?,
-        )),
+        Expr::Quotient(a, b) => convert_quotient(*a, *b, scope),
+        Expr::Power(a, b) => convert_power(*a, *b, scope),
         Expr::Sum(a, b) => Ok(math::add(
             convert_expr(*a, scope)?,
             convert_expr(*b, scope)?,
@@ -44,16 +47,77 @@ pub fn convert_expr(expr: Expr, scope: &mut Scope) -> Result<SqlExpr, String> {
             convert_expr(*b, scope)?,
         )),
         Expr::Comparison(c) => convert_comparison(*c, scope),
+        Expr::Window(_) => Err(msg::window_without_function()),
+    }
+}
+
+fn convert_number(n: String, scope: &Scope) -> SqlExpr {
+    let casts = &scope.options.numeric_literal_casts;
+    let type_name = if is_decimal(&n) {
+        &casts.decimal
+    } else {
+        &casts.integer
+    };
+    match type_name {
+        Some(type_name) => scope.options.dialect.cast(SqlExpr::atom(n), type_name),
+        None => SqlExpr::atom(n),
+    }
+}
+
+fn convert_quotient(a: Expr, b: Expr, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let a = convert_expr(a, scope)?;
+    let b = convert_expr(b, scope)?;
+    Ok(divide_by_policy(a, b, scope))
+}
+
+/// Divide in the way chosen by the `division_by_zero` option
+pub fn divide_by_policy(a: SqlExpr, b: SqlExpr, scope: &Scope) -> SqlExpr {
+    match &scope.options.division_by_zero {
+        DivisionByZero::Error => math::divide(a, b),
+        DivisionByZero::Null => math::divide_or_null(a, b),
+        DivisionByZero::Value(fallback) => {
+            math::divide_or_else(a, b, SqlExpr::atom(fallback.clone()))
+        }
+    }
+}
+
+fn convert_power(a: Expr, b: Expr, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let is_non_numeric =
+        |data_type: DataType| data_type != DataType::Unknown && !data_type.is_numeric();
+    if is_non_numeric(infer_type(&a, scope)) || is_non_numeric(infer_type(&b, scope)) {
+        return Err(msg::non_numeric_power_operand());
+    }
+    let a = convert_expr(a, scope)?;
+    let b = convert_expr(b, scope)?;
+    Ok(scope.options.dialect.power(a, b))
+}
+
+fn convert_placeholder(name: &str, scope: &Scope) -> Result<SqlExpr, String> {
+    let (index, parameter) = scope
+        .get_parameter(name)
+        .ok_or_else(|| msg::unknown_placeholder(name))?;
+    // Parameters are compared with expressions on the base table of the whole statement
+    parameter.get_type_name(scope.get_root())?;
+    if !scope.options.dialect.supports_placeholders() {
+        return Err(msg::placeholders_unsupported());
     }
+    Ok(SqlExpr::atom(
+        scope.options.dialect.placeholder(index, name),
+    ))
 }
 
-fn convert_variable(variable: &str, _: &Scope) -> Result<SqlExpr, String> {
+fn convert_variable(variable: &str, scope: &Scope) -> Result<SqlExpr, String> {
+    let is_integer = scope.options.boolean_rendering == BooleanRendering::Integer;
     let sql = match variable {
-        VAR_NOW => func::now(),
+        VAR_NOW => scope.options.dialect.now(),
         VAR_INFINITY => value::infinity(),
+        VAR_TRUE if is_integer => value::one(),
+        VAR_FALSE if is_integer => value::zero(),
         VAR_TRUE => value::true_(),
         VAR_FALSE => value::false_(),
         VAR_NULL => value::null(),
+        VAR_CURRENT_USER if scope.allows_session_values() => func::current_user(),
+        VAR_CURRENT_USER => return Err(msg::session_value_outside_policy()),
         // TODO handle user-defined variables from scope
         name => return Err(msg::unknown_variable(name)),
     };
@@ -61,19 +125,25 @@ fn convert_variable(variable: &str, _: &Scope) -> Result<SqlExpr, String> {
 }
 
 fn convert_path(parts: Vec<PathPart>, scope: &mut Scope) -> Result<SqlExpr, String> {
-    let prefixed_parts = scope.path_prefix.iter().cloned().chain(parts).collect();
+    let prefixed_parts: Vec<PathPart> = scope.path_prefix.iter().cloned().chain(parts).collect();
+    if let Some(cross_joined_column) = clarify_cross_joined_column(&prefixed_parts, scope) {
+        let cross_joined_column = cross_joined_column?;
+        return Ok(
+            scope.table_column_expr(&cross_joined_column.alias, &cross_joined_column.column_name)
+        );
+    }
     let clarified_path = clarify_path(prefixed_parts, scope)?;
     match (clarified_path.head, clarified_path.tail) {
         (None, None) => Ok(SqlExpr::empty()),
         (None, Some(ClarifiedPathTail::Column(column_name))) => {
-            let table_name = scope.get_base_table().name.clone();
+            let table_name = scope.alias_for_base_table()?.to_owned();
             Ok(scope.table_column_expr(&table_name, &column_name))
         }
         (Some(chain_to_one), None) => {
             let (truncated_chain_to_one_opt, last_link) = chain_to_one.with_last_link_broken_off();
             let table_name = match truncated_chain_to_one_opt {
                 Some(truncated_chain_to_one) => scope.join_chain_to_one(&truncated_chain_to_one),
-                None => scope.get_base_table().name.clone(),
+                None => scope.alias_for_base_table()?.to_owned(),
             };
             let column_reference = last_link.get_start();
             let column_name = scope.schema.get_referenced_column_name(&column_reference);
@@ -104,13 +174,37 @@ pub fn convert_condition_set(
     Ok(cmp::condition_set(conditions, &condition_set.conjunction))
 }
 
+/// Convert the conditions of the main query. Because every row must satisfy each entry of a
+/// top-level AND condition set, "has some" entries there can filter via an inner join.
+pub fn convert_top_level_condition_set(
+    condition_set: ConditionSet,
+    scope: &mut Scope,
+) -> Result<SqlExpr, String> {
+    if condition_set.conjunction != Conjunction::And {
+        return convert_condition_set(condition_set, scope);
+    }
+    let conditions = condition_set
+        .entries
+        .into_iter()
+        .map(|expr| match expr {
+            Expr::HasQuantity(HasQuantity {
+                quantity: Quantity::AtLeastOne,
+                path_parts,
+                ..
+            }) => convert_required_presence(path_parts, scope),
+            expr => convert_expr(expr, scope),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(cmp::condition_set(conditions, &Conjunction::And))
+}
+
 fn convert_has_quantity(has_quantity: HasQuantity, scope: &mut Scope) -> Result<SqlExpr, String> {
     let operator = match has_quantity.quantity {
         Quantity::AtLeastOne => Operator::Gt,
         Quantity::Zero => Operator::Eq,
     };
     let comparison = Comparison {
-        left: ComparisonSide::Expr(Expr::Path(has_quantity.path_parts)),
+        left: ComparisonSide::Expr(Expr::path(has_quantity.path_parts)),
         operator,
         right: ComparisonSide::Expr(Expr::zero()),
     };
@@ -1,19 +1,39 @@
-use std::collections::HashMap;
-
 use itertools::Itertools;
-use querydown_parser::ast::{Call, Expr, FunctionDimension};
+use querydown_parser::ast::{
+    Call, CallSyntax, Conjunction, Duration, Expr, FrameExclusion, FunctionDimension,
+    SortDirection, SortSpec, Span, Window,
+};
 
 use crate::{
     compiler::{
-        expr::convert_expr,
-        paths::{clarify_path, AggregateExprTemplate, ClarifiedPathTail},
+        compositions::{
+            Arity, Arity::*, Composition, CompositionMap, Func, ReturnType, ReturnType::*,
+        },
+        constants::{
+            UNNEST_ORDINALITY_COLUMN_ALIAS, UNNEST_VALUE_COLUMN_ALIAS, VAR_FALSE, VAR_NULL,
+            VAR_TRUE,
+        },
+        expr::{convert_expr, divide_by_policy},
+        paths::{
+            clarify_path, is_path_to_many, AggregateExprTemplate, ClarifiedPathTail, RowLimit,
+        },
+        result_columns::sorting::SortingStack,
         scope::Scope,
+        type_inference::infer_type,
     },
     errors::msg::{self, unknown_aggregate_function, unknown_scalar_function},
-    sql::expr::build::{agg::*, cond::*, date_time::*, func::*, math::*, strings::*},
-    sql::tree::{CtePurpose, SqlExpr},
+    schema::DataType::*,
+    sql::expr::build::{
+        agg::*, array::*, cmp, cond::*, date_time::*, func::*, math::*, strings::*, text_search::*,
+        value::cast, window,
+    },
+    sql::tree::{CtePurpose, SortEntry, SqlExpr},
+    sql::{BitwiseOperator, DateDiffUnit, HashAlgorithm, JsonPathSegment, RegExReplaceFlags},
 };
 
+/// The timestamp at which bins start when `bin` isn't given an origin
+const DEFAULT_BIN_ORIGIN: &str = "2001-01-01 00:00:00";
+
 pub fn convert_call(call: Call, scope: &mut Scope) -> Result<SqlExpr, String> {
     match call.dimension {
         FunctionDimension::Scalar => convert_scalar_call(&call.name, call.args, scope),
@@ -22,21 +42,51 @@ pub fn convert_call(call: Call, scope: &mut Scope) -> Result<SqlExpr, String> {
 }
 
 fn convert_scalar_call(name: &str, e: Vec<Expr>, s: &mut Scope) -> Result<SqlExpr, String> {
-    let func = s
+    let composition = s
         .get_scalar_function(name)
-        .ok_or_else(|| unknown_scalar_function(name))?;
-    func(e, s)
+        .ok_or_else(|| unknown_scalar_function(name))?
+        .clone();
+    let is_custom = s.options.custom_functions.contains_key(name);
+    if !is_custom
+        && !s
+            .options
+            .dialect
+            .supports_function(FunctionDimension::Scalar, name)
+    {
+        return Err(msg::function_unsupported_by_dialect(name));
+    }
+    composition.convert(name, e, s)
 }
 
 fn convert_aggregate_call(name: &str, e: Vec<Expr>, s: &mut Scope) -> Result<SqlExpr, String> {
-    let func = s
+    if !s
+        .options
+        .dialect
+        .supports_function(FunctionDimension::Aggregate, name)
+    {
+        return Err(msg::function_unsupported_by_dialect(name));
+    }
+    if let (Some(Expr::Window(_)), Some(agg_wrapper)) = (e.first(), get_window_aggregate(name)) {
+        return window_aggregate(e, s, agg_wrapper);
+    }
+    let composition = s
         .get_aggregate_function(name)
-        .ok_or_else(|| unknown_aggregate_function(name))?;
-    func(e, s)
+        .ok_or_else(|| unknown_aggregate_function(name))?
+        .clone();
+    composition.convert(name, e, s)
 }
 
-pub type FuncMap = HashMap<String, Func>;
-pub type Func = fn(Vec<Expr>, &mut Scope) -> Result<SqlExpr, String>;
+fn build_composition_map<const N: usize>(
+    templates: [(&str, Arity, ReturnType, Func); N],
+) -> CompositionMap {
+    templates
+        .into_iter()
+        .map(|(name, arity, return_type, func)| {
+            let composition = Composition::from_func(arity, return_type, func);
+            (name.to_string(), composition)
+        })
+        .collect()
+}
 
 /// Get the first item out of an Iterator, ensuring it has no more
 fn iter_one<T>(items: impl IntoIterator<Item = T>) -> Option<T> {
@@ -89,38 +139,595 @@ fn args_2(
     Ok(f(convert_expr(a, scope)?, convert_expr(b, scope)?))
 }
 
-pub fn get_standard_scalar_functions() -> FuncMap {
+/// Used for a scalar function that combines the current timestamp with its one argument, e.g.
+/// `now - a`
+fn args_from_now(
+    args: Vec<Expr>,
+    scope: &mut Scope,
+    f: fn(SqlExpr, SqlExpr) -> SqlExpr,
+) -> Result<SqlExpr, String> {
+    let arg0 = iter_one(args).ok_or_else(msg::expected_one_arg)?;
+    let a = convert_expr(arg0, scope)?;
+    Ok(f(scope.options.dialect.now(), a))
+}
+
+fn args_mod(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let (a, b) = iter_two(args).ok_or_else(msg::expected_two_args)?;
+    let a = convert_expr(a, scope)?;
+    let b = convert_expr(b, scope)?;
+    Ok(scope.options.dialect.modulo(a, b))
+}
+
+/// Used for a scalar function that compares a value to a LIKE pattern built from a string literal
+///
+/// * `make_pattern` - Adds wildcards to the escaped string literal
+fn args_like(
+    args: Vec<Expr>,
+    scope: &mut Scope,
+    make_pattern: fn(String) -> String,
+    is_case_sensitive: bool,
+) -> Result<SqlExpr, String> {
+    let (a, b) = iter_two(args).ok_or_else(msg::expected_two_args)?;
+    let Expr::String(s, _) = b else {
+        return Err(msg::expected_string_literal());
+    };
+    let escape_char = scope.options.checked_like_escape_char()?;
+    let mut value = convert_expr(a, scope)?;
+    let mut pattern = make_pattern(escape_like(&s, escape_char));
+    if !is_case_sensitive {
+        value = lower(value);
+        pattern = pattern.to_lowercase();
+    }
+    let dialect = &scope.options.dialect;
+    let pattern = SqlExpr::atom(dialect.quote_string(&pattern));
+    let escape = dialect.quote_string(&escape_char.to_string());
+    Ok(cmp::like_escaped(value, pattern, &escape))
+}
+
+/// Used for a scalar function that extracts a value from JSON. The first argument is the JSON value
+/// and the remaining arguments are literals which form the path to extract.
+fn args_json(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let mut args = args.into_iter();
+    let json = convert_expr(args.next().ok_or_else(msg::expected_one_arg)?, scope)?;
+    let path = args
+        .map(|arg| match arg {
+            Expr::String(key, _) => Some(JsonPathSegment::Key(key)),
+            Expr::Number(n, _) => n.parse::<u64>().ok().map(JsonPathSegment::Index),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(msg::json_path_segment_not_literal)?;
+    Ok(scope.options.dialect.json_extract(json, &path))
+}
+
+/// Used for a scalar function that operates on an array. The first argument is the array and the
+/// remaining arguments are integer literals, e.g. the bounds of a slice.
+fn args_array<const N: usize>(
+    args: Vec<Expr>,
+    scope: &mut Scope,
+    f: fn(SqlExpr, [u64; N]) -> SqlExpr,
+) -> Result<SqlExpr, String> {
+    if !scope.options.dialect.supports_arrays() {
+        return Err(msg::arrays_unsupported());
+    }
+    let mut args = args.into_iter();
+    let array = convert_expr(args.next().ok_or_else(msg::expected_one_arg)?, scope)?;
+    let indices: [u64; N] = args
+        .map(|arg| match arg {
+            Expr::Number(n, _) => n.parse::<u64>().ok(),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .and_then(|indices| indices.try_into().ok())
+        .ok_or_else(msg::array_index_not_literal)?;
+    Ok(f(array, indices))
+}
+
+/// Used for the scalar functions that expand an array into one row per element, giving either the
+/// element or its position within the array
+fn args_unnest(args: Vec<Expr>, scope: &mut Scope, is_ordinality: bool) -> Result<SqlExpr, String> {
+    let arg0 = iter_one(args).ok_or_else(msg::expected_one_arg)?;
+    let array = convert_expr(arg0, scope)?;
+    let alias = scope.unnest(array, is_ordinality)?;
+    let column = if is_ordinality {
+        UNNEST_ORDINALITY_COLUMN_ALIAS
+    } else {
+        UNNEST_VALUE_COLUMN_ALIAS
+    };
+    Ok(scope.table_column_expr(&alias, column))
+}
+
+/// Used for the scalar function that rounds a timestamp down to the start of its bin. The second
+/// argument is the width of each bin. The optional third argument is the timestamp at which some
+/// bin starts, defaulting to midnight so that bins line up with the clock.
+fn args_bin(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    if !scope.options.dialect.supports_date_bin() {
+        return Err(msg::date_bin_unsupported());
+    }
+    let mut args = args.into_iter();
+    let timestamp = convert_expr(args.next().ok_or_else(msg::expected_two_args)?, scope)?;
+    let Some(width @ Expr::Duration(..)) = args.next() else {
+        return Err(msg::bin_width_not_duration_literal());
+    };
+    let width = convert_expr(width, scope)?;
+    let origin = match args.next() {
+        Some(origin) => convert_expr(origin, scope)?,
+        // An untyped literal lets the database match the type of the timestamp
+        None => SqlExpr::atom(scope.options.dialect.quote_string(DEFAULT_BIN_ORIGIN)),
+    };
+    Ok(scope.options.dialect.date_bin(width, timestamp, origin))
+}
+
+/// Used for the scalar function that rounds a timestamp to the nearest start of a bin, taking the
+/// same arguments as `bin`. The timestamp moves forward by half a bin before being rounded down,
+/// so one exactly halfway between two bins rounds up to the later one. Months and years vary in
+/// length, so the width can't include them.
+fn args_round_time(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    if !scope.options.dialect.supports_date_bin() {
+        return Err(msg::round_time_unsupported());
+    }
+    let mut args = args.into_iter();
+    let timestamp = convert_expr(args.next().ok_or_else(msg::expected_two_args)?, scope)?;
+    let Some(Expr::Duration(width, _)) = args.next() else {
+        return Err(msg::bin_width_not_duration_literal());
+    };
+    if width.years != 0.0 || width.months != 0.0 {
+        return Err(msg::round_time_width_with_months());
+    }
+    let half_width = Duration {
+        weeks: width.weeks / 2.0,
+        days: width.days / 2.0,
+        hours: width.hours / 2.0,
+        minutes: width.minutes / 2.0,
+        seconds: width.seconds / 2.0,
+        ..Duration::default()
+    };
+    let origin = match args.next() {
+        Some(origin) => convert_expr(origin, scope)?,
+        None => SqlExpr::atom(scope.options.dialect.quote_string(DEFAULT_BIN_ORIGIN)),
+    };
+    let dialect = &scope.options.dialect;
+    let shifted = add(timestamp, SqlExpr::atom(dialect.duration(&half_width)));
+    let width = SqlExpr::atom(dialect.duration(&width));
+    Ok(dialect.date_bin(width, shifted, origin))
+}
+
+/// Used for the scalar function that assigns a number to one of several equal-width buckets. The
+/// remaining arguments are numeric literals giving the lower and upper bounds of the range to
+/// divide and the number of buckets.
+fn args_width_bucket(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    if !scope.options.dialect.supports_width_bucket() {
+        return Err(msg::width_bucket_unsupported());
+    }
+    let mut args = args.into_iter();
+    let value = convert_expr(args.next().ok_or_else(msg::expected_one_arg)?, scope)?;
+    let literals = args
+        .map(|arg| match arg {
+            Expr::Number(n, _) => n.parse::<f64>().ok().map(|v| (n, v)),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>();
+    let Some([(low, low_value), (high, high_value), (count, _)]) =
+        literals.and_then(|literals| <[_; 3]>::try_from(literals).ok())
+    else {
+        return Err(msg::width_bucket_args_not_literal());
+    };
+    if low_value == high_value {
+        return Err(msg::width_bucket_empty_range());
+    }
+    if !count.parse::<u64>().is_ok_and(|c| c > 0) {
+        return Err(msg::width_bucket_count_not_positive());
+    }
+    let [low, high, count] = [low, high, count].map(SqlExpr::atom);
+    Ok(width_bucket(value, low, high, count))
+}
+
+/// Used for the scalar function that keeps a value within a lower and an upper bound. When both
+/// bounds are numeric literals, they must be in order.
+fn args_clamp(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let (value, low, high) = args
+        .into_iter()
+        .collect_tuple()
+        .ok_or_else(msg::expected_three_args)?;
+    if let (Expr::Number(low, _), Expr::Number(high, _)) = (&low, &high) {
+        if let (Ok(low), Ok(high)) = (low.parse::<f64>(), high.parse::<f64>()) {
+            if low > high {
+                return Err(msg::clamp_bounds_reversed());
+            }
+        }
+    }
+    Ok(clamp(
+        convert_expr(value, scope)?,
+        convert_expr(low, scope)?,
+        convert_expr(high, scope)?,
+    ))
+}
+
+/// Used for the scalar function that maps values to others via a simple CASE expression. The
+/// arguments after the first alternate between keys and the values they map to, optionally
+/// followed by a default value for keys which aren't listed. All of them must be literals.
+fn args_map(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let mut args = args.into_iter();
+    let value = convert_expr(args.next().ok_or_else(msg::expected_one_arg)?, scope)?;
+    let mut literals = args
+        .map(|arg| match arg {
+            Expr::Number(..)
+            | Expr::Date(..)
+            | Expr::Duration(..)
+            | Expr::String(..)
+            | Expr::Bytes(..)
+            | Expr::Uuid(..) => convert_expr(arg, scope),
+            Expr::Variable(v) if [VAR_TRUE, VAR_FALSE, VAR_NULL].contains(&v.as_str()) => {
+                convert_expr(Expr::Variable(v), scope)
+            }
+            _ => Err(msg::map_args_not_literal()),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let default = (literals.len() % 2 == 1).then(|| literals.pop()).flatten();
+    let mut pairs = Vec::<(SqlExpr, SqlExpr)>::new();
+    for (from, to) in literals.into_iter().tuples() {
+        if pairs.iter().any(|(f, _)| f.to_string() == from.to_string()) {
+            return Err(msg::duplicate_map_key(&from.to_string()));
+        }
+        pairs.push((from, to));
+    }
+    Ok(case(value, pairs, default))
+}
+
+/// Used for the scalar function that assigns values to labeled buckets via a searched CASE
+/// expression. The second argument lists the ascending numbers at which each bucket after the
+/// first starts, and the third lists the labels of the buckets, of which there's one more than the
+/// thresholds. Null values belong to no bucket.
+fn args_bucket(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let mut args = args.into_iter();
+    let value = convert_expr(args.next().ok_or_else(msg::expected_one_arg)?, scope)?;
+    let as_list = |arg: Option<Expr>| match arg {
+        Some(Expr::ConditionSet(set)) if set.conjunction == Conjunction::Or => Ok(set.entries),
+        _ => Err(msg::bucket_args_not_lists()),
+    };
+    let thresholds = as_list(args.next())?;
+    let labels = as_list(args.next())?;
+    let mut numbers = Vec::<f64>::new();
+    for threshold in &thresholds {
+        let Expr::Number(n, _) = threshold else {
+            return Err(msg::bucket_threshold_not_number());
+        };
+        let n = n
+            .parse::<f64>()
+            .map_err(|_| msg::bucket_threshold_not_number())?;
+        if numbers.last().is_some_and(|&previous| previous >= n) {
+            return Err(msg::unsorted_bucket_thresholds());
+        }
+        numbers.push(n);
+    }
+    if thresholds.is_empty() || labels.len() != thresholds.len() + 1 {
+        return Err(msg::bucket_label_count(thresholds.len() + 1));
+    }
+    let thresholds = thresholds
+        .into_iter()
+        .map(|t| convert_expr(t, scope))
+        .collect::<Result<Vec<_>, _>>()?;
+    let labels = labels
+        .into_iter()
+        .map(|label| match label {
+            Expr::Number(..) | Expr::Date(..) | Expr::Duration(..) | Expr::String(..) => {
+                convert_expr(label, scope)
+            }
+            Expr::Variable(v) if [VAR_TRUE, VAR_FALSE, VAR_NULL].contains(&v.as_str()) => {
+                convert_expr(Expr::Variable(v), scope)
+            }
+            _ => Err(msg::bucket_label_not_literal()),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let last_threshold = thresholds.last().unwrap().clone();
+    let mut labels = labels.into_iter();
+    let mut pairs = thresholds
+        .into_iter()
+        .zip(labels.by_ref())
+        .map(|(threshold, label)| (cmp::lt(value.clone(), threshold), label))
+        .collect::<Vec<_>>();
+    // The last bucket also has a condition so that null values fall through to NULL
+    pairs.push((cmp::gte(value, last_threshold), labels.next().unwrap()));
+    Ok(searched_case(pairs, None))
+}
+
+/// Used for the scalar functions that parse text as a date or timestamp. The second argument is a
+/// string literal giving the format of the text, e.g. `"YYYY-MM-DD"`.
+fn args_to_temporal(
+    args: Vec<Expr>,
+    scope: &mut Scope,
+    with_time: bool,
+) -> Result<SqlExpr, String> {
+    let (a, b) = iter_two(args).ok_or_else(msg::expected_two_args)?;
+    let Expr::String(format, _) = b else {
+        return Err(msg::expected_string_literal());
+    };
+    let value = convert_expr(a, scope)?;
+    let dialect = &scope.options.dialect;
+    let parsed = if with_time {
+        dialect.to_timestamp(value, &format)
+    } else {
+        dialect.to_date(value, &format)
+    };
+    parsed.ok_or_else(|| msg::date_format_unsupported(&format))
+}
+
+/// Used for the scalar function that applies a collation to a value. The second argument is a
+/// string literal naming the collation.
+fn args_collate(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let (a, b) = iter_two(args).ok_or_else(msg::expected_two_args)?;
+    let Expr::String(collation, _) = b else {
+        return Err(msg::expected_string_literal());
+    };
+    let value = convert_expr(a, scope)?;
+    Ok(scope.options.dialect.collate(value, &collation))
+}
+
+/// Used for the scalar function that converts a timestamp to another time zone. The second
+/// argument is a string literal naming the zone.
+fn args_at_time_zone(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    if !scope.options.dialect.supports_at_time_zone() {
+        return Err(msg::at_time_zone_unsupported());
+    }
+    let (a, b) = iter_two(args).ok_or_else(msg::expected_two_args)?;
+    let Expr::String(zone, _) = b else {
+        return Err(msg::expected_string_literal());
+    };
+    let value = convert_expr(a, scope)?;
+    Ok(scope.options.dialect.at_time_zone(value, &zone))
+}
+
+/// Used for the scalar function that performs a full-text search. The second argument is the
+/// plain text to search for, and the optional third argument is a string literal naming the text
+/// search configuration, e.g. `english`. Without one, the database's default configuration is used.
+fn args_search(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    if !scope.options.dialect.supports_full_text_search() {
+        return Err(msg::full_text_search_unsupported());
+    }
+    let mut args = args.into_iter();
+    let value = convert_expr(args.next().ok_or_else(msg::expected_two_args)?, scope)?;
+    let query = convert_expr(args.next().ok_or_else(msg::expected_two_args)?, scope)?;
+    let config = match args.next() {
+        Some(Expr::String(config, _)) => {
+            Some(SqlExpr::atom(scope.options.dialect.quote_string(&config)))
+        }
+        Some(_) => return Err(msg::expected_string_literal()),
+        None => None,
+    };
+    let vector = to_tsvector(config.clone(), value);
+    Ok(text_search_match(vector, plainto_tsquery(config, query)))
+}
+
+/// Used for the scalar function that measures the difference between two dates or timestamps. The
+/// second argument is subtracted from the first, and the third is a string literal naming the unit.
+fn args_date_diff(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let mut args = args.into_iter();
+    let (Some(a), Some(b), Some(unit)) = (args.next(), args.next(), args.next()) else {
+        return Err(msg::expected_three_args());
+    };
+    let Expr::String(unit, _) = unit else {
+        return Err(msg::expected_string_literal());
+    };
+    let unit = DateDiffUnit::from_name(&unit).ok_or_else(|| msg::unknown_date_diff_unit(&unit))?;
+    let a = convert_expr(a, scope)?;
+    let b = convert_expr(b, scope)?;
+    Ok(scope.options.dialect.date_diff(a, b, unit))
+}
+
+/// Used for the scalar function that converts a date or timestamp to Unix time
+fn args_epoch(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let arg = iter_one(args).ok_or_else(msg::expected_one_arg)?;
+    let is_temporal = |data_type| matches!(data_type, Date | Timestamp | TimestampTz | Unknown);
+    if !is_temporal(infer_type(&arg, scope)) {
+        return Err(msg::non_temporal_epoch_operand());
+    }
+    let a = convert_expr(arg, scope)?;
+    Ok(scope.options.dialect.epoch(a))
+}
+
+/// Used for the scalar function that turns an empty string into null
+fn args_empty_to_null(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let arg = iter_one(args).ok_or_else(msg::expected_one_arg)?;
+    if !matches!(infer_type(&arg, scope), Text | Unknown) {
+        return Err(msg::non_text_empty_to_null_operand());
+    }
+    let a = convert_expr(arg, scope)?;
+    let empty = SqlExpr::atom(scope.options.dialect.quote_string(""));
+    Ok(nullif(a, empty))
+}
+
+/// Used for the scalar functions that hash text or binary values, e.g. to anonymize them
+fn args_hash(
+    args: Vec<Expr>,
+    scope: &mut Scope,
+    algorithm: HashAlgorithm,
+) -> Result<SqlExpr, String> {
+    let arg = iter_one(args).ok_or_else(msg::expected_one_arg)?;
+    if !matches!(infer_type(&arg, scope), Text | Binary | Unknown) {
+        return Err(msg::non_text_hash_operand(algorithm.name()));
+    }
+    let a = convert_expr(arg, scope)?;
+    scope
+        .options
+        .dialect
+        .hash(a, algorithm)
+        .ok_or_else(|| msg::hash_unsupported(algorithm.name()))
+}
+
+/// Used for the scalar function that replaces null with zero, e.g. for an aggregate over related
+/// records when there are none
+fn args_zero_if_null(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let arg = iter_one(args).ok_or_else(msg::expected_one_arg)?;
+    let data_type = infer_type(&arg, scope);
+    if data_type != Unknown && !data_type.is_numeric() {
+        return Err(msg::non_numeric_zero_if_null_operand());
+    }
+    let a = convert_expr(arg, scope)?;
+    Ok(coalesce(vec![a, SqlExpr::atom("0".to_string())]))
+}
+
+/// Used for the scalar function that reads a configuration parameter of the database session. The
+/// piped value is a string literal naming the parameter.
+fn args_setting(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    if !scope.allows_session_values() {
+        return Err(msg::session_value_outside_policy());
+    }
+    let Some(Expr::String(name, _)) = iter_one(args) else {
+        return Err(msg::expected_string_literal());
+    };
+    let name = SqlExpr::atom(scope.options.dialect.quote_string(&name));
+    Ok(current_setting(name))
+}
+
+fn args_round(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let mut args = args.into_iter();
+    let value = convert_expr(args.next().ok_or_else(msg::expected_one_arg)?, scope)?;
+    let places = args.next().map(|p| convert_expr(p, scope)).transpose()?;
+    Ok(scope.options.dialect.round(value, places))
+}
+
+/// Text is cast to the dialect's own name for the type when it has one, since e.g. SQL Server
+/// can't convert numbers to its legacy `TEXT` type
+fn args_to_text(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let arg0 = iter_one(args).ok_or_else(msg::expected_one_arg)?;
+    let a = convert_expr(arg0, scope)?;
+    let dialect = &scope.options.dialect;
+    let type_name = dialect
+        .type_name(&Text)
+        .unwrap_or_else(|| "TEXT".to_owned());
+    Ok(dialect.cast(a, &type_name))
+}
+
+/// Used for the scalar function that replaces the matches of a regular expression. The pattern,
+/// the replacement, and the optional flags are string literals. The flags are `g` to replace every
+/// match instead of only the first one and `i` to ignore case.
+fn args_regexp_replace(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let mut args = args.into_iter();
+    let value = convert_expr(args.next().ok_or_else(msg::expected_one_arg)?, scope)?;
+    let literals = args
+        .map(|arg| match arg {
+            Expr::String(s, _) => Ok(s),
+            _ => Err(msg::expected_string_literal()),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let (pattern, replacement, flags) = match literals.as_slice() {
+        [pattern, replacement] => (pattern, replacement, ""),
+        [pattern, replacement, flags] => (pattern, replacement, flags.as_str()),
+        _ => return Err(msg::expected_string_literal()),
+    };
+    if let Some(flag) = flags.chars().find(|c| !"gi".contains(*c)) {
+        return Err(msg::invalid_regexp_replace_flag(flag));
+    }
+    let flags = RegExReplaceFlags {
+        is_case_sensitive: !flags.contains('i'),
+        is_global: flags.contains('g'),
+    };
+    scope
+        .options
+        .dialect
+        .regexp_replace(value, pattern, replacement, &flags)
+        .ok_or_else(msg::regexp_replace_unsupported)
+}
+
+/// Used for the scalar function that rounds toward zero. The optional second argument is the
+/// number of decimal places to keep.
+fn args_trunc(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let mut args = args.into_iter();
+    let value = convert_expr(args.next().ok_or_else(msg::expected_one_arg)?, scope)?;
+    let places = args.next().map(|p| convert_expr(p, scope)).transpose()?;
+    if places.is_some() && !scope.options.dialect.supports_trunc_places() {
+        return Err(msg::trunc_places_unsupported());
+    }
+    Ok(scope.options.dialect.trunc(value, places))
+}
+
+/// Used for a scalar function that applies a bitwise operator to two integers
+fn args_bitwise(
+    args: Vec<Expr>,
+    scope: &mut Scope,
+    operator: BitwiseOperator,
+) -> Result<SqlExpr, String> {
+    let (a, b) = iter_two(args).ok_or_else(msg::expected_two_args)?;
+    let is_non_integer = |data_type| !matches!(data_type, Integer | Unknown);
+    if is_non_integer(infer_type(&a, scope)) || is_non_integer(infer_type(&b, scope)) {
+        return Err(msg::non_integer_bitwise_operand());
+    }
+    let a = convert_expr(a, scope)?;
+    let b = convert_expr(b, scope)?;
+    Ok(scope.options.dialect.bitwise(operator, a, b))
+}
+
+pub fn get_standard_scalar_functions() -> CompositionMap {
     #[rustfmt::skip]
-    let templates: [(&str, Func); 24] = [
-        ("abs",         |e, s| args_1(e, s, abs)),
-        ("age",         |e, s| args_1(e, s, |a| subtract(now(), a))),
-        ("ago",         |e, s| args_1(e, s, |a| subtract(now(), a))),
-        ("away",        |e, s| args_1(e, s, |a| add(now(), a))),
-        ("ceil",        |e, s| args_1(e, s, ceil)),
-        ("days",        |e, s| args_1(e, s, days)),
-        ("divide",      |e, s| args_2(e, s, divide)),
-        ("else",        |e, s| args_1(e, s, coalesce)),
-        ("floor",       |e, s| args_1(e, s, floor)),
-        ("hours",       |e, s| args_1(e, s, hours)),
-        ("keep_above",  |e, s| args_v(e, s, greatest)),
-        ("keep_below",  |e, s| args_v(e, s, least)),
-        ("length",      |e, s| args_1(e, s, char_length)),
-        ("lowercase",   |e, s| args_1(e, s, lower)),
-        ("max",         |e, s| args_v(e, s, greatest)),
-        ("min",         |e, s| args_v(e, s, least)),
-        ("minus",       |e, s| args_2(e, s, subtract)),
-        ("minutes",     |e, s| args_1(e, s, minutes)),
-        ("mod",         |e, s| args_2(e, s, modulo)),
-        ("not",         |e, s| args_1(e, s, not)),
-        ("plus",        |e, s| args_2(e, s, add)),
-        ("seconds",     |e, s| args_1(e, s, seconds)),
-        ("times",       |e, s| args_2(e, s, multiply)),
-        ("uppercase",   |e, s| args_1(e, s, upper)),
+    let templates: [(&str, Arity, ReturnType, Func); 66] = [
+        ("abs",          Exactly(1),    FirstArg,         |e, s| args_1(e, s, abs)),
+        ("age",          Exactly(1),    Fixed(Interval),  |e, s| args_from_now(e, s, subtract)),
+        ("ago",          Exactly(1),    Fixed(Timestamp), |e, s| args_from_now(e, s, subtract)),
+        ("array_length", Exactly(1),    Fixed(Integer),   |e, s| args_array(e, s, |a, []| array_length(a))),
+        ("at_time_zone", Exactly(2),    TimeZoneFlipped,  args_at_time_zone),
+        ("away",         Exactly(1),    Fixed(Timestamp), |e, s| args_from_now(e, s, add)),
+        ("bin",          Between(2, 3), Fixed(Timestamp), args_bin),
+        ("bit_and",      Exactly(2),    Fixed(Integer),   |e, s| args_bitwise(e, s, BitwiseOperator::And)),
+        ("bit_or",       Exactly(2),    Fixed(Integer),   |e, s| args_bitwise(e, s, BitwiseOperator::Or)),
+        ("bit_xor",      Exactly(2),    Fixed(Integer),   |e, s| args_bitwise(e, s, BitwiseOperator::Xor)),
+        ("bucket",       Exactly(3),    BucketLabels,     args_bucket),
+        ("ceil",         Exactly(1),    FirstArg,         |e, s| args_1(e, s, ceil)),
+        ("clamp",        Exactly(3),    FirstArg,         args_clamp),
+        ("collate",      Exactly(2),    FirstArg,         args_collate),
+        ("contains",     Exactly(2),    Fixed(Boolean),   |e, s| args_like(e, s, |p| format!("%{p}%"), true)),
+        ("date_diff",    Exactly(3),    Fixed(Numeric),   args_date_diff),
+        ("days",         Exactly(1),    Fixed(Numeric),   |e, s| args_1(e, s, days)),
+        ("divide",       Exactly(2),    Product,          |e, s| args_2(e, s, divide)),
+        ("else",         AtLeast(2),    FirstArg,         |e, s| args_v(e, s, coalesce)),
+        ("empty_to_null", Exactly(1),   FirstArg,         args_empty_to_null),
+        ("ends_with",    Exactly(2),    Fixed(Boolean),   |e, s| args_like(e, s, |p| format!("%{p}"), true)),
+        ("epoch",        Exactly(1),    Fixed(Numeric),   args_epoch),
+        ("floor",        Exactly(1),    FirstArg,         |e, s| args_1(e, s, floor)),
+        ("hours",        Exactly(1),    Fixed(Numeric),   |e, s| args_1(e, s, hours)),
+        ("icontains",    Exactly(2),    Fixed(Boolean),   |e, s| args_like(e, s, |p| format!("%{p}%"), false)),
+        ("iends_with",   Exactly(2),    Fixed(Boolean),   |e, s| args_like(e, s, |p| format!("%{p}"), false)),
+        ("istarts_with", Exactly(2),    Fixed(Boolean),   |e, s| args_like(e, s, |p| format!("{p}%"), false)),
+        ("json_extract", AtLeast(2),    Fixed(Text),      args_json),
+        ("keep_above",   AtLeast(1),    FirstArg,         |e, s| args_v(e, s, greatest)),
+        ("keep_below",   AtLeast(1),    FirstArg,         |e, s| args_v(e, s, least)),
+        ("length",       Exactly(1),    Fixed(Integer),   |e, s| args_1(e, s, char_length)),
+        ("lowercase",    Exactly(1),    Fixed(Text),      |e, s| args_1(e, s, lower)),
+        ("map",          AtLeast(3),    MappedValues,     args_map),
+        ("max",          AtLeast(1),    FirstArg,         |e, s| args_v(e, s, greatest)),
+        ("md5",          Exactly(1),    Fixed(Text),      |e, s| args_hash(e, s, HashAlgorithm::Md5)),
+        ("min",          AtLeast(1),    FirstArg,         |e, s| args_v(e, s, least)),
+        ("minus",        Exactly(2),    Difference,       |e, s| args_2(e, s, subtract)),
+        ("minutes",      Exactly(1),    Fixed(Numeric),   |e, s| args_1(e, s, minutes)),
+        ("mod",          Exactly(2),    FirstArg,         args_mod),
+        ("not",          Exactly(1),    Fixed(Boolean),   |e, s| args_1(e, s, not)),
+        ("plus",         Exactly(2),    Sum,              |e, s| args_2(e, s, add)),
+        ("regexp_replace", Between(3, 4), Fixed(Text), args_regexp_replace),
+        ("round",        Between(1, 2), FirstArg,         args_round),
+        ("round_time",   Between(2, 3), Fixed(Timestamp), args_round_time),
+        ("search",       Between(2, 3), Fixed(Boolean),   args_search),
+        ("seconds",      Exactly(1),    Fixed(Numeric),   |e, s| args_1(e, s, seconds)),
+        ("setting",      Exactly(1),    Fixed(Text),      args_setting),
+        ("sha256",       Exactly(1),    Fixed(Text),      |e, s| args_hash(e, s, HashAlgorithm::Sha256)),
+        ("shift_left",   Exactly(2),    Fixed(Integer),   |e, s| args_bitwise(e, s, BitwiseOperator::ShiftLeft)),
+        ("shift_right",  Exactly(2),    Fixed(Integer),   |e, s| args_bitwise(e, s, BitwiseOperator::ShiftRight)),
+        ("sign",         Exactly(1),    FirstArg,         |e, s| args_1(e, s, sign)),
+        ("slice",        Exactly(3),    FirstArg,         |e, s| args_array(e, s, |a, [l, u]| slice(a, l, u))),
+        ("starts_with",  Exactly(2),    Fixed(Boolean),   |e, s| args_like(e, s, |p| format!("{p}%"), true)),
+        ("times",        Exactly(2),    Product,          |e, s| args_2(e, s, multiply)),
+        ("to_date",      Exactly(2),    Fixed(Date),      |e, s| args_to_temporal(e, s, false)),
+        ("to_integer",   Exactly(1),    Fixed(Integer),   |e, s| args_1(e, s, |a| cast(a, "INTEGER"))),
+        ("to_numeric",   Exactly(1),    Fixed(Numeric),   |e, s| args_1(e, s, |a| cast(a, "NUMERIC"))),
+        ("to_text",      Exactly(1),    Fixed(Text),      args_to_text),
+        ("to_timestamp", Exactly(2),    Fixed(Timestamp), |e, s| args_to_temporal(e, s, true)),
+        ("trim_array",   Exactly(2),    FirstArg,         |e, s| args_array(e, s, |a, [n]| trim_array(a, n))),
+        ("trunc",        Between(1, 2), FirstArg,         args_trunc),
+        ("unnest",       Exactly(1),    FirstArgElement,  |e, s| args_unnest(e, s, false)),
+        ("unnest_index", Exactly(1),    Fixed(Integer),   |e, s| args_unnest(e, s, true)),
+        ("uppercase",    Exactly(1),    Fixed(Text),      |e, s| args_1(e, s, upper)),
+        ("width_bucket", Exactly(4),    Fixed(Integer),   args_width_bucket),
+        ("zero_if_null", Exactly(1),    FirstArg,         args_zero_if_null),
     ];
-    templates
-        .into_iter()
-        .map(|(s, f)| (s.to_string(), f))
-        .collect()
+    build_composition_map(templates)
 }
 
 /// Used for an aggregate function that takes one argument
@@ -130,7 +737,236 @@ fn agg_1(
     agg_wrapper: fn(SqlExpr) -> SqlExpr,
 ) -> Result<SqlExpr, String> {
     let arg0 = iter_one(args).ok_or_else(msg::expected_one_arg)?;
-    let Expr::Path(path_parts) = arg0 else {
+    if scope.is_grouped() && !is_path_to_many(&arg0, scope) {
+        return grouped_aggregate(arg0, scope, agg_wrapper);
+    }
+    join_aggregate(arg0, scope, AggregateExprTemplate::new, agg_wrapper)
+}
+
+/// Used for an aggregate function whose result depends on the order of its values. The optional
+/// second argument orders them, either by a single column or by the sorted columns of a window,
+/// e.g. `%list(%%(created_at\sd))`.
+fn agg_ordered(
+    args: Vec<Expr>,
+    scope: &mut Scope,
+    agg_wrapper: fn(SqlExpr) -> SqlExpr,
+    is_distinct: bool,
+) -> Result<SqlExpr, String> {
+    if args.len() < 2 {
+        return agg_1(args, scope, agg_wrapper);
+    }
+    let (arg0, arg1) = iter_two(args).ok_or_else(msg::expected_two_args)?;
+    if !scope.options.dialect.supports_ordered_aggregates() {
+        return Err(msg::ordered_aggregate_unsupported());
+    }
+    let ordering = match arg1 {
+        Expr::Window(window) => {
+            if window.frame.is_some() {
+                return Err(msg::aggregate_ordering_not_sorted());
+            }
+            window
+                .specs
+                .into_iter()
+                .map(|spec| match spec.column_control.sort {
+                    Some(sort_spec) if !spec.column_control.is_partition_by => {
+                        Ok((spec.expr, sort_spec))
+                    }
+                    _ => Err(msg::aggregate_ordering_not_sorted()),
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        }
+        expr => vec![(expr, SortSpec::default())],
+    };
+    if scope.is_grouped() && !is_path_to_many(&arg0, scope) {
+        let value = convert_expr(arg0, scope)?;
+        let mut sorting_stack = SortingStack::new(scope.options.nulls_sort.clone());
+        for (expr, sort_spec) in ordering {
+            sorting_stack.push(convert_expr(expr, scope)?, sort_spec);
+        }
+        let order_by = Vec::<SortEntry>::from(sorting_stack);
+        if is_distinct && order_by.iter().any(|e| e.expr.content != value.content) {
+            return Err(msg::distinct_aggregate_ordering());
+        }
+        let dialect = &scope.options.dialect;
+        let order_by = order_by
+            .iter()
+            .map(|e| dialect.sort_entry(&e.expr, &e.direction, &e.nulls_sort))
+            .collect();
+        scope.count_grouped_aggregate();
+        return Ok(ordered(agg_wrapper(value), order_by));
+    }
+    let ordering = ordering
+        .into_iter()
+        .map(|(expr, sort_spec)| match expr {
+            Expr::Path(parts, _) => Ok((parts, sort_spec)),
+            _ => Err(msg::aggregate_ordering_not_column()),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let make_template = |column_name, agg_wrapper| {
+        AggregateExprTemplate::new(column_name, agg_wrapper).with_ordering(ordering, is_distinct)
+    };
+    join_aggregate(arg0, scope, make_template, agg_wrapper)
+}
+
+/// Used for the aggregate function which estimates the number of distinct values, which is
+/// rendered differently by each dialect
+fn agg_approx_count_distinct(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let Some(agg_wrapper) = scope.options.dialect.approx_count_distinct() else {
+        return Err(msg::approx_count_distinct_unsupported());
+    };
+    agg_1(args, scope, agg_wrapper)
+}
+
+/// Used for the aggregate function which tells whether a grouped value has been rolled up within
+/// a subtotal row
+fn agg_grouping(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let arg0 = iter_one(args).ok_or_else(msg::expected_one_arg)?;
+    if !scope.is_grouped() {
+        return Err(msg::grouping_fn_without_grouping());
+    }
+    grouped_aggregate(arg0, scope, grouping)
+}
+
+/// Aggregate values from the base table across each group of a grouped query
+fn grouped_aggregate(
+    arg0: Expr,
+    scope: &mut Scope,
+    agg_wrapper: fn(SqlExpr) -> SqlExpr,
+) -> Result<SqlExpr, String> {
+    let expr = convert_expr(arg0, scope)?;
+    scope.count_grouped_aggregate();
+    Ok(agg_wrapper(expr))
+}
+
+/// Used for an aggregate function that only considers the N greatest values within each group.
+/// The second argument is N.
+fn agg_top(
+    args: Vec<Expr>,
+    scope: &mut Scope,
+    agg_wrapper: fn(SqlExpr) -> SqlExpr,
+) -> Result<SqlExpr, String> {
+    let (arg0, arg1) = iter_two(args).ok_or_else(msg::expected_two_args)?;
+    let count = parse_row_count(arg1).ok_or_else(msg::row_limit_not_positive_integer)?;
+    let row_limit = RowLimit {
+        count,
+        offset: None,
+        direction: SortDirection::Desc,
+        is_per_group: true,
+    };
+    let make_template = |column_name, agg_wrapper| {
+        AggregateExprTemplate::new(column_name, agg_wrapper).with_row_limit(row_limit)
+    };
+    join_aggregate(arg0, scope, make_template, agg_wrapper)
+}
+
+/// Used for an aggregate function that only considers the N greatest values among all the related
+/// records, before they're grouped. The second argument is N, and the optional third argument is
+/// the number of greatest values to skip first.
+fn agg_top_overall(
+    args: Vec<Expr>,
+    scope: &mut Scope,
+    agg_wrapper: fn(SqlExpr) -> SqlExpr,
+) -> Result<SqlExpr, String> {
+    let mut args = args.into_iter();
+    let arg0 = args.next().ok_or_else(msg::expected_two_args)?;
+    let count = args
+        .next()
+        .and_then(parse_row_count)
+        .ok_or_else(msg::row_limit_not_positive_integer)?;
+    let offset = args
+        .next()
+        .map(|arg| match arg {
+            Expr::Number(n, _) => n.parse::<u64>().map_err(|_| msg::row_offset_not_integer()),
+            _ => Err(msg::row_offset_not_integer()),
+        })
+        .transpose()?;
+    let row_limit = RowLimit {
+        count,
+        offset,
+        direction: SortDirection::Desc,
+        is_per_group: false,
+    };
+    let make_template = |column_name, agg_wrapper| {
+        AggregateExprTemplate::new(column_name, agg_wrapper).with_row_limit(row_limit)
+    };
+    join_aggregate(arg0, scope, make_template, agg_wrapper)
+}
+
+fn parse_row_count(expr: Expr) -> Option<u64> {
+    match expr {
+        Expr::Number(n, _) => n.parse::<u64>().ok().filter(|count| *count > 0),
+        _ => None,
+    }
+}
+
+/// Used for the aggregate functions which test a condition against each related record. The second
+/// argument is the condition.
+///
+/// * `is_universal` - When true, every related record must satisfy the condition, which is also
+///   the case when there are no related records. Otherwise at least one must.
+fn agg_quantifier(
+    args: Vec<Expr>,
+    scope: &mut Scope,
+    is_universal: bool,
+) -> Result<SqlExpr, String> {
+    let (arg0, condition) = iter_two(args).ok_or_else(msg::expected_two_args)?;
+    let Expr::Path(path_parts, _) = arg0 else {
+        return Err(msg::aggregate_fn_applied_to_a_non_path());
+    };
+    let clarified_path = clarify_path(path_parts, scope)?;
+    let Some(ClarifiedPathTail::ChainToMany((mut chain, column_name_opt))) = clarified_path.tail
+    else {
+        return Err(msg::aggregate_fn_applied_to_path_to_one());
+    };
+    if column_name_opt.is_some() {
+        return Err(msg::quantifier_applied_to_a_column());
+    }
+    // All records satisfy the condition when none of them fail to satisfy it
+    let (condition, purpose) = if is_universal {
+        let negation = Expr::Call(Call {
+            name: "not".to_string(),
+            dimension: FunctionDimension::Scalar,
+            args: vec![condition],
+            syntax: CallSyntax::Standalone,
+            span: Span::Unknown,
+        });
+        (negation, CtePurpose::Exclusion)
+    } else {
+        (condition, CtePurpose::ConditionalInclusion)
+    };
+    chain.add_final_condition(condition);
+    let pk = scope.join_chain_to_many(&clarified_path.head, chain, None, purpose)?;
+    Ok(if is_universal {
+        cmp::is_null(pk)
+    } else {
+        cmp::is_not_null(pk)
+    })
+}
+
+/// Used for the aggregate function that counts the rows within each group for which the piped
+/// condition holds, e.g. `$(status:"paid")%count_if`. Dialects without `FILTER` add up a 1 for
+/// each of those rows instead.
+fn agg_count_if(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let condition = iter_one(args).ok_or_else(msg::expected_one_arg)?;
+    if !scope.is_grouped() {
+        return Err(msg::count_if_outside_grouping());
+    }
+    let condition = convert_expr(condition, scope)?;
+    scope.count_grouped_aggregate();
+    Ok(if scope.options.dialect.supports_aggregate_filter() {
+        filter(count_star(), condition)
+    } else {
+        sum(bit_of(condition))
+    })
+}
+
+fn join_aggregate(
+    arg0: Expr,
+    scope: &mut Scope,
+    make_template: impl FnOnce(String, fn(SqlExpr) -> SqlExpr) -> AggregateExprTemplate,
+    agg_wrapper: fn(SqlExpr) -> SqlExpr,
+) -> Result<SqlExpr, String> {
+    let Expr::Path(path_parts, _) = arg0 else {
         return Err(msg::aggregate_fn_applied_to_a_non_path());
     };
     let clarified_path = clarify_path(path_parts, scope)?;
@@ -140,7 +976,7 @@ fn agg_1(
     let Some(column_name) = column_name_opt else {
         return Err(msg::aggregate_fn_applied_to_a_path_without_a_column());
     };
-    let aggregate_expr_template = AggregateExprTemplate::new(column_name, agg_wrapper);
+    let aggregate_expr_template = make_template(column_name, agg_wrapper);
     scope.join_chain_to_many(
         &clarified_path.head,
         chain_to_many,
@@ -149,21 +985,157 @@ fn agg_1(
     )
 }
 
-pub fn get_standard_aggregate_functions() -> FuncMap {
+/// Separate the window piped to a window function from the function's other arguments
+fn take_window(args: Vec<Expr>) -> Result<(Window, Vec<Expr>), String> {
+    let mut args = args.into_iter();
+    let Some(Expr::Window(window)) = args.next() else {
+        return Err(msg::window_function_without_window());
+    };
+    Ok((window, args.collect()))
+}
+
+/// Used for a window function, to which the window is piped. The remaining arguments are passed to
+/// the SQL function.
+fn window_fn(
+    args: Vec<Expr>,
+    scope: &mut Scope,
+    f: fn(Vec<SqlExpr>) -> SqlExpr,
+) -> Result<SqlExpr, String> {
+    let (window, args) = take_window(args)?;
+    let args = args
+        .into_iter()
+        .map(|arg| convert_expr(arg, scope))
+        .collect::<Result<Vec<_>, _>>()?;
+    let definition = convert_window(window, scope)?;
+    Ok(window::over(f(args), definition))
+}
+
+/// The aggregate functions which can also be computed over a window, e.g. for running totals
+fn get_window_aggregate(name: &str) -> Option<fn(SqlExpr) -> SqlExpr> {
+    match name {
+        "avg" => Some(avg),
+        "count" => Some(count),
+        "max" => Some(max),
+        "min" => Some(min),
+        "sum" => Some(sum),
+        _ => None,
+    }
+}
+
+/// Used for an aggregate function applied to a window instead of to related records. Each row
+/// keeps its own result, so nothing is grouped.
+fn window_aggregate(
+    args: Vec<Expr>,
+    scope: &mut Scope,
+    agg_wrapper: fn(SqlExpr) -> SqlExpr,
+) -> Result<SqlExpr, String> {
+    let (window, args) = take_window(args)?;
+    let arg = iter_one(args).ok_or_else(msg::expected_one_arg)?;
+    let expr = convert_expr(arg, scope)?;
+    let definition = convert_window(window, scope)?;
+    Ok(window::over(agg_wrapper(expr), definition))
+}
+
+/// Used for the aggregate function that divides each row's value by the sum over a window, e.g.
+/// for each row's share of its group's total. Integers are multiplied by 1.0 first, since most
+/// databases would otherwise truncate the ratio to a whole number.
+fn window_share(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    let (window, args) = take_window(args)?;
+    let arg = iter_one(args).ok_or_else(msg::expected_one_arg)?;
+    let is_integer = infer_type(&arg, scope) == Integer;
+    let a = convert_expr(arg, scope)?;
+    let definition = convert_window(window, scope)?;
+    let total = window::over(sum(a.clone()), definition);
+    let a = if is_integer {
+        multiply(SqlExpr::atom("1.0".to_string()), a)
+    } else {
+        a
+    };
+    Ok(divide_by_policy(a, total, scope))
+}
+
+fn window_nth_value(args: Vec<Expr>, scope: &mut Scope) -> Result<SqlExpr, String> {
+    if !scope.options.dialect.supports_nth_value() {
+        return Err(msg::nth_value_unsupported());
+    }
+    window_fn(args, scope, window::nth_value)
+}
+
+/// Render the contents of the `OVER` clause for a window
+fn convert_window(window: Window, scope: &mut Scope) -> Result<String, String> {
+    let mut partition_by = Vec::new();
+    let mut sorting_stack = SortingStack::new(scope.options.nulls_sort.clone());
+    for spec in window.specs {
+        let control = spec.column_control;
+        if !control.is_partition_by && control.sort.is_none() {
+            return Err(msg::unflagged_window_column());
+        }
+        let expr = convert_expr(spec.expr, scope)?;
+        if let Some(sort_spec) = control.sort {
+            sorting_stack.push(expr.clone(), sort_spec);
+        }
+        if control.is_partition_by {
+            partition_by.push(expr);
+        }
+    }
+    let dialect = &scope.options.dialect;
+    let order_by: Vec<String> = Vec::<SortEntry>::from(sorting_stack)
+        .iter()
+        .map(|entry| dialect.sort_entry(&entry.expr, &entry.direction, &entry.nulls_sort))
+        .collect();
+    let frame = match window.frame {
+        Some(frame) => {
+            if let (Some(start), Some(end)) = (frame.start, frame.end) {
+                if start > end {
+                    return Err(msg::inverted_window_frame());
+                }
+            }
+            if let Some(exclusion) = frame.exclude {
+                if !dialect.supports_frame_exclusion() {
+                    return Err(msg::frame_exclusion_unsupported());
+                }
+                let contains_current_row =
+                    frame.start.is_none_or(|s| s <= 0) && frame.end.is_none_or(|e| e >= 0);
+                if !contains_current_row {
+                    return Err(msg::frame_exclusion_outside_frame());
+                }
+                if exclusion != FrameExclusion::CurrentRow && order_by.is_empty() {
+                    return Err(msg::frame_exclusion_without_sorting());
+                }
+            }
+            Some(window::rows_between(frame.start, frame.end, frame.exclude))
+        }
+        None => None,
+    };
+    Ok(window::definition(partition_by, order_by, frame))
+}
+
+pub fn get_standard_aggregate_functions() -> CompositionMap {
     #[rustfmt::skip]
-    let templates: [(&str, Func); 9] = [
-        ("all_true", |e, s| agg_1(e, s, bool_and)),
-        ("any_true", |e, s| agg_1(e, s, bool_or)),
-        ("avg",      |e, s| agg_1(e, s, avg)),
-        ("count",    |e, s| agg_1(e, s, count)),
-        ("distinct", |e, s| agg_1(e, s, count_distinct)),
-        ("list",     |e, s| agg_1(e, s, string_agg)),
-        ("max",      |e, s| agg_1(e, s, max)),
-        ("min",      |e, s| agg_1(e, s, min)),
-        ("sum",      |e, s| agg_1(e, s, sum)),
+    let templates: [(&str, Arity, ReturnType, Func); 23] = [
+        ("all",                   Exactly(2),    Fixed(Boolean),        |e, s| agg_quantifier(e, s, true)),
+        ("all_true",              Exactly(1),    Fixed(Boolean),        |e, s| agg_1(e, s, bool_and)),
+        ("any",                   Exactly(2),    Fixed(Boolean),        |e, s| agg_quantifier(e, s, false)),
+        ("any_true",              Exactly(1),    Fixed(Boolean),        |e, s| agg_1(e, s, bool_or)),
+        ("approx_count_distinct", Exactly(1),    Fixed(Integer),        agg_approx_count_distinct),
+        ("array",                 Between(1, 2), AggregatedColumnArray, |e, s| agg_ordered(e, s, array_agg, false)),
+        ("array_distinct",        Between(1, 2), AggregatedColumnArray, |e, s| agg_ordered(e, s, array_agg_distinct, true)),
+        ("avg",                   Exactly(1),    Fixed(Numeric),        |e, s| agg_1(e, s, avg)),
+        ("count",                 Exactly(1),    Fixed(Integer),        |e, s| agg_1(e, s, count)),
+        ("count_if",              Exactly(1),    Fixed(Integer),        agg_count_if),
+        ("distinct",              Exactly(1),    Fixed(Integer),        |e, s| agg_1(e, s, count_distinct)),
+        ("first_value",           Exactly(2),    SecondArg,             |e, s| window_fn(e, s, window::first_value)),
+        ("grouping",              Exactly(1),    Fixed(Integer),        agg_grouping),
+        ("last_value",            Exactly(2),    SecondArg,             |e, s| window_fn(e, s, window::last_value)),
+        ("list",                  Between(1, 2), Fixed(Text),           |e, s| agg_ordered(e, s, string_agg, false)),
+        ("list_distinct",         Between(1, 2), Fixed(Text),           |e, s| agg_ordered(e, s, string_agg_distinct, true)),
+        ("max",                   Exactly(1),    AggregatedColumn,      |e, s| agg_1(e, s, max)),
+        ("min",                   Exactly(1),    AggregatedColumn,      |e, s| agg_1(e, s, min)),
+        ("nth_value",             Exactly(3),    SecondArg,             window_nth_value),
+        ("share",                 Exactly(2),    Fixed(Numeric),        window_share),
+        ("sum",                   Exactly(1),    Fixed(Numeric),        |e, s| agg_1(e, s, sum)),
+        ("top",                   Exactly(2),    Fixed(Text),           |e, s| agg_top(e, s, string_agg)),
+        ("top_overall",           Between(2, 3), Fixed(Text),           |e, s| agg_top_overall(e, s, string_agg)),
     ];
-    templates
-        .into_iter()
-        .map(|(s, f)| (s.to_string(), f))
-        .collect()
+    build_composition_map(templates)
 }
@@ -1,13 +1,13 @@
-use std::collections::HashMap;
-
 use crate::{
+    options::ToManyStrategy,
     schema::{
         chain::Chain,
-        links::{Link, LinkToOne, Reference},
+        links::{Link, LinkToOne},
+        TableId,
     },
     sql::{
         expr::build::*,
-        tree::{Cte, Join, JoinType},
+        tree::{Cte, CtePurpose, Join, JoinType, SqlExpr},
     },
 };
 
@@ -16,16 +16,22 @@ use super::{constants::CTE_PK_COLUMN_ALIAS, scope::Scope};
 #[derive(Debug)]
 pub struct JoinTree {
     alias: String,
-    dependents: HashMap<LinkToOne, JoinTree>,
+    /// In the order that the links were first encountered, so that the joins render in a stable
+    /// order
+    dependents: Vec<(LinkToOne, JoinTree)>,
     ctes: Vec<Cte>,
+    /// Tables joined to this one without any relationship, as (table id, alias). Only the root of
+    /// the tree has these.
+    cross_joins: Vec<(TableId, String)>,
 }
 
 impl JoinTree {
     pub fn new(alias: String) -> Self {
         Self {
             alias,
-            dependents: HashMap::new(),
+            dependents: Vec::new(),
             ctes: Vec::new(),
+            cross_joins: Vec::new(),
         }
     }
 
@@ -33,7 +39,7 @@ impl JoinTree {
         &self.alias
     }
 
-    pub fn take_dependents(&mut self) -> HashMap<LinkToOne, JoinTree> {
+    pub fn take_dependents(&mut self) -> Vec<(LinkToOne, JoinTree)> {
         std::mem::take(&mut self.dependents)
     }
 
@@ -41,6 +47,26 @@ impl JoinTree {
         std::mem::take(&mut self.ctes)
     }
 
+    /// The CTEs joined to the end of the chain, or `None` when the chain hasn't been integrated
+    pub fn ctes_at(
+        &mut self,
+        chain_to_one_opt: Option<&Chain<LinkToOne>>,
+    ) -> Option<&mut Vec<Cte>> {
+        let mut tree = self;
+        for next_link in chain_to_one_opt.map(Chain::get_links).unwrap_or_default() {
+            tree = tree
+                .dependents
+                .iter_mut()
+                .find(|(link, _)| link == next_link)
+                .map(|(_, subtree)| subtree)?;
+        }
+        Some(&mut tree.ctes)
+    }
+
+    pub fn add_cross_join(&mut self, table_id: TableId, alias: String) {
+        self.cross_joins.push((table_id, alias));
+    }
+
     pub fn integrate_chain(
         &mut self,
         chain_to_one_opt: Option<&Chain<LinkToOne>>,
@@ -52,7 +78,11 @@ impl JoinTree {
             return self.alias.clone();
         };
         let (next_link, remainder_chain_opt) = chain_to_one.with_first_link_broken_off();
-        let subtree_opt = self.dependents.get_mut(next_link);
+        let subtree_opt = self
+            .dependents
+            .iter_mut()
+            .find(|(link, _)| link == next_link)
+            .map(|(_, subtree)| subtree);
         match (subtree_opt, remainder_chain_opt) {
             // We have one more new link to add to the tree and then we're done. We add an empty
             // subtree and return its alias.
@@ -60,7 +90,7 @@ impl JoinTree {
                 let alias = get_alias(next_link);
                 let mut subtree = JoinTree::new(alias.clone());
                 subtree.ctes.extend(cte_to_add);
-                self.dependents.insert(*next_link, subtree);
+                self.dependents.push((*next_link, subtree));
                 alias
             }
 
@@ -68,7 +98,7 @@ impl JoinTree {
             // the alias of its furthest child.
             (None, Some(remainder_chain)) => {
                 let mut alias_of_furthest_subtree = String::new();
-                let mut dependents = HashMap::<LinkToOne, JoinTree>::new();
+                let mut dependents = Vec::<(LinkToOne, JoinTree)>::new();
                 let links = remainder_chain.get_links().to_vec();
                 for (index, link) in links.into_iter().rev().enumerate() {
                     let alias = get_alias(&link);
@@ -79,6 +109,7 @@ impl JoinTree {
                         alias,
                         dependents: std::mem::take(&mut dependents),
                         ctes: Vec::new(),
+                        cross_joins: Vec::new(),
                     };
                     if let Some(cte) = std::mem::take(&mut cte_to_add) {
                         // Take the CTE out of `cte_to_add` and add it to the subtree. This will
@@ -86,14 +117,15 @@ impl JoinTree {
                         // for `alias_of_furthest_subtree`.
                         subtree.ctes.push(cte);
                     }
-                    dependents.insert(link, subtree);
+                    dependents.push((link, subtree));
                 }
                 let subtree = JoinTree {
                     alias: get_alias(next_link),
                     dependents,
                     ctes: Vec::new(),
+                    cross_joins: Vec::new(),
                 };
-                self.dependents.insert(*next_link, subtree);
+                self.dependents.push((*next_link, subtree));
                 alias_of_furthest_subtree
             }
 
@@ -111,26 +143,48 @@ impl JoinTree {
     }
 
     /// Destroy this JoinTree and return the component parts needed to render a query.
-    pub fn decompose(mut self, scope: &Scope) -> (Vec<Join>, Vec<Cte>) {
-        let mut ctes = self.take_ctes();
-        let mut joins: Vec<Join> = ctes
-            .iter()
-            .map(|cte| build_join_for_cte(cte, self.get_alias().to_owned(), scope))
+    pub fn decompose(self, scope: &Scope) -> (Vec<Join>, Vec<Cte>) {
+        self.decompose_below(true, scope)
+    }
+
+    /// Links which always have a related record are rendered as inner joins, but only when every
+    /// join above them is also an inner join. Otherwise an inner join would discard rows which an
+    /// earlier left join was supposed to keep.
+    fn decompose_below(mut self, is_inner: bool, scope: &Scope) -> (Vec<Join>, Vec<Cte>) {
+        // Cross joins come first so that the joins after them may refer to any root table
+        let mut joins: Vec<Join> = std::mem::take(&mut self.cross_joins)
+            .into_iter()
+            .map(|(table_id, alias)| build_cross_join(table_id, alias, scope))
             .collect();
+        let mut ctes = Vec::new();
+        for cte in self.take_ctes() {
+            let mut join = build_join_for_cte(&cte, self.get_alias().to_owned(), scope);
+            let is_subquery = scope.options.to_many_strategy == ToManyStrategy::Subquery
+                && cte.select.ctes.is_empty();
+            if is_subquery {
+                join.is_cte = false;
+                join.subquery = Some(Box::new(cte.select));
+            } else {
+                ctes.push(cte);
+            }
+            joins.push(join);
+        }
         for (link, subtree) in self.take_dependents() {
             let starting_alias = self.get_alias();
             let ending_alias = subtree.get_alias();
-            let join_type = JoinType::LeftOuter;
-            let join = make_join_from_link(
-                link.get_start(),
-                starting_alias,
-                link.get_end(),
-                ending_alias,
-                join_type,
-                scope,
-            );
+            let is_full_outer = scope.is_full_outer_link(&link);
+            let is_subtree_inner =
+                is_inner && !is_full_outer && scope.schema.link_always_has_target(&link);
+            let join_type = if is_full_outer {
+                JoinType::FullOuter
+            } else if is_subtree_inner {
+                JoinType::Inner
+            } else {
+                JoinType::LeftOuter
+            };
+            let join = make_join_from_link(&link, starting_alias, ending_alias, join_type, scope);
             joins.push(join);
-            let (new_joins, new_ctes) = subtree.decompose(scope);
+            let (new_joins, new_ctes) = subtree.decompose_below(is_subtree_inner, scope);
             joins.extend(new_joins);
             ctes.extend(new_ctes);
         }
@@ -138,26 +192,50 @@ impl JoinTree {
     }
 }
 
+fn build_cross_join(table_id: TableId, alias: String, scope: &Scope) -> Join {
+    let table = scope.schema.tables.get(&table_id).unwrap();
+    Join {
+        annotation: format!("cross join of {}", table.name),
+        table: table.name.clone(),
+        table_schema: scope.get_table_schema(table),
+        is_cte: false,
+        alias,
+        conditions: SqlExpr::default(),
+        join_type: JoinType::Cross,
+        lateral_subquery: None,
+        subquery: None,
+    }
+}
+
 fn build_join_for_cte(cte: &Cte, table: String, scope: &Scope) -> Join {
     Join {
         table: cte.alias.clone(),
+        table_schema: None,
+        is_cte: true,
         alias: cte.alias.clone(),
         conditions: cmp::eq(
             scope.table_column_expr(&table, &cte.join_column_name),
             scope.table_column_expr(&cte.alias, CTE_PK_COLUMN_ALIAS),
         ),
-        join_type: JoinType::LeftOuter,
+        join_type: match cte.purpose {
+            CtePurpose::Inclusion => JoinType::Inner,
+            _ => JoinType::LeftOuter,
+        },
+        annotation: cte.annotation.clone(),
+        lateral_subquery: None,
+        subquery: None,
     }
 }
 
 pub fn make_join_from_link(
-    start: Reference,
+    link: &impl Link,
     starting_alias: &str,
-    end: Reference,
     ending_alias: &str,
     join_type: JoinType,
     scope: &Scope,
 ) -> Join {
+    let start = link.get_start();
+    let end = link.get_end();
     let starting_table_id = start.table_id;
     let starting_table = scope.schema.tables.get(&starting_table_id).unwrap();
     let starting_column_id = start.column_id;
@@ -169,18 +247,24 @@ pub fn make_join_from_link(
     let ending_column = ending_table.columns.get(&ending_column_id).unwrap();
 
     Join {
-        table: scope
-            .schema
-            .tables
-            .get(&ending_table_id)
-            .unwrap()
-            .name
-            .clone(),
+        table: ending_table.name.clone(),
+        table_schema: scope.get_table_schema(ending_table),
+        is_cte: false,
         alias: ending_alias.to_owned(),
         conditions: cmp::eq(
             scope.table_column_expr(starting_alias, &starting_column.name),
             scope.table_column_expr(ending_alias, &ending_column.name),
         ),
         join_type,
+        annotation: format!(
+            "{} link from {}.{} to {}.{}",
+            link.get_cardinality().describe(),
+            starting_table.name,
+            starting_column.name,
+            ending_table.name,
+            ending_column.name
+        ),
+        lateral_subquery: None,
+        subquery: None,
     }
 }
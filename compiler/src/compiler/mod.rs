@@ -1,12 +1,34 @@
+mod aliases;
 mod comparisons;
+mod compiled_batch;
+mod compiled_query;
 mod compiler;
+mod compositions;
+mod condition_fragment;
+mod constant_folding;
 mod constants;
-mod expr;
+mod count;
+mod cte_inlining;
+mod cte_ordering;
+pub(crate) mod expr;
 mod functions;
 mod join_tree;
+mod parameters;
 mod paths;
-mod rendering;
+pub(crate) mod rendering;
 mod result_columns;
-mod scope;
+pub(crate) mod scope;
+mod snapshot;
+mod type_inference;
 
+pub use compiled_batch::CompiledBatch;
+pub use compiled_query::{
+    ColumnMetadata, CompileStats, CompiledQuery, CteMetadata, CtePlan, JoinPlan, JoinPlanNode,
+    ParamInfo,
+};
 pub use compiler::Compiler;
+pub use compositions::{Arity, Composition, CompositionSignature, ReturnType};
+pub use condition_fragment::ConditionFragment;
+pub use count::compile_count;
+pub(crate) use functions::{get_standard_aggregate_functions, get_standard_scalar_functions};
+pub use snapshot::compile_for_snapshot;
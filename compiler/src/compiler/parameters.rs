@@ -0,0 +1,213 @@
+use querydown_parser::ast::*;
+
+use crate::{errors::msg, schema::DataType};
+
+use super::{scope::Scope, type_inference::infer_type};
+
+/// A placeholder within a query, which becomes a parameter of the compiled statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    /// The first expression that the placeholder is compared with, from which we infer its type.
+    /// This is `None` when the placeholder is never compared with an expression on the base
+    /// table. Expressions within the conditions on related records refer to other tables, so we
+    /// don't use them.
+    pub counterpart: Option<Expr>,
+    /// The type name given by an annotation on any appearance of the placeholder
+    pub type_name: Option<String>,
+}
+
+impl Parameter {
+    /// The name of the parameter's type, taken from its annotation if it has one, or else from
+    /// what it's compared with. Fails when the annotation contradicts the inferred type.
+    pub fn get_type_name(&self, scope: &Scope) -> Result<Option<String>, String> {
+        let inferred_type = self
+            .counterpart
+            .as_ref()
+            .map(|counterpart| infer_type(counterpart, scope))
+            .unwrap_or_default();
+        let Some(type_name) = &self.type_name else {
+            return Ok(scope.options.dialect.type_name(&inferred_type));
+        };
+        let annotated_type = DataType::from_name(type_name);
+        let is_compatible = annotated_type == inferred_type
+            || annotated_type == DataType::Unknown
+            || inferred_type == DataType::Unknown
+            || (annotated_type.is_numeric() && inferred_type.is_numeric())
+            || (annotated_type.is_temporal() && inferred_type.is_temporal());
+        if !is_compatible {
+            return Err(msg::conflicting_parameter_type(&self.name, type_name));
+        }
+        Ok(Some(type_name.clone()))
+    }
+
+    /// The type of the parameter, taken from its annotation when we recognize the annotated type,
+    /// or else from what it's compared with. Unlike `get_type_name`, this doesn't check that the
+    /// two agree.
+    pub fn get_data_type(&self, scope: &Scope) -> DataType {
+        let annotated_type = self.type_name.as_deref().map(DataType::from_name);
+        match annotated_type {
+            Some(data_type) if data_type != DataType::Unknown => data_type,
+            _ => self
+                .counterpart
+                .as_ref()
+                .map(|counterpart| infer_type(counterpart, scope))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Find the placeholders within a query, in order of their first appearance. A name which appears
+/// more than once refers to the same parameter each time.
+pub fn find_parameters(query: &Query) -> Result<Vec<Parameter>, String> {
+    let mut finder = ParameterFinder::default();
+    for transformation in &query.transformations {
+        finder.visit_condition_set(&transformation.conditions, false);
+        for statement in &transformation.result_columns {
+            match statement {
+                ResultColumnStatement::Spec(spec) => finder.visit_expr(&spec.expr, false),
+                ResultColumnStatement::Glob(glob) => {
+                    finder.visit_path(&glob.head);
+                    for spec in &glob.specs {
+                        finder.visit_expr(&spec.expr, true);
+                    }
+                }
+            }
+        }
+    }
+    finder.finish()
+}
+
+/// Like `find_parameters`, for expressions which are compiled without a query around them
+pub fn find_expr_parameters<'e>(
+    exprs: impl IntoIterator<Item = &'e Expr>,
+) -> Result<Vec<Parameter>, String> {
+    let mut finder = ParameterFinder::default();
+    for expr in exprs {
+        finder.visit_expr(expr, false);
+    }
+    finder.finish()
+}
+
+#[derive(Default)]
+struct ParameterFinder {
+    parameters: Vec<Parameter>,
+    /// The first placeholder we found annotated with a type that contradicts another annotation
+    conflict: Option<String>,
+}
+
+impl ParameterFinder {
+    fn add(&mut self, placeholder: &Placeholder, counterpart: Option<&Expr>) {
+        let Placeholder { name, type_name } = placeholder;
+        let Some(parameter) = self.parameters.iter_mut().find(|p| &p.name == name) else {
+            self.parameters.push(Parameter {
+                name: name.clone(),
+                counterpart: counterpart.cloned(),
+                type_name: type_name.clone(),
+            });
+            return;
+        };
+        if parameter.counterpart.is_none() {
+            parameter.counterpart = counterpart.cloned();
+        }
+        match (&parameter.type_name, type_name) {
+            (Some(a), Some(b)) if !a.eq_ignore_ascii_case(b) => {
+                self.conflict.get_or_insert_with(|| name.clone());
+            }
+            (None, Some(_)) => parameter.type_name = type_name.clone(),
+            _ => {}
+        }
+    }
+
+    fn finish(self) -> Result<Vec<Parameter>, String> {
+        match self.conflict {
+            Some(name) => Err(msg::conflicting_placeholder_annotations(&name)),
+            None => Ok(self.parameters),
+        }
+    }
+
+    /// * `is_related` - True within the conditions on related records
+    fn visit_expr(&mut self, expr: &Expr, is_related: bool) {
+        match expr {
+            Expr::Placeholder(placeholder) => self.add(placeholder, None),
+            Expr::Path(parts, _) => self.visit_path(parts),
+            Expr::ConditionSet(condition_set) => {
+                self.visit_condition_set(condition_set, is_related)
+            }
+            Expr::HasQuantity(has_quantity) => self.visit_path(&has_quantity.path_parts),
+            Expr::Call(call) => {
+                for arg in &call.args {
+                    self.visit_expr(arg, is_related);
+                }
+            }
+            Expr::Product(a, b)
+            | Expr::Quotient(a, b)
+            | Expr::Power(a, b)
+            | Expr::Sum(a, b)
+            | Expr::Difference(a, b) => {
+                self.visit_expr(a, is_related);
+                self.visit_expr(b, is_related);
+            }
+            Expr::Comparison(comparison) => {
+                let counterpart = |side: &ComparisonSide| match side {
+                    _ if is_related => None,
+                    ComparisonSide::Expr(expr) => Some(expr.clone()),
+                    ComparisonSide::Expansion(condition_set) => {
+                        condition_set.entries.first().cloned()
+                    }
+                    ComparisonSide::Range(_) => None,
+                };
+                let left_counterpart = counterpart(&comparison.right);
+                let right_counterpart = counterpart(&comparison.left);
+                self.visit_comparison_side(&comparison.left, left_counterpart, is_related);
+                self.visit_comparison_side(&comparison.right, right_counterpart, is_related);
+            }
+            Expr::Window(window) => {
+                for spec in &window.specs {
+                    self.visit_expr(&spec.expr, is_related);
+                }
+            }
+            Expr::Number(..)
+            | Expr::Date(..)
+            | Expr::Duration(..)
+            | Expr::String(..)
+            | Expr::Bytes(..)
+            | Expr::Uuid(..)
+            | Expr::Variable(_) => {}
+        }
+    }
+
+    fn visit_path(&mut self, parts: &[PathPart]) {
+        for part in parts {
+            if let PathPart::TableWithMany(table_with_many) = part {
+                self.visit_condition_set(&table_with_many.condition_set, true);
+            }
+        }
+    }
+
+    fn visit_condition_set(&mut self, condition_set: &ConditionSet, is_related: bool) {
+        for entry in &condition_set.entries {
+            self.visit_expr(entry, is_related);
+        }
+    }
+
+    /// Placeholders which make up one side of a comparison take on the type of the other side
+    fn visit_comparison_side(
+        &mut self,
+        side: &ComparisonSide,
+        counterpart: Option<Expr>,
+        is_related: bool,
+    ) {
+        let exprs = match side {
+            ComparisonSide::Expr(expr) => vec![expr],
+            ComparisonSide::Expansion(condition_set) => condition_set.entries.iter().collect(),
+            ComparisonSide::Range(range) => vec![&range.lower.expr, &range.upper.expr],
+        };
+        for expr in exprs {
+            match expr {
+                Expr::Placeholder(placeholder) => self.add(placeholder, counterpart.as_ref()),
+                _ => self.visit_expr(expr, is_related),
+            }
+        }
+    }
+}
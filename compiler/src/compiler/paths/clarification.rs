@@ -1,6 +1,6 @@
 use itertools::Itertools;
 
-use querydown_parser::ast::{ConditionSet, PathPart, TableWithMany};
+use querydown_parser::ast::{ConditionSet, Expr, PathPart, TableWithMany};
 
 use crate::{
     compiler::scope::Scope,
@@ -77,6 +77,64 @@ pub fn clarify_path(parts: Vec<PathPart>, scope: &Scope) -> Result<ClarifiedPath
     Ok(ClarifiedPath { head, tail })
 }
 
+/// True when the expression is a path which leads to many related records. Paths which fail to
+/// clarify are not considered to lead anywhere.
+pub fn is_path_to_many(expr: &Expr, scope: &Scope) -> bool {
+    let Expr::Path(parts, _) = expr else {
+        return false;
+    };
+    clarify_path(parts.clone(), scope)
+        .map(|path| matches!(path.tail, Some(ClarifiedPathTail::ChainToMany(_))))
+        .unwrap_or(false)
+}
+
+/// A column of a cross joined table, referenced via a path like `#users.username`
+#[derive(Debug)]
+pub struct CrossJoinedColumn<'a> {
+    pub table: &'a Table,
+    pub alias: String,
+    pub column_name: String,
+}
+
+/// Returns `None` when the path doesn't begin with a cross joined table, leaving the path to be
+/// handled by `clarify_path`.
+pub fn clarify_cross_joined_column<'a>(
+    parts: &[PathPart],
+    scope: &Scope<'a, '_>,
+) -> Option<Result<CrossJoinedColumn<'a>, String>> {
+    let table_name = match parts.first()? {
+        PathPart::TableWithMany(table_with_many) => {
+            if !table_with_many.condition_set.entries.is_empty()
+                || table_with_many.linking_column.is_some()
+            {
+                return None;
+            }
+            &table_with_many.table
+        }
+        PathPart::Column(name) if parts.len() > 1 && !qualifies_base_table(name, scope) => {
+            if names_base_table_column(name, scope) {
+                return None;
+            }
+            name
+        }
+        _ => return None,
+    };
+    let (table, alias) = scope.get_cross_joined_table(table_name)?;
+    let [_, PathPart::Column(column_name)] = parts else {
+        return Some(Err(msg::cross_joined_table_path(&table.name)));
+    };
+    let column = scope
+        .options
+        .resolve_identifier(&table.column_lookup, column_name)
+        .and_then(|id| table.columns.get(id))
+        .ok_or_else(|| msg::col_not_in_table(column_name, &table.name));
+    Some(column.map(|column| CrossJoinedColumn {
+        table,
+        alias: alias.to_owned(),
+        column_name: column.name.clone(),
+    }))
+}
+
 #[derive(Debug)]
 struct LinkedPath {
     pub chain: Option<Chain<FilteredLink>>,
@@ -86,10 +144,56 @@ struct LinkedPath {
     pub column: Option<String>,
 }
 
-fn build_linked_path(parts: Vec<PathPart>, scope: &Scope) -> Result<LinkedPath, String> {
-    let mut current_table_opt: Option<&Table> = Some(scope.get_base_table());
+/// True when the base table has a column of this name
+fn names_base_table_column(name: &str, scope: &Scope) -> bool {
+    scope.get_base_table().is_ok_and(|table| {
+        let lookup = &table.column_lookup;
+        scope.options.resolve_identifier(lookup, name).is_some()
+    })
+}
+
+/// True when a path beginning with this name refers explicitly to a column of the base table, as
+/// in `orders.total` from `#orders`. The base table's alias works too. The base table takes
+/// precedence over a column of the same name and over a join which would be aliased by the name.
+fn qualifies_base_table(name: &str, scope: &Scope) -> bool {
+    let Ok(base_table) = scope.get_base_table() else {
+        return false;
+    };
+    scope.get_base_table_alias() == Some(name)
+        || scope.get_table_by_name(name).map(|t| t.id) == Some(base_table.id)
+}
+
+/// The linking column of the base table whose join the compiler would alias with this name, as
+/// in `users.name` from `#projects` when `projects.owner` is the only link to `users`. A column
+/// of the same name takes precedence.
+fn column_for_join_alias(name: &str, scope: &Scope) -> Option<String> {
+    let base_table = scope.get_base_table().ok()?;
+    if names_base_table_column(name, scope) {
+        return None;
+    }
+    base_table
+        .forward_links_to_one
+        .values()
+        .find(|link| {
+            let link = LinkToOne::ForwardLinkToOne(**link);
+            scope.schema.get_ideal_alias_for_link_to_one(&link) == name
+        })
+        .and_then(|link| base_table.columns.get(&link.base.column_id))
+        .map(|column| column.name.clone())
+}
+
+fn build_linked_path(mut parts: Vec<PathPart>, scope: &Scope) -> Result<LinkedPath, String> {
+    if let [PathPart::Column(name), _, ..] = parts.as_slice() {
+        if qualifies_base_table(name, scope) {
+            parts.remove(0);
+        } else if let Some(column_name) = column_for_join_alias(name, scope) {
+            parts[0] = PathPart::Column(column_name);
+        }
+    }
+    let mut current_table_opt: Option<&Table> = Some(scope.get_base_table()?);
     let mut chain_opt: Option<Chain<FilteredLink>> = None;
     let mut final_column_name: Option<String> = None;
+    let max_len = scope.options.max_chain_length;
     for part in parts {
         let current_table = current_table_opt.ok_or_else(msg::no_current_table)?;
         match part {
@@ -122,10 +226,13 @@ fn build_linked_path(parts: Vec<PathPart>, scope: &Scope) -> Result<LinkedPath,
                 todo!()
             }
             PathPart::TableWithMany(mut table_with_many) => {
+                if scope.options.strict {
+                    validate_linking_column(&table_with_many, scope)?;
+                }
                 let base = ChainSearchBase::TableId(current_table.id);
                 let condition_set = std::mem::take(&mut table_with_many.condition_set);
                 let mut new_chain =
-                    get_chain_to_table_with_many(base, &table_with_many, None, scope)?;
+                    get_chain_to_table_with_many(base, &table_with_many, Some(max_len), scope)?;
                 new_chain.set_final_condition_set(condition_set);
                 new_chain.allow_intersecting();
                 current_table_opt = scope.schema.tables.get(&new_chain.get_ending_table_id());
@@ -139,6 +246,9 @@ fn build_linked_path(parts: Vec<PathPart>, scope: &Scope) -> Result<LinkedPath,
                 final_column_name = None;
             }
         };
+        if chain_opt.as_ref().map_or(0, |chain| chain.len()) > max_len {
+            return Err(msg::chain_too_long(max_len));
+        }
     }
     Ok(LinkedPath {
         chain: chain_opt,
@@ -146,6 +256,22 @@ fn build_linked_path(parts: Vec<PathPart>, scope: &Scope) -> Result<LinkedPath,
     })
 }
 
+/// We don't yet use the linking column to choose a chain, so outside of strict mode it's ignored
+/// entirely.
+fn validate_linking_column(table_with_many: &TableWithMany, scope: &Scope) -> Result<(), String> {
+    let Some(column_name) = &table_with_many.linking_column else {
+        return Ok(());
+    };
+    let table = scope
+        .get_table_by_name(&table_with_many.table)
+        .ok_or_else(|| msg::table_not_found(&table_with_many.table))?;
+    scope
+        .options
+        .resolve_identifier(&table.column_lookup, column_name)
+        .ok_or_else(|| msg::col_not_in_table(column_name, &table.name))?;
+    Ok(())
+}
+
 fn get_chain_to_table_with_many(
     base: ChainSearchBase,
     target: &TableWithMany,
@@ -159,7 +285,7 @@ fn get_chain_to_table_with_many(
     }
     let target_table = scope
         .get_table_by_name(&target.table)
-        .ok_or_else(|| "Target table not found.".to_string())?;
+        .ok_or_else(|| msg::table_not_found(&target.table))?;
 
     // Success case where the base is already at the target
     if base.get_ending_table_id() == Some(target_table.id) {
@@ -185,7 +311,7 @@ fn get_chain_to_table_with_many(
     }
 
     if base.len() + 1 >= max_chain_len {
-        return Err("Max chain length reached.".to_string());
+        return Err(msg::chain_too_long(max_chain_len));
     }
 
     let get_transitive_chain = |link: MultiLink, max: usize| {
@@ -221,6 +347,9 @@ fn get_chain_to_table_with_many(
     match result {
         ChainSearchResult::Winner(chain) => Ok(chain),
         ChainSearchResult::Tie(_) => Err("Two chains tie for the same length".to_string()),
-        ChainSearchResult::NoneFound => Err("No chain found.".to_string()),
+        ChainSearchResult::NoneFound => Err(msg::no_such_relationship(
+            &base_table.name,
+            &target_table.name,
+        )),
     }
 }
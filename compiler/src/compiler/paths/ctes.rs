@@ -1,19 +1,27 @@
+use querydown_parser::ast::{NullsSort, PathPart, SortDirection, SortSpec};
+
 use crate::{
     compiler::{
-        constants::{CTE_PK_COLUMN_ALIAS, CTE_VALUE_COLUMN_PREFIX},
+        constants::{
+            CTE_PK_COLUMN_ALIAS, CTE_VALUE_COLUMN_PREFIX, LIMITED_CTE_ALIAS, RANKED_CTE_ALIAS,
+            ROW_NUMBER_COLUMN_ALIAS,
+        },
         expr::convert_condition_set,
         join_tree::make_join_from_link,
+        paths::{clarify_path, ClarifiedPathTail},
+        result_columns::sorting::SortingStack,
         scope::Scope,
     },
     errors::msg,
     schema::{
         chain::Chain,
         links::{FilteredLink, Link},
+        Table,
     },
     sql::expr::build,
     sql::{
         expr::{build::cmp, SqlExpr},
-        tree::{Column, CtePurpose, JoinType, Select},
+        tree::{Column, Cte, CtePurpose, JoinType, Select, SortEntry},
     },
 };
 
@@ -37,6 +45,14 @@ pub struct AggregateExprTemplate {
     /// resolved to a table.column expression, and then the agg_wrapper is applied to that
     /// expression.
     agg_wrapper: fn(SqlExpr) -> SqlExpr,
+    /// When present, only the first few rows (sorted by the column) are passed to the aggregate
+    /// expression.
+    row_limit: Option<RowLimit>,
+    /// The paths, relative to the related records, by which the aggregate orders its values
+    ordering: Vec<(Vec<PathPart>, SortSpec)>,
+    /// When true, the aggregate only considers distinct values, which Postgres only allows to be
+    /// ordered by the values themselves
+    is_distinct: bool,
 }
 
 impl AggregateExprTemplate {
@@ -44,8 +60,37 @@ impl AggregateExprTemplate {
         Self {
             column_name,
             agg_wrapper,
+            row_limit: None,
+            ordering: Vec::new(),
+            is_distinct: false,
         }
     }
+
+    pub fn with_row_limit(mut self, row_limit: RowLimit) -> Self {
+        self.row_limit = Some(row_limit);
+        self
+    }
+
+    pub fn with_ordering(
+        mut self,
+        ordering: Vec<(Vec<PathPart>, SortSpec)>,
+        is_distinct: bool,
+    ) -> Self {
+        self.ordering = ordering;
+        self.is_distinct = is_distinct;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RowLimit {
+    pub count: u64,
+    /// The number of rows to skip before the ones which are kept
+    pub offset: Option<u64>,
+    pub direction: SortDirection,
+    /// When true, the limit applies within each group. Otherwise it applies once to all of the
+    /// related records together, before they're grouped.
+    pub is_per_group: bool,
 }
 
 pub fn build_cte_select(
@@ -61,40 +106,48 @@ pub fn build_cte_select(
     let base_table = schema.tables.get(&end.table_id).unwrap();
     let base_column = base_table.columns.get(&end.column_id).unwrap();
     let mut cte_scope = parent_scope.spawn(&base_table);
-    let mut select = Select::from(cte_scope.get_base_table().name.clone());
-    let pk_expr = cte_scope.table_column_expr(&base_table.name, &base_column.name);
+    cte_scope.enter_cte()?;
+    let mut select = Select::from(base_table.name.clone());
+    select.base_table_schema = parent_scope.get_table_schema(base_table);
+    select.base_table_alias = cte_scope.get_base_table_alias().map(str::to_owned);
+    let base_alias = cte_scope.alias_for_base_table()?.to_owned();
+    let pk_expr = cte_scope.table_column_expr(&base_alias, &base_column.name);
     select.grouping.push(pk_expr.clone());
     let pr_expr_col = Column::new(pk_expr, Some(CTE_PK_COLUMN_ALIAS.to_owned()));
     select.columns.push(pr_expr_col);
     select.conditions = convert_condition_set(first_link.condition_set.clone(), &mut cte_scope)?;
-    let mut starting_alias = base_table.name.clone();
+    let mut starting_alias = base_alias;
     let mut ending_table = schema.tables.get(&first_link.get_end().table_id).unwrap();
     for link in links_iter {
         ending_table = schema.tables.get(&link.get_end().table_id).unwrap();
         let ideal_ending_alias = ending_table.name.as_str();
         let ending_alias = cte_scope.get_alias(ideal_ending_alias);
         let join_type = JoinType::Inner;
-        let link_start = link.get_start();
-        let link_end = link.get_end();
+        let filtered_link = link.link;
+        let mut link_joins = vec![];
         if !link.condition_set.is_empty() {
             let link_table = schema.tables.get(&link.get_end().table_id).unwrap();
             let mut link_scope = cte_scope.spawn(&link_table);
             let converted = convert_condition_set(link.condition_set, &mut link_scope)?;
             select.conditions = cmp::and([select.conditions, converted]);
+            let (joins, ctes) = link_scope.decompose_join_tree();
+            link_joins = joins;
+            select.ctes.extend(ctes);
         }
         let join = make_join_from_link(
-            link_start,
+            &filtered_link,
             &starting_alias,
-            link_end,
             &ending_alias,
             join_type,
             &cte_scope,
         );
         select.joins.push(join);
+        select.joins.extend(link_joins);
         starting_alias = ending_alias;
     }
 
     if purpose == CtePurpose::AggregateValue {
+        let value_alias = format!("{}{}", CTE_VALUE_COLUMN_PREFIX, 1);
         let value_expr = match aggregate_expr_template_opt {
             Some(template) => {
                 let column_name = template.column_name;
@@ -103,13 +156,54 @@ pub fn build_cte_select(
                     .resolve_identifier(&ending_table.column_lookup, &column_name)
                     .ok_or_else(|| msg::col_not_in_table(&column_name, &ending_table.name))?;
                 let column = ending_table.columns.get(column_id).unwrap();
-                let reference = cte_scope.table_column_expr(&ending_table.name, &column.name);
+                let reference = cte_scope.table_column_expr(&starting_alias, &column.name);
                 let wrapper = template.agg_wrapper;
-                wrapper(reference)
+                if let Some(row_limit) = template.row_limit {
+                    let limit_rows = if row_limit.is_per_group {
+                        limit_rows_per_group
+                    } else {
+                        limit_rows_overall
+                    };
+                    add_related_records(&mut select, &mut cte_scope);
+                    let select = limit_rows(
+                        select,
+                        reference,
+                        wrapper,
+                        row_limit,
+                        &value_alias,
+                        &mut cte_scope,
+                    );
+                    return Ok(ValueViaCte {
+                        select,
+                        value_alias,
+                    });
+                }
+                if template.ordering.is_empty() {
+                    wrapper(reference)
+                } else {
+                    let order_by = order_related_values(
+                        template.ordering,
+                        ending_table,
+                        &starting_alias,
+                        &mut select,
+                        &mut cte_scope,
+                    )?;
+                    if template.is_distinct
+                        && order_by.iter().any(|e| e.expr.content != reference.content)
+                    {
+                        return Err(msg::distinct_aggregate_ordering());
+                    }
+                    let dialect = &cte_scope.options.dialect;
+                    let order_by = order_by
+                        .iter()
+                        .map(|e| dialect.sort_entry(&e.expr, &e.direction, &e.nulls_sort))
+                        .collect();
+                    build::agg::ordered(wrapper(reference), order_by)
+                }
             }
             None => build::agg::count_star(),
         };
-        let value_alias = format!("{}{}", CTE_VALUE_COLUMN_PREFIX, 1);
+        add_related_records(&mut select, &mut cte_scope);
         select
             .columns
             .push(Column::new(value_expr, Some(value_alias.clone())));
@@ -118,8 +212,173 @@ pub fn build_cte_select(
             value_alias,
         });
     }
+    add_related_records(&mut select, &mut cte_scope);
     Ok(ValueViaCte {
         select,
         value_alias: CTE_PK_COLUMN_ALIAS.to_owned(),
     })
 }
+
+/// Join the records which the rest of the CTE refers to via paths from its base table, e.g. within
+/// its conditions
+fn add_related_records(select: &mut Select, scope: &mut Scope) {
+    let (joins, ctes) = scope.decompose_join_tree();
+    select.joins.extend(joins);
+    select.ctes.extend(ctes);
+}
+
+/// Resolve the columns by which an aggregate orders the values of the related records. The paths
+/// begin at the related records, and any links they follow from there are joined to the select.
+fn order_related_values(
+    ordering: Vec<(Vec<PathPart>, SortSpec)>,
+    ending_table: &Table,
+    ending_alias: &str,
+    select: &mut Select,
+    scope: &mut Scope,
+) -> Result<Vec<SortEntry>, String> {
+    let mut sorting_stack = SortingStack::new(scope.options.nulls_sort.clone());
+    for (parts, sort_spec) in ordering {
+        let clarified_path = clarify_path(parts, &scope.spawn(ending_table))?;
+        let Some(ClarifiedPathTail::Column(column_name)) = clarified_path.tail else {
+            return Err(msg::aggregate_ordering_not_column());
+        };
+        let mut alias = ending_alias.to_owned();
+        let mut table = ending_table;
+        match &clarified_path.head {
+            // Paths from the base table of the CTE share its joins with the conditions
+            Some(head) if ending_alias == scope.alias_for_base_table()? => {
+                let table_id = head.get_ending_table_id();
+                table = scope.schema.tables.get(&table_id).unwrap();
+                alias = scope.join_chain_to_one(head);
+            }
+            Some(head) => {
+                for link in head.get_links() {
+                    table = scope.schema.tables.get(&link.get_end().table_id).unwrap();
+                    let next_alias = scope.get_alias(&table.name);
+                    let join =
+                        make_join_from_link(link, &alias, &next_alias, JoinType::LeftOuter, scope);
+                    select.joins.push(join);
+                    alias = next_alias;
+                }
+            }
+            None => {}
+        }
+        let column_id = scope
+            .options
+            .resolve_identifier(&table.column_lookup, &column_name)
+            .ok_or_else(|| msg::col_not_in_table(&column_name, &table.name))?;
+        let column = table.columns.get(column_id).unwrap();
+        sorting_stack.push(scope.table_column_expr(&alias, &column.name), sort_spec);
+    }
+    Ok(sorting_stack.into())
+}
+
+/// Transform a grouped CTE select so that the aggregate only applies to the first few rows within
+/// each group. The original select (minus its grouping) becomes a nested CTE which ranks the rows
+/// within each group, and the returned select aggregates only the top-ranked rows.
+fn limit_rows_per_group(
+    mut ranked_select: Select,
+    value_expr: SqlExpr,
+    agg_wrapper: fn(SqlExpr) -> SqlExpr,
+    row_limit: RowLimit,
+    value_alias: &str,
+    scope: &mut Scope,
+) -> Select {
+    let partition_expr = std::mem::take(&mut ranked_select.grouping)
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    let order_by =
+        scope
+            .options
+            .dialect
+            .sort_entry(&value_expr, &row_limit.direction, &NullsSort::Last);
+    let row_number_expr = build::window::row_number(partition_expr, order_by);
+    ranked_select.columns.extend([
+        Column::new(value_expr, Some(value_alias.to_owned())),
+        Column::new(row_number_expr, Some(ROW_NUMBER_COLUMN_ALIAS.to_owned())),
+    ]);
+
+    let ranked_alias = scope.get_alias(RANKED_CTE_ALIAS);
+    let row_number_expr = scope.table_column_expr(&ranked_alias, ROW_NUMBER_COLUMN_ALIAS);
+    let annotation = "rows ranked within each group";
+    let mut select = aggregate_nested_cte(
+        ranked_select,
+        ranked_alias,
+        annotation,
+        agg_wrapper,
+        value_alias,
+        scope,
+    );
+    let offset = row_limit.offset.unwrap_or(0);
+    let end_expr = SqlExpr::atom((offset + row_limit.count).to_string());
+    select.conditions = cmp::lte(row_number_expr.clone(), end_expr);
+    if offset > 0 {
+        let offset_expr = SqlExpr::atom(offset.to_string());
+        select.conditions = cmp::and([cmp::gt(row_number_expr, offset_expr), select.conditions]);
+    }
+    select
+}
+
+/// Transform a grouped CTE select so that the aggregate only applies to the first few related
+/// rows overall, regardless of the group to which each belongs. The original select (minus its
+/// grouping) becomes a nested CTE which sorts and limits the rows, and the returned select
+/// aggregates the remaining rows within each group.
+fn limit_rows_overall(
+    mut limited_select: Select,
+    value_expr: SqlExpr,
+    agg_wrapper: fn(SqlExpr) -> SqlExpr,
+    row_limit: RowLimit,
+    value_alias: &str,
+    scope: &mut Scope,
+) -> Select {
+    limited_select.grouping.clear();
+    limited_select.sorting = vec![SortEntry {
+        expr: value_expr.clone(),
+        direction: row_limit.direction,
+        nulls_sort: NullsSort::Last,
+    }];
+    limited_select.limit = Some(row_limit.count);
+    limited_select.offset = row_limit.offset;
+    limited_select
+        .columns
+        .push(Column::new(value_expr, Some(value_alias.to_owned())));
+    let limited_alias = scope.get_alias(LIMITED_CTE_ALIAS);
+    let annotation = "rows limited across all groups";
+    aggregate_nested_cte(
+        limited_select,
+        limited_alias,
+        annotation,
+        agg_wrapper,
+        value_alias,
+        scope,
+    )
+}
+
+/// Build a select which aggregates the values from a nested CTE within each group. The nested CTE
+/// must have a primary key column and a value column.
+fn aggregate_nested_cte(
+    nested_select: Select,
+    nested_alias: String,
+    annotation: &str,
+    agg_wrapper: fn(SqlExpr) -> SqlExpr,
+    value_alias: &str,
+    scope: &mut Scope,
+) -> Select {
+    let pk_expr = scope.table_column_expr(&nested_alias, CTE_PK_COLUMN_ALIAS);
+    let value_expr = scope.table_column_expr(&nested_alias, value_alias);
+    let mut select = Select::from(nested_alias.clone());
+    select.ctes.push(Cte {
+        alias: nested_alias,
+        select: nested_select,
+        purpose: CtePurpose::AggregateValue,
+        join_column_name: CTE_PK_COLUMN_ALIAS.to_owned(),
+        annotation: annotation.to_owned(),
+    });
+    select.columns = vec![
+        Column::new(pk_expr.clone(), Some(CTE_PK_COLUMN_ALIAS.to_owned())),
+        Column::new(agg_wrapper(value_expr), Some(value_alias.to_owned())),
+    ];
+    select.grouping.push(pk_expr);
+    select
+}
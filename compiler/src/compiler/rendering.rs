@@ -1,9 +1,13 @@
 use itertools::Itertools;
-use querydown_parser::ast::{NullsSort, SortDirection};
 
-use crate::{compiler::scope::Scope, sql::tree::*};
+use crate::{
+    compiler::scope::Scope,
+    options::{BooleanRendering, CteMaterialization},
+    schema::{DataType, Table},
+    sql::{expr::build::value, tree::*},
+};
 
-use super::constants::INDENT_SPACER;
+use super::constants::{INDENT_SPACER, UNNEST_ORDINALITY_COLUMN_ALIAS, UNNEST_VALUE_COLUMN_ALIAS};
 
 pub trait Render {
     fn render(&self, scope: &mut Scope) -> String;
@@ -17,15 +21,48 @@ impl Render for SqlExpr {
 
 impl Render for Select {
     fn render(&self, scope: &mut Scope) -> String {
-        let base_table_name = scope.options.dialect.quote_identifier(&self.base_table);
-
-        let select = "SELECT".to_string();
+        let select = if self.distinct_on.is_empty() {
+            "SELECT".to_string()
+        } else {
+            let distinct_on = self.distinct_on.iter().join(", ");
+            format!("SELECT DISTINCT ON ({distinct_on})")
+        };
         let columns = indent(self.columns.render(scope));
-        let from = format!("FROM {}", base_table_name);
+        let from = match (&self.base_subquery, &self.base_table) {
+            (Some(select), _) => {
+                let alias = self.base_table_alias.as_deref().unwrap_or_default();
+                format!("FROM {}", aliased(subquery(select, scope), alias, scope))
+            }
+            (None, Some(base_table)) => {
+                let schema = self.base_table_schema.as_deref();
+                let alias = self.base_table_alias.as_deref();
+                let table = match &self.system_time_as_of {
+                    Some(point) => {
+                        let table = qualified_table(schema, base_table, scope);
+                        let clause = scope.options.dialect.system_time_as_of(point);
+                        let table = format!("{table} {clause}");
+                        match alias {
+                            Some(alias) => aliased(table, alias, scope),
+                            None => table,
+                        }
+                    }
+                    None => source_table(schema, base_table, alias, scope),
+                };
+                let from = format!("FROM {table}");
+                match &self.table_sample {
+                    Some(sample) => {
+                        format!("{from} {}", scope.options.dialect.table_sample(sample))
+                    }
+                    None => from,
+                }
+            }
+            (None, None) => String::new(),
+        };
         let joins = self.joins.render(scope);
+        let unnests = self.unnests.iter().map(|u| u.render(scope)).join("\n");
 
         let ctes = self.ctes.render(scope);
-        let main = [select, columns, from, joins]
+        let main = [select, columns, from, joins, unnests]
             .into_iter()
             .filter(|s| !s.is_empty())
             .join("\n");
@@ -43,7 +80,19 @@ impl Render for Select {
                 .iter()
                 .map(|g| g.render(scope))
                 .filter(|s| !s.is_empty())
-                .join(", ");
+                .collect::<Vec<_>>();
+            let grouping = match &self.grouping_extension {
+                None => grouping.join(", "),
+                Some(GroupingExtension::Rollup) => format!("ROLLUP({})", grouping.join(", ")),
+                Some(GroupingExtension::Cube) => format!("CUBE({})", grouping.join(", ")),
+                Some(GroupingExtension::Sets(sets)) => {
+                    let sets = sets
+                        .iter()
+                        .map(|set| format!("({})", set.iter().map(|i| &grouping[*i]).join(", ")))
+                        .join(", ");
+                    format!("GROUPING SETS ({sets})")
+                }
+            };
             format!("GROUP BY {grouping}")
         };
         let order = if self.sorting.is_empty() {
@@ -52,7 +101,21 @@ impl Render for Select {
             let sorting = indent(self.sorting.render(scope));
             format!("ORDER BY\n{sorting}")
         };
-        [ctes, main, where_, group, order]
+        let pagination = scope.options.dialect.pagination(self.limit, self.offset);
+        let lock = match &self.lock {
+            Some(lock) => {
+                // When other tables are joined, we only lock rows of the base table. Postgres
+                // can't lock the nullable side of an outer join.
+                let table = self
+                    .base_table_alias
+                    .as_deref()
+                    .or(self.base_table.as_deref())
+                    .filter(|_| !self.joins.is_empty() || !self.unnests.is_empty());
+                scope.options.dialect.locking(lock, table)
+            }
+            None => String::new(),
+        };
+        [ctes, main, where_, group, order, pagination, lock]
             .into_iter()
             .filter(|s| !s.is_empty())
             .join("\n")
@@ -78,7 +141,13 @@ impl Render for Cte {
     fn render(&self, scope: &mut Scope) -> String {
         let alias = scope.options.dialect.quote_identifier(&self.alias);
         let select = indent(self.select.render(scope));
-        format!("{alias} AS (\n{select}\n)")
+        let materialization = match scope.options.cte_materialization.get(&self.purpose) {
+            Some(CteMaterialization::Materialized) => "MATERIALIZED ",
+            Some(CteMaterialization::NotMaterialized) => "NOT MATERIALIZED ",
+            None => "",
+        };
+        let cte = format!("{alias} AS {materialization}(\n{select}\n)");
+        annotate(cte, &self.annotation, scope)
     }
 }
 
@@ -93,19 +162,46 @@ impl Render for Vec<Join> {
 
 impl Render for Join {
     fn render(&self, scope: &mut Scope) -> String {
-        let quoted_table = scope.options.dialect.quote_identifier(&self.table);
-        let table_expr = if self.alias == self.table {
-            quoted_table
-        } else {
-            let quoted_alias = scope.options.dialect.quote_identifier(&self.alias);
-            format!("{} AS {}", quoted_table, quoted_alias)
+        let alias = Some(self.alias.as_str()).filter(|a| *a != self.table);
+        let table_expr = match (&self.lateral_subquery, &self.subquery) {
+            (Some(select), _) => {
+                let lateral = format!("LATERAL {}", subquery(select, scope));
+                aliased(lateral, &self.alias, scope)
+            }
+            (None, Some(select)) => aliased(subquery(select, scope), &self.alias, scope),
+            (None, None) if self.is_cte => qualified_table(None, &self.table, scope),
+            (None, None) => source_table(self.table_schema.as_deref(), &self.table, alias, scope),
         };
-        let condition_set = indent(self.conditions.render(scope));
         let join_type = match self.join_type {
             JoinType::Inner => "JOIN",
             JoinType::LeftOuter => "LEFT JOIN",
+            JoinType::FullOuter => "FULL OUTER JOIN",
+            JoinType::Cross => "CROSS JOIN",
+        };
+        let join = match self.join_type {
+            JoinType::Cross => format!("{join_type} {table_expr}"),
+            _ => {
+                let condition_set = indent(self.conditions.render(scope));
+                format!("{join_type} {table_expr} ON\n{condition_set}")
+            }
         };
-        format!("{join_type} {table_expr} ON\n{condition_set}")
+        annotate(join, &self.annotation, scope)
+    }
+}
+
+impl Render for Unnest {
+    fn render(&self, scope: &mut Scope) -> String {
+        let dialect = &scope.options.dialect;
+        let value = dialect.quote_identifier(UNNEST_VALUE_COLUMN_ALIAS);
+        let (function, columns) = if self.with_ordinality {
+            let ordinality = dialect.quote_identifier(UNNEST_ORDINALITY_COLUMN_ALIAS);
+            let function = format!("unnest({}) WITH ORDINALITY", self.array);
+            (function, format!("{value}, {ordinality}"))
+        } else {
+            (format!("unnest({})", self.array), value)
+        };
+        let function = aliased(function, &self.alias, scope);
+        format!("CROSS JOIN LATERAL {function}({columns})")
     }
 }
 
@@ -113,9 +209,9 @@ impl Render for Vec<Column> {
     fn render(&self, scope: &mut Scope) -> String {
         if self.len() == 0 {
             let base_table_name = scope
-                .options
-                .dialect
-                .quote_identifier(&scope.get_base_table().name);
+                .alias_for_base_table()
+                .map(|alias| scope.options.dialect.quote_identifier(alias))
+                .unwrap_or_default();
             format!("{base_table_name}.*")
         } else {
             self.iter()
@@ -128,13 +224,11 @@ impl Render for Vec<Column> {
 
 impl Render for Column {
     fn render(&self, scope: &mut Scope) -> String {
-        let alias = self
-            .alias
-            .as_ref()
-            .map(|a| scope.options.dialect.quote_identifier(a))
-            .map(|alias| format!(" AS {}", alias))
-            .unwrap_or_default();
-        format!("{}{}", self.expr, alias)
+        let expr = self.expr.to_string();
+        match &self.alias {
+            Some(alias) => aliased(expr, alias, scope),
+            None => expr,
+        }
     }
 }
 
@@ -148,21 +242,173 @@ impl Render for Vec<SortEntry> {
 }
 
 impl Render for SortEntry {
-    fn render(&self, _: &mut Scope) -> String {
-        let direction = match self.direction {
-            SortDirection::Asc => "ASC",
-            SortDirection::Desc => "DESC",
-        };
-        let nulls_sort = match self.nulls_sort {
-            NullsSort::First => "NULLS FIRST",
-            NullsSort::Last => "NULLS LAST",
-        };
-        format!("{} {} {}", self.expr, direction, nulls_sort)
+    fn render(&self, scope: &mut Scope) -> String {
+        scope
+            .options
+            .dialect
+            .sort_entry(&self.expr, &self.direction, &self.nulls_sort)
     }
 }
 
+/// Render a select within parentheses, for use as an expression
+pub fn subquery(select: &Select, scope: &mut Scope) -> String {
+    format!("(\n{}\n)", indent(select.render(scope)))
+}
+
+impl Render for Delete {
+    fn render(&self, scope: &mut Scope) -> String {
+        let schema = self.base_table_schema.as_deref();
+        let alias = self.base_table_alias.as_deref();
+        let table = modified_table(schema, &self.base_table, alias, scope);
+        let delete = format!("DELETE FROM {table}");
+        let where_ = where_clause(&self.conditions, scope);
+        [delete, where_]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .join("\n")
+    }
+}
+
+impl Render for Update {
+    fn render(&self, scope: &mut Scope) -> String {
+        let schema = self.base_table_schema.as_deref();
+        let alias = self.base_table_alias.as_deref();
+        let table = modified_table(schema, &self.base_table, alias, scope);
+        let update = format!("UPDATE {table}");
+        let assignments = self
+            .assignments
+            .iter()
+            .map(|(column, value)| {
+                let column = scope.options.dialect.quote_identifier(column);
+                format!("{column} = {}", value.render(scope))
+            })
+            .join(",\n");
+        let set = format!("SET\n{}", indent(assignments));
+        let where_ = where_clause(&self.conditions, scope);
+        [update, set, where_]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .join("\n")
+    }
+}
+
+/// The table modified by a DELETE or UPDATE statement, with its alias if any
+fn modified_table(schema: Option<&str>, table: &str, alias: Option<&str>, scope: &Scope) -> String {
+    let table = qualified_table(schema, table, scope);
+    match alias {
+        Some(alias) => aliased(table, alias, scope),
+        None => table,
+    }
+}
+
+/// A table to select rows from, followed by its alias if it has one. Tables with values given
+/// inline within the schema are rendered as a `VALUES` list, which always has an alias.
+fn source_table(schema: Option<&str>, table: &str, alias: Option<&str>, scope: &Scope) -> String {
+    let values_table = scope
+        .schema
+        .table_lookup
+        .get(table)
+        .and_then(|id| scope.schema.tables.get(id))
+        .filter(|t| schema.is_none() && t.values.is_some());
+    match values_table {
+        Some(values_table) => values_list(values_table, alias.unwrap_or(table), scope),
+        None => modified_table(schema, table, alias, scope),
+    }
+}
+
+fn values_list(table: &Table, alias: &str, scope: &Scope) -> String {
+    let dialect = &scope.options.dialect;
+    let columns = table
+        .columns
+        .values()
+        .sorted_by_key(|c| c.id)
+        .collect::<Vec<_>>();
+    let mut rows = table.values.iter().flatten().enumerate().map(|(i, row)| {
+        let mut values = row.iter().zip(&columns).map(|(value, column)| {
+            let literal = value_literal(value, scope);
+            // The database infers the type of each column from the values within it, so the first
+            // row carries the declared types of the values which don't imply one themselves
+            let needs_cast = i == 0
+                && (value.is_null() || (value.is_string() && column.data_type != DataType::Text));
+            match dialect.type_name(&column.data_type).filter(|_| needs_cast) {
+                Some(type_name) => dialect.cast(literal, &type_name),
+                None => literal,
+            }
+        });
+        format!("({})", values.join(", "))
+    });
+    let values = format!("VALUES\n{}", indent(rows.join(",\n")));
+    if dialect.supports_values_column_list() {
+        let column_names = columns
+            .iter()
+            .map(|c| dialect.quote_identifier(&c.name))
+            .join(", ");
+        let relation = format!("(\n{}\n)", indent(values));
+        return format!("{}({column_names})", aliased(relation, alias, scope));
+    }
+    // Without a column list, the columns of a VALUES list are named by their positions
+    let mut renamed_columns = columns.iter().enumerate().map(|(i, c)| {
+        let positional_name = dialect.quote_identifier(&format!("column{}", i + 1));
+        aliased(positional_name, &c.name, scope)
+    });
+    let select = format!(
+        "SELECT\n{}\nFROM (\n{}\n)",
+        indent(renamed_columns.join(",\n")),
+        indent(values)
+    );
+    aliased(format!("(\n{}\n)", indent(select)), alias, scope)
+}
+
+fn value_literal(value: &serde_json::Value, scope: &Scope) -> SqlExpr {
+    let is_integer = scope.options.boolean_rendering == BooleanRendering::Integer;
+    match value {
+        serde_json::Value::Bool(true) if is_integer => value::one(),
+        serde_json::Value::Bool(false) if is_integer => value::zero(),
+        serde_json::Value::Bool(true) => value::true_(),
+        serde_json::Value::Bool(false) => value::false_(),
+        serde_json::Value::Number(n) => SqlExpr::atom(n.to_string()),
+        serde_json::Value::String(s) => SqlExpr::atom(scope.options.dialect.quote_string(s)),
+        _ => value::null(),
+    }
+}
+
+/// A column or table followed by its alias, with or without `AS` as configured
+fn aliased(sql: String, alias: &str, scope: &Scope) -> String {
+    let alias = scope.options.dialect.quote_identifier(alias);
+    if scope.options.alias_keyword {
+        format!("{sql} AS {alias}")
+    } else {
+        format!("{sql} {alias}")
+    }
+}
+
+fn where_clause(conditions: &SqlExpr, scope: &mut Scope) -> String {
+    if conditions.is_empty() {
+        return String::new();
+    }
+    format!("WHERE\n{}", indent(conditions.render(scope)))
+}
+
 fn indent(s: String) -> String {
     s.lines()
         .map(|line| format!("{}{}", INDENT_SPACER, line))
         .join("\n")
 }
+
+/// Precede the SQL with a comment, if the options call for it
+fn annotate(sql: String, annotation: &str, scope: &Scope) -> String {
+    if !scope.options.annotate || annotation.is_empty() {
+        return sql;
+    }
+    let comment = scope.options.dialect.comment(annotation);
+    format!("{comment}\n{sql}")
+}
+
+/// The schema is inserted verbatim because it may also contain a catalog
+fn qualified_table(schema: Option<&str>, table: &str, scope: &Scope) -> String {
+    let quoted_table = scope.options.dialect.quote_identifier(table);
+    match schema {
+        Some(schema) => format!("{}.{}", schema, quoted_table),
+        None => quoted_table,
+    }
+}
@@ -4,61 +4,411 @@ use itertools::Itertools;
 use querydown_parser::ast::*;
 
 use crate::{
-    compiler::paths::{clarify_path, ClarifiedPathTail},
+    compiler::paths::{clarify_path, is_path_to_many, ClarifiedPathTail},
     errors::msg,
+    options::BooleanRendering,
+    schema::{primitive_schema::DisplayFormat, DataType},
     sql::{
-        expr::SqlExpr,
+        expr::{build::cond::bit_of, SqlExpr},
         tree::{Column, SortEntry},
     },
 };
 
-use self::sorting::SortingStack;
+use self::{
+    grouping::{Grouping, GroupingStack},
+    sorting::SortingStack,
+};
+
+use super::{
+    compiled_query::ColumnMetadata,
+    expr::convert_expr,
+    scope::Scope,
+    type_inference::{
+        chain_always_has_target, infer_display_format, infer_name, infer_nullability, infer_type,
+    },
+};
+
+/// Metadata about each result column, in order
+pub type ResultColumnTypes = Vec<ColumnMetadata>;
+
+/// The name and expression of each result column flagged as distinct
+type DistinctOn = Vec<(String, SqlExpr)>;
 
-use super::{expr::convert_expr, scope::Scope};
+/// The columns, sorting, distinct expressions, and grouping of a SELECT, followed by metadata about
+/// each column
+type ConvertedResultColumns = (
+    Vec<Column>,
+    Vec<SortEntry>,
+    Vec<SqlExpr>,
+    Grouping,
+    ResultColumnTypes,
+);
 
 pub fn convert_result_columns(
     result_columns: Vec<ResultColumnStatement>,
     scope: &mut Scope,
-) -> Result<(Vec<Column>, Vec<SortEntry>), String> {
+) -> Result<ConvertedResultColumns, String> {
     let mut columns = Vec::<Column>::new();
-    let mut sorting_stack = SortingStack::new();
+    let mut column_types = ResultColumnTypes::new();
+    let mut sorting_stack = SortingStack::new(scope.options.nulls_sort.clone());
+    let mut grouping_stack = GroupingStack::new();
+    let mut distinct_on = DistinctOn::new();
+    let mut result_columns = inline_alias_references(result_columns, scope);
+    if scope.options.infer_grouping && !result_columns.iter().any(is_grouped) {
+        result_columns = infer_grouping(result_columns, scope);
+    }
+    scope.set_grouped(result_columns.iter().any(is_grouped));
     for column_statement in result_columns {
         match column_statement {
             ResultColumnStatement::Spec(spec) => {
-                handle_spec(spec, &mut columns, &mut sorting_stack, scope)?;
+                handle_spec(
+                    spec,
+                    &mut columns,
+                    &mut column_types,
+                    &mut sorting_stack,
+                    &mut grouping_stack,
+                    &mut distinct_on,
+                    scope,
+                )?;
             }
             ResultColumnStatement::Glob(glob) => {
-                handle_glob(glob, &mut columns, &mut sorting_stack, scope)?;
+                handle_glob(
+                    glob,
+                    &mut columns,
+                    &mut column_types,
+                    &mut sorting_stack,
+                    &mut grouping_stack,
+                    &mut distinct_on,
+                    scope,
+                )?;
             }
         }
     }
-    Ok((columns, sorting_stack.into()))
+    let grouping = grouping_stack.try_into_grouping()?;
+    let sorting: Vec<SortEntry> = sorting_stack.into();
+    let distinct_on = align_distinct_on(distinct_on, &sorting, scope)?;
+    Ok((columns, sorting, distinct_on, grouping, column_types))
+}
+
+/// Postgres requires the leading ORDER BY expressions to match the DISTINCT ON expressions, in any
+/// order and regardless of direction or NULLS placement. We check that here so that we don't
+/// produce SQL which the database rejects.
+fn align_distinct_on(
+    distinct_on: DistinctOn,
+    sorting: &[SortEntry],
+    scope: &Scope,
+) -> Result<Vec<SqlExpr>, String> {
+    if distinct_on.is_empty() {
+        return Ok(vec![]);
+    }
+    if !scope.options.dialect.supports_distinct_on() {
+        return Err(msg::distinct_on_unsupported());
+    }
+    if !sorting.is_empty() {
+        let leading_sorting = &sorting[..distinct_on.len().min(sorting.len())];
+        for (name, expr) in distinct_on.iter() {
+            if !leading_sorting
+                .iter()
+                .any(|e| e.expr.content == expr.content)
+            {
+                return Err(msg::distinct_on_not_sorted_first(name));
+            }
+        }
+    }
+    Ok(distinct_on.into_iter().map(|(_, expr)| expr).collect())
+}
+
+/// SQL doesn't let one result column refer to the alias of another, so when a result column uses
+/// the alias of an earlier one, e.g. `$a*2->x $x+1->y`, we substitute the earlier expression. Names
+/// which resolve against the schema keep their usual meaning.
+fn inline_alias_references(
+    result_columns: Vec<ResultColumnStatement>,
+    scope: &Scope,
+) -> Vec<ResultColumnStatement> {
+    let mut aliased_exprs = HashMap::<String, Expr>::new();
+    result_columns
+        .into_iter()
+        .map(|column_statement| {
+            let ResultColumnStatement::Spec(mut spec) = column_statement else {
+                return column_statement;
+            };
+            if !aliased_exprs.is_empty() {
+                spec.expr = inline_aliases(spec.expr, &aliased_exprs, scope);
+            }
+            if let Some(alias) = &spec.alias {
+                aliased_exprs.insert(alias.clone(), spec.expr.clone());
+            }
+            ResultColumnStatement::Spec(spec)
+        })
+        .collect()
+}
+
+fn inline_aliases(expr: Expr, aliased_exprs: &HashMap<String, Expr>, scope: &Scope) -> Expr {
+    let inline = |e: Expr| inline_aliases(e, aliased_exprs, scope);
+    let inline_box = |e: Box<Expr>| Box::new(inline(*e));
+    let inline_condition_set = |condition_set: ConditionSet| ConditionSet {
+        entries: condition_set.entries.into_iter().map(inline).collect(),
+        ..condition_set
+    };
+    match expr {
+        Expr::Path(parts, _) => {
+            let aliased_expr = match &parts[..] {
+                [PathPart::Column(name)] => aliased_exprs.get(name),
+                _ => None,
+            };
+            match aliased_expr {
+                Some(e) if clarify_path(parts.clone(), scope).is_err() => e.clone(),
+                _ => Expr::path(parts),
+            }
+        }
+        Expr::ConditionSet(condition_set) => {
+            Expr::ConditionSet(inline_condition_set(condition_set))
+        }
+        Expr::Call(call) => Expr::Call(Call {
+            args: call.args.into_iter().map(inline).collect(),
+            ..call
+        }),
+        Expr::Product(a, b) => Expr::Product(inline_box(a), inline_box(b)),
+        Expr::Quotient(a, b) => Expr::Quotient(inline_box(a), inline_box(b)),
+        Expr::Power(a, b) => Expr::Power(inline_box(a), inline_box(b)),
+        Expr::Sum(a, b) => Expr::Sum(inline_box(a), inline_box(b)),
+        Expr::Difference(a, b) => Expr::Difference(inline_box(a), inline_box(b)),
+        Expr::Comparison(comparison) => {
+            let inline_side = |side: ComparisonSide| match side {
+                ComparisonSide::Expr(e) => ComparisonSide::Expr(inline(e)),
+                ComparisonSide::Expansion(condition_set) => {
+                    ComparisonSide::Expansion(inline_condition_set(condition_set))
+                }
+                ComparisonSide::Range(range) => ComparisonSide::Range(Range {
+                    lower: RangeBound {
+                        expr: inline(range.lower.expr),
+                        ..range.lower
+                    },
+                    upper: RangeBound {
+                        expr: inline(range.upper.expr),
+                        ..range.upper
+                    },
+                }),
+            };
+            let Comparison {
+                left,
+                operator,
+                right,
+            } = *comparison;
+            Expr::Comparison(Box::new(Comparison {
+                left: inline_side(left),
+                operator,
+                right: inline_side(right),
+            }))
+        }
+        Expr::Window(window) => Expr::Window(Window {
+            specs: window
+                .specs
+                .into_iter()
+                .map(|spec| ColumnSpec {
+                    expr: inline(spec.expr),
+                    ..spec
+                })
+                .collect(),
+            ..window
+        }),
+        // Paths within these refer to other tables
+        Expr::HasQuantity(_) => expr,
+        Expr::Number(..)
+        | Expr::Date(..)
+        | Expr::Duration(..)
+        | Expr::String(..)
+        | Expr::Bytes(..)
+        | Expr::Uuid(..)
+        | Expr::Variable(_)
+        | Expr::Placeholder(_) => expr,
+    }
+}
+
+/// When any result column aggregates values from the base table, group by every other column, as
+/// the query would be invalid otherwise. Columns without such an aggregate are left alone when there
+/// are none.
+fn infer_grouping(
+    result_columns: Vec<ResultColumnStatement>,
+    scope: &Scope,
+) -> Vec<ResultColumnStatement> {
+    let is_aggregated = |spec: &ColumnSpec| has_grouped_aggregate(&spec.expr, scope);
+    let has_aggregate = result_columns
+        .iter()
+        .any(|column_statement| match column_statement {
+            ResultColumnStatement::Spec(spec) => is_aggregated(spec),
+            ResultColumnStatement::Glob(glob) => glob.specs.iter().any(is_aggregated),
+        });
+    if !has_aggregate {
+        return result_columns;
+    }
+    let group = |mut spec: ColumnSpec| {
+        if !is_aggregated(&spec) {
+            spec.column_control.group = Some(GroupSpec::default());
+        }
+        spec
+    };
+    result_columns
+        .into_iter()
+        .map(|column_statement| match column_statement {
+            ResultColumnStatement::Spec(spec) => ResultColumnStatement::Spec(group(spec)),
+            ResultColumnStatement::Glob(glob) => ResultColumnStatement::Glob(ColumnGlob {
+                specs: glob.specs.into_iter().map(group).collect(),
+                ..glob
+            }),
+        })
+        .collect()
+}
+
+/// True when the expression applies an aggregate function to values from the base table, which
+/// would aggregate across groups if the query were grouped. Aggregates over paths to many records
+/// and window functions yield one value per row instead.
+fn has_grouped_aggregate(expr: &Expr, scope: &Scope) -> bool {
+    let has = |e: &Expr| has_grouped_aggregate(e, scope);
+    let condition_set_has = |condition_set: &ConditionSet| condition_set.entries.iter().any(has);
+    match expr {
+        Expr::Call(call) => {
+            let is_grouped_aggregate = call.dimension == FunctionDimension::Aggregate
+                && call
+                    .args
+                    .first()
+                    .is_some_and(|a| !matches!(a, Expr::Window(_)) && !is_path_to_many(a, scope));
+            is_grouped_aggregate || call.args.iter().any(has)
+        }
+        Expr::ConditionSet(condition_set) => condition_set_has(condition_set),
+        Expr::Product(a, b)
+        | Expr::Quotient(a, b)
+        | Expr::Power(a, b)
+        | Expr::Sum(a, b)
+        | Expr::Difference(a, b) => has(a) || has(b),
+        Expr::Comparison(comparison) => {
+            [&comparison.left, &comparison.right]
+                .into_iter()
+                .any(|side| match side {
+                    ComparisonSide::Expr(e) => has(e),
+                    ComparisonSide::Expansion(condition_set) => condition_set_has(condition_set),
+                    ComparisonSide::Range(range) => {
+                        has(&range.lower.expr) || has(&range.upper.expr)
+                    }
+                })
+        }
+        Expr::Path(..)
+        | Expr::HasQuantity(_)
+        | Expr::Window(_)
+        | Expr::Number(..)
+        | Expr::Date(..)
+        | Expr::Duration(..)
+        | Expr::String(..)
+        | Expr::Bytes(..)
+        | Expr::Uuid(..)
+        | Expr::Variable(_)
+        | Expr::Placeholder(_) => false,
+    }
+}
+
+/// True when the statement groups by any column
+fn is_grouped(column_statement: &ResultColumnStatement) -> bool {
+    match column_statement {
+        ResultColumnStatement::Spec(spec) => spec.column_control.group.is_some(),
+        ResultColumnStatement::Glob(glob) => glob
+            .specs
+            .iter()
+            .any(|spec| spec.column_control.group.is_some()),
+    }
 }
 
 fn handle_spec(
     spec: ColumnSpec,
     columns: &mut Vec<Column>,
+    column_types: &mut ResultColumnTypes,
     sorting_stack: &mut SortingStack,
+    grouping_stack: &mut GroupingStack,
+    distinct_on: &mut DistinctOn,
     scope: &mut Scope,
 ) -> Result<(), String> {
-    let expr = convert_expr(spec.expr, scope)?;
+    let name = spec
+        .alias
+        .clone()
+        .unwrap_or_else(|| infer_name(&spec.expr, scope));
+    let mut data_type = infer_type(&spec.expr, scope);
+    let nullable = infer_nullability(&spec.expr, scope);
+    let is_computed = !matches!(spec.expr, Expr::Path(..)) || is_path_to_many(&spec.expr, scope);
+    let display_format = Some(&spec.expr)
+        .filter(|_| scope.options.display_formats && !is_computed)
+        .and_then(|expr| infer_display_format(expr, scope));
+    // Columns and literals already hold whatever the database uses for booleans
+    let needs_bit = scope.options.boolean_rendering == BooleanRendering::Integer
+        && data_type == DataType::Boolean
+        && !matches!(spec.expr, Expr::Path(..) | Expr::Variable(_));
+    let grouped_aggregate_count = scope.get_grouped_aggregate_count();
+    let mut expr = convert_expr(spec.expr, scope)?;
+    if needs_bit {
+        expr = bit_of(expr);
+    }
+    let has_grouped_aggregate = scope.get_grouped_aggregate_count() > grouped_aggregate_count;
+    if let Some(group_spec) = spec.column_control.group {
+        grouping_stack.push(expr.clone(), group_spec);
+    } else if scope.is_grouped() && !has_grouped_aggregate {
+        return Err(msg::ungrouped_column(&name));
+    }
     let alias = spec.alias;
-    if let Some(sort_spec) = spec.column_control.sort {
-        let sorting_expr = alias
+    // DISTINCT ON resolves aliases and positions the same way ORDER BY does, so both use the same
+    // expression. A formatted column is sorted by its own values rather than by its text.
+    let sorting_expr = if display_format.is_some() {
+        expr.clone()
+    } else if scope.options.sort_by_position && scope.options.dialect.sort_by_position() {
+        SqlExpr::atom((columns.len() + 1).to_string())
+    } else {
+        alias
             .as_ref()
+            .filter(|_| scope.options.dialect.sort_by_alias())
             .map(|a| SqlExpr::atom(scope.options.dialect.quote_identifier(a)))
-            .unwrap_or_else(|| expr.clone());
+            .unwrap_or_else(|| expr.clone())
+    };
+    if spec.column_control.is_distinct_on {
+        distinct_on.push((name.clone(), sorting_expr.clone()));
+    }
+    if let Some(sort_spec) = spec.column_control.sort {
         sorting_stack.push(sorting_expr, sort_spec);
     }
+    let cast_type_name = Some(&data_type)
+        .filter(|_| scope.options.cast_computed_columns && is_computed)
+        .and_then(|data_type| scope.options.dialect.type_name(data_type));
+    let expr = match cast_type_name {
+        Some(type_name) => scope.options.dialect.cast(expr, &type_name),
+        None => expr,
+    };
+    let expr = match display_format {
+        Some(DisplayFormat::DecimalPlaces(places)) => {
+            let places = SqlExpr::atom(places.to_string());
+            scope.options.dialect.round(expr, Some(places))
+        }
+        Some(DisplayFormat::Pattern(pattern)) => {
+            data_type = DataType::Text;
+            scope
+                .options
+                .dialect
+                .to_char(expr, &pattern)
+                .ok_or_else(|| msg::display_pattern_unsupported(&name))?
+        }
+        None => expr,
+    };
     columns.push(Column { expr, alias });
-    // TODO convert GroupSpec into GROUP BY
+    column_types.push(ColumnMetadata {
+        name,
+        data_type,
+        nullable,
+    });
     Ok(())
 }
 
 fn handle_glob(
     glob: ColumnGlob,
     columns: &mut Vec<Column>,
+    column_types: &mut ResultColumnTypes,
     sorting_stack: &mut SortingStack,
+    grouping_stack: &mut GroupingStack,
+    distinct_on: &mut DistinctOn,
     scope: &mut Scope,
 ) -> Result<(), String> {
     scope.with_path_prefix(glob.head.clone(), |scope| -> Result<(), String> {
@@ -67,14 +417,23 @@ fn handle_glob(
                 let sql_expr = convert_expr(spec.expr.clone(), scope)?;
                 sorting_stack.push(sql_expr, sort_spec.to_owned());
             }
-            // TODO convert GroupSpec into GROUP BY
+            if let Some(ref group_spec) = spec.column_control.group {
+                let sql_expr = convert_expr(spec.expr.clone(), scope)?;
+                grouping_stack.push(sql_expr, group_spec.to_owned());
+            }
+            if spec.column_control.is_distinct_on {
+                let name = infer_name(&spec.expr, scope);
+                let sql_expr = convert_expr(spec.expr.clone(), scope)?;
+                distinct_on.push((name, sql_expr));
+            }
         }
         Ok(())
     })?;
 
-    let (table, table_alias) = if glob.head.len() == 0 {
-        let base_table = scope.get_base_table();
-        (scope.get_base_table(), base_table.name.clone())
+    let (table, table_alias, is_joined_via_left_join) = if glob.head.len() == 0 {
+        let base_table = scope.get_base_table()?;
+        let alias = scope.alias_for_base_table()?.to_owned();
+        (base_table, alias, scope.may_full_outer_join())
     } else {
         let clarified_path = clarify_path(glob.head, scope)?;
         if let Some(tail) = clarified_path.tail {
@@ -93,14 +452,16 @@ fn handle_glob(
             .get(&chain_to_one.get_ending_table_id())
             .unwrap();
         let table_alias = scope.join_chain_to_one(&chain_to_one);
-        (table, table_alias)
+        let is_joined_via_left_join = !chain_always_has_target(&chain_to_one, scope);
+        (table, table_alias, is_joined_via_left_join)
     };
 
     let mut hidden_columns: HashSet<usize> = HashSet::new();
+    let mut grouped_columns: HashSet<usize> = HashSet::new();
     let mut column_aliases: HashMap<usize, String> = HashMap::new();
 
     for spec in glob.specs {
-        if let Expr::Path(ref path) = spec.expr {
+        if let Expr::Path(ref path, _) = spec.expr {
             if let Ok(first_path_part) = path.iter().exactly_one() {
                 if let PathPart::Column(column_name) = first_path_part {
                     let column_id = scope
@@ -111,6 +472,9 @@ fn handle_glob(
                     if spec.column_control.is_hidden {
                         hidden_columns.insert(column_id);
                     }
+                    if spec.column_control.group.is_some() {
+                        grouped_columns.insert(column_id);
+                    }
                     if let Some(alias) = spec.alias {
                         column_aliases.insert(column_id, alias);
                     }
@@ -123,14 +487,97 @@ fn handle_glob(
         let expr = scope.table_column_expr(&table_alias, &column.name);
         let alias = column_aliases.get(&column.id).cloned();
         if !hidden_columns.contains(&column.id) {
+            if scope.is_grouped() && !grouped_columns.contains(&column.id) {
+                return Err(msg::ungrouped_column(&column.name));
+            }
+            let name = alias.clone().unwrap_or_else(|| column.name.clone());
+            column_types.push(ColumnMetadata {
+                name,
+                data_type: column.data_type.clone(),
+                nullable: is_joined_via_left_join || column.nullable,
+            });
             columns.push(Column { expr, alias });
         }
     }
     Ok(())
 }
 
-mod sorting {
-    use querydown_parser::ast::SortSpec;
+mod grouping {
+    use std::collections::BTreeMap;
+
+    use querydown_parser::ast::{GroupSpec, GroupingKind};
+
+    use crate::{
+        errors::msg,
+        sql::tree::{GroupingExtension, SqlExpr},
+    };
+
+    /// The expressions to group by, along with any extension which combines them
+    pub type Grouping = (Vec<SqlExpr>, Option<GroupingExtension>);
+
+    pub struct UnplacedGroupEntry {
+        expr: SqlExpr,
+        spec: GroupSpec,
+    }
+
+    pub struct GroupingStack {
+        entries: Vec<UnplacedGroupEntry>,
+    }
+
+    impl GroupingStack {
+        pub fn new() -> Self {
+            Self {
+                entries: Vec::new(),
+            }
+        }
+
+        pub fn push(&mut self, expr: SqlExpr, spec: GroupSpec) {
+            self.entries.push(UnplacedGroupEntry { expr, spec });
+        }
+
+        pub fn try_into_grouping(self) -> Result<Grouping, String> {
+            let mut entries = self.entries;
+            let kind = entries.first().map(|e| e.spec.kind).unwrap_or_default();
+            if entries.iter().any(|e| e.spec.kind != kind) {
+                return Err(msg::mixed_grouping_kinds());
+            }
+            if kind == GroupingKind::Sets {
+                // Each digit of the ordinal names a set to which the column belongs. Sets are
+                // rendered in order of their numbers.
+                let mut sets = BTreeMap::<char, Vec<usize>>::new();
+                for (index, entry) in entries.iter().enumerate() {
+                    let digits = entry
+                        .spec
+                        .ordinal
+                        .ok_or_else(msg::grouping_set_without_number)?
+                        .to_string();
+                    for digit in digits.chars() {
+                        sets.entry(digit).or_default().push(index);
+                    }
+                }
+                let exprs = entries.into_iter().map(|e| e.expr).collect();
+                let sets = sets.into_values().collect();
+                return Ok((exprs, Some(GroupingExtension::Sets(sets))));
+            }
+            let max_ordinal = entries
+                .iter()
+                .filter_map(|e| e.spec.ordinal)
+                .max()
+                .unwrap_or(0);
+            entries.sort_by_key(|e| e.spec.ordinal.unwrap_or(max_ordinal.saturating_add(1)));
+            let exprs = entries.into_iter().map(|e| e.expr).collect();
+            let extension = match kind {
+                GroupingKind::Rollup => Some(GroupingExtension::Rollup),
+                GroupingKind::Cube => Some(GroupingExtension::Cube),
+                _ => None,
+            };
+            Ok((exprs, extension))
+        }
+    }
+}
+
+pub(super) mod sorting {
+    use querydown_parser::ast::{NullsSort, SortSpec};
 
     use crate::sql::tree::{SortEntry, SqlExpr};
 
@@ -141,12 +588,15 @@ mod sorting {
 
     pub struct SortingStack {
         entries: Vec<UnplacedSortEntry>,
+        /// Where nulls go for sort specs which don't say
+        default_nulls_sort: NullsSort,
     }
 
     impl SortingStack {
-        pub fn new() -> Self {
+        pub fn new(default_nulls_sort: NullsSort) -> Self {
             Self {
                 entries: Vec::new(),
+                default_nulls_sort,
             }
         }
 
@@ -155,7 +605,9 @@ mod sorting {
                 entry: SortEntry {
                     expr,
                     direction: sort_spec.direction,
-                    nulls_sort: sort_spec.nulls_sort,
+                    nulls_sort: sort_spec
+                        .nulls_sort
+                        .unwrap_or_else(|| self.default_nulls_sort.clone()),
                 },
                 ordinal: sort_spec.ordinal,
             };
@@ -3,82 +3,346 @@ use std::collections::{HashMap, HashSet};
 use querydown_parser::ast::PathPart;
 
 use crate::{
+    errors::msg,
+    options::ToManyStrategy,
     schema::{
         chain::Chain,
         links::{FilteredLink, Link, LinkToOne},
         Schema, Table,
     },
-    sql::tree::{Cte, CtePurpose, Join, SqlExpr},
+    sql::{
+        expr::build::{cmp, cond, value},
+        tree::{Column, Cte, CtePurpose, Join, JoinType, Select, SqlExpr, Unnest},
+    },
     Options,
 };
 
 use super::{
+    aliases::find_alias,
+    compositions::{Composition, CompositionMap},
     constants::*,
-    functions::{get_standard_aggregate_functions, get_standard_scalar_functions, Func, FuncMap},
+    functions::{get_standard_aggregate_functions, get_standard_scalar_functions},
     join_tree::JoinTree,
+    parameters::Parameter,
     paths::{build_cte_select, AggregateExprTemplate, ValueViaCte},
+    rendering::{subquery, Render},
+    type_inference::chain_always_has_target,
 };
 
 pub struct Scope<'a, 'b> {
     parent: Option<&'b Scope<'a, 'b>>,
     pub options: &'a Options,
     pub schema: &'a Schema,
-    base_table: &'a Table,
+    /// `None` when the query has no base table
+    base_table: Option<&'a Table>,
+    /// Tables cross joined to the base table, along with their aliases
+    cross_joined_tables: Vec<(&'a Table, String)>,
     join_tree: JoinTree,
+    /// Arrays expanded into rows, in order of creation
+    unnests: Vec<Unnest>,
+    /// Aggregates of related records joined via LATERAL, in order of creation. These come after
+    /// the join tree because they may refer to any table within it.
+    lateral_joins: Vec<Join>,
     pub path_prefix: Vec<PathPart>,
     aliases: HashSet<String>,
+    /// Prepended to every alias generated within this scope and the scopes it spawns
+    alias_prefix: String,
     cte_naming_index: usize,
-    scalar_functions: FuncMap,
-    aggregate_functions: FuncMap,
+    /// The alias of each CTE created within this scope, in order of creation
+    cte_aliases: Vec<String>,
+    /// When present, the n-th CTE created within this scope will use the n-th alias from this
+    /// plan instead of the next available one.
+    cte_alias_plan: Vec<String>,
+    /// CTEs supplied from outside of the compiler. These are not joined to anything.
+    external_ctes: Vec<Cte>,
+    scalar_functions: CompositionMap,
+    aggregate_functions: CompositionMap,
+    /// True when the query groups its rows, which lets aggregate functions apply to values from
+    /// the base table
+    is_grouped: bool,
+    /// The number of aggregate functions applied to values from the base table so far
+    grouped_aggregate_count: usize,
+    /// True when records related via chains to many are referenced through correlated subqueries
+    /// instead of joined CTEs, for statements which can't join other tables
+    uses_correlated_subqueries: bool,
+    /// True when values of the database session, like the current user, may be referenced, as
+    /// within row-level security policies
+    allows_session_values: bool,
+    /// The number of subqueries which aggregate related records, i.e. CTEs, correlated
+    /// subqueries, and lateral joins, that this scope is nested within
+    cte_depth: usize,
+    /// The placeholders within the statement being compiled. Only the root scope has these.
+    parameters: Vec<Parameter>,
 }
 
 impl<'a, 'b> Scope<'a, 'b> {
     pub fn build(
         options: &'a Options,
         schema: &'a Schema,
-        base_table_name: &'a str,
+        base_table_name: Option<&str>,
     ) -> Result<Self, String> {
-        let base_table = get_table_by_name(options, schema, base_table_name)
-            .ok_or(format!("Base table `{}` does not exist.", base_table_name))?;
-        Ok(Self {
+        let base_table = match base_table_name {
+            Some(name) => Some(
+                get_table_by_name(options, schema, name)
+                    .ok_or(format!("Base table `{}` does not exist.", name))?,
+            ),
+            None => None,
+        };
+        let mut scalar_functions = get_standard_scalar_functions();
+        scalar_functions.extend(options.custom_functions.clone());
+        let mut scope = Self {
             parent: None,
             options,
             schema,
             base_table,
-            join_tree: JoinTree::new(base_table.name.to_owned()),
+            join_tree: JoinTree::new(base_table.map(|t| t.name.to_owned()).unwrap_or_default()),
             path_prefix: vec![],
             aliases: HashSet::new(),
+            alias_prefix: options.alias_prefix.clone(),
             cte_naming_index: 0,
-            scalar_functions: get_standard_scalar_functions(),
+            cte_aliases: Vec::new(),
+            cte_alias_plan: Vec::new(),
+            external_ctes: Vec::new(),
+            cross_joined_tables: Vec::new(),
+            unnests: Vec::new(),
+            lateral_joins: Vec::new(),
+            scalar_functions,
             aggregate_functions: get_standard_aggregate_functions(),
+            is_grouped: false,
+            grouped_aggregate_count: 0,
+            uses_correlated_subqueries: false,
+            allows_session_values: false,
+            cte_depth: 0,
+            parameters: Vec::new(),
+        };
+        if let Some(alias) = base_table.and_then(|t| choose_base_table_alias(options, t)) {
+            let alias = scope.get_alias(&alias);
+            scope.alias_base_table(alias);
+        }
+        Ok(scope)
+    }
+
+    /// Reference the base table by the given alias instead of by its name
+    pub fn alias_base_table(&mut self, alias: String) {
+        self.join_tree = JoinTree::new(alias);
+    }
+
+    /// Reference records related via chains to many through correlated subqueries instead of
+    /// joins. Single related records can't be referenced this way, so any joins left in the join
+    /// tree after conversion need to be rejected by the caller.
+    pub fn with_correlated_subqueries(mut self) -> Self {
+        self.uses_correlated_subqueries = true;
+        self
+    }
+
+    pub fn uses_correlated_subqueries(&self) -> bool {
+        self.uses_correlated_subqueries
+    }
+
+    /// Allow references to values of the database session, like `@current_user`
+    pub fn with_session_values(mut self) -> Self {
+        self.allows_session_values = true;
+        self
+    }
+
+    pub fn allows_session_values(&self) -> bool {
+        self.allows_session_values
+    }
+
+    /// Mark this scope as the inside of a subquery which aggregates related records, nested one
+    /// level deeper than the scope it was spawned from
+    pub fn enter_cte(&mut self) -> Result<(), String> {
+        self.cte_depth += 1;
+        match self.options.max_cte_depth {
+            Some(max_cte_depth) if self.cte_depth > max_cte_depth => {
+                Err(msg::ctes_too_deep(max_cte_depth))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn get_base_table(&self) -> Result<&Table, String> {
+        self.base_table.ok_or_else(msg::no_base_table)
+    }
+
+    /// The name by which the base table is referenced within the query. This is the table's name
+    /// unless the base table is aliased.
+    pub fn alias_for_base_table(&self) -> Result<&str, String> {
+        self.get_base_table()?;
+        Ok(self.join_tree.get_alias())
+    }
+
+    /// The alias given to the base table, or `None` when it's referenced by its name
+    pub fn get_base_table_alias(&self) -> Option<&str> {
+        let name = &self.base_table?.name;
+        Some(self.join_tree.get_alias()).filter(|alias| alias != name)
+    }
+
+    pub fn has_base_table(&self) -> bool {
+        self.base_table.is_some()
+    }
+
+    /// Join another root table to the base table with a cross join. Its columns can then be
+    /// referenced via paths like `#table.column`.
+    pub fn cross_join_table(&mut self, table_name: &str) -> Result<(), String> {
+        self.get_base_table()?;
+        let table = get_table_by_name(self.options, self.schema, table_name)
+            .ok_or_else(|| msg::table_not_found(table_name))?;
+        // A cross join to the same table must not take the base table's alias
+        self.aliases.insert(self.join_tree.get_alias().to_owned());
+        let alias = self.get_alias(&table.name);
+        self.join_tree.add_cross_join(table.id, alias.clone());
+        self.cross_joined_tables.push((table, alias));
+        Ok(())
+    }
+
+    /// Find a cross joined table by name, returning the table and its alias
+    pub fn get_cross_joined_table(&self, table_name: &str) -> Option<(&'a Table, &str)> {
+        let table = self.get_table_by_name(table_name)?;
+        self.cross_joined_tables
+            .iter()
+            .find(|(t, _)| t.id == table.id)
+            .map(|(t, alias)| (*t, alias.as_str()))
+    }
+
+    /// True when the link follows a relationship which should be joined with a full outer join
+    pub fn is_full_outer_link(&self, link: &LinkToOne) -> bool {
+        let base = link.get_base();
+        self.options.full_outer_joins.iter().any(|linking_column| {
+            let Some(table) = self.get_table_by_name(&linking_column.table) else {
+                return false;
+            };
+            let lookup = &table.column_lookup;
+            table.id == base.table_id
+                && self
+                    .options
+                    .resolve_identifier(lookup, &linking_column.column)
+                    == Some(&base.column_id)
         })
     }
 
-    pub fn get_base_table(&self) -> &Table {
-        self.base_table
+    /// True when the query may use a full outer join, in which case even the values from the
+    /// base table may be NULL
+    pub fn may_full_outer_join(&self) -> bool {
+        !self.options.full_outer_joins.is_empty()
+    }
+
+    pub fn is_grouped(&self) -> bool {
+        self.is_grouped
+    }
+
+    pub fn set_grouped(&mut self, is_grouped: bool) {
+        self.is_grouped = is_grouped;
+    }
+
+    pub fn get_grouped_aggregate_count(&self) -> usize {
+        self.grouped_aggregate_count
+    }
+
+    pub fn count_grouped_aggregate(&mut self) {
+        self.grouped_aggregate_count += 1;
     }
 
     pub fn decompose_join_tree(&mut self) -> (Vec<Join>, Vec<Cte>) {
-        let join_tree = std::mem::replace(
-            &mut self.join_tree,
-            JoinTree::new(self.base_table.name.to_owned()),
-        );
-        join_tree.decompose(self)
+        let alias = self.join_tree.get_alias().to_owned();
+        let join_tree = std::mem::replace(&mut self.join_tree, JoinTree::new(alias));
+        let (joins, ctes) = join_tree.decompose(self);
+        let external_ctes = std::mem::take(&mut self.external_ctes);
+        (joins, external_ctes.into_iter().chain(ctes).collect())
+    }
+
+    /// Expand an array into one row per element, returning the alias of the expansion. Unnesting
+    /// the same array again reuses the expansion, so that each element lines up with its
+    /// ordinality.
+    pub fn unnest(&mut self, array: SqlExpr, with_ordinality: bool) -> Result<String, String> {
+        if !self.options.dialect.supports_arrays() {
+            return Err(msg::arrays_unsupported());
+        }
+        // Only the top level of a query renders the expansions it collects
+        if self.parent.is_some() || self.uses_correlated_subqueries {
+            return Err(msg::unnest_outside_query());
+        }
+        if let Some(unnest) = self
+            .unnests
+            .iter_mut()
+            .find(|u| u.array.content == array.content)
+        {
+            unnest.with_ordinality |= with_ordinality;
+            return Ok(unnest.alias.clone());
+        }
+        let alias = self.get_alias(UNNEST_ALIAS);
+        self.unnests.push(Unnest {
+            array,
+            alias: alias.clone(),
+            with_ordinality,
+        });
+        Ok(alias)
+    }
+
+    pub fn take_unnests(&mut self) -> Vec<Unnest> {
+        std::mem::take(&mut self.unnests)
+    }
+
+    pub fn take_lateral_joins(&mut self) -> Vec<Join> {
+        std::mem::take(&mut self.lateral_joins)
+    }
+
+    /// True when aggregates of related records are joined via LATERAL. Only the top level of a
+    /// query renders the joins it collects, so nested scopes use CTEs regardless.
+    fn uses_lateral_joins(&self) -> bool {
+        self.options.to_many_strategy == ToManyStrategy::Lateral
+            && self.parent.is_none()
+            && !self.uses_correlated_subqueries
+    }
+
+    /// True when aggregates of related records are computed by correlated scalar subqueries in
+    /// place of their values. As with LATERAL joins, nested scopes use CTEs regardless.
+    fn uses_scalar_subqueries(&self) -> bool {
+        self.options.to_many_strategy == ToManyStrategy::ScalarSubquery
+            && self.parent.is_none()
+            && !self.uses_correlated_subqueries
     }
 
     pub fn spawn(&'b self, base_table: &'a Table) -> Self {
-        Scope {
+        let mut aliases = HashSet::new();
+        if self.uses_correlated_subqueries
+            || self.uses_lateral_joins()
+            || self.uses_scalar_subqueries()
+        {
+            // A correlated subquery can see the tables outside of it, so it must not shadow them
+            aliases.extend(self.aliases.iter().cloned());
+            aliases.insert(self.join_tree.get_alias().to_owned());
+        }
+        let mut scope = Scope {
             parent: Some(self),
             options: self.options,
             schema: self.schema,
-            base_table,
+            base_table: Some(base_table),
             join_tree: JoinTree::new(base_table.name.to_owned()),
             path_prefix: vec![],
-            aliases: HashSet::new(),
+            aliases,
+            alias_prefix: self.alias_prefix.clone(),
             cte_naming_index: 0,
+            cte_aliases: Vec::new(),
+            cte_alias_plan: Vec::new(),
+            external_ctes: Vec::new(),
+            cross_joined_tables: Vec::new(),
+            unnests: Vec::new(),
+            lateral_joins: Vec::new(),
             scalar_functions: HashMap::new(),
             aggregate_functions: HashMap::new(),
+            is_grouped: false,
+            grouped_aggregate_count: 0,
+            uses_correlated_subqueries: self.uses_correlated_subqueries,
+            allows_session_values: self.allows_session_values,
+            cte_depth: self.cte_depth,
+            parameters: Vec::new(),
+        };
+        if scope.aliases.contains(&base_table.name) {
+            let alias = scope.get_alias(&base_table.name);
+            scope.alias_base_table(alias);
         }
+        scope
     }
 
     pub fn with_path_prefix<T>(
@@ -101,30 +365,17 @@ impl<'a, 'b> Scope<'a, 'b> {
 
     /// Returns a table alias that is unique within the context of the query.
     fn integrate_chain(&mut self, chain: Option<&Chain<LinkToOne>>, cte: Option<Cte>) -> String {
-        // TODO figure out how to reduce code duplication between the logic here and
-        // Scope::get_alias. There are some borrowing issues with using the get_alias method here.
-        // Need to find a way to structure this code so that both use-cases can share it.
+        // We can't borrow self mutably within the closure below, so we take the aliases out of
+        // self while integrating the chain.
         let mut aliases = std::mem::take(&mut self.aliases);
-        let mut try_alias = |alias: &str| -> bool {
-            if !aliases.contains(alias) {
-                aliases.insert(alias.to_string());
-                true
-            } else {
-                false
-            }
-        };
         let get_alias = |link: &LinkToOne| -> String {
-            let ideal_alias = self.schema.get_ideal_alias_for_link_to_one(link);
-            if try_alias(ideal_alias) {
-                return ideal_alias.to_string();
-            }
-            let suffix_index: usize = 1;
-            loop {
-                let new_alias = format!("{}_{}", ideal_alias, suffix_index);
-                if try_alias(&new_alias) {
-                    return new_alias;
-                }
-            }
+            let ideal_alias = format!(
+                "{}{}",
+                self.alias_prefix,
+                self.schema.get_ideal_alias_for_link_to_one(link)
+            );
+            let seed = (link, aliases.len());
+            find_alias(&ideal_alias, seed, |alias| aliases.insert(alias.to_owned()))
         };
         let alias = self.join_tree.integrate_chain(chain, get_alias, cte);
         self.aliases = aliases;
@@ -135,20 +386,32 @@ impl<'a, 'b> Scope<'a, 'b> {
         self.integrate_chain(Some(chain), None)
     }
 
-    pub fn get_alias(&mut self, ideal_alias: &str) -> String {
-        let mut suffix_index: usize = 0;
-        loop {
-            let alias = if suffix_index == 0 {
-                ideal_alias.to_owned()
-            } else {
-                format!("{}_{}", ideal_alias, suffix_index)
-            };
-            if !self.aliases.contains(&alias) {
-                self.aliases.insert(alias.clone());
-                return alias;
-            }
-            suffix_index += 1;
+    /// The number of aliases taken within this scope, including reserved ones
+    pub fn get_alias_count(&self) -> usize {
+        self.aliases.len()
+    }
+
+    /// The aliases taken within this scope, along with the name of the base table when it isn't
+    /// aliased
+    pub fn get_aliases(&self) -> HashSet<String> {
+        let mut aliases = self.aliases.clone();
+        if self.has_base_table() {
+            aliases.insert(self.join_tree.get_alias().to_owned());
         }
+        aliases
+    }
+
+    /// Prevent the given aliases from being used for any tables or CTEs joined within this scope
+    pub fn reserve_aliases(&mut self, aliases: impl IntoIterator<Item = String>) {
+        self.aliases.extend(aliases);
+    }
+
+    pub fn get_alias(&mut self, ideal_alias: &str) -> String {
+        let ideal_alias = format!("{}{}", self.alias_prefix, ideal_alias);
+        let seed = (&ideal_alias, self.aliases.len());
+        find_alias(&ideal_alias, seed, |alias| {
+            self.aliases.insert(alias.to_owned())
+        })
     }
 
     pub fn join_chain_to_many(
@@ -163,44 +426,243 @@ impl<'a, 'b> Scope<'a, 'b> {
         let starting_column_id = starting_reference.column_id;
         let starting_table = self.schema.tables.get(&starting_table_id).unwrap();
         let starting_column = starting_table.columns.get(&starting_column_id).unwrap();
+        let ending_table = self
+            .schema
+            .tables
+            .get(&chain.get_ending_table_id())
+            .unwrap();
+        let annotation = match purpose {
+            CtePurpose::Inclusion | CtePurpose::ConditionalInclusion => {
+                format!("presence of {}", ending_table.name)
+            }
+            CtePurpose::Exclusion => format!("absence of {}", ending_table.name),
+            CtePurpose::AggregateValue => format!("aggregation of {}", ending_table.name),
+        };
+        if purpose == CtePurpose::AggregateValue && self.uses_lateral_joins() {
+            if !self.options.dialect.supports_lateral() {
+                return Err(msg::lateral_unsupported());
+            }
+            // Join the head first so that the subquery can refer to the end of it
+            let outer_alias = self.integrate_chain(head.as_ref(), None);
+            let outer_expr = self.table_column_expr(&outer_alias, &starting_column.name);
+            let ValueViaCte {
+                select,
+                value_alias,
+            } = build_cte_select(chain, aggregate_expr_template_opt, self, purpose)?;
+            let alias = self.get_alias(&format!("{}_agg", ending_table.name));
+            // The subquery aggregates without grouping, so it yields one row for each outer row.
+            // It only needs an outer join when the row it's correlated with may be missing.
+            let is_inner = head
+                .as_ref()
+                .is_none_or(|h| chain_always_has_target(h, self));
+            let (join_type, conditions) = if is_inner {
+                (JoinType::Cross, SqlExpr::default())
+            } else {
+                (JoinType::LeftOuter, value::true_())
+            };
+            self.lateral_joins.push(Join {
+                table: alias.clone(),
+                table_schema: None,
+                is_cte: false,
+                alias: alias.clone(),
+                conditions,
+                join_type,
+                annotation,
+                lateral_subquery: Some(Box::new(correlate(select, outer_expr))),
+                subquery: None,
+            });
+            return Ok(self.table_column_expr(&alias, &value_alias));
+        }
+        if purpose == CtePurpose::AggregateValue && self.uses_scalar_subqueries() {
+            // Join the head first so that the subquery can refer to the end of it
+            let outer_alias = self.integrate_chain(head.as_ref(), None);
+            let outer_expr = self.table_column_expr(&outer_alias, &starting_column.name);
+            let select =
+                build_cte_select(chain, aggregate_expr_template_opt, self, purpose)?.select;
+            return Ok(self.correlated_subquery(select, outer_expr, purpose));
+        }
         let ValueViaCte {
-            select,
+            mut select,
             value_alias,
         } = build_cte_select(chain, aggregate_expr_template_opt, self, purpose)?;
+        if self.uses_correlated_subqueries {
+            if head.is_some() {
+                return Err(msg::single_related_record_without_join());
+            }
+            let outer_expr =
+                self.table_column_expr(self.alias_for_base_table()?, &starting_column.name);
+            return Ok(self.correlated_subquery(select, outer_expr, purpose));
+        }
+        if purpose == CtePurpose::AggregateValue {
+            let merged =
+                self.merge_aggregate_value(head.as_ref(), &starting_column.name, &mut select);
+            if let Some(reference) = merged {
+                return Ok(reference);
+            }
+        }
         let cte_alias = self.get_cte_alias();
         let cte = Cte {
             select,
             alias: cte_alias.clone(),
             purpose,
             join_column_name: starting_column.name.clone(),
+            annotation,
         };
         self.integrate_chain(head.as_ref(), Some(cte));
         Ok(self.table_column_expr(&cte_alias, &value_alias))
     }
 
+    /// Aggregates of the same related records share one CTE with a value column for each, e.g. for
+    /// `$#orders.id%count $#orders.status%distinct`. This adds the value column of `select` to an
+    /// existing CTE joined at the end of the same chain, when the two differ only in their value
+    /// columns, and returns a reference to the added column.
+    fn merge_aggregate_value(
+        &mut self,
+        head: Option<&Chain<LinkToOne>>,
+        join_column_name: &str,
+        select: &mut Select,
+    ) -> Option<SqlExpr> {
+        // We can't render while the CTEs are borrowed from the join tree, so we take them out
+        let mut ctes = self.join_tree.ctes_at(head).map(std::mem::take)?;
+        let rows = render_aggregated_rows(select, self);
+        let mut merged = None;
+        for cte in ctes.iter_mut() {
+            let is_candidate = cte.purpose == CtePurpose::AggregateValue
+                && cte.join_column_name == join_column_name;
+            if !is_candidate || render_aggregated_rows(&mut cte.select, self) != rows {
+                continue;
+            }
+            let value = select.columns.pop().unwrap().expr;
+            let existing_column = cte.select.columns[1..]
+                .iter()
+                .find(|column| column.expr.content == value.content);
+            let value_alias = match existing_column.and_then(|c| c.alias.clone()) {
+                // The same aggregate appears more than once, so we compute it once
+                Some(alias) => alias,
+                None => {
+                    let alias = format!("{}{}", CTE_VALUE_COLUMN_PREFIX, cte.select.columns.len());
+                    cte.select
+                        .columns
+                        .push(Column::new(value, Some(alias.clone())));
+                    alias
+                }
+            };
+            merged = Some((cte.alias.clone(), value_alias));
+            break;
+        }
+        *self.join_tree.ctes_at(head).unwrap() = ctes;
+        merged.map(|(cte_alias, value_alias)| self.table_column_expr(&cte_alias, &value_alias))
+    }
+
+    /// Turn a CTE select into a subquery which only considers the related records of the row given
+    /// by `outer_expr`. For CTEs which test presence, this returns an `EXISTS` or `NOT EXISTS`
+    /// condition instead of a value.
+    fn correlated_subquery(
+        &mut self,
+        select: Select,
+        outer_expr: SqlExpr,
+        purpose: CtePurpose,
+    ) -> SqlExpr {
+        let mut select = correlate(select, outer_expr);
+        if purpose == CtePurpose::AggregateValue {
+            return SqlExpr::atom(subquery(&select, self));
+        }
+        select.columns = vec![Column::new(value::one(), None)];
+        let exists = cond::exists(subquery(&select, self));
+        match purpose {
+            CtePurpose::Exclusion => cond::not(exists),
+            _ => exists,
+        }
+    }
+
     fn get_cte_alias(&mut self) -> String {
-        loop {
-            let alias = format!("{}{}", CTE_ALIAS_PREFIX, self.cte_naming_index);
+        let planned_alias = self.cte_alias_plan.get(self.cte_aliases.len()).cloned();
+        let alias = planned_alias.unwrap_or_else(|| loop {
+            let alias = format!(
+                "{}{}{}",
+                self.alias_prefix, CTE_ALIAS_PREFIX, self.cte_naming_index
+            );
             self.cte_naming_index += 1;
             if !self.aliases.contains(&alias) {
-                self.aliases.insert(alias.clone());
-                return alias;
+                break alias;
             }
+        });
+        self.aliases.insert(alias.clone());
+        self.cte_aliases.push(alias.clone());
+        alias
+    }
+
+    /// The alias of each CTE created within this scope, in order of creation
+    pub fn get_cte_aliases(&self) -> &[String] {
+        &self.cte_aliases
+    }
+
+    /// Number the placeholders within the statement being compiled. See `find_parameters`.
+    pub fn set_parameters(&mut self, parameters: Vec<Parameter>) {
+        self.parameters = parameters;
+    }
+
+    /// The placeholders within the statement being compiled, in order of their numbering
+    pub fn get_parameters(&self) -> &[Parameter] {
+        match self.parent {
+            Some(parent) => parent.get_parameters(),
+            None => &self.parameters,
+        }
+    }
+
+    /// The named parameter, along with its position among the parameters, starting from 1
+    pub fn get_parameter(&self, name: &str) -> Option<(usize, &Parameter)> {
+        let parameters = self.get_parameters();
+        let position = parameters.iter().position(|p| p.name == name)?;
+        Some((position + 1, &parameters[position]))
+    }
+
+    /// The scope of the whole statement being compiled
+    pub fn get_root(&self) -> &Scope<'a, 'b> {
+        match self.parent {
+            Some(parent) => parent.get_root(),
+            None => self,
         }
     }
 
+    /// Assign CTE aliases in a predetermined order. See `plan_cte_aliases`.
+    pub fn set_cte_alias_plan(&mut self, plan: Vec<String>) {
+        self.cte_alias_plan = plan;
+    }
+
+    /// Include a CTE that was built outside of the compiler within the query's WITH clause. The
+    /// CTE's alias is changed if necessary to avoid colliding with any other alias in the query.
+    /// Returns the alias by which the CTE can be referenced.
+    pub fn register_external_cte(&mut self, mut cte: Cte) -> String {
+        let alias = self.get_alias(&cte.alias);
+        cte.alias = alias.clone();
+        self.external_ctes.push(cte);
+        alias
+    }
+
+    /// The database schema used to qualify the table in FROM and JOIN clauses
+    pub fn get_table_schema(&self, table: &Table) -> Option<String> {
+        if table.values.is_some() {
+            return None;
+        }
+        table
+            .schema_name
+            .clone()
+            .or_else(|| self.options.default_schema.clone())
+    }
+
     pub fn get_table_by_name(&self, name: &str) -> Option<&Table> {
         get_table_by_name(self.options, self.schema, name)
     }
 
-    pub fn get_scalar_function(&self, name: &str) -> Option<&Func> {
+    pub fn get_scalar_function(&self, name: &str) -> Option<&Composition> {
         self.scalar_functions.get(name).or_else(|| {
             self.parent
                 .and_then(|parent| parent.get_scalar_function(name))
         })
     }
 
-    pub fn get_aggregate_function(&self, name: &str) -> Option<&Func> {
+    pub fn get_aggregate_function(&self, name: &str) -> Option<&Composition> {
         self.aggregate_functions.get(name).or_else(|| {
             self.parent
                 .and_then(|parent| parent.get_aggregate_function(name))
@@ -213,3 +675,44 @@ fn get_table_by_name<'a>(options: &Options, schema: &'a Schema, name: &str) -> O
         .resolve_identifier(&schema.table_lookup, name)
         .map(|id| schema.tables.get(id).unwrap())
 }
+
+/// The alias to request for the base table, or `None` to reference it by its name. Long names are
+/// abbreviated to the initials of their words, e.g. `customer_orders` to `co`.
+fn choose_base_table_alias(options: &Options, table: &Table) -> Option<String> {
+    if let Some(alias) = &options.base_table_alias {
+        return Some(alias.clone());
+    }
+    let max_length = options.max_unaliased_base_table_name_length?;
+    if table.name.len() <= max_length {
+        return None;
+    }
+    let initials: String = table
+        .name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter_map(|word| word.chars().next())
+        .flat_map(char::to_lowercase)
+        .collect();
+    Some(if initials.is_empty() {
+        BASE_TABLE_ALIAS_FALLBACK.to_owned()
+    } else {
+        initials
+    })
+}
+
+/// Render everything about an aggregating CTE select except its value columns, i.e. the parts
+/// which determine the groups of related records that it aggregates
+fn render_aggregated_rows(select: &mut Select, scope: &mut Scope) -> String {
+    let value_columns = select.columns.split_off(1);
+    let rendered = select.render(scope);
+    select.columns.extend(value_columns);
+    rendered
+}
+
+/// Turn a CTE select, which is grouped by the linking column, into one which only considers the
+/// related records of the row given by `outer_expr`
+fn correlate(mut select: Select, outer_expr: SqlExpr) -> Select {
+    let pk_expr = select.columns.remove(0).expr;
+    select.grouping.clear();
+    select.conditions = cmp::and([select.conditions, cmp::eq(pk_expr, outer_expr)]);
+    select
+}
@@ -0,0 +1,21 @@
+use crate::Options;
+
+use super::compiler::Compiler;
+
+/// Compile a query into text which is suitable for storing as a snapshot, e.g. in the tests of a
+/// downstream crate. Compiling the same inputs always yields byte-identical text: joins appear in
+/// the order their paths are first mentioned, CTEs appear in a canonical order with aliases handed
+/// out to match, and nothing depends on timing. A query which fails to compile still produces a
+/// snapshot, holding its error as an SQL comment, so that failures can be snapshotted too.
+pub fn compile_for_snapshot(source: &str, schema_json: &str, options: Options) -> String {
+    let options = Options {
+        collect_stats: false,
+        ..options
+    };
+    let result = Compiler::new(schema_json, options)
+        .and_then(|compiler| compiler.compile(source.to_owned()));
+    match result {
+        Ok(sql) => format!("{sql}\n"),
+        Err(e) => format!("-- Error: {e}\n"),
+    }
+}
@@ -0,0 +1,450 @@
+use querydown_parser::ast::*;
+
+use crate::{
+    options::DivisionByZero,
+    schema::{
+        chain::Chain,
+        links::{Link, LinkToOne},
+        primitive_schema::DisplayFormat,
+        DataType, Table,
+    },
+};
+
+use super::{
+    compositions::ReturnType,
+    constants::{VAR_CURRENT_USER, VAR_FALSE, VAR_INFINITY, VAR_NOW, VAR_NULL, VAR_TRUE},
+    paths::{clarify_cross_joined_column, clarify_path, is_path_to_many, ClarifiedPathTail},
+    scope::Scope,
+};
+
+/// True when a numeric literal is written with a decimal point or an exponent
+pub fn is_decimal(n: &str) -> bool {
+    n.contains(['.', 'e', 'E'])
+}
+
+/// Determine the type of the value that a Querydown expression will produce. This never fails. If
+/// we can't figure out the type, we return `DataType::Unknown` and leave it to the conversion
+/// step to report any errors within the expression.
+pub fn infer_type(expr: &Expr, scope: &Scope) -> DataType {
+    match expr {
+        Expr::Number(n, _) => {
+            if is_decimal(n) {
+                DataType::Numeric
+            } else {
+                DataType::Integer
+            }
+        }
+        Expr::Date(..) => DataType::Date,
+        Expr::Duration(..) => DataType::Interval,
+        Expr::String(..) => DataType::Text,
+        Expr::Bytes(..) => DataType::Binary,
+        Expr::Uuid(..) => DataType::Uuid,
+        Expr::Variable(v) => infer_variable_type(v),
+        Expr::Placeholder(placeholder) => placeholder
+            .type_name
+            .as_deref()
+            .map(DataType::from_name)
+            .unwrap_or_default(),
+        Expr::Window(_) => DataType::Unknown,
+        Expr::Path(parts, _) => infer_path_type(parts, scope),
+        Expr::ConditionSet(_) | Expr::HasQuantity(_) | Expr::Comparison(_) => DataType::Boolean,
+        Expr::Call(call) => infer_call_type(call, scope),
+        Expr::Sum(a, b) | Expr::Difference(a, b) => {
+            let is_difference = matches!(expr, Expr::Difference(_, _));
+            infer_additive_type(infer_type(a, scope), infer_type(b, scope), is_difference)
+        }
+        Expr::Product(a, b) | Expr::Quotient(a, b) => {
+            infer_multiplicative_type(infer_type(a, scope), infer_type(b, scope))
+        }
+        Expr::Power(a, b) => {
+            if infer_type(a, scope).is_numeric() && infer_type(b, scope).is_numeric() {
+                DataType::Numeric
+            } else {
+                DataType::Unknown
+            }
+        }
+    }
+}
+
+/// Determine whether a Querydown expression might produce NULL. Like `infer_type`, this never
+/// fails. When we're unsure, we say that the expression is nullable.
+pub fn infer_nullability(expr: &Expr, scope: &Scope) -> bool {
+    let any_nullable = |exprs: &[&Expr]| exprs.iter().any(|e| infer_nullability(e, scope));
+    match expr {
+        Expr::Number(..)
+        | Expr::Date(..)
+        | Expr::Duration(..)
+        | Expr::String(..)
+        | Expr::Bytes(..)
+        | Expr::Uuid(..) => false,
+        Expr::Variable(v) => v == VAR_NULL,
+        // Any value may be bound to a placeholder, including NULL
+        Expr::Placeholder(_) => true,
+        // A window isn't a value, and it can't be converted on its own
+        Expr::Window(_) => true,
+        Expr::Path(parts, _) => infer_path_nullability(parts, scope),
+        Expr::ConditionSet(condition_set) => condition_set
+            .entries
+            .iter()
+            .any(|e| infer_nullability(e, scope)),
+        Expr::HasQuantity(_) => false,
+        Expr::Comparison(comparison) => {
+            infer_comparison_side_nullability(&comparison.left, scope)
+                || infer_comparison_side_nullability(&comparison.right, scope)
+        }
+        Expr::Call(call) => infer_call_nullability(call, scope),
+        // Dividing by zero may yield NULL or a value we know nothing about
+        Expr::Quotient(a, b) => {
+            scope.options.division_by_zero != DivisionByZero::Error || any_nullable(&[a, b])
+        }
+        Expr::Sum(a, b) | Expr::Difference(a, b) | Expr::Product(a, b) | Expr::Power(a, b) => {
+            any_nullable(&[a, b])
+        }
+    }
+}
+
+/// Determine the name that a result column would be given by the compiler when no alias is
+/// specified. This is the name of the column for plain column references, and the name of the
+/// function for function calls.
+pub fn infer_name(expr: &Expr, scope: &Scope) -> String {
+    match expr {
+        Expr::Path(parts, _) => infer_path_name(parts, scope),
+        Expr::Call(call) => call.name.clone(),
+        _ => "?column?".to_string(),
+    }
+}
+
+fn infer_variable_type(variable: &str) -> DataType {
+    match variable {
+        VAR_NOW => DataType::Timestamp,
+        VAR_INFINITY => DataType::Numeric,
+        VAR_TRUE | VAR_FALSE => DataType::Boolean,
+        VAR_CURRENT_USER => DataType::Text,
+        _ => DataType::Unknown,
+    }
+}
+
+fn get_column_type(table: &Table, column_name: &str, scope: &Scope) -> DataType {
+    scope
+        .options
+        .resolve_identifier(&table.column_lookup, column_name)
+        .and_then(|id| table.columns.get(id))
+        .map(|column| column.data_type.clone())
+        .unwrap_or_default()
+}
+
+fn get_column_display_format(
+    table: &Table,
+    column_name: &str,
+    scope: &Scope,
+) -> Option<DisplayFormat> {
+    scope
+        .options
+        .resolve_identifier(&table.column_lookup, column_name)
+        .and_then(|id| table.columns.get(id))
+        .and_then(|column| column.display_format.clone())
+}
+
+/// The display format which the schema gives to the column at the end of a path, if any
+pub fn infer_display_format(expr: &Expr, scope: &Scope) -> Option<DisplayFormat> {
+    let Expr::Path(parts, _) = expr else {
+        return None;
+    };
+    if let Some(cross_joined_column) = clarify_cross_joined_column(parts, scope) {
+        let c = cross_joined_column.ok()?;
+        return get_column_display_format(c.table, &c.column_name, scope);
+    }
+    let clarified_path = clarify_path(parts.to_vec(), scope).ok()?;
+    let Some(ClarifiedPathTail::Column(column_name)) = clarified_path.tail else {
+        return None;
+    };
+    let table = match clarified_path.head {
+        None => scope.get_base_table().ok()?,
+        Some(chain_to_one) => scope
+            .schema
+            .tables
+            .get(&chain_to_one.get_ending_table_id())?,
+    };
+    get_column_display_format(table, &column_name, scope)
+}
+
+fn infer_path_type(parts: &[PathPart], scope: &Scope) -> DataType {
+    if let Some(cross_joined_column) = clarify_cross_joined_column(parts, scope) {
+        return cross_joined_column
+            .map(|c| get_column_type(c.table, &c.column_name, scope))
+            .unwrap_or_default();
+    }
+    let Ok(clarified_path) = clarify_path(parts.to_vec(), scope) else {
+        return DataType::Unknown;
+    };
+    let schema = scope.schema;
+    match (clarified_path.head, clarified_path.tail) {
+        (None, Some(ClarifiedPathTail::Column(column_name))) => {
+            let Ok(base_table) = scope.get_base_table() else {
+                return DataType::Unknown;
+            };
+            get_column_type(base_table, &column_name, scope)
+        }
+        (Some(chain_to_one), None) => {
+            let reference = chain_to_one.get_links().last().unwrap().get_start();
+            let table = schema.tables.get(&reference.table_id).unwrap();
+            let column = table.columns.get(&reference.column_id).unwrap();
+            column.data_type.clone()
+        }
+        (Some(chain_to_one), Some(ClarifiedPathTail::Column(column_name))) => {
+            let table = schema
+                .tables
+                .get(&chain_to_one.get_ending_table_id())
+                .unwrap();
+            get_column_type(table, &column_name, scope)
+        }
+        (_, Some(ClarifiedPathTail::ChainToMany((_, None)))) => DataType::Integer,
+        _ => DataType::Unknown,
+    }
+}
+
+fn infer_path_name(parts: &[PathPart], scope: &Scope) -> String {
+    if let Some(Ok(cross_joined_column)) = clarify_cross_joined_column(parts, scope) {
+        return cross_joined_column.column_name;
+    }
+    let Ok(clarified_path) = clarify_path(parts.to_vec(), scope) else {
+        return "?column?".to_string();
+    };
+    match (clarified_path.head, clarified_path.tail) {
+        (_, Some(ClarifiedPathTail::Column(column_name))) => column_name,
+        (Some(chain_to_one), None) => {
+            let reference = chain_to_one.get_links().last().unwrap().get_start();
+            scope.schema.get_referenced_column_name(&reference)
+        }
+        (_, Some(ClarifiedPathTail::ChainToMany(_))) => "count".to_string(),
+        (None, None) => "?column?".to_string(),
+    }
+}
+
+/// True when every link in the chain is rendered as an inner join, i.e. each record is guaranteed
+/// to have a related record at the end of the chain.
+pub fn chain_always_has_target(chain: &Chain<LinkToOne>, scope: &Scope) -> bool {
+    chain
+        .get_links()
+        .iter()
+        .all(|link| !scope.is_full_outer_link(link) && scope.schema.link_always_has_target(link))
+}
+
+fn is_column_nullable(table: &Table, column_name: &str, scope: &Scope) -> bool {
+    scope
+        .options
+        .resolve_identifier(&table.column_lookup, column_name)
+        .and_then(|id| table.columns.get(id))
+        .map(|column| column.nullable)
+        .unwrap_or(true)
+}
+
+fn infer_path_nullability(parts: &[PathPart], scope: &Scope) -> bool {
+    // A cross join never produces NULL values on its own
+    if let Some(cross_joined_column) = clarify_cross_joined_column(parts, scope) {
+        return cross_joined_column
+            .map(|c| is_column_nullable(c.table, &c.column_name, scope))
+            .unwrap_or(true);
+    }
+    let Ok(clarified_path) = clarify_path(parts.to_vec(), scope) else {
+        return true;
+    };
+    let schema = scope.schema;
+    match (clarified_path.head, clarified_path.tail) {
+        (None, Some(ClarifiedPathTail::Column(column_name))) => {
+            let Ok(base_table) = scope.get_base_table() else {
+                return true;
+            };
+            scope.may_full_outer_join() || is_column_nullable(base_table, &column_name, scope)
+        }
+        (Some(chain_to_one), None) => {
+            let (truncated_chain_opt, last_link) = chain_to_one.with_last_link_broken_off();
+            // Without a truncated chain, the linking column belongs to the base table
+            let is_joined_via_left_join = truncated_chain_opt
+                .map(|chain| !chain_always_has_target(&chain, scope))
+                .unwrap_or_else(|| scope.may_full_outer_join());
+            let reference = last_link.get_start();
+            let table = schema.tables.get(&reference.table_id).unwrap();
+            let column = table.columns.get(&reference.column_id).unwrap();
+            is_joined_via_left_join || column.nullable
+        }
+        (Some(chain_to_one), Some(ClarifiedPathTail::Column(column_name))) => {
+            let table = schema
+                .tables
+                .get(&chain_to_one.get_ending_table_id())
+                .unwrap();
+            !chain_always_has_target(&chain_to_one, scope)
+                || is_column_nullable(table, &column_name, scope)
+        }
+        // Values from paths to many come from a CTE which is LEFT JOINed, so they're always
+        // nullable.
+        _ => true,
+    }
+}
+
+fn infer_comparison_side_nullability(side: &ComparisonSide, scope: &Scope) -> bool {
+    match side {
+        ComparisonSide::Expr(expr) => infer_nullability(expr, scope),
+        ComparisonSide::Expansion(condition_set) => condition_set
+            .entries
+            .iter()
+            .any(|e| infer_nullability(e, scope)),
+        ComparisonSide::Range(range) => {
+            infer_nullability(&range.lower.expr, scope)
+                || infer_nullability(&range.upper.expr, scope)
+        }
+    }
+}
+
+fn infer_call_nullability(call: &Call, scope: &Scope) -> bool {
+    let mut arg_nullability = call.args.iter().map(|arg| infer_nullability(arg, scope));
+    match call.dimension {
+        // Quantifiers test whether the LEFT JOINed CTE found a row, which is never NULL
+        FunctionDimension::Aggregate if ["all", "any"].contains(&call.name.as_str()) => false,
+        // Aggregates come from a CTE which is LEFT JOINed
+        FunctionDimension::Aggregate => true,
+        // We don't know how user-defined functions handle NULL
+        FunctionDimension::Scalar if scope.options.custom_functions.contains_key(&call.name) => {
+            true
+        }
+        // `else` renders as COALESCE, which is only NULL when all its arguments are
+        FunctionDimension::Scalar if call.name == "else" => arg_nullability.all(|n| n),
+        FunctionDimension::Scalar if call.name == "zero_if_null" => false,
+        // NULLIF yields NULL for empty strings too
+        FunctionDimension::Scalar if call.name == "empty_to_null" => true,
+        // Array elements may be NULL, but their positions never are
+        FunctionDimension::Scalar if call.name == "unnest" => true,
+        FunctionDimension::Scalar if call.name == "unnest_index" => false,
+        // All other standard functions produce NULL when given NULL
+        FunctionDimension::Scalar => arg_nullability.any(|n| n),
+    }
+}
+
+/// The type of the column referenced by the path given to an aggregate function. Within a grouped
+/// query, the aggregate may instead apply to any value from the base table.
+fn infer_aggregated_column_type(args: &[Expr], scope: &Scope) -> DataType {
+    let Some(arg) = args.first() else {
+        return DataType::Unknown;
+    };
+    // Over a window, the aggregate applies to the value given after the window
+    if let (Expr::Window(_), Some(value)) = (arg, args.get(1)) {
+        return infer_type(value, scope);
+    }
+    if scope.is_grouped() && !is_path_to_many(arg, scope) {
+        return infer_type(arg, scope);
+    }
+    let Expr::Path(parts, _) = arg else {
+        return DataType::Unknown;
+    };
+    let Ok(clarified_path) = clarify_path(parts.to_vec(), scope) else {
+        return DataType::Unknown;
+    };
+    let Some(ClarifiedPathTail::ChainToMany((chain, Some(column_name)))) = clarified_path.tail
+    else {
+        return DataType::Unknown;
+    };
+    let table = scope
+        .schema
+        .tables
+        .get(&chain.get_ending_table_id())
+        .unwrap();
+    get_column_type(table, &column_name, scope)
+}
+
+fn infer_call_type(call: &Call, scope: &Scope) -> DataType {
+    let composition = match call.dimension {
+        FunctionDimension::Scalar => scope.get_scalar_function(&call.name),
+        FunctionDimension::Aggregate => scope.get_aggregate_function(&call.name),
+    };
+    let Some(composition) = composition else {
+        return DataType::Unknown;
+    };
+    let arg_type = |index: usize| {
+        call.args
+            .get(index)
+            .map(|arg| infer_type(arg, scope))
+            .unwrap_or_default()
+    };
+    match &composition.return_type {
+        ReturnType::Fixed(data_type) => data_type.clone(),
+        ReturnType::FirstArg => arg_type(0),
+        ReturnType::SecondArg => arg_type(1),
+        ReturnType::FirstArgElement => match arg_type(0) {
+            DataType::Array(element_type) => *element_type,
+            _ => DataType::Unknown,
+        },
+        ReturnType::AggregatedColumn => infer_aggregated_column_type(&call.args, scope),
+        ReturnType::AggregatedColumnArray => {
+            DataType::Array(Box::new(infer_aggregated_column_type(&call.args, scope)))
+        }
+        ReturnType::TimeZoneFlipped => match arg_type(0) {
+            DataType::TimestampTz => DataType::Timestamp,
+            DataType::Unknown => DataType::Unknown,
+            _ => DataType::TimestampTz,
+        },
+        ReturnType::MappedValues => {
+            let has_default = call.args.len().is_multiple_of(2);
+            let value_indices = (2..call.args.len()).step_by(2);
+            let default_index = has_default.then(|| call.args.len() - 1);
+            // NULL fits any type
+            let is_null =
+                |index: &usize| matches!(&call.args[*index], Expr::Variable(v) if v == VAR_NULL);
+            value_indices
+                .chain(default_index)
+                .filter(|index| !is_null(index))
+                .map(arg_type)
+                .reduce(infer_common_type)
+                .unwrap_or_default()
+        }
+        ReturnType::BucketLabels => match call.args.get(2) {
+            Some(Expr::ConditionSet(labels)) => labels
+                .entries
+                .iter()
+                .filter(|label| !matches!(label, Expr::Variable(v) if v == VAR_NULL))
+                .map(|label| infer_type(label, scope))
+                .reduce(infer_common_type)
+                .unwrap_or_default(),
+            _ => DataType::Unknown,
+        },
+        ReturnType::Sum => infer_additive_type(arg_type(0), arg_type(1), false),
+        ReturnType::Difference => infer_additive_type(arg_type(0), arg_type(1), true),
+        ReturnType::Product => infer_multiplicative_type(arg_type(0), arg_type(1)),
+    }
+}
+
+/// The type which can hold values of both types, e.g. the type of a CASE expression with results of
+/// those types
+fn infer_common_type(a: DataType, b: DataType) -> DataType {
+    use DataType::*;
+    match (a, b) {
+        (a, b) if a == b => a,
+        (a, b) if a.is_numeric() && b.is_numeric() => Numeric,
+        _ => Unknown,
+    }
+}
+
+fn infer_additive_type(a: DataType, b: DataType, is_difference: bool) -> DataType {
+    use DataType::*;
+    match (a, b) {
+        (Integer, Integer) => Integer,
+        (a, b) if a.is_numeric() && b.is_numeric() => Numeric,
+        (a, b) if a.is_temporal() && b.is_temporal() && is_difference => Interval,
+        (TimestampTz, Interval) => TimestampTz,
+        (Interval, TimestampTz) if !is_difference => TimestampTz,
+        (a, Interval) if a.is_temporal() => Timestamp,
+        (Interval, b) if b.is_temporal() && !is_difference => Timestamp,
+        (Interval, Interval) => Interval,
+        _ => Unknown,
+    }
+}
+
+fn infer_multiplicative_type(a: DataType, b: DataType) -> DataType {
+    use DataType::*;
+    match (a, b) {
+        (Integer, Integer) => Integer,
+        (a, b) if a.is_numeric() && b.is_numeric() => Numeric,
+        (Interval, b) if b.is_numeric() => Interval,
+        (a, Interval) if a.is_numeric() => Interval,
+        _ => Unknown,
+    }
+}
@@ -1,3 +1,5 @@
+use crate::compiler::Arity;
+
 pub fn no_current_table() -> String {
     "Non-FK columns can only appear at the end of a path.".to_string()
 }
@@ -18,6 +20,17 @@ pub fn unknown_aggregate_function(function_name: &str) -> String {
     format!("Aggregate function `{}` does not exist.", function_name)
 }
 
+pub fn wrong_number_of_args(function_name: &str, arity: &Arity) -> String {
+    let expected = match arity {
+        Arity::Exactly(1) => "exactly 1 argument".to_string(),
+        Arity::Exactly(n) => format!("exactly {} arguments", n),
+        Arity::AtLeast(1) => "at least 1 argument".to_string(),
+        Arity::AtLeast(n) => format!("at least {} arguments", n),
+        Arity::Between(min, max) => format!("between {} and {} arguments", min, max),
+    };
+    format!("Function `{}` expects {}.", function_name, expected)
+}
+
 pub fn unknown_variable(variable_name: &str) -> String {
     format!("Unknown variable `{}`.", variable_name)
 }
@@ -37,10 +50,220 @@ pub fn aggregate_fn_applied_to_path_to_one() -> String {
     "Aggregate functions can only be applied to data that joins many records.".to_string()
 }
 
+pub fn quantifier_applied_to_a_column() -> String {
+    "The `all` and `any` functions must be applied to related records, not a column.".to_string()
+}
+
 pub fn aggregate_fn_applied_to_a_non_path() -> String {
     "Aggregate functions must be applied directly to a column, without any intermediate computations. This restriction may be relaxed in future versions".to_string()
 }
 
+pub fn row_limit_not_positive_integer() -> String {
+    "The number of values to keep must be a positive integer.".to_string()
+}
+
+pub fn row_offset_not_integer() -> String {
+    "The number of values to skip must be a non-negative integer.".to_string()
+}
+
+pub fn chain_too_long(max_chain_length: usize) -> String {
+    format!(
+        "Path is too long. Paths may contain at most {} links.",
+        max_chain_length
+    )
+}
+
+pub fn ctes_too_deep(max_cte_depth: usize) -> String {
+    format!(
+        "Query is too deeply nested. Related records may be aggregated within at most {} levels.",
+        max_cte_depth
+    )
+}
+
+pub fn no_base_table() -> String {
+    "Columns can not be referenced in a query without a base table.".to_string()
+}
+
+pub fn no_result_columns_without_base_table() -> String {
+    "A query without a base table must specify at least one result column.".to_string()
+}
+
+pub fn expected_string_literal() -> String {
+    "Expected a string literal.".to_string()
+}
+
+pub fn json_path_segment_not_literal() -> String {
+    "JSON path segments must be string literals or non-negative integers.".to_string()
+}
+
+pub fn array_index_not_literal() -> String {
+    "Array indices must be non-negative integer literals.".to_string()
+}
+
+pub fn arrays_unsupported() -> String {
+    "Arrays are not supported by this SQL dialect.".to_string()
+}
+
+pub fn bin_width_not_duration_literal() -> String {
+    "The width of each bin must be a duration literal, e.g. `@t15m`.".to_string()
+}
+
+pub fn date_format_unsupported(format: &str) -> String {
+    format!("This SQL dialect can't parse dates or timestamps in the format `{format}`.")
+}
+
+pub fn width_bucket_unsupported() -> String {
+    "This SQL dialect does not support assigning values to equal-width buckets.".to_string()
+}
+
+pub fn width_bucket_args_not_literal() -> String {
+    "The bounds and number of buckets must be numeric literals, e.g. `width_bucket(0 100 10)`."
+        .to_string()
+}
+
+pub fn width_bucket_empty_range() -> String {
+    "The lower and upper bounds of the buckets must differ.".to_string()
+}
+
+pub fn width_bucket_count_not_positive() -> String {
+    "The number of buckets must be a positive integer.".to_string()
+}
+
+pub fn map_args_not_literal() -> String {
+    "The keys and values of `map` must be literals, e.g. `map(\"a\" \"Active\" \"c\" \"Closed\")`."
+        .to_string()
+}
+
+pub fn duplicate_map_key(key: &str) -> String {
+    format!("The key {key} appears more than once within `map`.")
+}
+
+pub fn bucket_args_not_lists() -> String {
+    "`bucket` takes a list of thresholds and a list of labels, e.g. \
+    `bucket([10 100] [\"low\" \"mid\" \"high\"])`."
+        .to_string()
+}
+
+pub fn bucket_threshold_not_number() -> String {
+    "The thresholds of `bucket` must be number literals.".to_string()
+}
+
+pub fn unsorted_bucket_thresholds() -> String {
+    "The thresholds of `bucket` must be in ascending order, with no duplicates.".to_string()
+}
+
+pub fn bucket_label_count(expected: usize) -> String {
+    format!("`bucket` needs {expected} labels: one more than the number of thresholds.")
+}
+
+pub fn bucket_label_not_literal() -> String {
+    "The labels of `bucket` must be literals.".to_string()
+}
+
+pub fn invalid_regexp_replace_flag(flag: char) -> String {
+    format!(
+        "`{flag}` is not a flag of `regexp_replace`. Use `g` to replace every match or `i` to \
+        ignore case."
+    )
+}
+
+pub fn regexp_replace_unsupported() -> String {
+    "This SQL dialect does not support replacing matches of regular expressions.".to_string()
+}
+
+pub fn hash_unsupported(name: &str) -> String {
+    format!("This SQL dialect does not support the `{name}` hash function.")
+}
+
+pub fn non_text_hash_operand(name: &str) -> String {
+    format!("`{name}` can only be applied to text or binary values.")
+}
+
+pub fn clamp_bounds_reversed() -> String {
+    "The lower bound of `clamp` must not be greater than its upper bound.".to_string()
+}
+
+pub fn ordered_aggregate_unsupported() -> String {
+    "This SQL dialect does not support ordering the values within an aggregate.".to_string()
+}
+
+pub fn aggregate_ordering_not_sorted() -> String {
+    r"The values within an aggregate can only be ordered by sorted columns, e.g. `%list(%%(created_at\sd))`."
+        .to_string()
+}
+
+pub fn aggregate_ordering_not_column() -> String {
+    "The values aggregated from related records can only be ordered by columns of those records."
+        .to_string()
+}
+
+pub fn distinct_aggregate_ordering() -> String {
+    "Distinct values within an aggregate can only be ordered by the values themselves.".to_string()
+}
+
+pub fn approx_count_distinct_unsupported() -> String {
+    "This SQL dialect does not support approximating the number of distinct values.".to_string()
+}
+
+pub fn trunc_places_unsupported() -> String {
+    "This SQL dialect does not support truncating to a number of decimal places.".to_string()
+}
+
+pub fn unknown_date_diff_unit(unit: &str) -> String {
+    format!(
+        "`{unit}` is not a unit of `date_diff`. Use `second`, `minute`, `hour`, `day`, or `week`."
+    )
+}
+
+pub fn non_temporal_epoch_operand() -> String {
+    "`epoch` can only be applied to dates and timestamps.".to_string()
+}
+
+pub fn function_unsupported_by_dialect(name: &str) -> String {
+    format!("Function `{name}` has no portable form in this SQL dialect.")
+}
+
+pub fn placeholders_unsupported() -> String {
+    "Placeholders aren't supported by this SQL dialect, since its positional parameters can't \
+    be referenced more than once."
+        .to_string()
+}
+
+pub fn non_numeric_zero_if_null_operand() -> String {
+    "`zero_if_null` can only be applied to numbers.".to_string()
+}
+
+pub fn display_pattern_unsupported(column: &str) -> String {
+    format!(
+        "The display format of column `{column}` is a pattern, which this dialect can't apply. \
+        Use `decimal_places` or disable the `display_formats` option."
+    )
+}
+
+pub fn non_text_empty_to_null_operand() -> String {
+    "`empty_to_null` can only be applied to text.".to_string()
+}
+
+pub fn full_text_search_unsupported() -> String {
+    "This SQL dialect does not support full-text search.".to_string()
+}
+
+pub fn at_time_zone_unsupported() -> String {
+    "This SQL dialect does not support converting timestamps between time zones.".to_string()
+}
+
+pub fn round_time_unsupported() -> String {
+    "This SQL dialect does not support rounding timestamps to intervals of any width.".to_string()
+}
+
+pub fn round_time_width_with_months() -> String {
+    "Timestamps can't be rounded to widths which include months or years.".to_string()
+}
+
+pub fn date_bin_unsupported() -> String {
+    "This SQL dialect does not support binning timestamps into intervals of any width.".to_string()
+}
+
 pub fn expected_one_arg() -> String {
     "Expected exactly one argument.".to_string()
 }
@@ -49,10 +272,53 @@ pub fn expected_two_args() -> String {
     "Expected exactly two arguments.".to_string()
 }
 
+pub fn expected_three_args() -> String {
+    "Expected exactly three arguments.".to_string()
+}
+
 pub fn multiple_fk_from_col() -> String {
     "Schema has multiple foreign keys from the same column".to_string()
 }
 
+pub fn conflicting_table(name: &str) -> String {
+    format!(
+        "The schemas being merged define the table `{}` differently.",
+        name
+    )
+}
+
+pub fn default_sort_column_not_found(table: &str, column: &str) -> String {
+    format!("Table `{table}` has no column `{column}` by which to sort it by default.")
+}
+
+pub fn values_without_rows(table: &str) -> String {
+    format!(
+        "The values of table `{}` must contain at least one row.",
+        table
+    )
+}
+
+pub fn values_with_schema(table: &str) -> String {
+    format!(
+        "Table `{}` has values, so it can't belong to a database schema.",
+        table
+    )
+}
+
+pub fn values_row_length(table: &str, column_count: usize) -> String {
+    format!(
+        "Each row of values in table `{}` must have {} values, one for each column.",
+        table, column_count
+    )
+}
+
+pub fn values_not_scalar(table: &str) -> String {
+    format!(
+        "The values of table `{}` must be numbers, strings, booleans, or null.",
+        table
+    )
+}
+
 pub fn column_glob_on_path_to_many() -> String {
     "Column globs (`*`) can not be used on paths that reference many records.".to_string()
 }
@@ -75,3 +341,281 @@ pub fn compare_two_ranges() -> String {
 pub fn compare_range_without_eq() -> String {
     "Range comparison must use the `:` operator.".to_string()
 }
+
+pub fn ungrouped_column(name: &str) -> String {
+    format!("Column `{name}` must be grouped or contain an aggregate function because the query is grouped.")
+}
+
+pub fn mixed_grouping_kinds() -> String {
+    "All grouped columns must use the same kind of grouping.".to_string()
+}
+
+pub fn grouping_set_without_number() -> String {
+    "Columns grouped via grouping sets must be numbered with the sets to which they belong."
+        .to_string()
+}
+
+pub fn grouping_extension_unsupported() -> String {
+    "This SQL dialect does not support rollup, cube, or grouping sets.".to_string()
+}
+
+pub fn grouping_fn_without_grouping() -> String {
+    "The `grouping` function can only be used within a grouped query.".to_string()
+}
+
+pub fn distinct_on_unsupported() -> String {
+    "This SQL dialect does not support distinct columns.".to_string()
+}
+
+pub fn distinct_on_not_sorted_first(name: &str) -> String {
+    format!("Distinct column `{name}` must be sorted before all columns which are not distinct.")
+}
+
+pub fn full_outer_join_unsupported() -> String {
+    "This SQL dialect does not support full outer joins.".to_string()
+}
+
+pub fn cte_materialization_unsupported() -> String {
+    "This SQL dialect does not support hints for whether to materialize CTEs.".to_string()
+}
+
+pub fn row_level_security_unsupported() -> String {
+    "This SQL dialect does not support row-level security policies.".to_string()
+}
+
+pub fn placeholder_in_policy() -> String {
+    "Row-level security policies can't contain placeholders because they have no parameters."
+        .to_string()
+}
+
+pub fn session_value_outside_policy() -> String {
+    "Values of the database session, like `@current_user` and `setting`, are only available \
+    within row-level security policies."
+        .to_string()
+}
+
+pub fn locking_unsupported() -> String {
+    "This SQL dialect does not support locking the selected rows.".to_string()
+}
+
+pub fn locking_with_aggregation() -> String {
+    "Rows can't be locked when the query groups rows or aggregates related records.".to_string()
+}
+
+pub fn locking_with_full_outer_join() -> String {
+    "Rows can't be locked when the query uses a full outer join.".to_string()
+}
+
+pub fn table_sample_unsupported() -> String {
+    "This SQL dialect does not support that table sampling method.".to_string()
+}
+
+pub fn table_sample_without_base_table() -> String {
+    "Table sampling requires a base table.".to_string()
+}
+
+pub fn invalid_sample_percentage(percentage: f64) -> String {
+    format!("`{percentage}` is not a valid sample percentage. It must be between 0 and 100.")
+}
+
+pub fn system_time_unsupported() -> String {
+    "This SQL dialect does not support reading temporal tables as of a point in time.".to_string()
+}
+
+pub fn system_time_without_versioned_base_table() -> String {
+    "Reading as of a point in time requires a base table which is not defined by its values."
+        .to_string()
+}
+
+pub fn invalid_point_in_time(timestamp: &str) -> String {
+    format!(
+        "`{timestamp}` is not a valid timestamp. It must be written as `YYYY-MM-DD`, optionally \
+        followed by a time of day as `HH:MM` or `HH:MM:SS`."
+    )
+}
+
+pub fn single_related_record_without_join() -> String {
+    "This statement can't join other tables, so it can't refer to single related records."
+        .to_string()
+}
+
+pub fn update_without_assignments() -> String {
+    "An update must assign a value to at least one column.".to_string()
+}
+
+pub fn assignment_to_related_column(target: &str) -> String {
+    format!("`{target}` belongs to a related record, which can't be updated through a join.")
+}
+
+pub fn invalid_assignment_target(target: &str) -> String {
+    format!("`{target}` is not a column which can be assigned a value.")
+}
+
+pub fn invalid_view_name(name: &str) -> String {
+    format!("`{name}` is not a valid view name.")
+}
+
+pub fn materialized_views_unsupported() -> String {
+    "This SQL dialect does not support materialized views.".to_string()
+}
+
+pub fn replacing_materialized_view() -> String {
+    "A materialized view can't be created with `or_replace`.".to_string()
+}
+
+pub fn replacing_views_unsupported() -> String {
+    "This SQL dialect does not support replacing an existing view.".to_string()
+}
+
+pub fn temporary_views_unsupported() -> String {
+    "This SQL dialect does not support the temporary views needed to share CTEs between queries."
+        .to_string()
+}
+
+pub fn view_in_batch() -> String {
+    "A batch of queries can't be compiled into a view.".to_string()
+}
+
+pub fn prepared_statements_unsupported() -> String {
+    "This SQL dialect does not support prepared statements.".to_string()
+}
+
+pub fn invalid_statement_name(name: &str) -> String {
+    format!("`{name}` is not a valid prepared statement name.")
+}
+
+pub fn prepared_view() -> String {
+    "A view can't also be a prepared statement.".to_string()
+}
+
+pub fn unknown_placeholder(name: &str) -> String {
+    format!("The placeholder `:{name}` can't be used here.")
+}
+
+pub fn conflicting_placeholder_annotations(name: &str) -> String {
+    format!("The placeholder `:{name}` is annotated with more than one type.")
+}
+
+pub fn conflicting_parameter_type(name: &str, type_name: &str) -> String {
+    format!("The placeholder `:{name}` is annotated as `{type_name}` but is compared with a value of a different type.")
+}
+
+pub fn pagination_without_sorting() -> String {
+    "This SQL dialect requires the query to be sorted when using a limit or offset.".to_string()
+}
+
+pub fn table_not_found(table_name: &str) -> String {
+    format!("Table `{table_name}` does not exist.")
+}
+
+pub fn not_a_foreign_key(column_name: &str, table_name: &str) -> String {
+    format!("Column `{column_name}` of table `{table_name}` is not a foreign key.")
+}
+
+pub fn no_such_relationship(from_table: &str, to_table: &str) -> String {
+    format!("No relationship found from table `{from_table}` to table `{to_table}`.")
+}
+
+pub fn cross_joined_table_path(table_name: &str) -> String {
+    format!("A path from cross joined table `{table_name}` may only contain one column.")
+}
+
+pub fn truth_test_without_truth_value() -> String {
+    "One side of `:==` or `!==` must be `@true`, `@false`, or `@null`.".to_string()
+}
+
+pub fn invalid_statement_timeout(timeout: &str) -> String {
+    format!(
+        "`{timeout}` is not a valid statement timeout. Use a positive whole number followed by \
+        `ms`, `s`, `min`, or `h`, e.g. `5s`."
+    )
+}
+
+pub fn statement_timeout_unsupported() -> String {
+    "This SQL dialect does not support setting a statement timeout.".to_string()
+}
+
+pub fn invalid_like_escape_char(c: char) -> String {
+    format!("`{c}` is a wildcard, so it can't be the escape character for LIKE patterns.")
+}
+
+pub fn non_text_case_insensitive_operand() -> String {
+    "The right side of a case-insensitive comparison must be text.".to_string()
+}
+
+pub fn non_numeric_power_operand() -> String {
+    "Both operands of `^` must be numeric.".to_string()
+}
+
+pub fn lateral_unsupported() -> String {
+    "This SQL dialect does not support LATERAL joins.".to_string()
+}
+
+pub fn unconditioned_join(alias: &str) -> String {
+    format!("The join of `{alias}` has no condition, so it would produce a cartesian product.")
+}
+
+pub fn count_if_outside_grouping() -> String {
+    "`count_if` can only be used within a grouped query. To count related records which satisfy \
+    a condition, filter them instead, e.g. `$#comments{body:\"+1\"}`."
+        .to_string()
+}
+
+pub fn cross_join_in_safe_mode(alias: &str) -> String {
+    format!("The cross join of `{alias}` is not allowed in safe mode.")
+}
+
+pub fn unbounded_query_in_safe_mode() -> String {
+    "A query must have a limit in safe mode.".to_string()
+}
+
+pub fn too_many_joins(max_joins: usize) -> String {
+    format!("The query needs more than {max_joins} joins, which is not allowed in safe mode.")
+}
+
+pub fn too_many_ctes(max_ctes: usize) -> String {
+    format!("The query needs more than {max_ctes} CTEs, which is not allowed in safe mode.")
+}
+
+pub fn unnest_outside_query() -> String {
+    "Arrays can only be unnested within the result columns and conditions of a query.".to_string()
+}
+
+pub fn window_without_function() -> String {
+    "A window must be piped to a window function, e.g. `%last_value(amount)`.".to_string()
+}
+
+pub fn window_function_without_window() -> String {
+    "Window functions must be applied to a window, e.g. `%%(created_at\\s)`.".to_string()
+}
+
+pub fn unflagged_window_column() -> String {
+    "Each column within a window must partition the rows via `\\p` or sort them via `\\s`."
+        .to_string()
+}
+
+pub fn inverted_window_frame() -> String {
+    "A window frame can't start after it ends.".to_string()
+}
+
+pub fn frame_exclusion_unsupported() -> String {
+    "This SQL dialect does not support excluding rows from a window frame.".to_string()
+}
+
+pub fn frame_exclusion_outside_frame() -> String {
+    "A window frame can only exclude rows when it contains the current row.".to_string()
+}
+
+pub fn frame_exclusion_without_sorting() -> String {
+    "Excluding the peers of the current row via `\\xg` or `\\xt` requires the window to sort \
+    its rows via `\\s`."
+        .to_string()
+}
+
+pub fn nth_value_unsupported() -> String {
+    "This SQL dialect does not support `nth_value`.".to_string()
+}
+
+pub fn non_integer_bitwise_operand() -> String {
+    "Both operands of a bitwise operation must be integers.".to_string()
+}
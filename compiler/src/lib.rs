@@ -6,6 +6,17 @@ mod sql;
 mod tests;
 mod utils;
 
-pub use compiler::Compiler;
-pub use options::{IdentifierResolution, Options};
-pub use sql::Postgres;
+pub use querydown_parser::{ast, StringWhitespace};
+
+pub use compiler::{
+    compile_count, compile_for_snapshot, Arity, ColumnMetadata, CompileStats, CompiledBatch,
+    CompiledQuery, Compiler, Composition, CompositionSignature, ConditionFragment, CteMetadata,
+    CtePlan, JoinPlan, JoinPlanNode, ParamInfo, ReturnType,
+};
+pub use options::{
+    BooleanRendering, CteMaterialization, DivisionByZero, IdentifierResolution, IdentifierResolver,
+    KeywordCase, LinkingColumn, Lock, LockStrength, LockWait, NumericLiteralCasts, Options,
+    PointInTime, PreparedStatement, SafeMode, SampleMethod, TableSample, ToManyStrategy, View,
+};
+pub use schema::DataType;
+pub use sql::{tree as sql_tree, tree::CtePurpose, Ansi, DuckDb, Postgres, SqlServer, Sqlite};
@@ -1,6 +1,26 @@
 use std::collections::HashMap;
 
-use crate::{sql::Dialect, utils::FlexMap};
+use itertools::Itertools;
+use querydown_parser::{
+    ast::{FunctionDimension, NullsSort},
+    ParseOptions, StringWhitespace,
+};
+
+use crate::{
+    compiler::{
+        get_standard_aggregate_functions, get_standard_scalar_functions, Arity, Composition,
+        CompositionSignature,
+    },
+    errors::msg,
+    schema::DataType,
+    sql::{
+        expr::build::LIKE_ESCAPE_CHAR,
+        lowercase_keywords,
+        tree::{Cte, CtePurpose, SqlTreeTransform},
+        Dialect, Postgres,
+    },
+    utils::flex_find,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IdentifierResolution {
@@ -14,20 +34,501 @@ impl Default for IdentifierResolution {
     }
 }
 
+/// Decides which name within the schema an identifier in the Querydown code refers to, e.g. to
+/// support synonyms for tables or to apply a tenant's prefix to them. It's consulted for the names
+/// of tables and for the names of the columns within each table.
+pub trait IdentifierResolver {
+    /// The one name among `names` to which `identifier` refers, or `None` when it refers to none of
+    /// them or is ambiguous
+    fn resolve<'n>(&self, identifier: &str, names: &[&'n str]) -> Option<&'n str>;
+}
+
+impl IdentifierResolver for IdentifierResolution {
+    fn resolve<'n>(&self, identifier: &str, names: &[&'n str]) -> Option<&'n str> {
+        match self {
+            IdentifierResolution::Strict => names.iter().copied().find(|name| *name == identifier),
+            IdentifierResolution::Flexible => flex_find(names.iter().copied(), identifier),
+        }
+    }
+}
+
+/// A locking clause to add to the query, e.g. `FOR UPDATE SKIP LOCKED`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lock {
+    pub strength: LockStrength,
+    pub wait: LockWait,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockStrength {
+    /// `FOR UPDATE`
+    Update,
+    /// `FOR SHARE`
+    Share,
+}
+
+/// What to do when a row is already locked by another transaction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LockWait {
+    /// Wait for the other transaction to release its lock
+    #[default]
+    Wait,
+    /// `NOWAIT` raises an error instead of waiting
+    NoWait,
+    /// `SKIP LOCKED` omits locked rows from the result
+    SkipLocked,
+}
+
+/// A view to define with the compiled query, e.g. `CREATE VIEW "name" AS ...`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct View {
+    /// The name of the view, optionally qualified by a schema, e.g. `app.open_issues`
+    pub name: String,
+    /// Replace any existing view of the same name
+    pub or_replace: bool,
+    /// Store the results of the query. Not every dialect supports this.
+    pub materialized: bool,
+}
+
+/// A prepared statement to define with the compiled query, e.g. `PREPARE "name" (bigint) AS ...`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreparedStatement {
+    /// The name of the statement. When `None`, a name is generated from the compiled query so
+    /// that different queries get different names.
+    pub name: Option<String>,
+}
+
+/// A column which links one table to another, identified by name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkingColumn {
+    pub table: String,
+    pub column: String,
+}
+
+/// Read only a random sample of the base table's rows, e.g. `TABLESAMPLE BERNOULLI (10)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TableSample {
+    pub method: SampleMethod,
+    /// The percentage of rows to sample, from 0 to 100
+    pub percentage: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleMethod {
+    /// Each row is sampled independently. Slower, but the sample is more random.
+    Bernoulli,
+    /// Whole pages of rows are sampled together. Faster, but rows in the sample may be clustered.
+    System,
+}
+
+/// A point in time at which to read a system-versioned temporal table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointInTime {
+    /// A timestamp written as `YYYY-MM-DD`, optionally followed by a time of day as `HH:MM` or
+    /// `HH:MM:SS`, e.g. `2024-01-31 09:30:00`
+    Timestamp(String),
+    /// A placeholder which becomes a parameter of the compiled statement, named without its `:`
+    Placeholder(String),
+}
+
+/// How to aggregate records related via chains to many
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ToManyStrategy {
+    /// Aggregate the related records of every row in a CTE, which is joined via the linking
+    /// column
+    #[default]
+    Cte,
+    /// Aggregate the related records of each row in a subquery joined via `CROSS JOIN LATERAL`,
+    /// or `LEFT JOIN LATERAL` when the subquery is correlated with a related record which may be
+    /// missing. This can be faster when only a few rows need aggregates. Conditions on the
+    /// presence of related records still use CTEs.
+    Lateral,
+    /// Like `Cte`, but each CTE is joined as a grouped subquery in its place, so the query has no
+    /// WITH clause. A CTE is kept when its own query needs nested CTEs, e.g. to limit the related
+    /// rows, since not every database accepts a WITH clause within a subquery.
+    Subquery,
+    /// Compute each aggregate value within a correlated scalar subquery in place of the value,
+    /// e.g. `(SELECT sum(...) FROM ... WHERE ... = outer.key)`, instead of a joined CTE. Since the
+    /// subquery aggregates without grouping, it yields exactly one value for each row. Conditions
+    /// on the presence of related records still use CTEs.
+    ScalarSubquery,
+}
+
+/// What dividing by zero yields
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DivisionByZero {
+    /// Divide as written, leaving the database to decide, which usually means raising an error
+    #[default]
+    Error,
+    /// Yield NULL, via `a / NULLIF(b, 0)`
+    Null,
+    /// Yield the given SQL expression, which is inserted verbatim, e.g. `0`
+    Value(String),
+}
+
+/// Whether the database should compute a CTE once, or inline it into the query which uses it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CteMaterialization {
+    /// Rendered as `AS MATERIALIZED`
+    Materialized,
+    /// Rendered as `AS NOT MATERIALIZED`
+    NotMaterialized,
+}
+
+/// How boolean values appear within the SQL
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BooleanRendering {
+    /// As `TRUE` and `FALSE`, for databases with a boolean type
+    #[default]
+    Native,
+    /// As `1` and `0`, for databases which store booleans as integers. Result columns which
+    /// compute a boolean are converted via `CASE`, e.g. `CASE WHEN a > b THEN 1 ELSE 0 END`, which
+    /// yields 0 when the condition is unknown.
+    Integer,
+}
+
+/// Safeguards for compiling queries written by untrusted users, e.g. in an application which lets
+/// its users query its database. Each violation produces its own error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SafeMode {
+    /// The limit to apply to queries which aren't given one by `limit` or `max_limit`. When absent,
+    /// such queries are rejected instead.
+    pub default_limit: Option<u64>,
+    /// The most joins the SQL may have in total, including those within CTEs and subqueries
+    pub max_joins: usize,
+    /// The most CTEs the SQL may have in total, including those nested within other CTEs
+    pub max_ctes: usize,
+}
+
+impl Default for SafeMode {
+    fn default() -> Self {
+        SafeMode {
+            default_limit: None,
+            max_joins: 16,
+            max_ctes: 8,
+        }
+    }
+}
+
+/// The case in which SQL keywords like `SELECT` and `FROM` are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeywordCase {
+    /// e.g. `SELECT`, which is how the compiler writes them
+    #[default]
+    Upper,
+    /// e.g. `select`
+    Lower,
+}
+
+/// Casts to apply to numeric literals, for databases or drivers which would otherwise infer an
+/// unwanted type, e.g. `numeric` where `double precision` is wanted. Each type name is inserted
+/// verbatim.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NumericLiteralCasts {
+    /// The type for literals written without a decimal point or exponent, e.g. `1`
+    pub integer: Option<String>,
+    /// The type for literals written with a decimal point or exponent, e.g. `1.5`, rendered as
+    /// `1.5::float8` under Postgres
+    pub decimal: Option<String>,
+}
+
 pub struct Options {
     pub dialect: Box<dyn Dialect>,
-    pub identifier_resolution: IdentifierResolution,
+    /// How identifiers in the Querydown code are matched to the names of tables and columns,
+    /// flexibly by default. See `IdentifierResolution`.
+    pub identifier_resolver: Box<dyn IdentifierResolver>,
+    /// The maximum number of rows to return
+    pub limit: Option<u64>,
+    /// The number of rows to skip before returning any rows
+    pub offset: Option<u64>,
+    /// A cap on the number of rows to return, e.g. for a tool which runs ad-hoc queries. When
+    /// `limit` is absent or greater, this is used in its place. Note that this changes the results
+    /// of any query which would otherwise return more rows. A smaller `limit` is kept.
+    pub max_limit: Option<u64>,
+    /// The maximum number of links allowed in a single path. This protects against runaway
+    /// compilation of pathological input, e.g. a self-referential foreign key chained to itself
+    /// many times.
+    pub max_chain_length: usize,
+    /// The maximum depth to which the subqueries that aggregate related records, e.g. CTEs, may be
+    /// nested. Each path to many records within the conditions of another adds a level. This
+    /// protects against queries which are expensive for the database to plan. `None` allows any
+    /// depth.
+    pub max_cte_depth: Option<usize>,
+    /// Additional scalar functions which can be called from Querydown code, keyed by name. These
+    /// take precedence over the standard functions of the same name.
+    pub custom_functions: HashMap<String, Composition>,
+    /// When true, every identifier in the Querydown code must resolve against the schema, even
+    /// in places where the compiler would otherwise ignore it.
+    pub strict: bool,
+    /// When true, scalar functions may also be called by name, e.g. `upper(trim(name))`, in
+    /// addition to being piped, e.g. `name|trim|upper`.
+    pub function_call_syntax: bool,
+    /// How whitespace within string literals is normalized as the Querydown code is parsed, e.g.
+    /// so that a search for `"big  dog"` matches `"big dog"`. Because this changes the values of
+    /// literals, the default preserves them exactly. Identifiers are never affected.
+    pub string_whitespace: StringWhitespace,
+    /// When true, each CTE and join is preceded by an SQL comment explaining which part of the
+    /// Querydown code produced it.
+    pub annotate: bool,
+    /// The database schema containing the tables, e.g. `app`. When present, it's prepended to
+    /// every table name in FROM and JOIN clauses. It's inserted verbatim, so it can also include
+    /// a catalog, e.g. `warehouse.app`.
+    pub default_schema: Option<String>,
+    /// Lock the selected rows of the base table. Not every dialect supports this, and it can't be
+    /// combined with grouping or aggregation of related records.
+    pub lock: Option<Lock>,
+    /// Where to sort nulls for columns which are sorted without an `n` or `l` flag. The SQL
+    /// always says so explicitly, overriding the dialect's own default (which varies by database
+    /// and by sort direction).
+    pub nulls_sort: NullsSort,
+    /// When true, ORDER BY refers to each sorted result column by its position, e.g. `ORDER BY 2`,
+    /// in dialects which allow it. Otherwise, and for columns sorted within a glob, ORDER BY
+    /// refers to the column by its alias or repeats its expression.
+    pub sort_by_position: bool,
+    /// When true, a query which doesn't sort its results is sorted by the default sort of its base
+    /// table, as declared in the schema, so that paginated results come in a stable order.
+    /// Queries which group or which have distinct columns are left unsorted.
+    pub default_sort: bool,
+    /// When true, each result column computed from an expression (rather than taken directly from
+    /// a table column) is cast to the database type matching its inferred type, for drivers which
+    /// would otherwise misinterpret it. Columns whose type can't be inferred, or which have no
+    /// lossless equivalent in the dialect, are left as they are.
+    pub cast_computed_columns: bool,
+    /// When true, each result column taken directly from a table column is rendered in the
+    /// `display_format` which the schema gives that column, if any. Filters and sorting still use
+    /// the column's own values.
+    pub display_formats: bool,
+    /// Wrap the query in a statement which defines a view instead of returning the results
+    pub view: Option<View>,
+    /// Wrap the query in a statement which defines a prepared statement, with a parameter for
+    /// each placeholder. The type of each parameter is inferred from what it's compared with.
+    pub prepared_statement: Option<PreparedStatement>,
+    /// Relationships to join with `FULL OUTER JOIN`, keeping the records on either side which
+    /// have no related record on the other. Each is identified by its linking column. Because
+    /// the base table is then on the nullable side of a join, all its columns become nullable.
+    pub full_outer_joins: Vec<LinkingColumn>,
+    /// When true, the compiled query includes statistics about how long each phase of
+    /// compilation took and how many joins, CTEs, and aliases it produced.
+    pub collect_stats: bool,
+    /// An alias for the base table, e.g. `u` to render `FROM "users" AS "u"`. References to the
+    /// base table's columns then use the alias.
+    pub base_table_alias: Option<String>,
+    /// When there's no `base_table_alias`, base tables with names longer than this are aliased
+    /// automatically by the initials of the words in their names, e.g. `customer_orders` becomes
+    /// `co`.
+    pub max_unaliased_base_table_name_length: Option<usize>,
+    /// Prepended to every alias generated for tables and CTEs, e.g. `sub_` to render
+    /// `"sub_cte0"`, so that the compiled SQL can be spliced into a host query alongside SQL
+    /// compiled with a different prefix. The base table is still referenced by its name or by its
+    /// `base_table_alias`.
+    pub alias_prefix: String,
+    /// Sample the rows of the base table instead of reading all of them. Not every dialect
+    /// supports this.
+    pub table_sample: Option<TableSample>,
+    /// Read the base table as it was at a point in time, e.g. `FOR SYSTEM_TIME AS OF ...`, which
+    /// requires the table to be system-versioned. Not every dialect supports this.
+    pub system_time_as_of: Option<PointInTime>,
+    /// Rewrites to apply, in order, to the SQL tree of each query before it's rendered
+    pub sql_tree_transforms: Vec<Box<dyn SqlTreeTransform>>,
+    /// CTEs built outside of the compiler, to include in the WITH clause of every query. A CTE's
+    /// alias is changed when it collides with another alias in the query. The compiled query's
+    /// `ctes` report the alias by which to reference it.
+    pub external_ctes: Vec<Cte>,
+    /// How to aggregate records related via chains to many. Not every dialect supports every
+    /// strategy.
+    pub to_many_strategy: ToManyStrategy,
+    /// When present, each CTE whose query is no more complex than this is joined as a subquery in
+    /// its place, as with `ToManyStrategy::Subquery`. The complexity counts one for each column,
+    /// join, grouping or sorting expression, and so on within the query, plus one for its
+    /// conditions if it has any, e.g. 3 for a CTE which counts related records.
+    pub inline_cte_max_complexity: Option<usize>,
+    /// Hints for whether the database should materialize CTEs, by the purpose of each CTE. CTEs
+    /// with purposes not listed here are declared without a hint. Not every dialect supports this.
+    pub cte_materialization: HashMap<CtePurpose, CteMaterialization>,
+    /// What the `/` operator yields when the divisor is zero
+    pub division_by_zero: DivisionByZero,
+    /// When present, `:~~` and `!~~` comparisons declare this as the escape character for their
+    /// patterns, e.g. `LIKE '50\%' ESCAPE '\'`. Functions like `contains` also escape the
+    /// wildcards within their string literals with it, in place of `!`.
+    pub like_escape_char: Option<char>,
+    /// When true, the aliases of columns and tables are introduced with `AS`, e.g.
+    /// `"users" AS "u"`. Otherwise the keyword is omitted, e.g. `"users" "u"`.
+    pub alias_keyword: bool,
+    /// How boolean literals and result columns which compute booleans are rendered
+    pub boolean_rendering: BooleanRendering,
+    /// When true, a query without any grouped columns which aggregates values from the base table
+    /// is grouped by all of its other columns, e.g. `#sales $region $amount%sum` groups by
+    /// `region` as though it were written `$region \g`.
+    pub infer_grouping: bool,
+    /// When true, conditions whose truth can be told from their literals alone are simplified
+    /// away, e.g. `1:2` or `id:..[]`. When the conditions can never be satisfied, the query filters
+    /// by `WHERE FALSE` instead, without the joins which the conditions would have needed.
+    pub fold_constant_conditions: bool,
+    /// Casts to apply to numeric literals, based on how they're written
+    pub numeric_literal_casts: NumericLiteralCasts,
+    /// The longest the query may run before the database cancels it, written as a whole number
+    /// followed by `ms`, `s`, `min`, or `h`, e.g. `5s`. The statement which sets the timeout is
+    /// returned as the compiled query's `prelude`. Not every dialect supports this.
+    pub statement_timeout: Option<String>,
+    /// The case of the SQL keywords throughout the compiled SQL, including within CTEs and the
+    /// prelude. Identifiers, string literals, and function names are unaffected.
+    pub keyword_case: KeywordCase,
+    /// When present, queries are rejected if they cross join tables, if they return an unbounded
+    /// number of rows, or if they need too many joins or CTEs. See `SafeMode`.
+    pub safe_mode: Option<SafeMode>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            dialect: Box::new(Postgres()),
+            identifier_resolver: Box::new(IdentifierResolution::default()),
+            limit: None,
+            offset: None,
+            max_limit: None,
+            max_chain_length: 16,
+            max_cte_depth: None,
+            custom_functions: HashMap::new(),
+            strict: false,
+            function_call_syntax: false,
+            string_whitespace: StringWhitespace::Preserve,
+            annotate: false,
+            default_schema: None,
+            lock: None,
+            nulls_sort: NullsSort::default(),
+            sort_by_position: false,
+            default_sort: false,
+            cast_computed_columns: false,
+            display_formats: false,
+            view: None,
+            prepared_statement: None,
+            full_outer_joins: Vec::new(),
+            collect_stats: false,
+            base_table_alias: None,
+            max_unaliased_base_table_name_length: None,
+            alias_prefix: String::new(),
+            table_sample: None,
+            system_time_as_of: None,
+            sql_tree_transforms: Vec::new(),
+            external_ctes: Vec::new(),
+            to_many_strategy: ToManyStrategy::default(),
+            inline_cte_max_complexity: None,
+            cte_materialization: HashMap::new(),
+            division_by_zero: DivisionByZero::default(),
+            like_escape_char: None,
+            alias_keyword: true,
+            boolean_rendering: BooleanRendering::default(),
+            infer_grouping: false,
+            fold_constant_conditions: false,
+            numeric_literal_casts: NumericLiteralCasts::default(),
+            statement_timeout: None,
+            keyword_case: KeywordCase::default(),
+            safe_mode: None,
+        }
+    }
 }
 
 impl Options {
+    pub(crate) fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            function_call_syntax: self.function_call_syntax,
+            string_whitespace: self.string_whitespace,
+            ..ParseOptions::default()
+        }
+    }
+
+    /// The character which escapes wildcards within LIKE patterns
+    pub(crate) fn checked_like_escape_char(&self) -> Result<char, String> {
+        match self.like_escape_char {
+            Some(c @ ('%' | '_')) => Err(msg::invalid_like_escape_char(c)),
+            Some(c) => Ok(c),
+            None => Ok(LIKE_ESCAPE_CHAR),
+        }
+    }
+
+    /// The statement timeout in milliseconds
+    pub(crate) fn checked_statement_timeout(&self) -> Result<Option<u64>, String> {
+        let Some(timeout) = &self.statement_timeout else {
+            return Ok(None);
+        };
+        let timeout = timeout.trim();
+        let unit_start = timeout
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(timeout.len());
+        let (amount, unit) = timeout.split_at(unit_start);
+        let milliseconds_per_unit = match unit.trim_start() {
+            "ms" => 1,
+            "s" => 1000,
+            "min" => 60 * 1000,
+            "h" => 60 * 60 * 1000,
+            _ => return Err(msg::invalid_statement_timeout(timeout)),
+        };
+        // A timeout of zero would disable the timeout instead
+        match amount.parse::<u64>() {
+            Ok(amount) if amount > 0 => amount
+                .checked_mul(milliseconds_per_unit)
+                .map(Some)
+                .ok_or_else(|| msg::invalid_statement_timeout(timeout)),
+            _ => Err(msg::invalid_statement_timeout(timeout)),
+        }
+    }
+
+    /// Apply `keyword_case` to SQL rendered by the compiler
+    pub(crate) fn case_keywords(&self, sql: String) -> String {
+        match self.keyword_case {
+            KeywordCase::Upper => sql,
+            KeywordCase::Lower => lowercase_keywords(&sql, self.dialect.as_ref()),
+        }
+    }
+
+    /// Allow Querydown code to call an SQL function (e.g. one defined in your database) via the
+    /// same name. Calls will be rendered as `name(arg0, arg1, ...)`.
+    pub fn add_sql_function(&mut self, name: &str, arg_count: usize, return_type: DataType) {
+        let composition = Composition::sql_function(name, Arity::Exactly(arg_count), return_type);
+        self.custom_functions.insert(name.to_owned(), composition);
+    }
+
+    /// The signature of every function which Querydown code can call. Custom functions replace
+    /// the standard functions of the same name. Scalar functions come before aggregate functions,
+    /// and each are sorted by name.
+    pub fn available_compositions(&self) -> Vec<CompositionSignature> {
+        let signature =
+            |name: &str, composition: &Composition, dimension, is_custom| CompositionSignature {
+                name: name.to_owned(),
+                dimension,
+                arity: composition.arity,
+                return_type: composition.return_type.clone(),
+                is_custom,
+            };
+        let scalar_functions = get_standard_scalar_functions();
+        let aggregate_functions = get_standard_aggregate_functions();
+        let standard = scalar_functions
+            .iter()
+            .filter(|(name, _)| !self.custom_functions.contains_key(*name))
+            .map(|(name, c)| signature(name, c, FunctionDimension::Scalar, false));
+        let custom = self
+            .custom_functions
+            .iter()
+            .map(|(name, c)| signature(name, c, FunctionDimension::Scalar, true));
+        let aggregate = aggregate_functions
+            .iter()
+            .map(|(name, c)| signature(name, c, FunctionDimension::Aggregate, false))
+            .sorted_by(|a, b| a.name.cmp(&b.name));
+        standard
+            .chain(custom)
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+            .chain(aggregate)
+            .collect()
+    }
+
     pub fn resolve_identifier<'b, T>(
         &self,
         map: &'b HashMap<String, T>,
         identifier: &str,
     ) -> Option<&'b T> {
-        match self.identifier_resolution {
-            IdentifierResolution::Strict => map.get(identifier),
-            IdentifierResolution::Flexible => map.flex_get(identifier),
-        }
+        // Sorted so that the resolver doesn't see the iteration order of the HashMap
+        let names = map.keys().map(String::as_str).sorted().collect::<Vec<_>>();
+        let name = self.identifier_resolver.resolve(identifier, &names)?;
+        map.get(name)
     }
 }
@@ -1,10 +1,13 @@
 use std::collections::HashSet;
 
-use querydown_parser::ast::ConditionSet;
+use querydown_parser::ast::{ConditionSet, Expr};
 
-use crate::schema::schema::TableId;
+use crate::{
+    errors::msg,
+    schema::schema::{Schema, TableId},
+};
 
-use super::links::{FilteredLink, Link, MultiLink};
+use super::links::{FilteredLink, Link, LinkToOne, MultiLink};
 
 #[derive(Debug, Clone)]
 struct ChainStats {
@@ -206,10 +209,54 @@ impl From<Chain<MultiLink>> for Chain<FilteredLink> {
     }
 }
 
+impl Chain<LinkToOne> {
+    /// Build the chain which travels the same foreign keys in the opposite direction, from the
+    /// ending table back to the starting table. Fails if the schema lacks the inverse of any link.
+    pub fn try_reverse(&self, schema: &Schema) -> Result<Chain<FilteredLink>, String> {
+        let no_inverse = || {
+            let name = |id| {
+                schema
+                    .tables
+                    .get(&id)
+                    .map(|t| t.name.as_str())
+                    .unwrap_or("")
+            };
+            msg::no_such_relationship(
+                name(self.get_ending_table_id()),
+                name(self.get_starting_table_id()),
+            )
+        };
+        let mut inverse_links = self
+            .links
+            .iter()
+            .rev()
+            .map(|link| schema.get_inverse_link(link).ok_or_else(no_inverse));
+        // unwrap is safe here because we know that a chain will have at least one link
+        let mut chain = Chain::try_new(inverse_links.next().unwrap()?, self.intersecting)?;
+        for link in inverse_links {
+            chain.try_append(link?)?;
+        }
+        Ok(chain.into())
+    }
+}
+
 impl Chain<FilteredLink> {
     pub fn set_final_condition_set(&mut self, condition_set: ConditionSet) {
         // unwrap is safe here because we know that a chain will have at least one link
         let last_link = self.links.last_mut().unwrap();
         last_link.condition_set = condition_set;
     }
+
+    /// Further restrict the records at the end of the chain, keeping any existing conditions
+    pub fn add_final_condition(&mut self, condition: Expr) {
+        // unwrap is safe here because we know that a chain will have at least one link
+        let last_link = self.links.last_mut().unwrap();
+        let existing = std::mem::take(&mut last_link.condition_set);
+        let entries = if existing.is_empty() {
+            vec![condition]
+        } else {
+            vec![Expr::ConditionSet(existing), condition]
+        };
+        last_link.condition_set = ConditionSet::via_and(entries);
+    }
 }
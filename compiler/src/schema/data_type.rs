@@ -0,0 +1,58 @@
+/// The type of a value, as far as the compiler is able to determine it. This is intentionally
+/// coarse. It's used to describe result columns to consumers, not to model the full type system of
+/// any particular database.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DataType {
+    Integer,
+    Numeric,
+    Text,
+    Boolean,
+    Date,
+    Timestamp,
+    /// A timestamp which identifies an instant regardless of time zone, e.g. Postgres `timestamptz`
+    TimestampTz,
+    Interval,
+    Binary,
+    Uuid,
+    /// An array whose elements all have the given type
+    Array(Box<DataType>),
+    #[default]
+    Unknown,
+}
+
+impl DataType {
+    /// Interpret a type name as it appears in a schema JSON file. Names we don't recognize yield
+    /// `Unknown` instead of an error so that schemas can carry database-specific types.
+    pub fn from_name(name: &str) -> Self {
+        if let Some(element_name) = name.strip_suffix("[]") {
+            return Self::Array(Box::new(Self::from_name(element_name)));
+        }
+        match name.to_lowercase().as_str() {
+            "integer" | "int" | "int2" | "int4" | "int8" | "smallint" | "bigint" => Self::Integer,
+            "numeric" | "decimal" | "real" | "float" | "float4" | "float8" | "double precision" => {
+                Self::Numeric
+            }
+            "text" | "varchar" | "char" | "character varying" | "string" => Self::Text,
+            "boolean" | "bool" => Self::Boolean,
+            "date" => Self::Date,
+            "timestamp" | "datetime" => Self::Timestamp,
+            "timestamptz" | "timestamp with time zone" => Self::TimestampTz,
+            "interval" => Self::Interval,
+            "bytea" | "blob" | "binary" | "varbinary" => Self::Binary,
+            "uuid" | "uniqueidentifier" => Self::Uuid,
+            _ => Self::Unknown,
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Self::Integer | Self::Numeric)
+    }
+
+    pub fn is_temporal(&self) -> bool {
+        matches!(self, Self::Date | Self::Timestamp | Self::TimestampTz)
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, Self::Array(_))
+    }
+}
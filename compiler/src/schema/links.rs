@@ -8,6 +8,8 @@ use super::schema::{ColumnId, TableId};
 pub struct ForwardLinkToOne {
     pub base: Reference,
     pub target: Reference,
+    /// True when no two records in the base table refer to the same target record
+    pub unique: bool,
 }
 
 impl From<ForeignKey> for ForwardLinkToOne {
@@ -15,6 +17,7 @@ impl From<ForeignKey> for ForwardLinkToOne {
         Self {
             base: foreign_key.base,
             target: foreign_key.target,
+            unique: foreign_key.unique,
         }
     }
 }
@@ -34,7 +37,7 @@ impl From<ForeignKey> for ReverseLinkToOne {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ReverseLinkToMany {
     pub base: Reference,
     pub target: Reference,
@@ -56,6 +59,7 @@ pub trait Link {
     fn get_target(&self) -> Reference;
     fn get_direction(&self) -> LinkDirection;
     fn get_join_quantity(&self) -> JoinQuantity;
+    fn get_cardinality(&self) -> Cardinality;
 }
 
 impl Link for ForwardLinkToOne {
@@ -67,6 +71,14 @@ impl Link for ForwardLinkToOne {
         One
     }
 
+    fn get_cardinality(&self) -> Cardinality {
+        if self.unique {
+            Cardinality::OneToOne
+        } else {
+            Cardinality::ManyToOne
+        }
+    }
+
     fn get_start(&self) -> Reference {
         self.base
     }
@@ -93,6 +105,10 @@ impl Link for ReverseLinkToOne {
         One
     }
 
+    fn get_cardinality(&self) -> Cardinality {
+        Cardinality::OneToOne
+    }
+
     fn get_start(&self) -> Reference {
         self.target
     }
@@ -119,6 +135,10 @@ impl Link for ReverseLinkToMany {
         Many
     }
 
+    fn get_cardinality(&self) -> Cardinality {
+        Cardinality::OneToMany
+    }
+
     fn get_start(&self) -> Reference {
         self.target
     }
@@ -156,6 +176,25 @@ impl BitAnd for JoinQuantity {
     }
 }
 
+/// How many records lie on each side of a link, from its start to its end. This is finer than
+/// `JoinQuantity`, which only describes the end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cardinality {
+    OneToOne,
+    ManyToOne,
+    OneToMany,
+}
+
+impl Cardinality {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Cardinality::OneToOne => "one-to-one",
+            Cardinality::ManyToOne => "many-to-one",
+            Cardinality::OneToMany => "one-to-many",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum LinkDirection {
     Forward,
@@ -196,6 +235,13 @@ impl Link for LinkToOne {
         }
     }
 
+    fn get_cardinality(&self) -> Cardinality {
+        match self {
+            LinkToOne::ForwardLinkToOne(link) => link.get_cardinality(),
+            LinkToOne::ReverseLinkToOne(link) => link.get_cardinality(),
+        }
+    }
+
     fn get_start(&self) -> Reference {
         match self {
             LinkToOne::ForwardLinkToOne(link) => link.get_start(),
@@ -264,6 +310,14 @@ impl Link for MultiLink {
         }
     }
 
+    fn get_cardinality(&self) -> Cardinality {
+        match self {
+            MultiLink::ForwardLinkToOne(link) => link.get_cardinality(),
+            MultiLink::ReverseLinkToOne(link) => link.get_cardinality(),
+            MultiLink::ReverseLinkToMany(link) => link.get_cardinality(),
+        }
+    }
+
     fn get_start(&self) -> Reference {
         match self {
             MultiLink::ForwardLinkToOne(link) => link.get_start(),
@@ -321,6 +375,10 @@ impl Link for FilteredLink {
         self.link.get_join_quantity()
     }
 
+    fn get_cardinality(&self) -> Cardinality {
+        self.link.get_cardinality()
+    }
+
     fn get_start(&self) -> Reference {
         self.link.get_start()
     }
@@ -1,6 +1,8 @@
+mod data_type;
 mod schema;
 
 pub mod chain;
 pub mod links;
 pub mod primitive_schema;
+pub use data_type::*;
 pub use schema::*;
@@ -9,12 +9,39 @@ pub struct PrimitiveSchema {
 #[derive(Debug, Deserialize)]
 pub struct PrimitiveTable {
     pub name: String,
+    /// The database schema containing the table, when it differs from the default
+    pub schema: Option<String>,
     pub columns: Vec<PrimitiveColumn>,
+    /// Rows of literal values, with one value for each column in order. A table with values
+    /// doesn't exist in the database and is rendered as a `VALUES` list wherever it's used.
+    pub values: Option<Vec<Vec<serde_json::Value>>>,
+    /// The column which uniquely identifies each row
+    pub primary_key: Option<String>,
+    /// The columns by which to sort the table's rows, ascending, when a query doesn't sort them
+    /// and `Options::default_sort` is set. Defaults to the primary key.
+    pub default_sort: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct PrimitiveColumn {
     pub name: String,
+    #[serde(rename = "type")]
+    pub data_type: Option<String>,
+    /// Columns are assumed to be nullable unless the schema says otherwise
+    pub nullable: Option<bool>,
+    /// How the column's values are meant to be shown, applied when `Options::display_formats` is
+    /// set
+    pub display_format: Option<DisplayFormat>,
+}
+
+/// A hint for rendering a column's values, e.g. `{"decimal_places": 2}` for an amount of money
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplayFormat {
+    /// Rounded to this many decimal places
+    DecimalPlaces(u32),
+    /// Converted to text via `to_char` with this pattern in the style of Postgres
+    Pattern(String),
 }
 
 #[derive(Debug, Deserialize)]
@@ -7,11 +7,12 @@ use crate::errors::msg;
 
 use super::{
     chain::{Chain, ChainIntersecting},
+    data_type::DataType,
     links::{
         ForeignKey, ForwardLinkToOne, Link, LinkToOne, MultiLink, Reference, ReverseLinkToMany,
         ReverseLinkToOne,
     },
-    primitive_schema::{PrimitiveSchema, PrimitiveTable},
+    primitive_schema::{DisplayFormat, PrimitiveSchema, PrimitiveTable},
 };
 
 pub type TableName = String;
@@ -59,6 +60,221 @@ impl Schema {
         }
     }
 
+    /// True when every record at the start of the link is guaranteed to have a related record at
+    /// the end of it. This is only the case for forward links from NOT NULL columns.
+    pub fn link_always_has_target(&self, link: &LinkToOne) -> bool {
+        let LinkToOne::ForwardLinkToOne(forward_link) = link else {
+            return false;
+        };
+        let table = self.tables.get(&forward_link.base.table_id).unwrap();
+        let column = table.columns.get(&forward_link.base.column_id).unwrap();
+        !column.nullable
+    }
+
+    /// The link which follows the same foreign key in the opposite direction, if the schema has
+    /// one.
+    pub fn get_inverse_link(&self, link: &LinkToOne) -> Option<MultiLink> {
+        let is_same_key = |other: &dyn Link| {
+            other.get_base() == link.get_base() && other.get_target() == link.get_target()
+        };
+        match link {
+            LinkToOne::ForwardLinkToOne(forward_link) => {
+                let target_table = self.tables.get(&forward_link.target.table_id)?;
+                let base_table_id = forward_link.base.table_id;
+                let to_many = target_table
+                    .reverse_links_to_many
+                    .get(&base_table_id)
+                    .into_iter()
+                    .flatten()
+                    .find(|l| is_same_key(*l))
+                    .map(|l| MultiLink::ReverseLinkToMany(*l));
+                let to_one = || {
+                    target_table
+                        .reverse_links_to_one
+                        .get(&base_table_id)
+                        .into_iter()
+                        .flatten()
+                        .find(|l| is_same_key(*l))
+                        .map(|l| MultiLink::ReverseLinkToOne(*l))
+                };
+                to_many.or_else(to_one)
+            }
+            LinkToOne::ReverseLinkToOne(reverse_link) => {
+                let base_table = self.tables.get(&reverse_link.base.table_id)?;
+                base_table
+                    .forward_links_to_one
+                    .get(&reverse_link.base.column_id)
+                    .filter(|l| is_same_key(*l))
+                    .map(|l| MultiLink::ForwardLinkToOne(*l))
+            }
+        }
+    }
+
+    /// Every chain of links to one which starts at the given table and has at most `max_depth`
+    /// links, e.g. to offer the related records that a user could filter by. Chains never visit
+    /// the same table twice. Shorter chains come first.
+    pub fn reachable_chains(&self, table_id: TableId, max_depth: usize) -> Vec<Chain<LinkToOne>> {
+        let links_from = |table_id: TableId| {
+            let Some(table) = self.tables.get(&table_id) else {
+                return vec![];
+            };
+            let forward_links = table.forward_links_to_one.values().copied();
+            let reverse_links = table.reverse_links_to_one.values().flatten().copied();
+            let mut links = forward_links
+                .map(LinkToOne::ForwardLinkToOne)
+                .chain(reverse_links.map(LinkToOne::ReverseLinkToOne))
+                .collect::<Vec<_>>();
+            // Sort so that the results don't depend on the iteration order of a HashMap
+            links.sort_by_key(|l| (l.get_end().table_id, l.get_start().column_id));
+            links
+        };
+        let mut chains = Vec::<Chain<LinkToOne>>::new();
+        // The chains which have the most links so far
+        let mut longest = 0..0;
+        for depth in 0..max_depth {
+            let longer_chains = if depth == 0 {
+                links_from(table_id)
+                    .into_iter()
+                    .filter_map(|link| Chain::try_new(link, ChainIntersecting::Disallowed).ok())
+                    .collect::<Vec<_>>()
+            } else {
+                chains[longest.clone()]
+                    .iter()
+                    .flat_map(|chain| {
+                        links_from(chain.get_ending_table_id())
+                            .into_iter()
+                            .filter_map(|link| {
+                                let mut longer_chain = chain.clone();
+                                longer_chain.try_append(link).ok().map(|_| longer_chain)
+                            })
+                    })
+                    .collect::<Vec<_>>()
+            };
+            if longer_chains.is_empty() {
+                break;
+            }
+            longest = chains.len()..chains.len() + longer_chains.len();
+            chains.extend(longer_chains);
+        }
+        chains
+    }
+
+    /// Combine the tables and links of two schemas, e.g. when some tables are described in a
+    /// separate file. A table which appears in both schemas is kept once, as long as both define
+    /// it with the same columns. Tables which only appear in `other` receive new ids.
+    pub fn merge(mut self, other: Schema) -> Result<Schema, String> {
+        let mut max_table_id = self.tables.keys().max().copied().unwrap_or(0);
+        // Keys are table ids in `other`
+        let mut table_ids = HashMap::<TableId, TableId>::new();
+        // Keys are ids of tables in `other` which were already in this schema
+        let mut column_ids = HashMap::<TableId, HashMap<ColumnId, ColumnId>>::new();
+        let mut other_table_ids = other.tables.keys().copied().collect::<Vec<_>>();
+        // Sort so that the new ids don't depend on the iteration order of a HashMap
+        other_table_ids.sort_unstable();
+        for other_table_id in other_table_ids {
+            let other_table = other.tables.get(&other_table_id).unwrap();
+            let existing_table = self
+                .tables
+                .values()
+                .find(|t| t.name == other_table.name && t.schema_name == other_table.schema_name);
+            match existing_table {
+                Some(table) => {
+                    let columns = table
+                        .match_columns(other_table)
+                        .filter(|_| table.values == other_table.values)
+                        .ok_or_else(|| msg::conflicting_table(&table.get_qualified_name()))?;
+                    table_ids.insert(other_table_id, table.id);
+                    column_ids.insert(other_table_id, columns);
+                }
+                None => {
+                    max_table_id += 1;
+                    table_ids.insert(other_table_id, max_table_id);
+                }
+            }
+        }
+        let remap = |reference: Reference| Reference {
+            table_id: *table_ids.get(&reference.table_id).unwrap(),
+            column_id: column_ids
+                .get(&reference.table_id)
+                .map_or(reference.column_id, |c| {
+                    *c.get(&reference.column_id).unwrap()
+                }),
+        };
+
+        for (other_table_id, other_table) in other.tables {
+            let id = *table_ids.get(&other_table_id).unwrap();
+            let forward_links_to_one = other_table
+                .forward_links_to_one
+                .into_values()
+                .map(|l| ForwardLinkToOne {
+                    base: remap(l.base),
+                    target: remap(l.target),
+                    unique: l.unique,
+                })
+                .collect::<Vec<_>>();
+            let reverse_links_to_one = other_table
+                .reverse_links_to_one
+                .into_values()
+                .flatten()
+                .map(|l| ReverseLinkToOne {
+                    base: remap(l.base),
+                    target: remap(l.target),
+                })
+                .collect::<Vec<_>>();
+            let reverse_links_to_many = other_table
+                .reverse_links_to_many
+                .into_values()
+                .flatten()
+                .map(|l| ReverseLinkToMany {
+                    base: remap(l.base),
+                    target: remap(l.target),
+                })
+                .collect::<Vec<_>>();
+            let table = self.tables.entry(id).or_insert_with(|| Table {
+                id,
+                name: other_table.name,
+                schema_name: other_table.schema_name,
+                columns: other_table.columns,
+                column_lookup: other_table.column_lookup,
+                values: other_table.values,
+                default_sort: other_table.default_sort,
+                forward_links_to_one: HashMap::new(),
+                reverse_links_to_one: HashMap::new(),
+                reverse_links_to_many: HashMap::new(),
+            });
+            for link in forward_links_to_one {
+                match table.forward_links_to_one.entry(link.base.column_id) {
+                    Occupied(e) if *e.get() == link => {}
+                    Occupied(_) => return Err(msg::multiple_fk_from_col()),
+                    Vacant(e) => {
+                        e.insert(link);
+                    }
+                }
+            }
+            for link in reverse_links_to_one {
+                let links = table
+                    .reverse_links_to_one
+                    .entry(link.base.table_id)
+                    .or_default();
+                if !links.contains(&link) {
+                    links.push(link);
+                }
+            }
+            for link in reverse_links_to_many {
+                let links = table
+                    .reverse_links_to_many
+                    .entry(link.base.table_id)
+                    .or_default();
+                if !links.contains(&link) {
+                    links.push(link);
+                }
+            }
+        }
+
+        self.table_lookup = make_table_lookup(&self.tables);
+        Ok(self)
+    }
+
     pub fn get_referenced_column_name(&self, reference: &Reference) -> String {
         let table = self.tables.get(&reference.table_id).unwrap();
         let column = table.columns.get(&reference.column_id).unwrap();
@@ -114,8 +330,16 @@ impl ChainSearchBase {
 pub struct Table {
     pub id: TableId,
     pub name: TableName,
+    /// The database schema containing this table. When `None`, the table is qualified using
+    /// `Options::default_schema`, if set.
+    pub schema_name: Option<String>,
     pub columns: HashMap<ColumnId, Column>,
     pub column_lookup: HashMap<ColumnName, ColumnId>,
+    /// The rows of a table given inline within the schema, which is rendered as a `VALUES` list
+    pub values: Option<Vec<Vec<serde_json::Value>>>,
+    /// The columns by which the table's rows are sorted when a query doesn't sort them, if
+    /// `Options::default_sort` is set
+    pub default_sort: Vec<ColumnId>,
     /// Keys are starting column ids in this table
     pub forward_links_to_one: HashMap<ColumnId, ForwardLinkToOne>,
     /// Keys are ending table ids in the other table
@@ -147,12 +371,96 @@ impl Table {
             .chain(reverse_links_to_many)
             .chain(reverse_links_to_one)
     }
+
+    fn get_qualified_name(&self) -> String {
+        match &self.schema_name {
+            Some(schema_name) => format!("{}.{}", schema_name, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Pair each column of `other` with the column of the same name in this table, or return
+    /// `None` when the two tables don't have the same columns.
+    fn match_columns(&self, other: &Table) -> Option<HashMap<ColumnId, ColumnId>> {
+        if self.columns.len() != other.columns.len() {
+            return None;
+        }
+        other
+            .columns
+            .values()
+            .map(|other_column| {
+                let id = *self.column_lookup.get(&other_column.name)?;
+                let column = self.columns.get(&id)?;
+                let is_same = column.data_type == other_column.data_type
+                    && column.nullable == other_column.nullable;
+                is_same.then_some((other_column.id, id))
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug)]
 pub struct Column {
     pub id: ColumnId,
     pub name: ColumnName,
+    pub data_type: DataType,
+    pub nullable: bool,
+    pub display_format: Option<DisplayFormat>,
+}
+
+/// Tables can always be found by their schema-qualified name, e.g. `billing.invoices`. They can
+/// also be found by their bare name, unless another table in a different schema shares it.
+fn make_table_lookup(tables: &HashMap<TableId, Table>) -> HashMap<TableName, TableId> {
+    let mut bare_names = HashMap::<&str, Vec<TableId>>::new();
+    let mut lookup = HashMap::new();
+    for (id, table) in tables {
+        bare_names.entry(table.name.as_str()).or_default().push(*id);
+        if let Some(schema_name) = &table.schema_name {
+            lookup.insert(format!("{}.{}", schema_name, table.name), *id);
+        }
+    }
+    for (name, ids) in bare_names {
+        if let [id] = ids[..] {
+            lookup.insert(name.to_owned(), id);
+        }
+    }
+    lookup
+}
+
+/// Ensure that the primary key and default sort of a table refer to its own columns
+fn validate_default_sort(primitive_table: &PrimitiveTable) -> Result<(), String> {
+    let name = &primitive_table.name;
+    let sort_columns = primitive_table.default_sort.iter().flatten();
+    for column in primitive_table.primary_key.iter().chain(sort_columns) {
+        if !primitive_table.columns.iter().any(|c| &c.name == column) {
+            return Err(msg::default_sort_column_not_found(name, column));
+        }
+    }
+    Ok(())
+}
+
+/// Ensure that the inline rows of a table, if any, form a valid `VALUES` list
+fn validate_values(primitive_table: &PrimitiveTable) -> Result<(), String> {
+    let Some(rows) = &primitive_table.values else {
+        return Ok(());
+    };
+    let name = &primitive_table.name;
+    if rows.is_empty() {
+        return Err(msg::values_without_rows(name));
+    }
+    if primitive_table.schema.is_some() {
+        return Err(msg::values_with_schema(name));
+    }
+    let column_count = primitive_table.columns.len();
+    for row in rows {
+        if row.len() != column_count {
+            return Err(msg::values_row_length(name, column_count));
+        }
+        if row.iter().any(|v| v.is_array() || v.is_object()) {
+            return Err(msg::values_not_scalar(name));
+        }
+    }
+    Ok(())
 }
 
 fn make_table(id: TableId, primitive_table: PrimitiveTable) -> Table {
@@ -163,18 +471,34 @@ fn make_table(id: TableId, primitive_table: PrimitiveTable) -> Table {
         let column = Column {
             id: max_column_id,
             name: primitive_column.name,
+            data_type: primitive_column
+                .data_type
+                .map(|name| DataType::from_name(&name))
+                .unwrap_or_default(),
+            nullable: primitive_column.nullable.unwrap_or(true),
+            display_format: primitive_column.display_format,
         };
         columns.insert(max_column_id, column);
     }
-    let column_lookup = columns
+    let column_lookup: HashMap<ColumnName, ColumnId> = columns
         .iter()
         .map(|(id, column)| (column.name.clone(), *id))
         .collect();
+    let default_sort = primitive_table
+        .default_sort
+        .or_else(|| primitive_table.primary_key.map(|pk| vec![pk]))
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|name| column_lookup.get(name).copied())
+        .collect();
     Table {
         id,
         name: primitive_table.name,
+        schema_name: primitive_table.schema,
         columns,
         column_lookup,
+        values: primitive_table.values,
+        default_sort,
         forward_links_to_one: HashMap::new(),
         reverse_links_to_one: HashMap::new(),
         reverse_links_to_many: HashMap::new(),
@@ -188,15 +512,14 @@ impl TryFrom<PrimitiveSchema> for Schema {
         let mut max_table_id: TableId = 0;
         let mut tables = HashMap::<TableId, Table>::new();
         for primitive_table in primitive_schema.tables {
+            validate_values(&primitive_table)?;
+            validate_default_sort(&primitive_table)?;
             max_table_id += 1;
             let table = make_table(max_table_id, primitive_table);
             tables.insert(max_table_id, table);
         }
 
-        let table_lookup: HashMap<TableName, TableId> = tables
-            .iter()
-            .map(|(id, table)| (table.name.clone(), *id))
-            .collect();
+        let table_lookup = make_table_lookup(&tables);
 
         let foreign_keys: Vec<ForeignKey> = {
             let get_table_by_name = |name: &String| -> Result<&Table, String> {
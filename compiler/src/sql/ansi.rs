@@ -0,0 +1,210 @@
+use itertools::Itertools;
+use querydown_parser::ast::{Date, Duration, FunctionDimension};
+
+use crate::{options::SampleMethod, schema::DataType};
+
+use super::{
+    dialect::{hex, json_path, Dialect, JsonPathSegment, RegExFlags},
+    expr::{
+        build::{
+            cond::not,
+            func::current_timestamp,
+            math::mod_function,
+            strings::{json_value, like_regex},
+        },
+        SqlExpr,
+    },
+    postgres::interval_parts,
+};
+
+/// The standard Querydown functions which need SQL beyond the standard, e.g. `GREATEST`, or which
+/// rely on an interval between two timestamps, which the standard can only express with a
+/// qualifier that depends on the values
+const VENDOR_SCALAR_FUNCTIONS: [&str; 22] = [
+    "age",
+    "bit_and",
+    "bit_or",
+    "bit_xor",
+    "clamp",
+    "date_diff",
+    "days",
+    "epoch",
+    "hours",
+    "keep_above",
+    "keep_below",
+    "max",
+    "min",
+    "minutes",
+    "round",
+    "seconds",
+    "setting",
+    "shift_left",
+    "shift_right",
+    "sign",
+    "to_text",
+    "trunc",
+];
+
+/// The standard Querydown aggregate functions which need SQL beyond the standard, e.g.
+/// `bool_and` or `string_agg`
+const VENDOR_AGGREGATE_FUNCTIONS: [&str; 6] = [
+    "all_true",
+    "any_true",
+    "list",
+    "list_distinct",
+    "top",
+    "top_overall",
+];
+
+/// A dialect which renders only the syntax of the SQL standard, for SQL which is meant to run on
+/// any database. Anything without a standard form is an error instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ansi();
+
+impl Dialect for Ansi {
+    fn quote_identifier(&self, ident: &str) -> String {
+        format!(r#""{}""#, ident.replace('"', r#""""#))
+    }
+
+    fn quote_string(&self, string: &str) -> String {
+        format!("'{}'", string.replace('\'', "''"))
+    }
+
+    fn date(&self, date: &Date) -> String {
+        format!("DATE '{}'", date.to_iso())
+    }
+
+    /// Each part of the duration becomes its own interval literal, since one literal can't mix
+    /// months with days
+    fn duration(&self, duration: &Duration) -> String {
+        let parts = interval_parts(duration)
+            .into_iter()
+            .map(|(name, value)| {
+                let (value, unit) = match name {
+                    "years" => (value, "YEAR"),
+                    "months" => (value, "MONTH"),
+                    "weeks" => ((value.parse::<i64>().unwrap() * 7).to_string(), "DAY"),
+                    "days" => (value, "DAY"),
+                    "hours" => (value, "HOUR"),
+                    "mins" => (value, "MINUTE"),
+                    _ => (value, "SECOND"),
+                };
+                format!("INTERVAL '{value}' {unit}")
+            })
+            .collect::<Vec<String>>();
+        match parts.as_slice() {
+            [] => "INTERVAL '0' SECOND".to_string(),
+            [part] => part.clone(),
+            _ => format!("({})", parts.join(" + ")),
+        }
+    }
+
+    fn bytes(&self, bytes: &[u8]) -> String {
+        format!("X'{}'", hex(bytes))
+    }
+
+    fn match_regex(
+        &self,
+        a: SqlExpr,
+        b: SqlExpr,
+        is_positive: bool,
+        flags: &RegExFlags,
+    ) -> SqlExpr {
+        let flag = (!flags.is_case_sensitive).then(|| self.quote_string("i"));
+        let matches = like_regex(a, b, flag);
+        if is_positive {
+            matches
+        } else {
+            not(matches)
+        }
+    }
+
+    fn json_extract(&self, json: SqlExpr, path: &[JsonPathSegment]) -> SqlExpr {
+        let path = SqlExpr::atom(self.quote_string(&json_path(path)));
+        json_value(json, path)
+    }
+
+    fn to_date(&self, _a: SqlExpr, _format: &str) -> Option<SqlExpr> {
+        None
+    }
+
+    fn to_timestamp(&self, _a: SqlExpr, _format: &str) -> Option<SqlExpr> {
+        None
+    }
+
+    fn to_char(&self, _a: SqlExpr, _format: &str) -> Option<SqlExpr> {
+        None
+    }
+
+    /// Types with a precision or a length are left out, since we can't know which one to use
+    fn type_name(&self, data_type: &DataType) -> Option<String> {
+        let name = match data_type {
+            DataType::Integer => "BIGINT",
+            DataType::Boolean => "BOOLEAN",
+            DataType::Date => "DATE",
+            DataType::Timestamp => "TIMESTAMP",
+            DataType::TimestampTz => "TIMESTAMP WITH TIME ZONE",
+            _ => return None,
+        };
+        Some(name.to_owned())
+    }
+
+    fn modulo(&self, a: SqlExpr, b: SqlExpr) -> SqlExpr {
+        mod_function(a, b)
+    }
+
+    fn now(&self) -> SqlExpr {
+        current_timestamp()
+    }
+
+    fn supports_function(&self, dimension: FunctionDimension, name: &str) -> bool {
+        match dimension {
+            FunctionDimension::Scalar => !VENDOR_SCALAR_FUNCTIONS.contains(&name),
+            FunctionDimension::Aggregate => !VENDOR_AGGREGATE_FUNCTIONS.contains(&name),
+        }
+    }
+
+    /// The standard offers `FETCH FIRST` and `OFFSET` as separate clauses
+    fn pagination(&self, limit: Option<u64>, offset: Option<u64>) -> String {
+        let offset = offset.map(|o| format!("OFFSET {o} ROWS"));
+        let limit = limit.map(|l| format!("FETCH FIRST {l} ROWS ONLY"));
+        [offset, limit].into_iter().flatten().join("\n")
+    }
+
+    fn supports_width_bucket(&self) -> bool {
+        true
+    }
+
+    fn supports_lateral(&self) -> bool {
+        true
+    }
+
+    fn supports_cte_materialization(&self) -> bool {
+        false
+    }
+
+    fn supports_locking(&self) -> bool {
+        false
+    }
+
+    fn supports_table_sample(&self, _method: SampleMethod) -> bool {
+        true
+    }
+
+    fn supports_system_time(&self) -> bool {
+        true
+    }
+
+    fn supports_replacing_views(&self) -> bool {
+        false
+    }
+
+    fn supports_temporary_views(&self) -> bool {
+        false
+    }
+
+    /// The standard's only placeholder is `?`, which is bound by position
+    fn supports_placeholders(&self) -> bool {
+        false
+    }
+}
@@ -1,11 +1,161 @@
-use querydown_parser::ast::{Date, Duration};
+use itertools::Itertools;
+use querydown_parser::ast::{Date, Duration, FunctionDimension, NullsSort, SortDirection};
 
-use super::expr::SqlExpr;
+use crate::{
+    options::{Lock, LockStrength, LockWait, SampleMethod, TableSample, View},
+    schema::DataType,
+};
+
+use super::expr::{
+    build::{
+        cmp::is_truth_value,
+        date_time::{date_bin, extract_epoch, to_date, to_timestamp},
+        func::now,
+        math::{bitwise_op, divide, modulo, power_function, round, subtract, trunc},
+        strings::to_char,
+        value::cast,
+    },
+    SqlExpr, SqlExprPrecedence,
+};
+
+/// Express a duration as a fractional number of days, for dialects which have no interval type. We
+/// use the same month and year lengths as Postgres so that results are consistent across dialects.
+pub(super) fn duration_in_days(duration: &Duration) -> f64 {
+    duration.years * 365.25
+        + duration.months * 30.0
+        + duration.weeks * 7.0
+        + duration.days
+        + duration.hours / 24.0
+        + duration.minutes / (24.0 * 60.0)
+        + duration.seconds / (24.0 * 60.0 * 60.0)
+}
+
+/// Uppercase hex digits, two per byte
+pub(super) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// An operation on the bits of two integers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitwiseOperator {
+    And,
+    Or,
+    Xor,
+    ShiftLeft,
+    ShiftRight,
+}
+
+impl BitwiseOperator {
+    /// The operator as most databases spell it
+    pub(super) fn symbol(&self) -> &'static str {
+        match self {
+            BitwiseOperator::And => "&",
+            BitwiseOperator::Or => "|",
+            BitwiseOperator::Xor => "^",
+            BitwiseOperator::ShiftLeft => "<<",
+            BitwiseOperator::ShiftRight => ">>",
+        }
+    }
+}
+
+/// One step along a path into a JSON value
+pub enum JsonPathSegment {
+    Key(String),
+    Index(u64),
+}
+
+/// Render a path in the SQL/JSON path syntax, e.g. `$.address.lines[0]`
+pub(super) fn json_path(segments: &[JsonPathSegment]) -> String {
+    let mut path = String::from("$");
+    for segment in segments {
+        match segment {
+            JsonPathSegment::Key(key) => {
+                let is_plain = !key.is_empty()
+                    && !key.starts_with(|c: char| c.is_ascii_digit())
+                    && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                if is_plain {
+                    path.push_str(&format!(".{key}"));
+                } else {
+                    let escaped = key.replace('\\', "\\\\").replace('"', "\\\"");
+                    path.push_str(&format!(".\"{escaped}\""));
+                }
+            }
+            JsonPathSegment::Index(index) => path.push_str(&format!("[{index}]")),
+        }
+    }
+    path
+}
+
+/// A hash function which renders its result as lowercase hexadecimal text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha256,
+}
+
+impl HashAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            Self::Sha256 => "sha256",
+        }
+    }
+}
 
 pub struct RegExFlags {
     pub is_case_sensitive: bool,
 }
 
+/// How `regexp_replace` treats its pattern
+pub struct RegExReplaceFlags {
+    pub is_case_sensitive: bool,
+    /// True to replace every match instead of only the first one
+    pub is_global: bool,
+}
+
+/// The unit in which `date_diff` measures the difference between two dates or timestamps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateDiffUnit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+}
+
+impl DateDiffUnit {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "second" => Some(Self::Second),
+            "minute" => Some(Self::Minute),
+            "hour" => Some(Self::Hour),
+            "day" => Some(Self::Day),
+            "week" => Some(Self::Week),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Second => "second",
+            Self::Minute => "minute",
+            Self::Hour => "hour",
+            Self::Day => "day",
+            Self::Week => "week",
+        }
+    }
+
+    pub fn seconds(&self) -> u32 {
+        match self {
+            Self::Second => 1,
+            Self::Minute => 60,
+            Self::Hour => 60 * 60,
+            Self::Day => 24 * 60 * 60,
+            Self::Week => 7 * 24 * 60 * 60,
+        }
+    }
+}
+
 pub trait Dialect {
     /// Quote a table or column for use in SQL.
     fn quote_identifier(&self, ident: &str) -> String;
@@ -13,12 +163,31 @@ pub trait Dialect {
     /// Quote a string for use in SQL.
     fn quote_string(&self, string: &str) -> String;
 
+    /// The character which escapes the character after it within the strings from `quote_string`,
+    /// if any
+    fn string_escape_char(&self) -> Option<char> {
+        None
+    }
+
     /// Render a date literal
     fn date(&self, date: &Date) -> String;
 
     /// Render a duration literal
     fn duration(&self, duration: &Duration) -> String;
 
+    /// Render a binary literal
+    fn bytes(&self, bytes: &[u8]) -> String;
+
+    /// Render a UUID literal, given in canonical form
+    fn uuid(&self, uuid: &str) -> String {
+        self.quote_string(uuid)
+    }
+
+    /// Render a single-line comment
+    fn comment(&self, text: &str) -> String {
+        format!("-- {}", text.replace(['\r', '\n'], " "))
+    }
+
     /// Render a table and column reference
     fn table_column(&self, table: &str, column: &str) -> String {
         let quoted_table = self.quote_identifier(table);
@@ -35,4 +204,408 @@ pub trait Dialect {
     /// * `flags` - Flags to control the behavior of the regular expression
     fn match_regex(&self, a: SqlExpr, b: SqlExpr, is_positive: bool, flags: &RegExFlags)
         -> SqlExpr;
+
+    /// Render `a` with the matches of the regular expression `pattern` replaced by `replacement`,
+    /// in which `\1` etc. refer to captured groups. Returns `None` when the dialect can't
+    /// replace matches of regular expressions.
+    fn regexp_replace(
+        &self,
+        _a: SqlExpr,
+        _pattern: &str,
+        _replacement: &str,
+        _flags: &RegExReplaceFlags,
+    ) -> Option<SqlExpr> {
+        None
+    }
+
+    /// Render an expression which hashes the text or binary value `a`, as hexadecimal text.
+    /// Returns `None` when the dialect has no such hash function.
+    fn hash(&self, _a: SqlExpr, _algorithm: HashAlgorithm) -> Option<SqlExpr> {
+        None
+    }
+
+    /// Render an expression which extracts the value at `path` within the JSON value `json`, as
+    /// text
+    fn json_extract(&self, json: SqlExpr, path: &[JsonPathSegment]) -> SqlExpr {
+        let path = self.quote_string(&json_path(path));
+        SqlExpr::atom(format!("json_extract({json}, {path})"))
+    }
+
+    /// Render an expression which parses the text `a` as a date, given a format pattern in the
+    /// style of Postgres, e.g. `YYYY-MM-DD`. Returns `None` when the dialect can't parse that
+    /// format.
+    fn to_date(&self, a: SqlExpr, format: &str) -> Option<SqlExpr> {
+        Some(to_date(a, SqlExpr::atom(self.quote_string(format))))
+    }
+
+    /// Like `to_date`, for text which also holds a time of day
+    fn to_timestamp(&self, a: SqlExpr, format: &str) -> Option<SqlExpr> {
+        Some(to_timestamp(a, SqlExpr::atom(self.quote_string(format))))
+    }
+
+    /// Render the number or timestamp `a` as text, given a format pattern in the style of
+    /// Postgres. Returns `None` when the dialect has no equivalent.
+    fn to_char(&self, a: SqlExpr, format: &str) -> Option<SqlExpr> {
+        Some(to_char(a, SqlExpr::atom(self.quote_string(format))))
+    }
+
+    /// Render `a` with the given collation, which the database is left to validate. COLLATE binds
+    /// more tightly than any operator we render, so the result needs no parentheses.
+    fn collate(&self, a: SqlExpr, collation: &str) -> SqlExpr {
+        let a = a.for_precedence(SqlExprPrecedence::Atom);
+        SqlExpr::atom(format!("{a} COLLATE {}", self.quote_identifier(collation)))
+    }
+
+    /// Render the difference `a - b` between two dates or timestamps as a number of the given
+    /// unit, which may be fractional
+    fn date_diff(&self, a: SqlExpr, b: SqlExpr, unit: DateDiffUnit) -> SqlExpr {
+        let seconds = extract_epoch(subtract(a, b));
+        match unit {
+            DateDiffUnit::Second => seconds,
+            _ => divide(seconds, SqlExpr::atom(unit.seconds().to_string())),
+        }
+    }
+
+    /// Render `a` rounded down to the start of the bin containing it, where bins of the given width
+    /// start at `origin`. Only called when `supports_date_bin` is true.
+    fn date_bin(&self, width: SqlExpr, a: SqlExpr, origin: SqlExpr) -> SqlExpr {
+        date_bin(width, a, origin)
+    }
+
+    /// Render the date or timestamp `a` as the number of seconds since the Unix epoch, which may be
+    /// fractional
+    fn epoch(&self, a: SqlExpr) -> SqlExpr {
+        extract_epoch(a)
+    }
+
+    /// Render the timestamp `a` converted to the given time zone with `AT TIME ZONE`. The result is
+    /// parenthesized because a cast that follows it would otherwise apply to the zone.
+    fn at_time_zone(&self, a: SqlExpr, zone: &str) -> SqlExpr {
+        let a = a.for_precedence(SqlExprPrecedence::Atom);
+        let zone = self.quote_string(zone);
+        SqlExpr::atom(format!("({a} AT TIME ZONE {zone})"))
+    }
+
+    /// The name of the database type which unambiguously holds values of the given type, or `None`
+    /// when converting values to such a type might change them
+    fn type_name(&self, _data_type: &DataType) -> Option<String> {
+        None
+    }
+
+    /// Render `a` converted to the type with the given name
+    fn cast(&self, a: SqlExpr, type_name: &str) -> SqlExpr {
+        cast(a, type_name)
+    }
+
+    /// Render `a` raised to the power of `b`
+    fn power(&self, a: SqlExpr, b: SqlExpr) -> SqlExpr {
+        power_function(a, b)
+    }
+
+    /// Render the remainder of dividing `a` by `b`
+    fn modulo(&self, a: SqlExpr, b: SqlExpr) -> SqlExpr {
+        modulo(a, b)
+    }
+
+    /// Render the current timestamp
+    fn now(&self) -> SqlExpr {
+        now()
+    }
+
+    /// True when the dialect can render the standard Querydown function with the given name.
+    /// Functions which would need SQL that the dialect doesn't allow are rejected.
+    fn supports_function(&self, _dimension: FunctionDimension, _name: &str) -> bool {
+        true
+    }
+
+    /// Render `a` rounded toward zero, keeping the given number of decimal places (zero by default)
+    fn trunc(&self, a: SqlExpr, places: Option<SqlExpr>) -> SqlExpr {
+        trunc(a, places)
+    }
+
+    /// Render `a` rounded to the nearest value with the given number of decimal places (zero by
+    /// default)
+    fn round(&self, a: SqlExpr, places: Option<SqlExpr>) -> SqlExpr {
+        round(a, places)
+    }
+
+    /// True when the dialect has the `nth_value` window function
+    fn supports_nth_value(&self) -> bool {
+        true
+    }
+
+    /// True when `trunc` accepts a number of decimal places to keep
+    fn supports_trunc_places(&self) -> bool {
+        true
+    }
+
+    /// Render a bitwise operation on the integers `a` and `b`
+    fn bitwise(&self, operator: BitwiseOperator, a: SqlExpr, b: SqlExpr) -> SqlExpr {
+        bitwise_op(a, operator.symbol(), b)
+    }
+
+    /// Render one entry within an ORDER BY clause
+    fn sort_entry(
+        &self,
+        expr: &SqlExpr,
+        direction: &SortDirection,
+        nulls_sort: &NullsSort,
+    ) -> String {
+        let direction = match direction {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        };
+        let nulls_sort = match nulls_sort {
+            NullsSort::First => "NULLS FIRST",
+            NullsSort::Last => "NULLS LAST",
+        };
+        format!("{} {} {}", expr, direction, nulls_sort)
+    }
+
+    /// True when ORDER BY may refer to a result column by its alias. Otherwise we repeat the
+    /// column's expression.
+    fn sort_by_alias(&self) -> bool {
+        true
+    }
+
+    /// True when ORDER BY may refer to a result column by its position, e.g. `ORDER BY 2`
+    fn sort_by_position(&self) -> bool {
+        true
+    }
+
+    /// Render the clause which limits the rows returned, or an empty string if there is no limit
+    /// and no offset.
+    fn pagination(&self, limit: Option<u64>, offset: Option<u64>) -> String {
+        let limit = limit.map(|l| format!("LIMIT {l}"));
+        let offset = offset.map(|o| format!("OFFSET {o}"));
+        [limit, offset].into_iter().flatten().join("\n")
+    }
+
+    /// True when the dialect supports full-text search via `to_tsvector` and `@@`
+    fn supports_full_text_search(&self) -> bool {
+        false
+    }
+
+    /// True when the dialect supports `AT TIME ZONE` for converting between timestamps with and
+    /// without a time zone
+    fn supports_at_time_zone(&self) -> bool {
+        false
+    }
+
+    /// True when the dialect supports `date_bin`, which rounds timestamps down to bins of any width
+    fn supports_date_bin(&self) -> bool {
+        false
+    }
+
+    /// True when the dialect supports `WIDTH_BUCKET`, which assigns numbers to equal-width buckets
+    fn supports_width_bucket(&self) -> bool {
+        false
+    }
+
+    /// True when an aggregate function may order the values passed to it, e.g.
+    /// `array_agg(a ORDER BY b)`
+    fn supports_ordered_aggregates(&self) -> bool {
+        false
+    }
+
+    /// True when an aggregate function may be restricted to some rows via `FILTER (WHERE ...)`
+    fn supports_aggregate_filter(&self) -> bool {
+        true
+    }
+
+    /// True when a window frame may exclude rows via `EXCLUDE`, e.g. `EXCLUDE CURRENT ROW`
+    fn supports_frame_exclusion(&self) -> bool {
+        false
+    }
+
+    /// The aggregate which estimates the number of distinct values, or `None` when the dialect has
+    /// no such function
+    fn approx_count_distinct(&self) -> Option<fn(SqlExpr) -> SqlExpr> {
+        None
+    }
+
+    /// Test whether `a` is `value` under three-valued logic, yielding false instead of NULL when
+    /// `a` is NULL
+    fn truth_test(&self, a: SqlExpr, value: bool, is_negated: bool) -> SqlExpr {
+        is_truth_value(a, value, is_negated)
+    }
+
+    /// True when the dialect supports array values, including slicing them
+    fn supports_arrays(&self) -> bool {
+        false
+    }
+
+    /// True when the dialect supports `FULL OUTER JOIN`
+    fn supports_full_outer_joins(&self) -> bool {
+        true
+    }
+
+    /// True when the dialect supports `ROLLUP`, `CUBE`, and `GROUPING SETS` within GROUP BY
+    fn supports_grouping_extensions(&self) -> bool {
+        true
+    }
+
+    /// True when `SELECT DISTINCT ON (...)` is supported
+    fn supports_distinct_on(&self) -> bool {
+        false
+    }
+
+    /// True when subqueries may be joined via `LATERAL`, letting them refer to the tables joined
+    /// before them
+    fn supports_lateral(&self) -> bool {
+        false
+    }
+
+    /// True when the alias of a `VALUES` list may name its columns, e.g.
+    /// `(VALUES (1, 'a')) AS "t"("id", "label")`
+    fn supports_values_column_list(&self) -> bool {
+        true
+    }
+
+    /// True when a CTE may be declared `AS MATERIALIZED` or `AS NOT MATERIALIZED`
+    fn supports_cte_materialization(&self) -> bool {
+        true
+    }
+
+    /// True when the dialect supports row-level security policies like `CREATE POLICY ... USING`
+    fn supports_row_level_security(&self) -> bool {
+        false
+    }
+
+    /// True when the dialect supports locking clauses like `FOR UPDATE`
+    fn supports_locking(&self) -> bool {
+        true
+    }
+
+    /// Render the clause which locks the selected rows
+    ///
+    /// * `table` - When present, only rows of this table are locked
+    fn locking(&self, lock: &Lock, table: Option<&str>) -> String {
+        let strength = match lock.strength {
+            LockStrength::Update => "FOR UPDATE",
+            LockStrength::Share => "FOR SHARE",
+        };
+        let table = table.map(|t| format!("OF {}", self.quote_identifier(t)));
+        let wait = match lock.wait {
+            LockWait::Wait => None,
+            LockWait::NoWait => Some("NOWAIT".to_string()),
+            LockWait::SkipLocked => Some("SKIP LOCKED".to_string()),
+        };
+        [Some(strength.to_string()), table, wait]
+            .into_iter()
+            .flatten()
+            .join(" ")
+    }
+
+    /// True when the dialect can sample the rows of a table with the given method
+    fn supports_table_sample(&self, _method: SampleMethod) -> bool {
+        false
+    }
+
+    /// Render the clause which follows the base table to sample its rows
+    fn table_sample(&self, sample: &TableSample) -> String {
+        let method = match sample.method {
+            SampleMethod::Bernoulli => "BERNOULLI",
+            SampleMethod::System => "SYSTEM",
+        };
+        format!("TABLESAMPLE {method} ({})", sample.percentage)
+    }
+
+    /// True when the dialect can read a system-versioned temporal table as it was at a point in
+    /// time
+    fn supports_system_time(&self) -> bool {
+        false
+    }
+
+    /// Render a timestamp literal, given as `YYYY-MM-DD` with an optional time of day
+    fn timestamp(&self, timestamp: &str) -> String {
+        let timestamp = timestamp.replacen('T', " ", 1);
+        format!("TIMESTAMP {}", self.quote_string(&timestamp))
+    }
+
+    /// Render the clause which follows a table, before its alias, to read the table as it was at
+    /// the given point in time
+    fn system_time_as_of(&self, point: &SqlExpr) -> String {
+        format!("FOR SYSTEM_TIME AS OF {point}")
+    }
+
+    /// True when the dialect supports `CREATE MATERIALIZED VIEW`
+    fn supports_materialized_views(&self) -> bool {
+        false
+    }
+
+    /// True when the dialect can replace an existing view while creating one
+    fn supports_replacing_views(&self) -> bool {
+        true
+    }
+
+    /// Render the beginning of a statement which defines a view, up to and including `AS`
+    fn create_view(&self, view: &View) -> String {
+        let name = view
+            .name
+            .split('.')
+            .map(|part| self.quote_identifier(part))
+            .join(".");
+        let or_replace = if view.or_replace { "OR REPLACE " } else { "" };
+        let materialized = if view.materialized {
+            "MATERIALIZED "
+        } else {
+            ""
+        };
+        format!("CREATE {or_replace}{materialized}VIEW {name} AS")
+    }
+
+    /// True when the dialect can create views which only last as long as the session
+    fn supports_temporary_views(&self) -> bool {
+        true
+    }
+
+    /// Render the beginning of a statement which defines a temporary view, up to and including
+    /// `AS`. A view of the same name, e.g. from an earlier batch within the session, is replaced.
+    fn create_temporary_view(&self, name: &str) -> String {
+        let name = self.quote_identifier(name);
+        format!("CREATE OR REPLACE TEMPORARY VIEW {name} AS")
+    }
+
+    /// True when parameters can be referenced by placeholders which identify them, so that one
+    /// parameter may be rendered more than once
+    fn supports_placeholders(&self) -> bool {
+        true
+    }
+
+    /// Render the placeholder for a parameter that's bound when the statement is executed. The
+    /// index starts from 1.
+    fn placeholder(&self, index: usize, _name: &str) -> String {
+        format!("${index}")
+    }
+
+    /// Render a statement which limits how long the statements after it within the same
+    /// transaction may run, or `None` when the dialect can't do that via SQL
+    fn statement_timeout(&self, _milliseconds: u64) -> Option<String> {
+        None
+    }
+
+    /// True when the dialect can define a prepared statement via SQL
+    fn supports_prepared_statements(&self) -> bool {
+        false
+    }
+
+    /// Render the beginning of a statement which defines a prepared statement, up to and
+    /// including `AS`. Each parameter has its type name, or `None` when the type is unknown.
+    fn prepare(&self, name: &str, parameter_types: &[Option<String>]) -> String {
+        let name = self.quote_identifier(name);
+        if parameter_types.is_empty() {
+            return format!("PREPARE {name} AS");
+        }
+        let types = parameter_types
+            .iter()
+            .map(|t| t.as_deref().unwrap_or("unknown"))
+            .join(", ");
+        format!("PREPARE {name} ({types}) AS")
+    }
+
+    /// True when the dialect can only paginate queries that have an ORDER BY clause
+    fn pagination_requires_sorting(&self) -> bool {
+        false
+    }
 }
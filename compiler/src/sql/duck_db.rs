@@ -0,0 +1,266 @@
+use querydown_parser::ast::{Date, Duration};
+
+use crate::schema::DataType;
+
+use super::{
+    dialect::{
+        json_path, BitwiseOperator, Dialect, HashAlgorithm, JsonPathSegment, RegExFlags,
+        RegExReplaceFlags,
+    },
+    expr::{
+        build::{
+            agg::approx_count_distinct,
+            cond::not,
+            date_time::{strptime, time_bucket},
+            math::{bitwise_op, xor_function},
+            strings::{md5, regexp_matches, regexp_replace, sha256},
+        },
+        SqlExpr, SqlExprPrecedence,
+    },
+    postgres::interval_parts,
+};
+
+/// The fields of a format pattern in the style of Postgres which have an equivalent in `strptime`
+const STRPTIME_FIELDS: [(&str, &str); 6] = [
+    ("YYYY", "%Y"),
+    ("MM", "%m"),
+    ("DD", "%d"),
+    ("HH24", "%H"),
+    ("MI", "%M"),
+    ("SS", "%S"),
+];
+
+/// DuckDB is mostly compatible with Postgres, so this dialect renders much the same SQL. It differs
+/// where DuckDB lacks a Postgres function or where its string literals follow the SQL standard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuckDb();
+
+impl Dialect for DuckDb {
+    fn quote_identifier(&self, ident: &str) -> String {
+        format!(r#""{}""#, ident.replace('"', r#""""#))
+    }
+
+    /// Backslashes have no special meaning within a string literal
+    fn quote_string(&self, string: &str) -> String {
+        format!("'{}'", string.replace('\'', "''"))
+    }
+
+    fn date(&self, date: &Date) -> String {
+        format!("DATE '{}'", date.to_iso())
+    }
+
+    fn bytes(&self, bytes: &[u8]) -> String {
+        let escaped = bytes
+            .iter()
+            .map(|b| format!("\\x{b:02X}"))
+            .collect::<String>();
+        format!("'{escaped}'::BLOB")
+    }
+
+    fn uuid(&self, uuid: &str) -> String {
+        format!("'{uuid}'::uuid")
+    }
+
+    /// DuckDB has no `make_interval`, so we add up the intervals built by its `to_*` functions
+    fn duration(&self, duration: &Duration) -> String {
+        let parts = interval_parts(duration)
+            .into_iter()
+            .map(|(name, value)| {
+                let unit = match name {
+                    "mins" => "minutes",
+                    "secs" => "seconds",
+                    _ => name,
+                };
+                format!("to_{unit}({value})")
+            })
+            .collect::<Vec<String>>();
+        match parts.as_slice() {
+            [] => "to_seconds(0)".to_string(),
+            [part] => part.clone(),
+            _ => format!("({})", parts.join(" + ")),
+        }
+    }
+
+    /// The `~` operator only matches the whole string, unlike in Postgres
+    fn match_regex(
+        &self,
+        a: SqlExpr,
+        b: SqlExpr,
+        is_positive: bool,
+        flags: &RegExFlags,
+    ) -> SqlExpr {
+        let options = (!flags.is_case_sensitive).then(|| SqlExpr::atom(self.quote_string("i")));
+        let matches = regexp_matches(a, b, options);
+        if is_positive {
+            matches
+        } else {
+            not(matches)
+        }
+    }
+
+    fn regexp_replace(
+        &self,
+        a: SqlExpr,
+        pattern: &str,
+        replacement: &str,
+        flags: &RegExReplaceFlags,
+    ) -> Option<SqlExpr> {
+        let pattern = SqlExpr::atom(self.quote_string(pattern));
+        let replacement = SqlExpr::atom(self.quote_string(replacement));
+        let flags = [(flags.is_global, "g"), (!flags.is_case_sensitive, "i")]
+            .into_iter()
+            .filter_map(|(is_set, flag)| is_set.then_some(flag))
+            .collect::<String>();
+        let flags = (!flags.is_empty()).then(|| SqlExpr::atom(self.quote_string(&flags)));
+        Some(regexp_replace(a, pattern, replacement, flags))
+    }
+
+    fn hash(&self, a: SqlExpr, algorithm: HashAlgorithm) -> Option<SqlExpr> {
+        match algorithm {
+            HashAlgorithm::Md5 => Some(md5(a)),
+            HashAlgorithm::Sha256 => Some(sha256(a)),
+        }
+    }
+
+    /// `json_extract` would yield JSON instead of text
+    fn json_extract(&self, json: SqlExpr, path: &[JsonPathSegment]) -> SqlExpr {
+        let path = self.quote_string(&json_path(path));
+        SqlExpr::atom(format!("json_extract_string({json}, {path})"))
+    }
+
+    fn to_date(&self, a: SqlExpr, format: &str) -> Option<SqlExpr> {
+        let timestamp = self.to_timestamp(a, format)?;
+        Some(self.cast(timestamp, "DATE"))
+    }
+
+    /// DuckDB has no `to_timestamp` which accepts a format, only `strptime`, so we translate the
+    /// patterns which have an equivalent
+    fn to_timestamp(&self, a: SqlExpr, format: &str) -> Option<SqlExpr> {
+        let format = strptime_format(format)?;
+        Some(strptime(a, SqlExpr::atom(self.quote_string(&format))))
+    }
+
+    /// DuckDB has no `to_char`, and `strftime` only formats timestamps
+    fn to_char(&self, _a: SqlExpr, _format: &str) -> Option<SqlExpr> {
+        None
+    }
+
+    /// An untyped string literal as the origin would be ambiguous, since `time_bucket` also has
+    /// versions which take an offset or a time zone in that position
+    fn date_bin(&self, width: SqlExpr, a: SqlExpr, origin: SqlExpr) -> SqlExpr {
+        let origin = if origin.content.starts_with('\'') {
+            SqlExpr::atom(format!("TIMESTAMP {origin}"))
+        } else {
+            origin
+        };
+        time_bucket(width, a, origin)
+    }
+
+    fn type_name(&self, data_type: &DataType) -> Option<String> {
+        let name = match data_type {
+            DataType::Integer => "bigint",
+            DataType::Text => "varchar",
+            DataType::Boolean => "boolean",
+            DataType::Date => "date",
+            DataType::TimestampTz => "timestamptz",
+            DataType::Interval => "interval",
+            DataType::Binary => "blob",
+            DataType::Uuid => "uuid",
+            DataType::Array(element_type) => return Some(self.type_name(element_type)? + "[]"),
+            // NUMERIC without a precision keeps only three fractional digits, and we can't tell
+            // whether a timestamp has a time zone
+            DataType::Numeric | DataType::Timestamp | DataType::Unknown => return None,
+        };
+        Some(name.to_owned())
+    }
+
+    fn cast(&self, a: SqlExpr, type_name: &str) -> SqlExpr {
+        let a = a.for_precedence(SqlExprPrecedence::Atom);
+        SqlExpr::atom(format!("{a}::{type_name}"))
+    }
+
+    fn bitwise(&self, operator: BitwiseOperator, a: SqlExpr, b: SqlExpr) -> SqlExpr {
+        match operator {
+            BitwiseOperator::Xor => xor_function(a, b),
+            _ => bitwise_op(a, operator.symbol(), b),
+        }
+    }
+
+    fn supports_trunc_places(&self) -> bool {
+        false
+    }
+
+    fn supports_at_time_zone(&self) -> bool {
+        true
+    }
+
+    fn supports_date_bin(&self) -> bool {
+        true
+    }
+
+    fn supports_ordered_aggregates(&self) -> bool {
+        true
+    }
+
+    fn supports_frame_exclusion(&self) -> bool {
+        true
+    }
+
+    fn approx_count_distinct(&self) -> Option<fn(SqlExpr) -> SqlExpr> {
+        Some(approx_count_distinct)
+    }
+
+    fn supports_distinct_on(&self) -> bool {
+        true
+    }
+
+    fn supports_lateral(&self) -> bool {
+        true
+    }
+
+    fn supports_locking(&self) -> bool {
+        false
+    }
+
+    fn supports_prepared_statements(&self) -> bool {
+        true
+    }
+
+    /// The types of the parameters are inferred by DuckDB and can't be declared
+    fn prepare(&self, name: &str, _parameter_types: &[Option<String>]) -> String {
+        format!("PREPARE {} AS", self.quote_identifier(name))
+    }
+}
+
+/// Translate a format pattern in the style of Postgres into one for `strptime`, or `None` when the
+/// pattern has a field without an equivalent. Text within double quotes is kept as it is.
+fn strptime_format(format: &str) -> Option<String> {
+    let mut result = String::new();
+    let mut rest = format;
+    while let Some(c) = rest.chars().next() {
+        if c == '"' {
+            let (literal, after) = rest[1..].split_once('"')?;
+            result.push_str(&literal.replace('%', "%%"));
+            rest = after;
+            continue;
+        }
+        let field = STRPTIME_FIELDS.iter().find(|(field, _)| {
+            rest.get(..field.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(field))
+        });
+        if let Some((field, specifier)) = field {
+            result.push_str(specifier);
+            rest = &rest[field.len()..];
+            continue;
+        }
+        if c.is_alphanumeric() {
+            return None;
+        }
+        match c {
+            '%' => result.push_str("%%"),
+            _ => result.push(c),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    Some(result)
+}
@@ -1,5 +1,5 @@
 use itertools::Itertools;
-use querydown_parser::ast::Conjunction;
+use querydown_parser::ast::{Conjunction, FrameExclusion};
 
 use super::{SqlExpr, SqlExprPrecedence};
 
@@ -15,6 +15,10 @@ fn binary_op(a: SqlExpr, op: &str, b: SqlExpr, precedence: SqlExprPrecedence) ->
     }
 }
 
+/// The character used to escape wildcards within LIKE patterns that we build from string literals,
+/// unless another is configured
+pub const LIKE_ESCAPE_CHAR: char = '!';
+
 fn sql_func(name: &str, args: impl IntoIterator<Item = SqlExpr>) -> SqlExpr {
     SqlExpr::atom(format!("{}({})", name, args.into_iter().join(", ")))
 }
@@ -22,6 +26,18 @@ fn sql_func(name: &str, args: impl IntoIterator<Item = SqlExpr>) -> SqlExpr {
 pub mod agg {
     use super::*;
 
+    pub fn approx_count_distinct(a: SqlExpr) -> SqlExpr {
+        sql_func("APPROX_COUNT_DISTINCT", [a])
+    }
+
+    pub fn array_agg(a: SqlExpr) -> SqlExpr {
+        sql_func("array_agg", [a])
+    }
+
+    pub fn array_agg_distinct(a: SqlExpr) -> SqlExpr {
+        SqlExpr::atom(format!("array_agg(DISTINCT {})", a.content))
+    }
+
     pub fn bool_and(a: SqlExpr) -> SqlExpr {
         sql_func("bool_and", [a])
     }
@@ -42,6 +58,11 @@ pub mod agg {
         SqlExpr::atom("count(*)".to_string())
     }
 
+    /// Restrict the rows passed to the aggregate `agg` to those for which `condition` holds
+    pub fn filter(agg: SqlExpr, condition: SqlExpr) -> SqlExpr {
+        SqlExpr::atom(format!("{agg} FILTER (WHERE {condition})"))
+    }
+
     pub fn count_distinct(a: SqlExpr) -> SqlExpr {
         // TODO: We should alter the query at a higher level to use an approach like this for
         // better performance:
@@ -49,6 +70,10 @@ pub mod agg {
         SqlExpr::atom(format!("count(DISTINCT {})", a.content))
     }
 
+    pub fn grouping(a: SqlExpr) -> SqlExpr {
+        sql_func("GROUPING", [a])
+    }
+
     pub fn max(a: SqlExpr) -> SqlExpr {
         sql_func("max", [a])
     }
@@ -57,6 +82,13 @@ pub mod agg {
         sql_func("min", [a])
     }
 
+    /// Order the values passed to an aggregate function, e.g. `array_agg(a ORDER BY b)`. The
+    /// ordering goes after the last argument, within the parentheses.
+    pub fn ordered(agg: SqlExpr, order_by: Vec<String>) -> SqlExpr {
+        let call = agg.content.strip_suffix(')').unwrap_or(&agg.content);
+        SqlExpr::atom(format!("{call} ORDER BY {})", order_by.join(", ")))
+    }
+
     pub fn string_agg(a: SqlExpr) -> SqlExpr {
         // TODO:
         // - Let user customize the separator
@@ -65,11 +97,36 @@ pub mod agg {
         sql_func("string_agg", [a, separator])
     }
 
+    pub fn string_agg_distinct(a: SqlExpr) -> SqlExpr {
+        let separator = SqlExpr::atom("', '".to_string());
+        SqlExpr::atom(format!("string_agg(DISTINCT {}, {})", a.content, separator))
+    }
+
     pub fn sum(a: SqlExpr) -> SqlExpr {
         sql_func("sum", [a])
     }
 }
 
+pub mod array {
+    use super::*;
+
+    /// Render the length of the first dimension of an array
+    pub fn array_length(a: SqlExpr) -> SqlExpr {
+        sql_func("array_length", [a, SqlExpr::atom("1".to_string())])
+    }
+
+    /// Render the elements of an array from `lower` to `upper`, inclusive. Only column references
+    /// may be subscripted without parentheses, so we always add them.
+    pub fn slice(a: SqlExpr, lower: u64, upper: u64) -> SqlExpr {
+        SqlExpr::atom(format!("({a})[{lower}:{upper}]"))
+    }
+
+    /// Render an array with `count` elements removed from its end
+    pub fn trim_array(a: SqlExpr, count: u64) -> SqlExpr {
+        sql_func("trim_array", [a, SqlExpr::atom(count.to_string())])
+    }
+}
+
 pub mod cmp {
     use super::*;
 
@@ -137,6 +194,28 @@ pub mod cmp {
         comparison(a, "NOT LIKE", b)
     }
 
+    /// A LIKE comparison with an explicit escape character, which must already be quoted. Use
+    /// `strings::escape_like` to build the pattern from a string literal.
+    pub fn like_escaped(a: SqlExpr, b: SqlExpr, escape: &str) -> SqlExpr {
+        comparison_escaped(a, "LIKE", b, escape)
+    }
+
+    pub fn nlike_escaped(a: SqlExpr, b: SqlExpr, escape: &str) -> SqlExpr {
+        comparison_escaped(a, "NOT LIKE", b, escape)
+    }
+
+    fn comparison_escaped(a: SqlExpr, op: &str, b: SqlExpr, escape: &str) -> SqlExpr {
+        let precedence = SqlExprPrecedence::Comparison;
+        SqlExpr {
+            content: format!(
+                "{} {op} {} ESCAPE {escape}",
+                a.for_precedence(precedence),
+                b.for_precedence(precedence),
+            ),
+            precedence,
+        }
+    }
+
     pub fn is_null(a: SqlExpr) -> SqlExpr {
         SqlExpr {
             content: format!("{} IS NULL", a.content),
@@ -150,13 +229,63 @@ pub mod cmp {
             precedence: SqlExprPrecedence::Comparison,
         }
     }
+
+    /// `a IS TRUE`, `a IS NOT FALSE`, etc. A comparison within `a` is parenthesized because
+    /// databases disagree about whether `IS` binds more tightly than the other comparisons.
+    pub fn is_truth_value(a: SqlExpr, value: bool, is_negated: bool) -> SqlExpr {
+        let not = if is_negated { "NOT " } else { "" };
+        let value = if value { "TRUE" } else { "FALSE" };
+        SqlExpr {
+            content: format!(
+                "{} IS {not}{value}",
+                a.for_precedence(SqlExprPrecedence::OtherOperator)
+            ),
+            precedence: SqlExprPrecedence::Comparison,
+        }
+    }
 }
 
 pub mod cond {
     use super::*;
 
-    pub fn coalesce(a: SqlExpr) -> SqlExpr {
-        sql_func("COALESCE", [a])
+    /// 1 when the condition `a` holds and 0 otherwise
+    pub fn bit_of(a: SqlExpr) -> SqlExpr {
+        SqlExpr::atom(format!("CASE WHEN {a} THEN 1 ELSE 0 END"))
+    }
+
+    /// A simple CASE expression, which compares `a` with the first value of each pair and yields
+    /// the second value of the first pair that's equal
+    pub fn case(a: SqlExpr, pairs: Vec<(SqlExpr, SqlExpr)>, default: Option<SqlExpr>) -> SqlExpr {
+        let whens = pairs
+            .into_iter()
+            .map(|(from, to)| format!(" WHEN {from} THEN {to}"))
+            .join("");
+        let else_ = default.map(|d| format!(" ELSE {d}")).unwrap_or_default();
+        SqlExpr::atom(format!("CASE {a}{whens}{else_} END"))
+    }
+
+    /// A searched CASE expression, which yields the value paired with the first condition that's
+    /// true
+    pub fn searched_case(pairs: Vec<(SqlExpr, SqlExpr)>, default: Option<SqlExpr>) -> SqlExpr {
+        let whens = pairs
+            .into_iter()
+            .map(|(condition, then)| format!(" WHEN {condition} THEN {then}"))
+            .join("");
+        let else_ = default.map(|d| format!(" ELSE {d}")).unwrap_or_default();
+        SqlExpr::atom(format!("CASE{whens}{else_} END"))
+    }
+
+    pub fn coalesce(args: Vec<SqlExpr>) -> SqlExpr {
+        sql_func("COALESCE", args)
+    }
+
+    pub fn nullif(a: SqlExpr, b: SqlExpr) -> SqlExpr {
+        sql_func("NULLIF", [a, b])
+    }
+
+    /// `subquery` must already be wrapped in parentheses
+    pub fn exists(subquery: String) -> SqlExpr {
+        SqlExpr::atom(format!("EXISTS {subquery}"))
     }
 
     pub fn not(a: SqlExpr) -> SqlExpr {
@@ -186,6 +315,43 @@ pub mod date_time {
         extract_epoch(a)
     }
 
+    /// Round `a` down to the start of the bin containing it, where bins of the given width start
+    /// at `origin`
+    pub fn date_bin(width: SqlExpr, a: SqlExpr, origin: SqlExpr) -> SqlExpr {
+        sql_func("date_bin", [width, a, origin])
+    }
+
+    /// The DuckDB equivalent of `date_bin`
+    pub fn time_bucket(width: SqlExpr, a: SqlExpr, origin: SqlExpr) -> SqlExpr {
+        sql_func("time_bucket", [width, a, origin])
+    }
+
+    /// Parse the text `a` as a timestamp, given a format pattern in the style of `strftime`
+    pub fn strptime(a: SqlExpr, format: SqlExpr) -> SqlExpr {
+        sql_func("strptime", [a, format])
+    }
+
+    /// Parse the text `a` as a date in the given format
+    pub fn to_date(a: SqlExpr, format: SqlExpr) -> SqlExpr {
+        sql_func("TO_DATE", [a, format])
+    }
+
+    /// Parse the text `a` as a timestamp in the given format
+    pub fn to_timestamp(a: SqlExpr, format: SqlExpr) -> SqlExpr {
+        sql_func("TO_TIMESTAMP", [a, format])
+    }
+
+    /// The SQL Server function which counts the boundaries of `unit` crossed between `start` and
+    /// `end`
+    pub fn datediff(unit: &str, start: SqlExpr, end: SqlExpr) -> SqlExpr {
+        sql_func("DATEDIFF", [SqlExpr::atom(unit.to_string()), start, end])
+    }
+
+    /// The SQLite function which converts a date or timestamp to a fractional Julian day number
+    pub fn julianday(a: SqlExpr) -> SqlExpr {
+        sql_func("julianday", [a])
+    }
+
     pub fn extract_epoch(a: SqlExpr) -> SqlExpr {
         SqlExpr::atom(format!("EXTRACT(epoch FROM {})", a.content))
     }
@@ -197,6 +363,29 @@ pub mod func {
     pub fn now() -> SqlExpr {
         SqlExpr::atom("NOW()".to_string())
     }
+
+    pub fn current_timestamp() -> SqlExpr {
+        SqlExpr::atom("CURRENT_TIMESTAMP".to_string())
+    }
+
+    pub fn current_user() -> SqlExpr {
+        SqlExpr::atom("CURRENT_USER".to_string())
+    }
+
+    /// The value of a Postgres configuration parameter, e.g. a custom one like `app.tenant_id`
+    /// which the application sets for each session
+    pub fn current_setting(name: SqlExpr) -> SqlExpr {
+        sql_func("current_setting", [name])
+    }
+}
+
+pub mod json {
+    use super::*;
+
+    /// The Postgres `#>>` operator, which extracts the text at a path within a JSON value
+    pub fn extract_path_text(a: SqlExpr, path: SqlExpr) -> SqlExpr {
+        binary_op(a, "#>>", path, SqlExprPrecedence::OtherOperator)
+    }
 }
 
 pub mod math {
@@ -214,10 +403,28 @@ pub mod math {
         sql_func("CEIL", [a])
     }
 
+    pub fn width_bucket(a: SqlExpr, low: SqlExpr, high: SqlExpr, count: SqlExpr) -> SqlExpr {
+        sql_func("WIDTH_BUCKET", [a, low, high, count])
+    }
+
     pub fn divide(a: SqlExpr, b: SqlExpr) -> SqlExpr {
         binary_op(a, "/", b, SqlExprPrecedence::Multiplication)
     }
 
+    /// `a / b`, or NULL when `b` is zero
+    pub fn divide_or_null(a: SqlExpr, b: SqlExpr) -> SqlExpr {
+        divide(a, cond::nullif(b, SqlExpr::atom("0".to_string())))
+    }
+
+    /// `a / b`, or `fallback` when `b` is zero
+    pub fn divide_or_else(a: SqlExpr, b: SqlExpr, fallback: SqlExpr) -> SqlExpr {
+        let is_zero = cmp::eq(b.clone(), SqlExpr::atom("0".to_string()));
+        SqlExpr::atom(format!(
+            "CASE WHEN {is_zero} THEN {fallback} ELSE {} END",
+            divide(a, b)
+        ))
+    }
+
     pub fn floor(a: SqlExpr) -> SqlExpr {
         sql_func("FLOOR", [a])
     }
@@ -230,17 +437,74 @@ pub mod math {
         sql_func("LEAST", args)
     }
 
+    /// `a` kept within the bounds `low` and `high`
+    pub fn clamp(a: SqlExpr, low: SqlExpr, high: SqlExpr) -> SqlExpr {
+        least(vec![greatest(vec![a, low]), high])
+    }
+
     pub fn modulo(a: SqlExpr, b: SqlExpr) -> SqlExpr {
         binary_op(a, "%", b, SqlExprPrecedence::Multiplication)
     }
 
+    /// The remainder of dividing `a` by `b`, via the function of the SQL standard
+    pub fn mod_function(a: SqlExpr, b: SqlExpr) -> SqlExpr {
+        sql_func("MOD", [a, b])
+    }
+
     pub fn multiply(a: SqlExpr, b: SqlExpr) -> SqlExpr {
         binary_op(a, "*", b, SqlExprPrecedence::Multiplication)
     }
 
+    pub fn sign(a: SqlExpr) -> SqlExpr {
+        sql_func("SIGN", [a])
+    }
+
     pub fn subtract(a: SqlExpr, b: SqlExpr) -> SqlExpr {
         binary_op(a, "-", b, SqlExprPrecedence::Addition)
     }
+
+    pub fn trunc(a: SqlExpr, places: Option<SqlExpr>) -> SqlExpr {
+        sql_func("TRUNC", [a].into_iter().chain(places))
+    }
+
+    pub fn round(a: SqlExpr, places: Option<SqlExpr>) -> SqlExpr {
+        sql_func("ROUND", [a].into_iter().chain(places))
+    }
+
+    /// The `^` operator. Postgres evaluates chains of `^` from left to right, so we parenthesize
+    /// the exponent unless it's an atom.
+    pub fn power_operator(a: SqlExpr, b: SqlExpr) -> SqlExpr {
+        SqlExpr {
+            content: format!(
+                "{} ^ {}",
+                a.for_precedence(SqlExprPrecedence::Exponentiation),
+                b.for_precedence(SqlExprPrecedence::Atom)
+            ),
+            precedence: SqlExprPrecedence::Exponentiation,
+        }
+    }
+
+    /// A bitwise operator. Databases disagree about how tightly these bind relative to arithmetic,
+    /// so we parenthesize any operand which isn't an atom.
+    pub fn bitwise_op(a: SqlExpr, op: &str, b: SqlExpr) -> SqlExpr {
+        SqlExpr {
+            content: format!(
+                "{} {op} {}",
+                a.for_precedence(SqlExprPrecedence::Atom),
+                b.for_precedence(SqlExprPrecedence::Atom)
+            ),
+            precedence: SqlExprPrecedence::OtherOperator,
+        }
+    }
+
+    pub fn power_function(a: SqlExpr, b: SqlExpr) -> SqlExpr {
+        sql_func("POWER", [a, b])
+    }
+
+    /// Bitwise XOR, for dialects which have a function for it instead of an operator
+    pub fn xor_function(a: SqlExpr, b: SqlExpr) -> SqlExpr {
+        sql_func("xor", [a, b])
+    }
 }
 
 pub mod strings {
@@ -254,14 +518,180 @@ pub mod strings {
         sql_func("upper", [a])
     }
 
+    /// Escape any LIKE wildcards within a string so that it matches literally
+    pub fn escape_like(s: &str, escape_char: char) -> String {
+        let mut escaped = String::with_capacity(s.len());
+        for c in s.chars() {
+            if c == escape_char || c == '%' || c == '_' {
+                escaped.push(escape_char);
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    /// Render the number or timestamp `a` as text in the given format
+    pub fn to_char(a: SqlExpr, format: SqlExpr) -> SqlExpr {
+        sql_func("TO_CHAR", [a, format])
+    }
+
     pub fn char_length(a: SqlExpr) -> SqlExpr {
         sql_func("char_length", [a])
     }
+
+    pub fn regexp_replace(
+        a: SqlExpr,
+        pattern: SqlExpr,
+        replacement: SqlExpr,
+        flags: Option<SqlExpr>,
+    ) -> SqlExpr {
+        sql_func(
+            "REGEXP_REPLACE",
+            [a, pattern, replacement].into_iter().chain(flags),
+        )
+    }
+
+    /// Test whether the regular expression `pattern` matches anywhere within `a`
+    pub fn regexp_matches(a: SqlExpr, pattern: SqlExpr, options: Option<SqlExpr>) -> SqlExpr {
+        sql_func("regexp_matches", [a, pattern].into_iter().chain(options))
+    }
+
+    /// The regular expression predicate of the SQL standard, given the quoted flags if any
+    pub fn like_regex(a: SqlExpr, pattern: SqlExpr, flags: Option<String>) -> SqlExpr {
+        let precedence = SqlExprPrecedence::Comparison;
+        let flags = flags.map(|f| format!(" FLAG {f}")).unwrap_or_default();
+        SqlExpr {
+            content: format!(
+                "{} LIKE_REGEX {}{flags}",
+                a.for_precedence(precedence),
+                pattern.for_precedence(precedence),
+            ),
+            precedence,
+        }
+    }
+
+    pub fn md5(a: SqlExpr) -> SqlExpr {
+        sql_func("MD5", [a])
+    }
+
+    pub fn sha256(a: SqlExpr) -> SqlExpr {
+        sql_func("SHA256", [a])
+    }
+
+    /// The binary hash of `a` by the named algorithm, via the `pgcrypto` extension of Postgres
+    pub fn digest(a: SqlExpr, algorithm: SqlExpr) -> SqlExpr {
+        sql_func("DIGEST", [a, algorithm])
+    }
+
+    /// The binary value `a` as text in the named format, e.g. `hex`
+    pub fn encode(a: SqlExpr, format: SqlExpr) -> SqlExpr {
+        sql_func("ENCODE", [a, format])
+    }
+
+    /// The text of the scalar value at `path` within the JSON value `json`
+    pub fn json_value(json: SqlExpr, path: SqlExpr) -> SqlExpr {
+        sql_func("JSON_VALUE", [json, path])
+    }
+}
+
+pub mod text_search {
+    use super::*;
+
+    /// The Postgres `@@` operator, which tests whether a `tsvector` matches a `tsquery`
+    pub fn text_search_match(vector: SqlExpr, query: SqlExpr) -> SqlExpr {
+        binary_op(vector, "@@", query, SqlExprPrecedence::OtherOperator)
+    }
+
+    /// The `tsvector` of the text `a`, under the given text search configuration, if any
+    pub fn to_tsvector(config: Option<SqlExpr>, a: SqlExpr) -> SqlExpr {
+        sql_func("to_tsvector", config.into_iter().chain([a]))
+    }
+
+    /// The `tsquery` which matches all the words of the plain text `a`, ignoring punctuation
+    pub fn plainto_tsquery(config: Option<SqlExpr>, a: SqlExpr) -> SqlExpr {
+        sql_func("plainto_tsquery", config.into_iter().chain([a]))
+    }
+}
+
+pub mod window {
+    use super::*;
+
+    pub fn first_value(args: Vec<SqlExpr>) -> SqlExpr {
+        sql_func("first_value", args)
+    }
+
+    pub fn last_value(args: Vec<SqlExpr>) -> SqlExpr {
+        sql_func("last_value", args)
+    }
+
+    pub fn nth_value(args: Vec<SqlExpr>) -> SqlExpr {
+        sql_func("nth_value", args)
+    }
+
+    /// `window` is expected to be a rendered window definition. See `definition`.
+    pub fn over(call: SqlExpr, window: String) -> SqlExpr {
+        SqlExpr::atom(format!("{call} OVER ({window})"))
+    }
+
+    /// The contents of the parentheses after `OVER`, omitting any clause which is empty. Each
+    /// entry of `order_by` is expected to be a fully rendered sort entry.
+    pub fn definition(
+        partition_by: Vec<SqlExpr>,
+        order_by: Vec<String>,
+        frame: Option<String>,
+    ) -> String {
+        let partition_by = (!partition_by.is_empty())
+            .then(|| format!("PARTITION BY {}", partition_by.into_iter().join(", ")));
+        let order_by = (!order_by.is_empty()).then(|| format!("ORDER BY {}", order_by.join(", ")));
+        [partition_by, order_by, frame]
+            .into_iter()
+            .flatten()
+            .join(" ")
+    }
+
+    /// A frame of rows between two offsets from the current row, where negative offsets precede
+    /// it. Omitted offsets extend to the edges of the partition.
+    pub fn rows_between(
+        start: Option<i64>,
+        end: Option<i64>,
+        exclude: Option<FrameExclusion>,
+    ) -> String {
+        let bound = |offset: Option<i64>, unbounded: &str| match offset {
+            None => format!("UNBOUNDED {unbounded}"),
+            Some(0) => "CURRENT ROW".to_string(),
+            Some(n) if n < 0 => format!("{} PRECEDING", n.unsigned_abs()),
+            Some(n) => format!("{n} FOLLOWING"),
+        };
+        let exclude = match exclude {
+            None => "",
+            Some(FrameExclusion::CurrentRow) => " EXCLUDE CURRENT ROW",
+            Some(FrameExclusion::Group) => " EXCLUDE GROUP",
+            Some(FrameExclusion::Ties) => " EXCLUDE TIES",
+        };
+        format!(
+            "ROWS BETWEEN {} AND {}{exclude}",
+            bound(start, "PRECEDING"),
+            bound(end, "FOLLOWING")
+        )
+    }
+
+    /// `order_by` is expected to be a fully rendered sort entry, e.g. `"t"."c" DESC NULLS LAST`
+    pub fn row_number(partition_by: SqlExpr, order_by: String) -> SqlExpr {
+        SqlExpr::atom(format!(
+            "row_number() OVER (PARTITION BY {} ORDER BY {})",
+            partition_by, order_by
+        ))
+    }
 }
 
 pub mod value {
     use super::*;
 
+    /// `type_name` is inserted verbatim
+    pub fn cast(a: SqlExpr, type_name: &str) -> SqlExpr {
+        SqlExpr::atom(format!("CAST({} AS {})", a.content, type_name))
+    }
+
     pub fn infinity() -> SqlExpr {
         SqlExpr::atom("INFINITY".to_string())
     }
@@ -278,6 +708,10 @@ pub mod value {
         SqlExpr::atom("FALSE".to_string())
     }
 
+    pub fn one() -> SqlExpr {
+        SqlExpr::atom("1".to_string())
+    }
+
     pub fn zero() -> SqlExpr {
         SqlExpr::atom("0".to_string())
     }
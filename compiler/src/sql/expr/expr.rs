@@ -52,18 +52,22 @@ impl Display for SqlExpr {
 pub enum SqlExprPrecedence {
     /// A literal value, a column name, a function call, or parentheses.
     Atom = 0,
+    /// `^`
+    Exponentiation = -1,
     /// `*` `/` `%`
-    Multiplication = -1,
+    Multiplication = -2,
     /// `+` `-`
-    Addition = -2,
+    Addition = -3,
+    /// Any other operator, e.g. `#>>`
+    OtherOperator = -4,
     /// `=` `<>` `>` `>=` `<` `<=` `IS` `IS NOT` `IN` `LIKE` `NOT LIKE
-    Comparison = -3,
+    Comparison = -5,
     /// `NOT`
-    LogicalNot = -4,
+    LogicalNot = -6,
     /// `AND`
-    LogicalAnd = -5,
+    LogicalAnd = -7,
     /// `OR`
-    LogicalOr = -6,
+    LogicalOr = -8,
 }
 
 impl Default for SqlExprPrecedence {
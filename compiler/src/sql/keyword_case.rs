@@ -0,0 +1,187 @@
+use super::Dialect;
+
+/// The keywords which the compiler renders, all of which are written in uppercase. They include
+/// the names of the types which the compiler writes in uppercase, e.g. `CAST(... AS BIGINT)`.
+/// Function names are left alone. Kept sorted for the binary search within `lowercase_keywords`.
+const KEYWORDS: &[&str] = &[
+    "ALL",
+    "ALTER",
+    "AND",
+    "ANY",
+    "AS",
+    "ASC",
+    "AT",
+    "BERNOULLI",
+    "BETWEEN",
+    "BIGINT",
+    "BOOLEAN",
+    "BY",
+    "CASE",
+    "CAST",
+    "COLLATE",
+    "CREATE",
+    "CROSS",
+    "CUBE",
+    "CURRENT",
+    "CURRENT_TIMESTAMP",
+    "CURRENT_USER",
+    "DATE",
+    "DAY",
+    "DELETE",
+    "DESC",
+    "DISTINCT",
+    "DROP",
+    "ELSE",
+    "END",
+    "ESCAPE",
+    "EXCLUDE",
+    "EXISTS",
+    "EXTRACT",
+    "FALSE",
+    "FETCH",
+    "FILTER",
+    "FIRST",
+    "FLAG",
+    "FOLLOWING",
+    "FOR",
+    "FROM",
+    "FULL",
+    "GROUP",
+    "GROUPING",
+    "GROUPS",
+    "HAVING",
+    "HOUR",
+    "IF",
+    "ILIKE",
+    "IN",
+    "INFINITY",
+    "INNER",
+    "INTEGER",
+    "INTERVAL",
+    "IS",
+    "JOIN",
+    "KEY",
+    "LAST",
+    "LATERAL",
+    "LEFT",
+    "LIKE",
+    "LIKE_REGEX",
+    "LIMIT",
+    "LOCAL",
+    "LOCKED",
+    "MATERIALIZED",
+    "MINUTE",
+    "MONTH",
+    "NEXT",
+    "NO",
+    "NOT",
+    "NOWAIT",
+    "NULL",
+    "NULLS",
+    "NUMERIC",
+    "OF",
+    "OFFSET",
+    "ON",
+    "ONLY",
+    "OR",
+    "ORDER",
+    "ORDINALITY",
+    "OTHERS",
+    "OUTER",
+    "OVER",
+    "PARTITION",
+    "PERCENT",
+    "PRECEDING",
+    "PREPARE",
+    "RANGE",
+    "REGEXP",
+    "REPEATABLE",
+    "REPLACE",
+    "ROLLUP",
+    "ROW",
+    "ROWS",
+    "SECOND",
+    "SELECT",
+    "SET",
+    "SETS",
+    "SHARE",
+    "SKIP",
+    "SYSTEM",
+    "SYSTEM_TIME",
+    "TABLESAMPLE",
+    "TEMPORARY",
+    "TEXT",
+    "THEN",
+    "TIES",
+    "TIME",
+    "TIMESTAMP",
+    "TOP",
+    "TRUE",
+    "UNBOUNDED",
+    "UPDATE",
+    "USING",
+    "VALUES",
+    "VIEW",
+    "WHEN",
+    "WHERE",
+    "WITH",
+    "YEAR",
+    "ZONE",
+];
+
+/// Render the keywords within the SQL in lowercase, e.g. `select ... from ...`. Quoted
+/// identifiers, string literals, comments, and placeholders are left as they are. String literals
+/// are delimited according to the dialect which rendered them.
+pub fn lowercase_keywords(sql: &str, dialect: &dyn Dialect) -> String {
+    let mut result = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    let mut previous: Option<char> = None;
+    while let Some(c) = chars.next() {
+        let closing = match c {
+            '\'' => Some('\''),
+            '"' => Some('"'),
+            '`' => Some('`'),
+            '[' => Some(']'),
+            '-' if chars.peek() == Some(&'-') => Some('\n'),
+            _ => None,
+        };
+        if let Some(closing) = closing {
+            // A doubled quote within a quoted span escapes itself, which this handles by treating
+            // the span as two adjacent spans
+            let escape_char = (c == '\'').then(|| dialect.string_escape_char()).flatten();
+            result.push(c);
+            while let Some(c) = chars.next() {
+                result.push(c);
+                if Some(c) == escape_char {
+                    result.extend(chars.next());
+                } else if c == closing {
+                    break;
+                }
+            }
+            previous = result.chars().last();
+            continue;
+        }
+        if c.is_ascii_alphabetic() || c == '_' {
+            let mut word = c.to_string();
+            while let Some(&next) = chars.peek() {
+                if !next.is_ascii_alphanumeric() && next != '_' {
+                    break;
+                }
+                word.push(next);
+                chars.next();
+            }
+            // `::` casts to a type rather than beginning a placeholder
+            let is_placeholder =
+                matches!(previous, Some('@' | ':' | '$')) && !result.ends_with("::");
+            if !is_placeholder && KEYWORDS.binary_search(&word.as_str()).is_ok() {
+                word.make_ascii_lowercase();
+            }
+            result.push_str(&word);
+            previous = word.chars().last();
+            continue;
+        }
+        result.push(c);
+        previous = Some(c);
+    }
+    result
+}
@@ -1,8 +1,18 @@
+mod ansi;
 mod dialect;
+mod duck_db;
+mod keyword_case;
 mod postgres;
+mod sql_server;
+mod sqlite;
 
 pub mod expr;
 pub mod tree;
 
+pub use ansi::*;
 pub use dialect::*;
+pub use duck_db::*;
+pub use keyword_case::*;
 pub use postgres::*;
+pub use sql_server::*;
+pub use sqlite::*;
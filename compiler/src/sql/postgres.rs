@@ -2,9 +2,22 @@ use std::fmt::Display;
 
 use querydown_parser::ast::{Date, Duration};
 
+use crate::{options::SampleMethod, schema::DataType};
+
 use super::{
-    dialect::{Dialect, RegExFlags},
-    expr::{build::cmp::comparison, SqlExpr},
+    dialect::{
+        hex, BitwiseOperator, Dialect, HashAlgorithm, JsonPathSegment, RegExFlags,
+        RegExReplaceFlags,
+    },
+    expr::{
+        build::{
+            cmp::comparison,
+            json::extract_path_text,
+            math::{bitwise_op, power_operator},
+            strings::{digest, encode, md5, regexp_replace},
+        },
+        SqlExpr, SqlExprPrecedence,
+    },
 };
 
 const SECONDS_PER_MINUTE: i64 = 60;
@@ -42,58 +55,38 @@ pub struct Postgres();
 // stored in the AST are not unintentionally processed as escape sequences by Postgres. See
 // https://www.postgresql.org/docs/current/sql-syntax-lexical.html for continued research.
 impl Dialect for Postgres {
+    /// Within a quoted identifier, a double quote is escaped by doubling it. Backslashes have no
+    /// special meaning.
     fn quote_identifier(&self, ident: &str) -> String {
-        format!(r#""{}""#, ident.replace(r"\", r"\\").replace('"', r#"\""#))
+        format!(r#""{}""#, ident.replace('"', r#""""#))
     }
 
     fn quote_string(&self, string: &str) -> String {
         format!("'{}'", string.replace(r"\", r"\\").replace("'", r"\'"))
     }
 
+    fn string_escape_char(&self) -> Option<char> {
+        Some('\\')
+    }
+
     fn date(&self, date: &Date) -> String {
         format!("DATE '{}'", date.to_iso())
     }
 
-    fn duration(&self, duration: &Duration) -> String {
-        // The complexity in this function is due to the following requirements:
-        //
-        // - In Querydown code, we'd like to support defining durations in terms of floats, but
-        // Postgres only supports integer values for most of the arguments to the [make_interval]
-        // function. So we have to convert the float values into integers.
-        //
-        // - We'd like to render SQL that doesn't show zero values for duration parts.
-        //
-        // [make_interval]: https://www.postgresql.org/docs/current/functions-datetime.html
-
-        let mut seconds = duration.seconds;
-
-        let mut convert = |v: f64, multiplier: i64| -> i64 {
-            seconds += v.fract() * multiplier as f64;
-            v.floor() as i64
-        };
-
-        fn part<T>(value: T, name: &str) -> Option<String>
-        where
-            T: PartialEq + Zero + Display,
-        {
-            if value == T::ZERO {
-                None
-            } else {
-                Some(format!("{name} => {value}"))
-            }
-        }
+    fn bytes(&self, bytes: &[u8]) -> String {
+        format!("'\\x{}'::bytea", hex(bytes))
+    }
 
-        #[rustfmt::skip]
-        let args = [
-            part(convert(duration.years,   SECONDS_PER_YEAR  ), "years"),
-            part(convert(duration.months,  SECONDS_PER_MONTH ), "months"),
-            part(convert(duration.weeks,   SECONDS_PER_WEEK  ), "weeks"),
-            part(convert(duration.days,    SECONDS_PER_DAY   ), "days"),
-            part(convert(duration.hours,   SECONDS_PER_HOUR  ), "hours"),
-            part(convert(duration.minutes, SECONDS_PER_MINUTE), "mins"),
-            part(seconds, "secs"),
-        ].into_iter().filter_map(|v| v).collect::<Vec<String>>().join(", ");
+    fn uuid(&self, uuid: &str) -> String {
+        format!("'{uuid}'::uuid")
+    }
 
+    fn duration(&self, duration: &Duration) -> String {
+        let args = interval_parts(duration)
+            .into_iter()
+            .map(|(name, value)| format!("{name} => {value}"))
+            .collect::<Vec<String>>()
+            .join(", ");
         format!("make_interval({args})")
     }
 
@@ -112,4 +105,210 @@ impl Dialect for Postgres {
         };
         comparison(a, op, b)
     }
+
+    fn regexp_replace(
+        &self,
+        a: SqlExpr,
+        pattern: &str,
+        replacement: &str,
+        flags: &RegExReplaceFlags,
+    ) -> Option<SqlExpr> {
+        let pattern = SqlExpr::atom(self.quote_string(pattern));
+        let replacement = SqlExpr::atom(self.quote_string(replacement));
+        let flags = [(flags.is_global, "g"), (!flags.is_case_sensitive, "i")]
+            .into_iter()
+            .filter_map(|(is_set, flag)| is_set.then_some(flag))
+            .collect::<String>();
+        let flags = (!flags.is_empty()).then(|| SqlExpr::atom(self.quote_string(&flags)));
+        Some(regexp_replace(a, pattern, replacement, flags))
+    }
+
+    /// `md5` is built in, while the other algorithms need `digest` from the `pgcrypto` extension
+    fn hash(&self, a: SqlExpr, algorithm: HashAlgorithm) -> Option<SqlExpr> {
+        let hex = SqlExpr::atom(self.quote_string("hex"));
+        match algorithm {
+            HashAlgorithm::Md5 => Some(md5(a)),
+            _ => {
+                let name = SqlExpr::atom(self.quote_string(algorithm.name()));
+                Some(encode(digest(a, name), hex))
+            }
+        }
+    }
+
+    fn json_extract(&self, json: SqlExpr, path: &[JsonPathSegment]) -> SqlExpr {
+        // The path is a text array literal, within which some elements need quoting
+        let elements = path.iter().map(|segment| match segment {
+            JsonPathSegment::Key(key) => {
+                let needs_quotes = key.is_empty()
+                    || key.eq_ignore_ascii_case("null")
+                    || key.contains(|c: char| "{},\"\\".contains(c) || c.is_whitespace());
+                if needs_quotes {
+                    format!("\"{}\"", key.replace('\\', "\\\\").replace('"', "\\\""))
+                } else {
+                    key.clone()
+                }
+            }
+            JsonPathSegment::Index(index) => index.to_string(),
+        });
+        let array = format!("{{{}}}", elements.collect::<Vec<_>>().join(","));
+        extract_path_text(json, SqlExpr::atom(self.quote_string(&array)))
+    }
+
+    fn type_name(&self, data_type: &DataType) -> Option<String> {
+        let name = match data_type {
+            DataType::Integer => "bigint",
+            DataType::Numeric => "numeric",
+            DataType::Text => "text",
+            DataType::Boolean => "boolean",
+            DataType::Date => "date",
+            DataType::TimestampTz => "timestamptz",
+            DataType::Interval => "interval",
+            DataType::Binary => "bytea",
+            DataType::Uuid => "uuid",
+            DataType::Array(element_type) => return Some(self.type_name(element_type)? + "[]"),
+            // We can't tell whether a timestamp has a time zone
+            DataType::Timestamp | DataType::Unknown => return None,
+        };
+        Some(name.to_owned())
+    }
+
+    fn cast(&self, a: SqlExpr, type_name: &str) -> SqlExpr {
+        let a = a.for_precedence(SqlExprPrecedence::Atom);
+        SqlExpr::atom(format!("{a}::{type_name}"))
+    }
+
+    fn power(&self, a: SqlExpr, b: SqlExpr) -> SqlExpr {
+        power_operator(a, b)
+    }
+
+    fn bitwise(&self, operator: BitwiseOperator, a: SqlExpr, b: SqlExpr) -> SqlExpr {
+        // Postgres uses `^` for exponentiation
+        let symbol = match operator {
+            BitwiseOperator::Xor => "#",
+            _ => operator.symbol(),
+        };
+        bitwise_op(a, symbol, b)
+    }
+
+    fn supports_at_time_zone(&self) -> bool {
+        true
+    }
+
+    fn supports_full_text_search(&self) -> bool {
+        true
+    }
+
+    fn supports_date_bin(&self) -> bool {
+        true
+    }
+
+    fn supports_width_bucket(&self) -> bool {
+        true
+    }
+
+    fn supports_ordered_aggregates(&self) -> bool {
+        true
+    }
+
+    fn supports_frame_exclusion(&self) -> bool {
+        true
+    }
+
+    fn approx_count_distinct(&self) -> Option<fn(SqlExpr) -> SqlExpr> {
+        Some(hll_count_distinct)
+    }
+
+    fn supports_arrays(&self) -> bool {
+        true
+    }
+
+    fn supports_materialized_views(&self) -> bool {
+        true
+    }
+
+    fn supports_row_level_security(&self) -> bool {
+        true
+    }
+
+    /// `SET LOCAL` lasts until the end of the transaction, so other queries on the same connection
+    /// aren't affected
+    fn statement_timeout(&self, milliseconds: u64) -> Option<String> {
+        let units = [
+            (60 * 60 * 1000, "h"),
+            (60 * 1000, "min"),
+            (1000, "s"),
+            (1, "ms"),
+        ];
+        let (size, unit) = units
+            .into_iter()
+            .find(|(size, _)| milliseconds.is_multiple_of(*size))?;
+        let timeout = self.quote_string(&format!("{}{unit}", milliseconds / size));
+        Some(format!("SET LOCAL statement_timeout = {timeout};"))
+    }
+
+    fn supports_prepared_statements(&self) -> bool {
+        true
+    }
+
+    fn supports_table_sample(&self, _method: SampleMethod) -> bool {
+        true
+    }
+
+    fn supports_distinct_on(&self) -> bool {
+        true
+    }
+
+    fn supports_lateral(&self) -> bool {
+        true
+    }
+}
+
+/// Divide a duration into the arguments of [make_interval], named as they are there. The
+/// complexity in this function is due to the following requirements:
+///
+/// - In Querydown code, we'd like to support defining durations in terms of floats, but Postgres
+///   only supports integer values for most of the arguments to `make_interval`. So we have to
+///   convert the float values into integers.
+///
+/// - We'd like to render SQL that doesn't show zero values for duration parts.
+///
+/// [make_interval]: https://www.postgresql.org/docs/current/functions-datetime.html
+pub(super) fn interval_parts(duration: &Duration) -> Vec<(&'static str, String)> {
+    let mut seconds = duration.seconds;
+
+    let mut convert = |v: f64, multiplier: i64| -> i64 {
+        seconds += v.fract() * multiplier as f64;
+        v.floor() as i64
+    };
+
+    fn part<T>(value: T, name: &'static str) -> Option<(&'static str, String)>
+    where
+        T: PartialEq + Zero + Display,
+    {
+        if value == T::ZERO {
+            None
+        } else {
+            Some((name, value.to_string()))
+        }
+    }
+
+    #[rustfmt::skip]
+    let parts = [
+        part(convert(duration.years,   SECONDS_PER_YEAR  ), "years"),
+        part(convert(duration.months,  SECONDS_PER_MONTH ), "months"),
+        part(convert(duration.weeks,   SECONDS_PER_WEEK  ), "weeks"),
+        part(convert(duration.days,    SECONDS_PER_DAY   ), "days"),
+        part(convert(duration.hours,   SECONDS_PER_HOUR  ), "hours"),
+        part(convert(duration.minutes, SECONDS_PER_MINUTE), "mins"),
+        part(seconds, "secs"),
+    ];
+    parts.into_iter().flatten().collect()
+}
+
+/// Estimate the number of distinct values via HyperLogLog, using functions from the `hll`
+/// extension
+fn hll_count_distinct(a: SqlExpr) -> SqlExpr {
+    SqlExpr::atom(format!(
+        "hll_cardinality(hll_add_agg(hll_hash_any({a})))::bigint"
+    ))
 }
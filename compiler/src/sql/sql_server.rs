@@ -0,0 +1,289 @@
+use itertools::Itertools;
+use querydown_parser::ast::{Date, Duration, NullsSort, SortDirection};
+
+use crate::{
+    options::{SampleMethod, TableSample, View},
+    schema::DataType,
+};
+
+use super::{
+    dialect::{
+        duration_in_days, hex, json_path, DateDiffUnit, Dialect, JsonPathSegment, RegExFlags,
+        RegExReplaceFlags,
+    },
+    expr::{
+        build::{
+            agg::approx_count_distinct,
+            cmp::eq,
+            cond::{coalesce, not},
+            date_time::datediff,
+        },
+        SqlExpr, SqlExprPrecedence,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SqlServer();
+
+impl Dialect for SqlServer {
+    fn quote_identifier(&self, ident: &str) -> String {
+        format!("[{}]", ident.replace(']', "]]"))
+    }
+
+    fn quote_string(&self, string: &str) -> String {
+        format!("'{}'", string.replace('\'', "''"))
+    }
+
+    fn placeholder(&self, _index: usize, name: &str) -> String {
+        format!("@{name}")
+    }
+
+    fn supports_nth_value(&self) -> bool {
+        false
+    }
+
+    fn date(&self, date: &Date) -> String {
+        format!("CAST('{}' AS DATE)", date.to_iso())
+    }
+
+    fn bytes(&self, bytes: &[u8]) -> String {
+        format!("0x{}", hex(bytes))
+    }
+
+    fn duration(&self, duration: &Duration) -> String {
+        // SQL Server has no interval type. Adding a number to a DATETIME value adds that number of
+        // days though, so we render durations as a fractional number of days.
+        duration_in_days(duration).to_string()
+    }
+
+    fn match_regex(
+        &self,
+        a: SqlExpr,
+        b: SqlExpr,
+        is_positive: bool,
+        flags: &RegExFlags,
+    ) -> SqlExpr {
+        let flags = if flags.is_case_sensitive { "c" } else { "i" };
+        let expr = SqlExpr::atom(format!("REGEXP_LIKE({a}, {b}, '{flags}')"));
+        if is_positive {
+            expr
+        } else {
+            not(expr)
+        }
+    }
+
+    /// The arguments after the replacement are the position at which to start searching and which
+    /// match to replace, where 0 means all of them
+    fn regexp_replace(
+        &self,
+        a: SqlExpr,
+        pattern: &str,
+        replacement: &str,
+        flags: &RegExReplaceFlags,
+    ) -> Option<SqlExpr> {
+        let pattern = self.quote_string(pattern);
+        let replacement = self.quote_string(replacement);
+        let occurrence = if flags.is_global { 0 } else { 1 };
+        let flags = if flags.is_case_sensitive { "c" } else { "i" };
+        Some(SqlExpr::atom(format!(
+            "REGEXP_REPLACE({a}, {pattern}, {replacement}, 1, {occurrence}, '{flags}')"
+        )))
+    }
+
+    fn json_extract(&self, json: SqlExpr, path: &[JsonPathSegment]) -> SqlExpr {
+        let path = self.quote_string(&json_path(path));
+        SqlExpr::atom(format!("JSON_VALUE({json}, {path})"))
+    }
+
+    /// SQL Server has no format patterns, only numbered styles for CONVERT, so we translate the
+    /// patterns which have an equivalent style
+    fn to_date(&self, a: SqlExpr, format: &str) -> Option<SqlExpr> {
+        let style = [
+            ("YYYY-MM-DD", 23),
+            ("MM/DD/YYYY", 101),
+            ("DD/MM/YYYY", 103),
+            ("DD.MM.YYYY", 104),
+            ("YYYYMMDD", 112),
+        ]
+        .into_iter()
+        .find(|(f, _)| f.eq_ignore_ascii_case(format))?
+        .1;
+        Some(SqlExpr::atom(format!("CONVERT(date, {a}, {style})")))
+    }
+
+    fn to_timestamp(&self, a: SqlExpr, format: &str) -> Option<SqlExpr> {
+        let style = [
+            ("YYYY-MM-DD HH24:MI:SS", 120),
+            (r#"YYYY-MM-DD"T"HH24:MI:SS"#, 126),
+        ]
+        .into_iter()
+        .find(|(f, _)| f.eq_ignore_ascii_case(format))?
+        .1;
+        Some(SqlExpr::atom(format!("CONVERT(datetime2, {a}, {style})")))
+    }
+
+    /// `FORMAT` takes .NET format strings, which don't correspond to Postgres patterns
+    fn to_char(&self, _a: SqlExpr, _format: &str) -> Option<SqlExpr> {
+        None
+    }
+
+    /// DATEDIFF counts the boundaries of the unit crossed between the two values, so the result is
+    /// always a whole number
+    fn date_diff(&self, a: SqlExpr, b: SqlExpr, unit: DateDiffUnit) -> SqlExpr {
+        datediff(unit.name(), b, a)
+    }
+
+    /// DATEDIFF_BIG avoids overflowing the range of `int`, but the seconds are whole
+    fn epoch(&self, a: SqlExpr) -> SqlExpr {
+        SqlExpr::atom(format!("DATEDIFF_BIG(second, '1970-01-01', {a})"))
+    }
+
+    fn type_name(&self, data_type: &DataType) -> Option<String> {
+        let name = match data_type {
+            DataType::Integer => "bigint",
+            DataType::Text => "nvarchar(max)",
+            DataType::Date => "date",
+            DataType::Binary => "varbinary(max)",
+            DataType::Uuid => "uniqueidentifier",
+            // DECIMAL without a precision would round away every fractional digit, and the others
+            // have no SQL Server equivalent which is certain to hold them
+            _ => return None,
+        };
+        Some(name.to_owned())
+    }
+
+    fn collate(&self, a: SqlExpr, collation: &str) -> SqlExpr {
+        // SQL Server doesn't accept a delimited collation name, but names like
+        // `Latin1_General_CI_AS` don't need one. Anything else is still quoted so that it can't
+        // inject SQL, leaving the database to reject it.
+        let is_plain = collation
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        let collation = if is_plain {
+            collation.to_owned()
+        } else {
+            self.quote_identifier(collation)
+        };
+        let a = a.for_precedence(SqlExprPrecedence::Atom);
+        SqlExpr::atom(format!("{a} COLLATE {collation}"))
+    }
+
+    fn round(&self, a: SqlExpr, places: Option<SqlExpr>) -> SqlExpr {
+        // SQL Server requires the number of places
+        let places = places.unwrap_or_else(|| SqlExpr::atom("0".to_owned()));
+        SqlExpr::atom(format!("ROUND({a}, {places})"))
+    }
+
+    fn trunc(&self, a: SqlExpr, places: Option<SqlExpr>) -> SqlExpr {
+        // A non-zero third argument makes ROUND truncate instead
+        let places = places.unwrap_or_else(|| SqlExpr::atom("0".to_owned()));
+        SqlExpr::atom(format!("ROUND({a}, {places}, 1)"))
+    }
+
+    fn sort_entry(
+        &self,
+        expr: &SqlExpr,
+        direction: &SortDirection,
+        nulls_sort: &NullsSort,
+    ) -> String {
+        // SQL Server doesn't support `NULLS FIRST` or `NULLS LAST`. It always sorts nulls as the
+        // lowest values, so when that's not what we want we sort on a null check first.
+        match (direction, nulls_sort) {
+            (SortDirection::Asc, NullsSort::First) => format!("{expr} ASC"),
+            (SortDirection::Desc, NullsSort::Last) => format!("{expr} DESC"),
+            (SortDirection::Asc, NullsSort::Last) => {
+                format!("CASE WHEN {expr} IS NULL THEN 1 ELSE 0 END, {expr} ASC")
+            }
+            (SortDirection::Desc, NullsSort::First) => {
+                format!("CASE WHEN {expr} IS NULL THEN 0 ELSE 1 END, {expr} DESC")
+            }
+        }
+    }
+
+    fn approx_count_distinct(&self) -> Option<fn(SqlExpr) -> SqlExpr> {
+        Some(approx_count_distinct)
+    }
+
+    fn supports_aggregate_filter(&self) -> bool {
+        false
+    }
+
+    fn sort_by_alias(&self) -> bool {
+        // SQL Server allows a bare alias in ORDER BY, but not within an expression, and our
+        // `sort_entry` may wrap the sorting expression in a CASE.
+        false
+    }
+
+    fn sort_by_position(&self) -> bool {
+        // Within the CASE that `sort_entry` may add, a position would be a constant
+        false
+    }
+
+    fn pagination(&self, limit: Option<u64>, offset: Option<u64>) -> String {
+        if limit.is_none() && offset.is_none() {
+            return String::new();
+        }
+        let offset = format!("OFFSET {} ROWS", offset.unwrap_or(0));
+        match limit {
+            Some(limit) => format!("{offset}\nFETCH NEXT {limit} ROWS ONLY"),
+            None => offset,
+        }
+    }
+
+    fn pagination_requires_sorting(&self) -> bool {
+        true
+    }
+
+    /// SQL Server decides for itself whether to materialize each CTE
+    fn supports_cte_materialization(&self) -> bool {
+        false
+    }
+
+    /// SQL Server locks rows via table hints instead, which we don't yet support
+    fn supports_locking(&self) -> bool {
+        false
+    }
+
+    /// SQL Server only samples whole pages of rows
+    fn supports_table_sample(&self, method: SampleMethod) -> bool {
+        method == SampleMethod::System
+    }
+
+    fn table_sample(&self, sample: &TableSample) -> String {
+        format!("TABLESAMPLE SYSTEM ({} PERCENT)", sample.percentage)
+    }
+
+    fn supports_system_time(&self) -> bool {
+        true
+    }
+
+    /// A string literal converts implicitly to the `datetime2` of the period columns
+    fn timestamp(&self, timestamp: &str) -> String {
+        self.quote_string(timestamp)
+    }
+
+    fn create_view(&self, view: &View) -> String {
+        let name = view
+            .name
+            .split('.')
+            .map(|part| self.quote_identifier(part))
+            .join(".");
+        let or_alter = if view.or_replace { "OR ALTER " } else { "" };
+        format!("CREATE {or_alter}VIEW {name} AS")
+    }
+
+    fn supports_temporary_views(&self) -> bool {
+        false
+    }
+
+    /// There's no `IS TRUE`, so we compare to a bit instead, substituting a value for NULL when
+    /// NULL should pass the test
+    fn truth_test(&self, a: SqlExpr, value: bool, is_negated: bool) -> SqlExpr {
+        let bit = |value: bool| SqlExpr::atom(if value { "1" } else { "0" }.to_string());
+        if is_negated {
+            eq(coalesce(vec![a, bit(!value)]), bit(!value))
+        } else {
+            eq(a, bit(value))
+        }
+    }
+}
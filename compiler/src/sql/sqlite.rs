@@ -0,0 +1,155 @@
+use querydown_parser::ast::{Date, Duration};
+
+use super::{
+    dialect::{duration_in_days, hex, BitwiseOperator, DateDiffUnit, Dialect, RegExFlags},
+    expr::{
+        build::{
+            cmp::comparison,
+            date_time::julianday,
+            math::{bitwise_op, divide, multiply, subtract},
+        },
+        SqlExpr,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sqlite();
+
+impl Dialect for Sqlite {
+    fn quote_identifier(&self, ident: &str) -> String {
+        format!(r#""{}""#, ident.replace('"', r#""""#))
+    }
+
+    fn quote_string(&self, string: &str) -> String {
+        format!("'{}'", string.replace('\'', "''"))
+    }
+
+    fn placeholder(&self, index: usize, _name: &str) -> String {
+        format!("?{index}")
+    }
+
+    fn date(&self, date: &Date) -> String {
+        format!("DATE('{}')", date.to_iso())
+    }
+
+    fn bytes(&self, bytes: &[u8]) -> String {
+        format!("X'{}'", hex(bytes))
+    }
+
+    fn duration(&self, duration: &Duration) -> String {
+        // SQLite has no interval type. Dates and times are commonly compared as Julian day
+        // numbers, so we render durations as a fractional number of days.
+        duration_in_days(duration).to_string()
+    }
+
+    fn match_regex(
+        &self,
+        a: SqlExpr,
+        b: SqlExpr,
+        is_positive: bool,
+        flags: &RegExFlags,
+    ) -> SqlExpr {
+        // SQLite only provides the `REGEXP` syntax. The function behind it must be supplied by an
+        // extension, and the common ones accept an inline flag for case-insensitive matching.
+        let op = if is_positive { "REGEXP" } else { "NOT REGEXP" };
+        let pattern = if flags.is_case_sensitive {
+            b
+        } else {
+            SqlExpr::atom(format!("'(?i)' || {b}"))
+        };
+        comparison(a, op, pattern)
+    }
+
+    fn bitwise(&self, operator: BitwiseOperator, a: SqlExpr, b: SqlExpr) -> SqlExpr {
+        match operator {
+            // SQLite has no XOR operator, but the bits set in either operand and not in both are
+            // the difference between OR and AND
+            BitwiseOperator::Xor => {
+                subtract(bitwise_op(a.clone(), "|", b.clone()), bitwise_op(a, "&", b))
+            }
+            _ => bitwise_op(a, operator.symbol(), b),
+        }
+    }
+
+    /// SQLite has no format patterns. Its date functions only parse ISO 8601 text.
+    fn to_date(&self, a: SqlExpr, format: &str) -> Option<SqlExpr> {
+        format
+            .eq_ignore_ascii_case("YYYY-MM-DD")
+            .then(|| SqlExpr::atom(format!("date({a})")))
+    }
+
+    fn to_timestamp(&self, a: SqlExpr, format: &str) -> Option<SqlExpr> {
+        ["YYYY-MM-DD HH24:MI:SS", r#"YYYY-MM-DD"T"HH24:MI:SS"#]
+            .iter()
+            .any(|f| f.eq_ignore_ascii_case(format))
+            .then(|| SqlExpr::atom(format!("datetime({a})")))
+    }
+
+    fn to_char(&self, _a: SqlExpr, _format: &str) -> Option<SqlExpr> {
+        None
+    }
+
+    /// Julian day numbers are fractional, so their difference is a number of days
+    fn date_diff(&self, a: SqlExpr, b: SqlExpr, unit: DateDiffUnit) -> SqlExpr {
+        let days = subtract(julianday(a), julianday(b));
+        let seconds_per_day = DateDiffUnit::Day.seconds();
+        match unit {
+            DateDiffUnit::Day => days,
+            DateDiffUnit::Week => divide(days, SqlExpr::atom("7".to_string())),
+            _ => multiply(
+                days,
+                SqlExpr::atom((seconds_per_day / unit.seconds()).to_string()),
+            ),
+        }
+    }
+
+    /// The Julian day number of the Unix epoch is 2440587.5
+    fn epoch(&self, a: SqlExpr) -> SqlExpr {
+        let days = subtract(julianday(a), SqlExpr::atom("2440587.5".to_string()));
+        let seconds_per_day = DateDiffUnit::Day.seconds();
+        multiply(days, SqlExpr::atom(seconds_per_day.to_string()))
+    }
+
+    fn supports_full_outer_joins(&self) -> bool {
+        false
+    }
+
+    fn supports_values_column_list(&self) -> bool {
+        false
+    }
+
+    fn supports_grouping_extensions(&self) -> bool {
+        false
+    }
+
+    fn supports_locking(&self) -> bool {
+        false
+    }
+
+    fn supports_replacing_views(&self) -> bool {
+        false
+    }
+
+    /// There's no `OR REPLACE`, so any view of the same name is dropped first
+    fn create_temporary_view(&self, name: &str) -> String {
+        let name = self.quote_identifier(name);
+        format!("DROP VIEW IF EXISTS {name};\nCREATE TEMPORARY VIEW {name} AS")
+    }
+
+    fn supports_trunc_places(&self) -> bool {
+        false
+    }
+
+    fn pagination(&self, limit: Option<u64>, offset: Option<u64>) -> String {
+        // SQLite only accepts an OFFSET clause after a LIMIT clause. A negative limit means there
+        // is no limit.
+        match (limit, offset) {
+            (None, None) => String::new(),
+            (Some(limit), None) => format!("LIMIT {limit}"),
+            (limit, Some(offset)) => {
+                let limit = limit.map(|l| l as i64).unwrap_or(-1);
+                format!("LIMIT {limit}\nOFFSET {offset}")
+            }
+        }
+    }
+}
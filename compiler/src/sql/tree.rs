@@ -1,19 +1,85 @@
+use std::fmt::Display;
+
 use querydown_parser::ast::{NullsSort, SortDirection};
+use serde::Serialize;
+
+use crate::options::{Lock, TableSample};
 
 pub use super::expr::{SqlExpr, SqlExprPrecedence};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct Select {
-    pub base_table: String,
+    /// `None` renders a query without a FROM clause
+    pub base_table: Option<String>,
+    /// The database schema used to qualify the base table, if any
+    pub base_table_schema: Option<String>,
+    /// `None` when the base table is referenced by its name
+    pub base_table_alias: Option<String>,
+    /// A subquery which is selected from in place of the base table, under the base table alias
+    pub base_subquery: Option<Box<Select>>,
+    pub table_sample: Option<TableSample>,
+    /// The point in time at which to read the base table, when it's system-versioned
+    pub system_time_as_of: Option<SqlExpr>,
+    /// Rows are reduced to the first one for each distinct combination of these expressions
+    pub distinct_on: Vec<SqlExpr>,
     pub columns: Vec<Column>,
     pub ctes: Vec<Cte>,
     pub joins: Vec<Join>,
+    /// Arrays expanded into rows, after all the joins
+    pub unnests: Vec<Unnest>,
     pub conditions: SqlExpr,
     pub sorting: Vec<SortEntry>,
     pub grouping: Vec<SqlExpr>,
+    /// Combines the grouping expressions to produce subtotal rows
+    pub grouping_extension: Option<GroupingExtension>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+    pub lock: Option<Lock>,
 }
 
+/// A rewrite of the SQL tree which runs after compilation and before rendering, e.g. to apply an
+/// optimization which the compiler doesn't know about. The transform is responsible for keeping
+/// the tree consistent, e.g. by updating every reference to a CTE which it renames.
+pub trait SqlTreeTransform {
+    fn transform(&self, select: &mut Select) -> Result<(), String>;
+}
+
+/// A DELETE statement, which removes the rows of the base table that satisfy the conditions
 #[derive(Debug)]
+pub struct Delete {
+    pub base_table: String,
+    /// The database schema used to qualify the base table, if any
+    pub base_table_schema: Option<String>,
+    /// `None` when the base table is referenced by its name
+    pub base_table_alias: Option<String>,
+    /// Empty when every row is deleted
+    pub conditions: SqlExpr,
+}
+
+/// An UPDATE statement, which assigns new values to columns of the base table within the rows
+/// that satisfy the conditions
+#[derive(Debug)]
+pub struct Update {
+    pub base_table: String,
+    /// The database schema used to qualify the base table, if any
+    pub base_table_schema: Option<String>,
+    /// `None` when the base table is referenced by its name
+    pub base_table_alias: Option<String>,
+    /// Each column name along with the value to assign to it
+    pub assignments: Vec<(String, SqlExpr)>,
+    /// Empty when every row is updated
+    pub conditions: SqlExpr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupingExtension {
+    Rollup,
+    Cube,
+    /// Each set holds indexes into the grouping expressions. An empty set yields a grand total.
+    Sets(Vec<Vec<usize>>),
+}
+
+#[derive(Debug, Clone)]
 pub struct Column {
     pub expr: SqlExpr,
     pub alias: Option<String>,
@@ -25,7 +91,7 @@ impl Column {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Cte {
     pub alias: String,
     pub select: Select,
@@ -34,13 +100,19 @@ pub struct Cte {
     /// table name because we already have that from the JoinTree. This column name is usually the
     /// primary key of that table.
     pub join_column_name: String,
+    /// Explains which Querydown construct produced this CTE, for use in an SQL comment
+    pub annotation: String,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
 pub enum CtePurpose {
     /// A CTE that is used to filter the base table on the presence of related records. It will be
     /// joined via an inner join to accomplish the filtering.
     Inclusion,
+    /// A CTE that is used to filter the base table on the presence of related records when that
+    /// filter is only one alternative, e.g. within an OR condition set. Will be joined via a left
+    /// outer join, and a WHERE clause will be added to test for a related record.
+    ConditionalInclusion,
     /// A CTE that is used to filter the base table on the absence of related records. Will be
     /// joined via a left outer join, and a WHERE clause will be added to filter out rows that
     /// have a related record.
@@ -50,21 +122,47 @@ pub enum CtePurpose {
     AggregateValue,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Join {
     pub table: String,
+    /// The database schema used to qualify the table, if any. CTEs never have one.
+    pub table_schema: Option<String>,
+    /// True when `table` is the alias of a CTE rather than the name of a table in the schema. It's
+    /// then referenced by name alone, even when a table of the same name exists.
+    pub is_cte: bool,
     pub alias: String,
     pub conditions: SqlExpr,
     pub join_type: JoinType,
+    /// Explains which Querydown construct produced this join, for use in an SQL comment
+    pub annotation: String,
+    /// A subquery to join via LATERAL in place of the table. It may refer to the tables joined
+    /// before it.
+    pub lateral_subquery: Option<Box<Select>>,
+    /// A subquery to join in place of the table, which can't refer to any other table
+    pub subquery: Option<Box<Select>>,
 }
 
-#[derive(Debug)]
+/// An array expanded into one row per element, e.g.
+/// `CROSS JOIN LATERAL unnest("posts"."tags") AS "unnest"("value")`
+#[derive(Debug, Clone)]
+pub struct Unnest {
+    pub array: SqlExpr,
+    pub alias: String,
+    /// Also number the elements, starting from 1
+    pub with_ordinality: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum JoinType {
     Inner,
     LeftOuter,
+    /// Keeps unmatched rows from both sides
+    FullOuter,
+    /// Joins every row to every row of the other table. Has no conditions.
+    Cross,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SortEntry {
     pub expr: SqlExpr,
     pub direction: SortDirection,
@@ -74,13 +172,162 @@ pub struct SortEntry {
 impl From<String> for Select {
     fn from(base_table: String) -> Self {
         Self {
-            base_table,
+            base_table: Some(base_table),
+            base_table_schema: None,
+            base_table_alias: None,
+            base_subquery: None,
+            table_sample: None,
+            system_time_as_of: None,
+            distinct_on: vec![],
             columns: vec![],
             ctes: vec![],
             joins: vec![],
+            unnests: vec![],
             conditions: SqlExpr::default(),
             sorting: vec![],
             grouping: vec![],
+            grouping_extension: None,
+            limit: None,
+            offset: None,
+            lock: None,
+        }
+    }
+}
+
+impl Select {
+    /// An outline of the tree for debugging, which shows how the compiler structured the query
+    /// before rendering it: the CTEs with their purposes and join columns, the joins with their
+    /// aliases, and each clause. Expressions appear as the SQL they render.
+    pub fn pretty_debug(&self) -> String {
+        let mut outline = Outline::default();
+        outline.select(self, 0);
+        outline.lines.join("\n")
+    }
+}
+
+/// The lines of `Select::pretty_debug`, each indented by two spaces per level of nesting
+#[derive(Default)]
+struct Outline {
+    lines: Vec<String>,
+}
+
+impl Outline {
+    fn line(&mut self, depth: usize, text: impl Display) {
+        self.lines.push(format!("{}{text}", "  ".repeat(depth)));
+    }
+
+    /// A heading followed by one line for each item, or nothing when there are no items
+    fn list<T: Display>(
+        &mut self,
+        depth: usize,
+        heading: &str,
+        items: impl IntoIterator<Item = T>,
+    ) {
+        let mut items = items.into_iter().peekable();
+        if items.peek().is_none() {
+            return;
+        }
+        self.line(depth, format!("{heading}:"));
+        for item in items {
+            self.line(depth + 1, item);
+        }
+    }
+
+    fn select(&mut self, select: &Select, depth: usize) {
+        self.line(depth, "Select");
+        let depth = depth + 1;
+        if let Some(base_table) = &select.base_table {
+            let schema = select.base_table_schema.iter().map(|s| format!("{s}."));
+            let alias = select.base_table_alias.iter().map(|a| format!(" AS {a}"));
+            let schema = schema.collect::<String>();
+            let alias = alias.collect::<String>();
+            self.line(depth, format!("base table: {schema}{base_table}{alias}"));
+        }
+        if let Some(base_subquery) = &select.base_subquery {
+            let alias = select.base_table_alias.as_deref().unwrap_or_default();
+            self.line(depth, format!("base subquery AS {alias}:"));
+            self.select(base_subquery, depth + 1);
+        }
+        if let Some(sample) = &select.table_sample {
+            self.line(depth, format!("table sample: {sample:?}"));
+        }
+        if let Some(point) = &select.system_time_as_of {
+            self.line(depth, format!("system time as of: {point}"));
+        }
+        self.list(depth, "distinct on", &select.distinct_on);
+        let columns = select.columns.iter().map(|column| match &column.alias {
+            Some(alias) => format!("{} AS {alias}", column.expr),
+            None => column.expr.to_string(),
+        });
+        self.list(depth, "columns", columns);
+        if !select.ctes.is_empty() {
+            self.line(depth, "ctes:");
+        }
+        for cte in &select.ctes {
+            self.line(depth + 1, format!("{} ({:?})", cte.alias, cte.purpose));
+            self.line(depth + 2, format!("join column: {}", cte.join_column_name));
+            self.line(depth + 2, format!("annotation: {}", cte.annotation));
+            self.select(&cte.select, depth + 2);
+        }
+        if !select.joins.is_empty() {
+            self.line(depth, "joins:");
+        }
+        for join in &select.joins {
+            let source = if join.lateral_subquery.is_some() {
+                "lateral subquery".to_owned()
+            } else if join.subquery.is_some() {
+                "subquery".to_owned()
+            } else if join.is_cte {
+                format!("cte {}", join.table)
+            } else {
+                let schema = join.table_schema.iter().map(|s| format!("{s}."));
+                format!("{}{}", schema.collect::<String>(), join.table)
+            };
+            self.line(
+                depth + 1,
+                format!("{:?} {source} AS {}", join.join_type, join.alias),
+            );
+            if !join.conditions.is_empty() {
+                self.line(depth + 2, format!("on: {}", join.conditions));
+            }
+            self.line(depth + 2, format!("annotation: {}", join.annotation));
+            for subquery in join.subquery.iter().chain(&join.lateral_subquery) {
+                self.select(subquery, depth + 2);
+            }
+        }
+        let unnests = select.unnests.iter().map(|unnest| {
+            let ordinality = if unnest.with_ordinality {
+                " WITH ORDINALITY"
+            } else {
+                ""
+            };
+            format!("{}{ordinality} AS {}", unnest.array, unnest.alias)
+        });
+        self.list(depth, "unnests", unnests);
+        if !select.conditions.is_empty() {
+            self.line(depth, format!("conditions: {}", select.conditions));
+        }
+        self.list(depth, "grouping", &select.grouping);
+        if let Some(extension) = &select.grouping_extension {
+            self.line(depth, format!("grouping extension: {extension:?}"));
+        }
+        let sorting = select.sorting.iter().map(|entry| {
+            let SortEntry {
+                expr,
+                direction,
+                nulls_sort,
+            } = entry;
+            format!("{expr} {direction:?} (nulls {nulls_sort:?})")
+        });
+        self.list(depth, "sorting", sorting);
+        if let Some(limit) = select.limit {
+            self.line(depth, format!("limit: {limit}"));
+        }
+        if let Some(offset) = select.offset {
+            self.line(depth, format!("offset: {offset}"));
+        }
+        if let Some(lock) = &select.lock {
+            self.line(depth, format!("lock: {lock:?}"));
         }
     }
 }
@@ -0,0 +1,38 @@
+#[test]
+fn test_alias_collisions() {
+    use std::collections::HashSet;
+
+    use crate::compiler::scope::Scope;
+    use crate::options::Options;
+    use crate::schema::{primitive_schema::PrimitiveSchema, Schema};
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let primitive_schema = serde_json::from_str::<PrimitiveSchema>(&schema_json).unwrap();
+    let schema = Schema::try_from(primitive_schema).unwrap();
+    let options = Options::default();
+    let mut scope = Scope::build(&options, &schema, Some("issues")).unwrap();
+
+    let aliases = (0..1000)
+        .map(|_| scope.get_alias("users"))
+        .collect::<Vec<_>>();
+    assert_eq!(aliases[0], "users");
+    assert_eq!(aliases[9], "users_9");
+    assert_eq!(aliases[10].len(), "users_".len() + 8);
+    assert_eq!(aliases.iter().collect::<HashSet<_>>().len(), 1000);
+
+    let long_alias = "a".repeat(100);
+    let get_long_aliases = || {
+        let mut scope = Scope::build(&options, &schema, Some("issues")).unwrap();
+        (0..1000)
+            .map(|_| scope.get_alias(&long_alias))
+            .collect::<Vec<_>>()
+    };
+    let long_aliases = get_long_aliases();
+    assert!(long_aliases.iter().all(|alias| alias.len() <= 63));
+    assert_eq!(long_aliases.iter().collect::<HashSet<_>>().len(), 1000);
+
+    // Aliases are deterministic
+    assert_eq!(long_aliases, get_long_aliases());
+}
@@ -0,0 +1,27 @@
+#[test]
+fn test_alias_prefix() {
+    use crate::options::Options;
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let compile = |prefix: &str| {
+        let options = Options {
+            alias_prefix: prefix.to_owned(),
+            ..Options::default()
+        };
+        let compiler = Compiler::new(&schema_json, options).unwrap();
+        let input = "#issues author.username:\"alice\" ++#comments $id";
+        compiler.compile_query(input.to_owned()).unwrap()
+    };
+
+    let a = compile("a_");
+    let b = compile("b_");
+    assert!(a.aliases().contains("a_users"));
+    assert!(a.aliases().contains("a_cte0"));
+    // Only the base table is shared
+    let shared = a.aliases().intersection(b.aliases()).collect::<Vec<_>>();
+    assert_eq!(shared, ["issues"]);
+    assert!(a.sql.contains("\"a_users\".\"username\""));
+}
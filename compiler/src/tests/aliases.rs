@@ -0,0 +1,38 @@
+#[test]
+fn test_aliases() {
+    use std::collections::HashSet;
+
+    use crate::options::Options;
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    let aliases = |input: &str| {
+        let compiled_query = compiler.compile_query(input.to_owned()).unwrap();
+        let mut aliases = compiled_query.aliases().iter().cloned().collect::<Vec<_>>();
+        aliases.sort();
+        aliases
+    };
+
+    assert_eq!(
+        aliases("#issues $author.username $project.name $#comments"),
+        ["cte0", "issues", "projects", "users"]
+    );
+    assert_eq!(aliases("#issues $id"), ["issues"]);
+    assert_eq!(aliases("$1"), Vec::<String>::new());
+
+    let options = Options {
+        base_table_alias: Some("i".to_owned()),
+        ..Options::default()
+    };
+    let compiler = Compiler::new(&schema_json, options).unwrap();
+    let compiled_query = compiler
+        .compile_query("#issues $author.username".to_owned())
+        .unwrap();
+    assert_eq!(
+        compiled_query.aliases(),
+        &HashSet::from(["i".to_owned(), "users".to_owned()])
+    );
+}
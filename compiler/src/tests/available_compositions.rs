@@ -0,0 +1,53 @@
+#[test]
+fn test_available_compositions() {
+    use crate::ast::FunctionDimension;
+    use crate::options::Options;
+    use crate::schema::DataType;
+    use crate::{Arity, Composition, ReturnType};
+
+    let mut options = Options::default();
+    options.add_sql_function("similarity", 2, DataType::Numeric);
+    options.custom_functions.insert(
+        "upper".to_owned(),
+        Composition::sql_function("UCASE", Arity::Exactly(1), DataType::Text),
+    );
+    let signatures = options.available_compositions();
+    let find = |name: &str, dimension: FunctionDimension| {
+        signatures
+            .iter()
+            .find(|s| s.name == name && s.dimension == dimension)
+    };
+
+    let round = find("round", FunctionDimension::Scalar).unwrap();
+    assert_eq!(round.arity, Arity::Between(1, 2));
+    assert_eq!(round.return_type, ReturnType::FirstArg);
+    assert!(!round.is_custom);
+    let count = find("count", FunctionDimension::Aggregate).unwrap();
+    assert_eq!(count.return_type, ReturnType::Fixed(DataType::Integer));
+
+    let similarity = find("similarity", FunctionDimension::Scalar).unwrap();
+    assert_eq!(similarity.arity, Arity::Exactly(2));
+    assert_eq!(similarity.return_type, ReturnType::Fixed(DataType::Numeric));
+    assert!(similarity.is_custom);
+
+    // A custom function replaces the standard function of the same name
+    let upper = signatures
+        .iter()
+        .filter(|s| s.name == "upper")
+        .collect::<Vec<_>>();
+    assert_eq!(upper.len(), 1);
+    assert!(upper[0].is_custom);
+
+    // Scalar functions come first, and each dimension is sorted by name
+    let dimensions = signatures.iter().map(|s| &s.dimension).collect::<Vec<_>>();
+    let first_aggregate = dimensions
+        .iter()
+        .position(|d| **d == FunctionDimension::Aggregate)
+        .unwrap();
+    assert!(dimensions[first_aggregate..]
+        .iter()
+        .all(|d| **d == FunctionDimension::Aggregate));
+    let (scalar, aggregate) = signatures.split_at(first_aggregate);
+    assert!(scalar.windows(2).all(|w| w[0].name < w[1].name));
+    assert!(aggregate.windows(2).all(|w| w[0].name < w[1].name));
+}
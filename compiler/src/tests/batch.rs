@@ -0,0 +1,99 @@
+#[test]
+fn test_compile_batch() {
+    use crate::options::{Options, View};
+    use crate::{Compiler, SqlServer, Sqlite};
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    let batch = compiler
+        .compile_batch(&[
+            "#issues $title $#comments.created_at%max",
+            "#issues #comments.created_at%max:@2024-01-01 $id",
+            "#issues $id $#labels.name%list_distinct",
+        ])
+        .unwrap();
+    assert_eq!(
+        batch.prelude,
+        [
+            "CREATE OR REPLACE TEMPORARY VIEW \"cte1\" AS",
+            "SELECT",
+            "  \"comments\".\"issue\" AS \"pk\",",
+            "  max(\"comments\".\"created_at\") AS \"v1\"",
+            "FROM \"comments\"",
+            "GROUP BY \"comments\".\"issue\";",
+        ]
+        .join("\n")
+    );
+    assert_eq!(
+        batch.queries[0].sql,
+        [
+            "SELECT",
+            "  \"issues\".\"title\",",
+            "  \"cte1\".\"v1\"",
+            "FROM \"issues\"",
+            "LEFT JOIN \"cte1\" ON",
+            "  \"issues\".\"id\" = \"cte1\".\"pk\";",
+        ]
+        .join("\n")
+    );
+    assert!(batch.queries[1]
+        .sql
+        .contains("\"cte1\".\"v1\" = DATE '2024-01-01'"));
+    assert!(batch.queries[0].ctes.is_empty());
+    // The CTE which only one query needs stays within that query, and its alias doesn't collide
+    // with the name of the shared view
+    assert!(batch.queries[2].sql.starts_with("WITH\n  \"cte0\" AS ("));
+    assert_eq!(batch.queries[2].ctes.len(), 1);
+    assert!(batch
+        .sql()
+        .starts_with(&format!("{}\n\nSELECT", batch.prelude)));
+
+    let batch = compiler
+        .compile_batch(&["#issues $id", "#issues $title"])
+        .unwrap();
+    assert_eq!(batch.prelude, "");
+    assert_eq!(
+        batch.sql(),
+        "SELECT\n  \"issues\".\"id\"\nFROM \"issues\";\n\nSELECT\n  \"issues\".\"title\"\nFROM \"issues\";"
+    );
+
+    let shared = ["#issues $#comments.id%max", "#issues $id $#comments.id%max"];
+    // A view left from an earlier batch within the session is replaced
+    let sqlite = Options {
+        dialect: Box::new(Sqlite()),
+        ..Default::default()
+    };
+    let compiler = Compiler::new(&schema_json, sqlite).unwrap();
+    let prelude = compiler.compile_batch(&shared).unwrap().prelude;
+    assert!(
+        prelude.starts_with("DROP VIEW IF EXISTS \"cte1\";\nCREATE TEMPORARY VIEW \"cte1\" AS\n")
+    );
+    let sql_server = Options {
+        dialect: Box::new(SqlServer()),
+        ..Default::default()
+    };
+    let compiler = Compiler::new(&schema_json, sql_server).unwrap();
+    assert_eq!(
+        compiler.compile_batch(&shared).map(|batch| batch.prelude),
+        Err(
+            "This SQL dialect does not support the temporary views needed to share CTEs between \
+            queries."
+                .to_owned()
+        )
+    );
+    let with_view = Options {
+        view: Some(View {
+            name: "issue_ids".to_owned(),
+            or_replace: false,
+            materialized: false,
+        }),
+        ..Default::default()
+    };
+    let compiler = Compiler::new(&schema_json, with_view).unwrap();
+    assert_eq!(
+        compiler.compile_batch(&shared).map(|batch| batch.prelude),
+        Err("A batch of queries can't be compiled into a view.".to_owned())
+    );
+}
@@ -0,0 +1,52 @@
+#[test]
+fn test_boolean_expression() {
+    use crate::options::{BooleanRendering, Options};
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("shop_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    assert_eq!(
+        compiler.compile_boolean_expression("orders", "amount:>=0"),
+        Ok("\"orders\".\"amount\" >= 0".to_owned())
+    );
+    assert_eq!(
+        compiler.compile_boolean_expression("customers", r#"credit:>=0 name:..["Ann" "Bo"]"#),
+        Ok(concat!(
+            "\"customers\".\"credit\" >= 0 AND\n",
+            "(\"customers\".\"name\" = 'Ann' OR \"customers\".\"name\" = 'Bo')",
+        )
+        .to_owned())
+    );
+    assert_eq!(
+        compiler.compile_boolean_expression("orders", ""),
+        Ok("TRUE".to_owned())
+    );
+    assert_eq!(
+        compiler.compile_boolean_expression("customers", "++#orders"),
+        Ok(concat!(
+            "EXISTS (\n",
+            "  SELECT\n",
+            "    1\n",
+            "  FROM \"orders\"\n",
+            "  WHERE\n",
+            "    \"orders\".\"customer\" = \"customers\".\"id\"\n",
+            ")",
+        )
+        .to_owned())
+    );
+    assert!(compiler
+        .compile_boolean_expression("orders", r#"customer.name:"Ann""#)
+        .is_err());
+
+    let options = Options {
+        boolean_rendering: BooleanRendering::Integer,
+        ..Options::default()
+    };
+    let compiler = Compiler::new(&schema_json, options).unwrap();
+    assert_eq!(
+        compiler.compile_boolean_expression("orders", ""),
+        Ok("1 = 1".to_owned())
+    );
+}
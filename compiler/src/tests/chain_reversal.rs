@@ -0,0 +1,104 @@
+#[test]
+fn test_chain_reversal() {
+    use crate::schema::chain::{Chain, ChainIntersecting};
+    use crate::schema::links::{JoinQuantity, Link, LinkToOne};
+    use crate::schema::primitive_schema::PrimitiveSchema;
+    use crate::schema::Schema;
+
+    let schema_json = r#"{
+        "tables": [
+            {
+                "name": "customers",
+                "columns": [{ "name": "id", "type": "integer" }]
+            },
+            {
+                "name": "orders",
+                "columns": [
+                    { "name": "id", "type": "integer" },
+                    { "name": "customer", "type": "integer" }
+                ]
+            }
+        ],
+        "links": [
+            {
+                "from": { "table": "orders", "column": "customer" },
+                "to": { "table": "customers", "column": "id" },
+                "unique": false
+            }
+        ]
+    }"#;
+    let primitive_schema = serde_json::from_str::<PrimitiveSchema>(schema_json).unwrap();
+    let schema = Schema::try_from(primitive_schema).unwrap();
+    let table_id = |name: &str| *schema.table_lookup.get(name).unwrap();
+    let orders = schema.tables.get(&table_id("orders")).unwrap();
+    let customer = *orders.forward_links_to_one.values().next().unwrap();
+
+    let chain = Chain::try_new(
+        LinkToOne::ForwardLinkToOne(customer),
+        ChainIntersecting::Disallowed,
+    )
+    .unwrap();
+    let reversed = chain.try_reverse(&schema).unwrap();
+    assert_eq!(reversed.len(), 1);
+    assert_eq!(reversed.get_starting_table_id(), table_id("customers"));
+    assert_eq!(reversed.get_ending_table_id(), table_id("orders"));
+    let link = reversed.get_first_link();
+    assert_eq!(link.get_join_quantity(), JoinQuantity::Many);
+    assert_eq!(link.get_base(), customer.base);
+    assert_eq!(link.get_target(), customer.target);
+
+    let mut without_reverse =
+        Schema::try_from(serde_json::from_str::<PrimitiveSchema>(schema_json).unwrap()).unwrap();
+    without_reverse
+        .tables
+        .get_mut(&table_id("customers"))
+        .unwrap()
+        .reverse_links_to_many
+        .clear();
+    assert_eq!(
+        chain.try_reverse(&without_reverse).unwrap_err(),
+        "No relationship found from table `customers` to table `orders`."
+    );
+}
+
+#[test]
+fn test_reverse_path() {
+    use crate::options::Options;
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("shop_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    let reverse = |table_name: &str, path: &str| compiler.reverse_path(table_name, path);
+
+    assert_eq!(
+        reverse("orders", "customer"),
+        Ok("#orders(customer)".to_owned())
+    );
+    assert_eq!(
+        reverse("orders", "shipping_address.country"),
+        Ok("#addresses(country).#orders(shipping_address)".to_owned())
+    );
+    // The reversed path can be used within a query from the table where the path ends
+    assert!(compiler
+        .compile("#countries $#addresses(country).#orders(shipping_address).id%count".to_owned())
+        .is_ok());
+    assert_eq!(
+        reverse("loyalty_cards", "customer"),
+        Ok(">>loyalty_cards".to_owned())
+    );
+
+    assert_eq!(
+        reverse("orders", "amount"),
+        Err("Column `amount` of table `orders` is not a foreign key.".to_owned())
+    );
+    assert_eq!(
+        reverse("orders", "nope"),
+        Err("Column `nope` not found within table `orders`.".to_owned())
+    );
+    assert_eq!(
+        reverse("tickets", "customer"),
+        Err("Table `tickets` does not exist.".to_owned())
+    );
+}
@@ -0,0 +1,203 @@
+#[test]
+fn test_result_column_types() {
+    use crate::options::{IdentifierResolution, Options};
+    use crate::{Compiler, DataType, Postgres};
+
+    use super::get_test_resource;
+
+    let options = Options {
+        dialect: Box::new(Postgres()),
+        identifier_resolver: Box::new(IdentifierResolution::Flexible),
+        ..Default::default()
+    };
+    let compiler = |schema_file_name: &str, options: Options| {
+        Compiler::new(&get_test_resource(schema_file_name), options).unwrap()
+    };
+    let library = compiler("library_schema.json", options);
+    let shop = compiler("shop_schema.json", Options::default());
+    let issues = compiler("issue_schema.json", Options::default());
+    let column_types = |compiler: &Compiler, input: &str| {
+        let columns = compiler.compile_query(input.to_owned()).unwrap().columns;
+        columns
+            .into_iter()
+            .map(|c| (c.name, c.data_type))
+            .collect::<Vec<_>>()
+    };
+    let compile = |input: &str| column_types(&library, input);
+    let compile_shop = |input: &str| column_types(&shop, input);
+    let compile_issues = |input: &str| column_types(&issues, input);
+
+    assert_eq!(
+        compile("#publishers $name $#books.page_count%sum->total"),
+        vec![
+            ("Name".to_owned(), DataType::Text),
+            ("total".to_owned(), DataType::Numeric),
+        ]
+    );
+    assert_eq!(
+        compile("#books $title $#items $publication_year+1"),
+        vec![
+            ("Title".to_owned(), DataType::Text),
+            ("count".to_owned(), DataType::Integer),
+            ("?column?".to_owned(), DataType::Integer),
+        ]
+    );
+    assert_eq!(
+        compile("#items $*(acquisition_price->price) $book.publisher.name"),
+        vec![
+            ("id".to_owned(), DataType::Integer),
+            ("Barcode".to_owned(), DataType::Text),
+            ("Acquisition Date".to_owned(), DataType::Date),
+            ("price".to_owned(), DataType::Numeric),
+            ("Book".to_owned(), DataType::Integer),
+            ("Name".to_owned(), DataType::Text),
+        ]
+    );
+    assert_eq!(
+        compile_shop("#products $id $checksum"),
+        vec![
+            ("id".to_owned(), DataType::Integer),
+            ("checksum".to_owned(), DataType::Binary),
+        ]
+    );
+    assert_eq!(
+        compile_shop(r#"#customers $data|json_extract("address" "zip")|to_integer"#),
+        vec![("to_integer".to_owned(), DataType::Integer)]
+    );
+    let text_array = DataType::Array(Box::new(DataType::Text));
+    assert_eq!(
+        compile_shop("#products $tags|slice(1 3) $tags|array_length $tags|trim_array(2)"),
+        vec![
+            ("slice".to_owned(), text_array.clone()),
+            ("array_length".to_owned(), DataType::Integer),
+            ("trim_array".to_owned(), text_array),
+        ]
+    );
+    assert_eq!(
+        compile_issues("#issues $created_at|bin(@t15m)->quarter_hour"),
+        vec![("quarter_hour".to_owned(), DataType::Timestamp)]
+    );
+    assert_eq!(
+        compile_shop("#customers $external_id"),
+        vec![("external_id".to_owned(), DataType::Uuid)]
+    );
+    assert_eq!(
+        compile_issues(r"#issues $status \g $author%approx_count_distinct->authors"),
+        vec![
+            ("status".to_owned(), DataType::Text),
+            ("authors".to_owned(), DataType::Integer),
+        ]
+    );
+    assert_eq!(
+        compile_shop("#products $tags|unnest->tag $tags|unnest_index->position"),
+        vec![
+            ("tag".to_owned(), DataType::Text),
+            ("position".to_owned(), DataType::Integer),
+        ]
+    );
+    assert_eq!(
+        compile_shop("#products $price|width_bucket(0 100 10)->bucket"),
+        vec![("bucket".to_owned(), DataType::Integer)]
+    );
+    assert_eq!(
+        compile_shop(
+            r#"#imports $raw_date|to_date("YYYY-MM-DD")->day $raw_date|to_date("YYYY-MM-DD")|age|days->age $raw_time|to_timestamp("DD Mon YYYY HH12:MI")->time"#
+        ),
+        vec![
+            ("day".to_owned(), DataType::Date),
+            ("age".to_owned(), DataType::Numeric),
+            ("time".to_owned(), DataType::Timestamp),
+        ]
+    );
+    assert_eq!(
+        compile_shop("#orders $amount|clamp(0 100)->amount"),
+        vec![("amount".to_owned(), DataType::Numeric)]
+    );
+    assert_eq!(
+        compile("#Patrons $#Checkouts.Item.Acquisition_Price%sum|round(2)|to_text->spent"),
+        vec![("spent".to_owned(), DataType::Text)]
+    );
+    assert_eq!(
+        compile_shop(
+            r#"#orders $shipped_at|at_time_zone("UTC")->utc $placed_at|at_time_zone("Europe/Paris")->paris"#
+        ),
+        vec![
+            ("utc".to_owned(), DataType::Timestamp),
+            ("paris".to_owned(), DataType::TimestampTz),
+        ]
+    );
+    assert_eq!(
+        compile_issues(concat!(
+            r#"#issues $status|map("o" "Open" "c" "Closed")->label $status|map("o" 1 "c" 2.5 0)->n "#,
+            r#"$status|map("o" 1 "c" @null)->i $status|map("o" 1 "c" "Closed")->mixed"#,
+        )),
+        vec![
+            ("label".to_owned(), DataType::Text),
+            ("n".to_owned(), DataType::Numeric),
+            ("i".to_owned(), DataType::Integer),
+            ("mixed".to_owned(), DataType::Unknown),
+        ]
+    );
+    assert_eq!(
+        compile_shop(r#"#contacts $phone|regexp_replace("[^0-9]" "" "g")->digits"#),
+        vec![("digits".to_owned(), DataType::Text)]
+    );
+    assert_eq!(
+        compile_shop("#products $description|search(:q)->hit"),
+        vec![("hit".to_owned(), DataType::Boolean)]
+    );
+    assert_eq!(
+        compile_issues(r#"#issues $created_at|date_diff(@now "day")->age"#),
+        vec![("age".to_owned(), DataType::Numeric)]
+    );
+    assert_eq!(
+        compile_issues("#issues $created_at|epoch->created"),
+        vec![("created".to_owned(), DataType::Numeric)]
+    );
+    assert_eq!(
+        compile_issues(r#"#issues $project \g $(status:"paid")%count_if->paid"#),
+        vec![
+            ("project".to_owned(), DataType::Integer),
+            ("paid".to_owned(), DataType::Integer),
+        ]
+    );
+    assert_eq!(
+        compile_issues(r#"#issues $description|empty_to_null|else("n/a")->notes"#),
+        vec![("notes".to_owned(), DataType::Text)]
+    );
+    assert_eq!(
+        compile_issues(r"#issues $%%(project\p)%share(id)->share"),
+        vec![("share".to_owned(), DataType::Numeric)]
+    );
+    assert_eq!(
+        compile_issues("#issues $created_at|round_time(@t15m)->quarter_hour"),
+        vec![("quarter_hour".to_owned(), DataType::Timestamp)]
+    );
+    assert_eq!(
+        compile_issues("#users $email|sha256->hash"),
+        vec![("hash".to_owned(), DataType::Text)]
+    );
+    // The type of a bucket comes from its labels
+    assert_eq!(
+        compile_issues(r#"#issues $id|bucket([0 10] ["few" "some" "many"])->size"#),
+        vec![("size".to_owned(), DataType::Text)]
+    );
+    assert_eq!(
+        compile_issues("#issues $id|bucket([1.5] [1 @null])->size"),
+        vec![("size".to_owned(), DataType::Integer)]
+    );
+
+    // A display format pattern converts the values to text
+    let options = Options {
+        display_formats: true,
+        ..Options::default()
+    };
+    let formatted_shop = compiler("shop_schema.json", options);
+    assert_eq!(
+        column_types(&formatted_shop, "#orders $placed_at->placed $amount"),
+        vec![
+            ("placed".to_owned(), DataType::Text),
+            ("amount".to_owned(), DataType::Numeric),
+        ]
+    );
+}
@@ -0,0 +1,32 @@
+#[test]
+fn test_compile_stats() {
+    use std::time::Duration;
+
+    use crate::options::Options;
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let input = "#issues ++#assignments $title $author.username $#comments $#labels";
+
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    let unmeasured = compiler.compile_query(input.to_owned()).unwrap();
+    assert_eq!(unmeasured.stats, None);
+
+    let options = Options {
+        collect_stats: true,
+        ..Default::default()
+    };
+    let compiler = Compiler::new(&schema_json, options).unwrap();
+    let measured = compiler.compile_query(input.to_owned()).unwrap();
+    assert_eq!(measured.sql, unmeasured.sql);
+    let stats = measured.stats.unwrap();
+    assert!(stats.parse_time > Duration::ZERO);
+    assert!(stats.resolution_time > Duration::ZERO);
+    assert!(stats.generation_time > Duration::ZERO);
+    assert_eq!(stats.join_count, 4);
+    assert_eq!(stats.cte_count, 3);
+    // Three CTEs plus the joined `users` table
+    assert_eq!(stats.alias_count, 4);
+}
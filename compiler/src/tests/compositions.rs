@@ -0,0 +1,27 @@
+#[test]
+fn test_compositions() {
+    use crate::options::Options;
+    use crate::{Arity, Compiler, Composition, DataType};
+
+    use super::get_test_resource;
+
+    let mut options = Options::default();
+    let reverse = Composition::new(Arity::Exactly(1), DataType::Text, |args| {
+        format!("reverse({})", args.join(", "))
+    });
+    options
+        .custom_functions
+        .insert("reverse".to_owned(), reverse);
+    let schema_json = get_test_resource("issue_schema.json");
+    let compiler = Compiler::new(&schema_json, options).unwrap();
+    let compile = |input: &str| compiler.compile(input.to_owned());
+
+    assert_eq!(
+        compile("#issues $title|reverse"),
+        Ok("SELECT\n  reverse(\"issues\".\"title\")\nFROM \"issues\";".to_owned())
+    );
+    assert_eq!(
+        compile("#issues $title|reverse(1)"),
+        Err("Function `reverse` expects exactly 1 argument.".to_owned())
+    );
+}
@@ -0,0 +1,51 @@
+#[test]
+fn test_condition_fragment() {
+    use crate::options::Options;
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    let input = r#"++#comments author.username:"alice""#;
+
+    let fragment = compiler.compile_condition("issues", input, &[]).unwrap();
+    assert_eq!(
+        fragment.sql,
+        "\"cte0\".\"pk\" IS NOT NULL AND\n\"users\".\"username\" = 'alice'"
+    );
+    assert_eq!(fragment.aliases, vec!["cte0", "users"]);
+
+    // Compile the same conditions for a host query which already uses those aliases
+    let reserved_aliases = vec!["cte0".to_owned(), "users".to_owned()];
+    let fragment = compiler
+        .compile_condition("issues", input, &reserved_aliases)
+        .unwrap();
+    assert_eq!(
+        fragment.sql,
+        "\"cte1\".\"pk\" IS NOT NULL AND\n\"users_1\".\"username\" = 'alice'"
+    );
+    assert_eq!(fragment.aliases, vec!["cte1", "users_1"]);
+    assert_eq!(
+        fragment.joins,
+        vec![
+            "LEFT JOIN \"cte1\" ON\n  \"issues\".\"id\" = \"cte1\".\"pk\"",
+            "LEFT JOIN \"users\" AS \"users_1\" ON\n  \"issues\".\"author\" = \"users_1\".\"id\"",
+        ]
+    );
+    assert_eq!(fragment.ctes.len(), 1);
+    assert!(fragment.ctes[0].starts_with("\"cte1\" AS ("));
+
+    // The fragment has no FROM clause into which to join the unnested values
+    let schema_json = get_test_resource("shop_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    assert_eq!(
+        compiler
+            .compile_condition("products", r#"tags|unnest:"rust""#, &[])
+            .map(|fragment| fragment.sql),
+        Err(
+            "Arrays can only be unnested within the result columns and conditions of a query."
+                .to_owned()
+        )
+    );
+}
@@ -4,71 +4,294 @@ fn test_corpus() {
     // one small test which calls all these other functions. There's some special behavior for
     // imports within integration tests that I don't fully understand yet. That behavior was
     // preventing me from writing these imports at the top of the file like normal.
-    use crate::options::{IdentifierResolution, Options};
+    use crate::ast::NullsSort;
+    use crate::options::{
+        BooleanRendering, CteMaterialization, DivisionByZero, IdentifierResolution, KeywordCase,
+        LinkingColumn, Lock, LockStrength, LockWait, NumericLiteralCasts, Options, PointInTime,
+        PreparedStatement, SafeMode, SampleMethod, TableSample, ToManyStrategy, View,
+    };
+    use crate::sql::tree::CtePurpose;
+    use crate::sql::Dialect;
     use crate::Compiler;
-    use crate::Postgres;
+    use crate::{Ansi, DuckDb, Postgres, SqlServer, Sqlite};
 
     use super::get_test_resource;
 
     use std::path::PathBuf;
     use testcase_markdown::*;
-    use toml::{from_str, map::Map, Table, Value};
+    use toml::{from_str, Table, Value};
 
-    #[derive(Debug, Clone, PartialEq, Eq)]
+    /// The options of a heading, which apply to every case beneath it unless a nested heading
+    /// overrides them
+    #[derive(Debug, Clone, Default, PartialEq)]
     struct Opts {
-        schema_json: String,
-        identifier_resolution: IdentifierResolution,
-        dialect: String,
+        values: Table,
     }
 
-    impl Default for Opts {
-        fn default() -> Self {
-            Opts {
-                schema_json: get_test_resource("issue_schema.json"),
-                identifier_resolution: IdentifierResolution::Flexible,
-                dialect: "postgres".to_owned(),
-            }
+    impl MergeSerialized for Opts {
+        fn merge_serialized(&self, source: String) -> Result<Self, String> {
+            let mut values = self.values.clone();
+            values.extend(from_str::<Table>(&source).map_err(|e| e.to_string())?);
+            Ok(Opts { values })
         }
     }
 
-    fn get_schema_json(toml_values: &Map<String, Value>) -> Option<String> {
-        let schema_name = toml_values.get("schema").map(|v| v.as_str())??;
-        let schema_file_name = match schema_name {
-            "issues" => "issue_schema.json",
-            "library" => "library_schema.json",
-            _ => return None,
-        };
-        Some(get_test_resource(schema_file_name))
+    fn get_str<'a>(key: &str, value: &'a Value) -> &'a str {
+        value
+            .as_str()
+            .unwrap_or_else(|| panic!("Option `{key}` must be a string"))
     }
 
-    fn get_identifier_resolution(toml_values: &Map<String, Value>) -> Option<IdentifierResolution> {
-        let identifier_resolution = toml_values
-            .get("identifier_resolution")
-            .map(|v| v.as_str())??;
-        match identifier_resolution {
-            "strict" => Some(IdentifierResolution::Strict),
-            "flexible" => Some(IdentifierResolution::Flexible),
-            _ => None,
-        }
+    fn get_u64(key: &str, value: &Value) -> u64 {
+        value
+            .as_integer()
+            .and_then(|i| i.try_into().ok())
+            .unwrap_or_else(|| panic!("Option `{key}` must be a non-negative integer"))
     }
 
-    fn get_dialect<'a>(toml_values: &'a Map<String, Value>) -> Option<&'a str> {
-        toml_values.get("dialect").map(|v| v.as_str())?
+    fn get_bool(key: &str, value: &Value) -> bool {
+        value
+            .as_bool()
+            .unwrap_or_else(|| panic!("Option `{key}` must be a boolean"))
     }
 
-    impl MergeSerialized for Opts {
-        fn merge_serialized(&self, source: String) -> Result<Self, String> {
-            let values = from_str::<Table>(&source).map_err(|e| e.to_string())?;
-            Ok(Opts {
-                schema_json: get_schema_json(&values)
-                    .unwrap_or_else(|| self.schema_json.to_owned()),
-                identifier_resolution: get_identifier_resolution(&values)
-                    .unwrap_or(self.identifier_resolution),
-                dialect: get_dialect(&values)
-                    .map(|d| d.to_owned())
-                    .unwrap_or_else(|| self.dialect.clone()),
-            })
+    fn get_table<'a>(key: &str, value: &'a Value) -> &'a Table {
+        value
+            .as_table()
+            .unwrap_or_else(|| panic!("Option `{key}` must be a table"))
+    }
+
+    fn unknown(key: &str, value: &str) -> ! {
+        panic!("Unknown value `{value}` for option `{key}`")
+    }
+
+    fn get_schema_json(values: &Table) -> String {
+        let schema_file_name = match values.get("schema").map(|v| get_str("schema", v)) {
+            None | Some("issues") => "issue_schema.json",
+            Some("library") => "library_schema.json",
+            Some("shop") => "shop_schema.json",
+            Some("multi") => "multi_schema.json",
+            Some("quoted") => "quoted_schema.json",
+            Some("sorted") => "sorted_schema.json",
+            Some(schema) => unknown("schema", schema),
+        };
+        get_test_resource(schema_file_name)
+    }
+
+    fn get_dialect(key: &str, value: &Value) -> Box<dyn Dialect> {
+        match get_str(key, value) {
+            "postgres" => Box::new(Postgres()),
+            "sqlserver" => Box::new(SqlServer()),
+            "sqlite" => Box::new(Sqlite()),
+            "duckdb" => Box::new(DuckDb()),
+            "ansi" => Box::new(Ansi()),
+            dialect => unknown(key, dialect),
+        }
+    }
+
+    fn get_options(values: &Table) -> Options {
+        let mut options = Options::default();
+        for (key, value) in values {
+            let key = key.as_str();
+            match key {
+                "schema" => {}
+                "identifier_resolution" => {
+                    options.identifier_resolver = Box::new(match get_str(key, value) {
+                        "strict" => IdentifierResolution::Strict,
+                        "flexible" => IdentifierResolution::Flexible,
+                        resolution => unknown(key, resolution),
+                    })
+                }
+                "dialect" => options.dialect = get_dialect(key, value),
+                "limit" => options.limit = Some(get_u64(key, value)),
+                "offset" => options.offset = Some(get_u64(key, value)),
+                "annotate" => options.annotate = get_bool(key, value),
+                "default_schema" => options.default_schema = Some(get_str(key, value).to_owned()),
+                "to_many_strategy" => {
+                    options.to_many_strategy = match get_str(key, value) {
+                        "cte" => ToManyStrategy::Cte,
+                        "scalar_subquery" => ToManyStrategy::ScalarSubquery,
+                        "lateral" => ToManyStrategy::Lateral,
+                        "subquery" => ToManyStrategy::Subquery,
+                        strategy => unknown(key, strategy),
+                    }
+                }
+                "max_chain_length" => options.max_chain_length = get_u64(key, value) as usize,
+                "strict" => options.strict = get_bool(key, value),
+                "lock" => {
+                    let lock = get_table(key, value);
+                    let strength = lock.get("strength").map(|v| get_str(key, v));
+                    let wait = lock.get("wait").map(|v| get_str(key, v));
+                    options.lock = Some(Lock {
+                        strength: match strength {
+                            Some("update") => LockStrength::Update,
+                            Some("share") => LockStrength::Share,
+                            strength => unknown(key, strength.unwrap_or_default()),
+                        },
+                        wait: match wait {
+                            None | Some("wait") => LockWait::Wait,
+                            Some("nowait") => LockWait::NoWait,
+                            Some("skip_locked") => LockWait::SkipLocked,
+                            Some(wait) => unknown(key, wait),
+                        },
+                    })
+                }
+                "nulls_sort" => {
+                    options.nulls_sort = match get_str(key, value) {
+                        "first" => NullsSort::First,
+                        "last" => NullsSort::Last,
+                        nulls_sort => unknown(key, nulls_sort),
+                    }
+                }
+                "view" => {
+                    let view = get_table(key, value);
+                    let get_flag = |flag| view.get(flag).map(|v| get_bool(key, v));
+                    options.view = Some(View {
+                        name: get_str(key, view.get("name").unwrap()).to_owned(),
+                        or_replace: get_flag("or_replace").unwrap_or_default(),
+                        materialized: get_flag("materialized").unwrap_or_default(),
+                    })
+                }
+                "full_outer_joins" => {
+                    let linking_columns = value.as_array().unwrap();
+                    options.full_outer_joins = linking_columns
+                        .iter()
+                        .map(|v| {
+                            let linking_column = get_table(key, v);
+                            let get = |k| get_str(key, linking_column.get(k).unwrap()).to_owned();
+                            LinkingColumn {
+                                table: get("table"),
+                                column: get("column"),
+                            }
+                        })
+                        .collect()
+                }
+                "base_table_alias" => {
+                    options.base_table_alias = Some(get_str(key, value).to_owned())
+                }
+                "max_unaliased_base_table_name_length" => {
+                    options.max_unaliased_base_table_name_length =
+                        Some(get_u64(key, value) as usize)
+                }
+                "table_sample" => {
+                    let sample = get_table(key, value);
+                    let method = sample.get("method").map(|v| get_str(key, v));
+                    options.table_sample = Some(TableSample {
+                        method: match method {
+                            Some("bernoulli") => SampleMethod::Bernoulli,
+                            Some("system") => SampleMethod::System,
+                            method => unknown(key, method.unwrap_or_default()),
+                        },
+                        percentage: sample.get("percentage").unwrap().as_float().unwrap(),
+                    })
+                }
+                "max_limit" => options.max_limit = Some(get_u64(key, value)),
+                "sort_by_position" => options.sort_by_position = get_bool(key, value),
+                "cast_computed_columns" => options.cast_computed_columns = get_bool(key, value),
+                "function_call_syntax" => options.function_call_syntax = get_bool(key, value),
+                "division_by_zero" => {
+                    options.division_by_zero = match value.as_table() {
+                        Some(v) => {
+                            DivisionByZero::Value(get_str(key, v.get("value").unwrap()).to_owned())
+                        }
+                        None => match get_str(key, value) {
+                            "error" => DivisionByZero::Error,
+                            "null" => DivisionByZero::Null,
+                            division_by_zero => unknown(key, division_by_zero),
+                        },
+                    }
+                }
+                "like_escape_char" => {
+                    let mut chars = get_str(key, value).chars();
+                    options.like_escape_char = match (chars.next(), chars.next()) {
+                        (Some(c), None) => Some(c),
+                        _ => panic!("Option `{key}` must be a single character"),
+                    }
+                }
+                "alias_keyword" => options.alias_keyword = get_bool(key, value),
+                "prepared_statement" => {
+                    let prepared_statement = get_table(key, value);
+                    let name = prepared_statement.get("name").map(|v| get_str(key, v));
+                    options.prepared_statement = Some(PreparedStatement {
+                        name: name.map(str::to_owned),
+                    })
+                }
+                "boolean_rendering" => {
+                    options.boolean_rendering = match get_str(key, value) {
+                        "native" => BooleanRendering::Native,
+                        "integer" => BooleanRendering::Integer,
+                        rendering => unknown(key, rendering),
+                    }
+                }
+                "infer_grouping" => options.infer_grouping = get_bool(key, value),
+                "max_cte_depth" => options.max_cte_depth = Some(get_u64(key, value) as usize),
+                "numeric_literal_casts" => {
+                    let casts = get_table(key, value);
+                    let get = |k| casts.get(k).map(|v| get_str(key, v).to_owned());
+                    options.numeric_literal_casts = NumericLiteralCasts {
+                        integer: get("integer"),
+                        decimal: get("decimal"),
+                    }
+                }
+                "default_sort" => options.default_sort = get_bool(key, value),
+                "cte_materialization" => {
+                    let policy = get_table(key, value);
+                    options.cte_materialization = policy
+                        .iter()
+                        .map(|(purpose, materialization)| {
+                            let purpose = match purpose.as_str() {
+                                "inclusion" => CtePurpose::Inclusion,
+                                "conditional_inclusion" => CtePurpose::ConditionalInclusion,
+                                "exclusion" => CtePurpose::Exclusion,
+                                "aggregate_value" => CtePurpose::AggregateValue,
+                                purpose => unknown(key, purpose),
+                            };
+                            let materialization = match get_str(key, materialization) {
+                                "materialized" => CteMaterialization::Materialized,
+                                "not_materialized" => CteMaterialization::NotMaterialized,
+                                materialization => unknown(key, materialization),
+                            };
+                            (purpose, materialization)
+                        })
+                        .collect()
+                }
+                "keyword_case" => {
+                    options.keyword_case = match get_str(key, value) {
+                        "upper" => KeywordCase::Upper,
+                        "lower" => KeywordCase::Lower,
+                        keyword_case => unknown(key, keyword_case),
+                    }
+                }
+                "safe_mode" => {
+                    let safe_mode = get_table(key, value);
+                    let get = |k| safe_mode.get(k).map(|v| get_u64(key, v));
+                    let default = SafeMode::default();
+                    options.safe_mode = Some(SafeMode {
+                        default_limit: get("default_limit"),
+                        max_joins: get("max_joins").map_or(default.max_joins, |n| n as usize),
+                        max_ctes: get("max_ctes").map_or(default.max_ctes, |n| n as usize),
+                    })
+                }
+                "display_formats" => options.display_formats = get_bool(key, value),
+                "system_time_as_of" => {
+                    let point = get_table(key, value);
+                    let get = |k| point.get(k).map(|v| get_str(key, v).to_owned());
+                    options.system_time_as_of = Some(match (get("timestamp"), get("placeholder")) {
+                        (Some(timestamp), None) => PointInTime::Timestamp(timestamp),
+                        (None, Some(placeholder)) => PointInTime::Placeholder(placeholder),
+                        _ => panic!("Option `{key}` needs either a timestamp or a placeholder"),
+                    })
+                }
+                "inline_cte_max_complexity" => {
+                    options.inline_cte_max_complexity = Some(get_u64(key, value) as usize)
+                }
+                "fold_constant_conditions" => {
+                    options.fold_constant_conditions = get_bool(key, value)
+                }
+                _ => panic!("Unknown option `{key}`"),
+            }
         }
+        options
     }
 
     /// Removes spaces so that it's easy to compare two SQL strings without worrying about
@@ -103,24 +326,20 @@ fn test_corpus() {
         .join("\n")
     }
 
-    fn test(mut case: TestCase<Opts>) {
+    /// Returns false when the case fails. A query which fails to compile produces its error
+    /// message in place of SQL, so that a case can expect an error.
+    fn test(mut case: TestCase<Opts>) -> bool {
         let expected = case.args.pop().unwrap();
         let input = case.args.pop().unwrap();
-        let options = Options {
-            identifier_resolution: case.options.identifier_resolution,
-            dialect: match case.options.dialect.as_str() {
-                "postgres" => Box::new(Postgres()),
-                _ => panic!("Unknown dialect"),
-            },
-        };
-        // println!("{:}", case.options.schema_json);
-        let compiler = Compiler::new(&case.options.schema_json, options).unwrap();
-        let actual = compiler.compile(input.to_owned()).unwrap();
+        let schema_json = get_schema_json(&case.options.values);
+        let options = get_options(&case.options.values);
+        let compiler = Compiler::new(&schema_json, options).unwrap();
+        let actual = compiler.compile(input.to_owned()).unwrap_or_else(|e| e);
         if clean(actual.clone()) == clean(expected.clone()) {
-            return;
+            return true;
         }
         println!("{}", get_output(&case, &input, &expected, &actual));
-        panic!("Test corpus failure");
+        false
     }
 
     fn name_or_heading_contains(case: &TestCase<Opts>, s: &str) -> bool {
@@ -140,6 +359,7 @@ fn test_corpus() {
         let content = std::fs::read_to_string(path).unwrap();
         let cases = get_test_cases(content, Opts::default());
         let has_soloed_tests = cases.iter().any(is_soloed);
+        let mut failure_count = 0;
         for case in cases {
             if is_skipped(&case) {
                 continue;
@@ -147,7 +367,13 @@ fn test_corpus() {
             if has_soloed_tests && !is_soloed(&case) {
                 continue;
             }
-            test(case)
+            if !test(case) {
+                failure_count += 1;
+            }
+        }
+        // Every case runs before we fail, so that one failure doesn't hide the others
+        if failure_count > 0 {
+            panic!("Test corpus failures: {failure_count}");
         }
     }
 
@@ -0,0 +1,64 @@
+#[test]
+fn test_count_queries() {
+    use crate::options::Options;
+    use crate::{compile_count, Compiler, SqlServer};
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let limited = || Options {
+        limit: Some(20),
+        offset: Some(40),
+        ..Options::default()
+    };
+    let count = |options: Options, input: &str| compile_count(input, &schema_json, options);
+
+    assert_eq!(
+        count(limited(), r#"#issues status:"open" $id $title \s"#).unwrap(),
+        [
+            r#"SELECT"#,
+            r#"  count(*)"#,
+            r#"FROM "issues""#,
+            r#"WHERE"#,
+            r#"  "issues"."status" = 'open';"#,
+        ]
+        .join("\n")
+    );
+    let compiler = Compiler::new(&schema_json, limited()).unwrap();
+    assert!(compiler
+        .compile(r#"#issues status:"open" $id $title \s"#.to_owned())
+        .unwrap()
+        .contains("LIMIT 20"));
+
+    // Conditions on related records keep what they need
+    let sql = count(limited(), "#issues ++#comments $id").unwrap();
+    assert!(sql.starts_with("WITH\n"));
+    assert!(sql.contains("SELECT\n  count(*)\nFROM \"issues\"\nJOIN \"cte0\""));
+
+    assert_eq!(
+        count(limited(), r#"#issues $author.username\g $id%count"#).unwrap(),
+        [
+            r#"SELECT"#,
+            r#"  count(*)"#,
+            r#"FROM ("#,
+            r#"  SELECT"#,
+            r#"    1 AS "one""#,
+            r#"  FROM "issues""#,
+            r#"  LEFT JOIN "users" ON"#,
+            r#"    "issues"."author" = "users"."id""#,
+            r#"  GROUP BY "users"."username""#,
+            r#") AS "counted";"#,
+        ]
+        .join("\n")
+    );
+
+    let options = Options {
+        dialect: Box::new(SqlServer()),
+        ..limited()
+    };
+    // SQL Server only paginates sorted results, but the count needs neither
+    let sql = count(options, r#"#issues ++#comments $status\g $id%count"#).unwrap();
+    assert!(sql.starts_with("WITH\n"));
+    assert!(sql.contains("SELECT\n  count(*)\nFROM (\n  SELECT\n    1 AS [one]"));
+    assert!(!sql.contains("OFFSET"));
+}
@@ -0,0 +1,24 @@
+#[test]
+fn test_cte_count_by_purpose() {
+    use std::collections::HashMap;
+
+    use crate::options::Options;
+    use crate::{Compiler, CtePurpose};
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    let compiled = compiler
+        .compile_query("#issues ++#comments $id $#assignments".to_owned())
+        .unwrap();
+
+    assert_eq!(compiled.ctes.len(), 2);
+    assert_eq!(
+        compiled.cte_count_by_purpose(),
+        HashMap::from([(CtePurpose::Inclusion, 1), (CtePurpose::AggregateValue, 1)])
+    );
+
+    let compiled = compiler.compile_query("#issues $id".to_owned()).unwrap();
+    assert!(compiled.cte_count_by_purpose().is_empty());
+}
@@ -0,0 +1,13 @@
+#[test]
+fn test_unknown_primary_key() {
+    use crate::options::Options;
+    use crate::Compiler;
+
+    let schema_json = r#"{
+        "tables": [
+            { "name": "a", "primary_key": "id", "columns": [{ "name": "pk" }] }
+        ],
+        "links": []
+    }"#;
+    assert!(Compiler::new(schema_json, Options::default()).is_err());
+}
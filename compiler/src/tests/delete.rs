@@ -0,0 +1,77 @@
+#[test]
+fn test_delete() {
+    use crate::options::Options;
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("shop_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    let delete = |input: &str| compiler.compile_delete("customers", input);
+
+    assert_eq!(
+        delete("--#orders"),
+        Ok([
+            "DELETE FROM \"customers\"",
+            "WHERE",
+            "  NOT EXISTS (",
+            "    SELECT",
+            "      1",
+            "    FROM \"orders\"",
+            "    WHERE",
+            "      \"orders\".\"customer\" = \"customers\".\"id\"",
+            "  );",
+        ]
+        .join("\n"))
+    );
+    assert_eq!(
+        delete("++#orders{amount:>100} name:\"Foo\""),
+        Ok([
+            "DELETE FROM \"customers\"",
+            "WHERE",
+            "  EXISTS (",
+            "    SELECT",
+            "      1",
+            "    FROM \"orders\"",
+            "    WHERE",
+            "      \"orders\".\"amount\" > 100 AND",
+            "      \"orders\".\"customer\" = \"customers\".\"id\"",
+            "  ) AND",
+            "  \"customers\".\"name\" = 'Foo';",
+        ]
+        .join("\n"))
+    );
+    assert_eq!(
+        delete("#orders.amount%sum:<10"),
+        Ok([
+            "DELETE FROM \"customers\"",
+            "WHERE",
+            "  (",
+            "    SELECT",
+            "      sum(\"orders\".\"amount\") AS \"v1\"",
+            "    FROM \"orders\"",
+            "    WHERE",
+            "      \"orders\".\"customer\" = \"customers\".\"id\"",
+            "  ) < 10;",
+        ]
+        .join("\n"))
+    );
+    assert_eq!(delete(""), Ok("DELETE FROM \"customers\";".to_owned()));
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    assert_eq!(
+        compiler.compile_delete("issues", "author.username:\"alice\""),
+        Err(
+            "This statement can't join other tables, so it can't refer to single related records."
+                .to_owned()
+        )
+    );
+    assert_eq!(
+        compiler.compile_delete("issues", "author.#comments:0"),
+        Err(
+            "This statement can't join other tables, so it can't refer to single related records."
+                .to_owned()
+        )
+    );
+}
@@ -0,0 +1,59 @@
+#[test]
+fn test_external_ctes() {
+    use crate::options::Options;
+    use crate::sql::tree::{Column, Cte, CtePurpose, Select, SqlExpr};
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let external_cte = |alias: &str| {
+        let mut select = Select::from("users".to_owned());
+        let expr = SqlExpr::atom("\"users\".\"id\"".to_owned());
+        select.columns.push(Column::new(expr, None));
+        Cte {
+            alias: alias.to_owned(),
+            select,
+            purpose: CtePurpose::AggregateValue,
+            join_column_name: "id".to_owned(),
+            annotation: String::new(),
+        }
+    };
+    let options = Options {
+        external_ctes: vec![external_cte("cte0"), external_cte("cte0")],
+        ..Options::default()
+    };
+    let compiler = Compiler::new(&schema_json, options).unwrap();
+    let compiled = compiler
+        .compile_query("#issues ++#comments $id".to_owned())
+        .unwrap();
+    let aliases = compiled.ctes.iter().map(|cte| cte.alias.as_str());
+    // External CTEs avoid each other and generated CTEs avoid the external aliases
+    assert_eq!(aliases.collect::<Vec<_>>(), ["cte0", "cte0_1", "cte1"]);
+    let expected = [
+        "WITH",
+        "  \"cte0\" AS (",
+        "    SELECT",
+        "      \"users\".\"id\"",
+        "    FROM \"users\"",
+        "  ),",
+        "  \"cte0_1\" AS (",
+        "    SELECT",
+        "      \"users\".\"id\"",
+        "    FROM \"users\"",
+        "  ),",
+        "  \"cte1\" AS (",
+        "    SELECT",
+        "      \"comments\".\"issue\" AS \"pk\"",
+        "    FROM \"comments\"",
+        "    GROUP BY \"comments\".\"issue\"",
+        "  )",
+        "SELECT",
+        "  \"issues\".\"id\"",
+        "FROM \"issues\"",
+        "JOIN \"cte1\" ON",
+        "  \"issues\".\"id\" = \"cte1\".\"pk\";",
+    ]
+    .join("\n");
+    assert_eq!(compiled.sql, expected);
+}
@@ -0,0 +1,39 @@
+#[test]
+fn test_identifier_resolvers() {
+    use crate::options::{IdentifierResolution, IdentifierResolver, Options};
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    /// Resolves `me` to the users table, and other identifiers strictly
+    struct Synonyms;
+
+    impl IdentifierResolver for Synonyms {
+        fn resolve<'n>(&self, identifier: &str, names: &[&'n str]) -> Option<&'n str> {
+            let identifier = match identifier {
+                "me" => "users",
+                identifier => identifier,
+            };
+            IdentifierResolution::Strict.resolve(identifier, names)
+        }
+    }
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let compile = |options: Options, input: &str| {
+        let compiler = Compiler::new(&schema_json, options).unwrap();
+        compiler.compile(input.to_owned())
+    };
+    let synonyms = || Options {
+        identifier_resolver: Box::new(Synonyms),
+        ..Options::default()
+    };
+
+    assert_eq!(
+        compile(synonyms(), "#me $username"),
+        Ok(["SELECT", "  \"users\".\"username\"", "FROM \"users\";"].join("\n"))
+    );
+    assert!(compile(Options::default(), "#me $username").is_err());
+    // Unlike the default resolution, this one is strict about everything else
+    assert!(compile(synonyms(), "#Users $username").is_err());
+    assert!(compile(Options::default(), "#Users $username").is_ok());
+}
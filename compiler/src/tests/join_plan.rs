@@ -0,0 +1,38 @@
+#[test]
+fn test_join_plan() {
+    use crate::options::Options;
+    use crate::sql::tree::{CtePurpose, JoinType};
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    let compile = |input: &str| compiler.compile_query(input.to_owned()).unwrap();
+
+    let plan = compile("#issues $author.username $project.name").join_plan;
+    assert_eq!(plan.base_table.as_deref(), Some("issues"));
+    assert!(plan.ctes.is_empty());
+    assert_eq!(plan.joins.len(), 2);
+    // Sibling joins come in no particular order
+    let join = |table: &str| plan.joins.iter().find(|j| j.table == table).unwrap();
+    assert_eq!(join("users").join_type, JoinType::LeftOuter);
+    assert_eq!(
+        join("users").condition,
+        r#""issues"."author" = "users"."id""#
+    );
+    assert_eq!(
+        join("projects").condition,
+        r#""issues"."project" = "projects"."id""#
+    );
+
+    let plan = compile("#issues $#comments").join_plan;
+    assert_eq!(plan.ctes.len(), 1);
+    let cte = &plan.ctes[0];
+    assert_eq!(cte.purpose, CtePurpose::AggregateValue);
+    assert_eq!(cte.plan.base_table.as_deref(), Some("comments"));
+    assert_eq!(plan.joins[0].table, cte.alias);
+
+    let json = serde_json::to_value(&plan).unwrap();
+    assert_eq!(json["ctes"][0]["purpose"], "AggregateValue");
+}
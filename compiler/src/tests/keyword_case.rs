@@ -0,0 +1,85 @@
+#[test]
+fn test_no_uppercase_keywords_remain() {
+    use crate::options::{Options, PointInTime};
+    use crate::sql::Dialect;
+    use crate::{Ansi, Compiler, DuckDb, KeywordCase, Postgres, SqlServer, Sqlite};
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let inputs = [
+        "#issues created_at:>=@2023-01-01 created_at:>@6y|ago $id \\sd",
+        "#issues $created_at|epoch \\sd",
+        "#issues created_at:<@now created_at:<@infinity $title->t $#comments.id%count",
+        "#issues title:~\"x\" status:[\"a\" \"b\"] $id+1.5 $title|to_date(\"YYYY-MM-DD\")",
+        "#issues ++#comments --#assignments $id|width_bucket(0 10 5) $id|to_integer",
+        "#issues $created_at|round_time(@t15m) $id|to_numeric $id|to_text",
+        "#issues $created_at|at_time_zone(\"UTC\") $created_at|date_diff(@now \"day\")",
+    ];
+    let dialects: [fn() -> Box<dyn Dialect>; 5] = [
+        || Box::new(Postgres()),
+        || Box::new(Ansi()),
+        || Box::new(DuckDb()),
+        || Box::new(SqlServer()),
+        || Box::new(Sqlite()),
+    ];
+    // The functions which some dialects render in uppercase
+    let function_names = [
+        "CONVERT",
+        "DATEDIFF_BIG",
+        "NOW",
+        "REGEXP_LIKE",
+        "TO_DATE",
+        "WIDTH_BUCKET",
+    ];
+    let uppercase_words = |sql: &str| {
+        let mut words = Vec::<String>::new();
+        let mut closing: Option<char> = None;
+        let mut word = String::new();
+        for c in sql.chars().chain([' ']) {
+            if let Some(closing_char) = closing {
+                closing = (c != closing_char).then_some(closing_char);
+                continue;
+            }
+            if c.is_ascii_alphanumeric() || c == '_' {
+                word.push(c);
+                continue;
+            }
+            let is_uppercase = word.chars().any(|c| c.is_ascii_uppercase())
+                && !word.chars().any(|c| c.is_ascii_lowercase());
+            if is_uppercase && !function_names.contains(&word.as_str()) {
+                words.push(std::mem::take(&mut word));
+            }
+            word.clear();
+            closing = match c {
+                '\'' | '"' => Some(c),
+                '[' => Some(']'),
+                _ => None,
+            };
+        }
+        words
+    };
+
+    let mut compiled_count = 0;
+    for dialect in dialects {
+        for system_time_as_of in [None, Some(PointInTime::Timestamp("2024-01-31".to_owned()))] {
+            let options = Options {
+                dialect: dialect(),
+                keyword_case: KeywordCase::Lower,
+                cast_computed_columns: true,
+                system_time_as_of,
+                ..Options::default()
+            };
+            let compiler = Compiler::new(&schema_json, options).unwrap();
+            // Not every dialect supports everything within the inputs
+            for sql in inputs
+                .iter()
+                .filter_map(|i| compiler.compile(i.to_string()).ok())
+            {
+                assert_eq!(uppercase_words(&sql), Vec::<String>::new(), "{sql}");
+                compiled_count += 1;
+            }
+        }
+    }
+    assert!(compiled_count > 20);
+}
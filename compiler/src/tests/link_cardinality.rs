@@ -0,0 +1,27 @@
+#[test]
+fn test_link_cardinality() {
+    use crate::schema::links::{Cardinality, Link, MultiLink};
+    use crate::schema::primitive_schema::PrimitiveSchema;
+    use crate::schema::Schema;
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("shop_schema.json");
+    let primitive_schema = serde_json::from_str::<PrimitiveSchema>(&schema_json).unwrap();
+    let schema = Schema::try_from(primitive_schema).unwrap();
+    let table = |name: &str| {
+        let table_id = schema.table_lookup.get(name).unwrap();
+        schema.tables.get(table_id).unwrap()
+    };
+    let forward_cardinality = |name: &str| {
+        let link = table(name).forward_links_to_one.values().next().unwrap();
+        MultiLink::ForwardLinkToOne(*link).get_cardinality()
+    };
+    assert_eq!(forward_cardinality("loyalty_cards"), Cardinality::OneToOne);
+    assert_eq!(forward_cardinality("refunds"), Cardinality::ManyToOne);
+    let customers = table("customers");
+    let reverse_link = customers.reverse_links_to_one.values().next().unwrap()[0];
+    assert_eq!(reverse_link.get_cardinality(), Cardinality::OneToOne);
+    let reverse_link = customers.reverse_links_to_many.values().next().unwrap()[0];
+    assert_eq!(reverse_link.get_cardinality(), Cardinality::OneToMany);
+}
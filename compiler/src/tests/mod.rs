@@ -1,4 +1,40 @@
+mod alias_collisions;
+mod alias_prefix;
+mod aliases;
+mod available_compositions;
+mod batch;
+mod boolean_expression;
+mod chain_reversal;
+mod column_types;
+mod compile_stats;
+mod compositions;
+mod condition_fragment;
 mod corpus;
+mod count_queries;
+mod cte_purposes;
+mod default_sort;
+mod delete;
+mod external_cte;
+mod identifier_resolvers;
+mod join_plan;
+mod keyword_case;
+mod link_cardinality;
+mod nullability;
+mod parameter_metadata;
+mod policy_expressions;
+mod query_builder;
+mod reachable_paths;
+mod schema_merge;
+mod snapshots;
+mod sql_functions;
+mod sql_tree_transforms;
+mod statement_timeout;
+mod string_whitespace;
 mod test_utils;
+mod tree_debug;
+mod unconditioned_joins;
+mod update;
+mod validation;
+mod values_tables;
 
 pub use test_utils::*;
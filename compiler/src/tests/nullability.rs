@@ -0,0 +1,79 @@
+#[test]
+fn test_result_column_nullability() {
+    use crate::options::{DivisionByZero, LinkingColumn, Options};
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("shop_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    let nullability = |input: &str| {
+        compiler
+            .compile_query(input.to_owned())
+            .unwrap()
+            .columns
+            .into_iter()
+            .map(|c| c.nullable)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(nullability("#orders $customer.name"), vec![false]);
+    assert_eq!(nullability("#orders $shipping_address.zip"), vec![true]);
+    assert_eq!(
+        nullability("#orders $id $shipping_address.country.name"),
+        vec![false, true]
+    );
+    assert_eq!(
+        nullability("#orders $billing_address.country.name"),
+        vec![false]
+    );
+    assert_eq!(
+        nullability("#orders $id $shipping_address $shipping_address.zip|else('none') $id+1"),
+        vec![false, true, false, false]
+    );
+    assert_eq!(
+        nullability("#orders $customer.*"),
+        vec![false, false, true, true, true, true]
+    );
+    assert_eq!(
+        nullability("#orders $shipping_address.*"),
+        vec![true, true, true]
+    );
+    assert_eq!(nullability("#customers $#orders%any(paid)"), vec![false]);
+    assert_eq!(
+        nullability("#products $tags|unnest $tags|unnest_index"),
+        vec![true, false]
+    );
+    assert_eq!(
+        nullability("#customers $#orders.amount%sum $#orders.amount%sum|zero_if_null"),
+        vec![true, false]
+    );
+
+    // Either side of a full outer join may lack a matching record
+    let options = Options {
+        full_outer_joins: vec![LinkingColumn {
+            table: "orders".to_owned(),
+            column: "customer".to_owned(),
+        }],
+        ..Default::default()
+    };
+    let compiler = Compiler::new(&schema_json, options).unwrap();
+    let columns = compiler
+        .compile_query("#orders $id $customer.name".to_owned())
+        .unwrap()
+        .columns;
+    assert!(columns.iter().all(|column| column.nullable));
+
+    let quotient_nullability = |division_by_zero: DivisionByZero| {
+        let options = Options {
+            division_by_zero,
+            ..Default::default()
+        };
+        let compiler = Compiler::new(&schema_json, options).unwrap();
+        let compiled = compiler.compile_query("#orders $amount/quantity".to_owned());
+        compiled.unwrap().columns[0].nullable
+    };
+    assert!(!quotient_nullability(DivisionByZero::Error));
+    assert!(quotient_nullability(DivisionByZero::Null));
+    assert!(quotient_nullability(DivisionByZero::Value("0".to_owned())));
+}
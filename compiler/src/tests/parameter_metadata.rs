@@ -0,0 +1,75 @@
+#[test]
+fn test_parameter_metadata() {
+    use crate::options::Options;
+    use crate::{Compiler, DataType, ParamInfo, PointInTime, SqlServer};
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    let compile = |input: &str| compiler.compile_query(input.to_owned()).unwrap();
+
+    let compiled = compile("#issues title::q::text author:>:min_author::int title!:q $id");
+    assert_eq!(
+        compiled.parameters(),
+        [
+            ParamInfo {
+                index: 1,
+                name: "q".to_owned(),
+                data_type: DataType::Text,
+                type_name: Some("text".to_owned()),
+            },
+            ParamInfo {
+                index: 2,
+                name: "min_author".to_owned(),
+                data_type: DataType::Integer,
+                type_name: Some("int".to_owned()),
+            },
+        ]
+    );
+
+    // Without annotations, types come from whatever each placeholder is compared with
+    let compiled = compile("#issues author:>:min created_at:>:since $id $(:factor * 2)");
+    let parameters = compiled
+        .parameters()
+        .iter()
+        .map(|p| (p.index, p.name.as_str(), p.type_name.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        parameters,
+        vec![
+            (1, "min", Some("bigint")),
+            (2, "since", None),
+            (3, "factor", None)
+        ]
+    );
+    assert_eq!(compiled.parameters()[1].data_type, DataType::Timestamp);
+    assert_eq!(compiled.parameters()[2].data_type, DataType::Unknown);
+
+    assert!(compile("#issues $id").parameters().is_empty());
+
+    let options = Options {
+        dialect: Box::new(SqlServer()),
+        ..Options::default()
+    };
+    let compiler = Compiler::new(&schema_json, options).unwrap();
+    let compiled = compiler
+        .compile_query("#issues author::a $id".to_owned())
+        .unwrap();
+    assert!(compiled.sql.contains("@a"));
+    assert_eq!(compiled.parameters()[0].index, 1);
+    assert_eq!(compiled.parameters()[0].data_type, DataType::Integer);
+
+    // A placeholder for the point in time of a temporal table becomes the first parameter
+    let options = Options {
+        dialect: Box::new(SqlServer()),
+        system_time_as_of: Some(PointInTime::Placeholder("as_of".to_owned())),
+        ..Options::default()
+    };
+    let compiler = Compiler::new(&schema_json, options).unwrap();
+    let compiled = compiler
+        .compile_query("#issues id:>:min_id $id".to_owned())
+        .unwrap();
+    let names = compiled.parameters().iter().map(|p| p.name.as_str());
+    assert_eq!(names.collect::<Vec<_>>(), ["as_of", "min_id"]);
+}
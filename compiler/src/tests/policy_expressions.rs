@@ -0,0 +1,73 @@
+#[test]
+fn test_policy_expressions() {
+    use crate::options::Options;
+    use crate::{Compiler, SqlServer};
+
+    let schema_json = r#"{
+        "tables": [
+            {
+                "name": "documents",
+                "columns": [
+                    { "name": "id", "type": "integer" },
+                    { "name": "tenant_id", "type": "integer" },
+                    { "name": "owner", "type": "text" },
+                    { "name": "folder", "type": "integer" }
+                ]
+            },
+            {
+                "name": "shares",
+                "columns": [
+                    { "name": "document", "type": "integer" },
+                    { "name": "grantee", "type": "text" }
+                ]
+            },
+            {
+                "name": "folders",
+                "columns": [{ "name": "id", "type": "integer" }]
+            }
+        ],
+        "links": [
+            {
+                "from": { "table": "shares", "column": "document" },
+                "to": { "table": "documents", "column": "id" },
+                "unique": false
+            },
+            {
+                "from": { "table": "documents", "column": "folder" },
+                "to": { "table": "folders", "column": "id" },
+                "unique": false
+            }
+        ]
+    }"#;
+    let compiler = Compiler::new(schema_json, Options::default()).unwrap();
+    let compile = |input: &str| compiler.compile_policy_expression("documents", input);
+
+    // Tenant isolation via a setting of the session
+    assert_eq!(
+        compile(r#"tenant_id:"app.tenant_id"|setting|to_integer"#).unwrap(),
+        r#""documents"."tenant_id" = CAST(current_setting('app.tenant_id') AS INTEGER)"#
+    );
+    let sql = compile("[owner:@current_user ++#shares{grantee:@current_user}]").unwrap();
+    assert!(sql.starts_with(r#"("documents"."owner" = CURRENT_USER OR EXISTS ("#));
+    assert!(sql.contains(r#""shares"."grantee" = CURRENT_USER"#));
+    assert_eq!(compile("").unwrap(), "TRUE");
+
+    // Constructs which a policy can't contain
+    assert!(compile("tenant_id::tenant").is_err());
+    assert!(compile("folder.id:1").is_err());
+
+    // Session values are only available within policies
+    let query = compiler.compile("#documents owner:@current_user $id".to_owned());
+    assert!(query.is_err());
+    let query = compiler.compile(r#"#documents $"app.tenant_id"|setting"#.to_owned());
+    assert!(query.is_err());
+
+    let options = Options {
+        dialect: Box::new(SqlServer()),
+        ..Options::default()
+    };
+    let compiler = Compiler::new(schema_json, options).unwrap();
+    assert!(compiler
+        .compile_policy_expression("documents", "owner:@current_user")
+        .is_err());
+}
@@ -0,0 +1,29 @@
+#[test]
+fn test_query_builder() {
+    use crate::ast::{ColumnSpec, Expr, Operator, PathPart, Query, SortDirection};
+    use crate::options::Options;
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+
+    let built = Query::new("issues")
+        .filter(Expr::column("status").compare(Operator::Eq, Expr::string("open")))
+        .column(Expr::column("id"))
+        .column(
+            ColumnSpec::new(Expr::path(vec![
+                PathPart::Column("author".to_owned()),
+                PathPart::Column("username".to_owned()),
+            ]))
+            .alias("author")
+            .sort(SortDirection::Asc),
+        );
+    let text = r#"#issues status:"open" $id $author.username->author \s"#;
+
+    let from_builder = compiler.compile_ast(built).unwrap();
+    let from_text = compiler.compile_query(text.to_owned()).unwrap();
+    assert_eq!(from_builder.sql, from_text.sql);
+    assert_eq!(from_builder.columns, from_text.columns);
+}
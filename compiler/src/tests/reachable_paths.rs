@@ -0,0 +1,33 @@
+#[test]
+fn test_reachable_paths() {
+    use crate::options::Options;
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    let reachable = |max_depth: usize| compiler.reachable_paths("issues", max_depth).unwrap();
+
+    assert_eq!(reachable(0), Vec::<String>::new());
+    assert_eq!(reachable(1), vec!["author", "project"]);
+    // `duplicate_of` is left out because it leads back to `issues`
+    assert_eq!(
+        reachable(2),
+        vec!["author", "project", "author.team", "project.product"]
+    );
+    assert_eq!(
+        reachable(3),
+        vec![
+            "author",
+            "project",
+            "author.team",
+            "project.product",
+            "project.product.client",
+        ]
+    );
+    assert_eq!(
+        compiler.reachable_paths("tickets", 1),
+        Err("Table `tickets` does not exist.".to_owned())
+    );
+}
@@ -0,0 +1,56 @@
+#[test]
+fn test_schema_merge() {
+    use crate::options::Options;
+    use crate::schema::primitive_schema::PrimitiveSchema;
+    use crate::schema::Schema;
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    let issue_json = get_test_resource("issue_schema.json");
+    let library_json = get_test_resource("library_schema.json");
+    let shop_json = get_test_resource("shop_schema.json");
+    let schema = |json: &str| {
+        let primitive_schema = serde_json::from_str::<PrimitiveSchema>(json).unwrap();
+        Schema::try_from(primitive_schema).unwrap()
+    };
+    let table_count = |json: &str| schema(json).tables.len();
+    let issue_link_count = |schema: &Schema| {
+        let table_id = schema.table_lookup.get("issues").unwrap();
+        schema
+            .tables
+            .get(table_id)
+            .unwrap()
+            .forward_links_to_one
+            .len()
+    };
+
+    let merged = schema(&issue_json).merge(schema(&library_json)).unwrap();
+    assert_eq!(
+        merged.tables.len(),
+        table_count(&issue_json) + table_count(&library_json)
+    );
+
+    // Queries against either schema compile as they would against that schema alone
+    let merged_compiler =
+        Compiler::from_schemas(&[&issue_json, &library_json], Options::default()).unwrap();
+    for (json, input) in [
+        (&issue_json, "#issues $author.username"),
+        (&library_json, "#Books $Media.*"),
+    ] {
+        let compiler = Compiler::new(json, Options::default()).unwrap();
+        let expected = compiler.compile(input.to_owned()).unwrap();
+        assert_eq!(merged_compiler.compile(input.to_owned()), Ok(expected));
+    }
+
+    // A table defined identically in both schemas is kept once
+    let merged = schema(&issue_json).merge(schema(&issue_json)).unwrap();
+    assert_eq!(merged.tables.len(), table_count(&issue_json));
+    assert_eq!(
+        issue_link_count(&merged),
+        issue_link_count(&schema(&issue_json))
+    );
+
+    // Both schemas have a `products` table, with different columns
+    assert!(schema(&issue_json).merge(schema(&shop_json)).is_err());
+}
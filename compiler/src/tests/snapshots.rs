@@ -0,0 +1,37 @@
+#[test]
+fn test_snapshots() {
+    use crate::compile_for_snapshot;
+    use crate::options::Options;
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let corpus = [
+        "#issues $author.team.name $project.product.client.name $author.username",
+        "#issues ++#labels ++#comments $author.username $project.name $id",
+        "#issues ++#comments{user.username:'alice'} ++#labels{name:'bug'} $title",
+        "#users $#issues.project.name%list $#comments.id%count",
+        "#issues $nonexistent",
+    ];
+    let snapshot = || {
+        corpus
+            .iter()
+            .map(|source| compile_for_snapshot(source, &schema_json, Options::default()))
+            .collect::<Vec<_>>()
+    };
+    let first = snapshot();
+    for _ in 0..10 {
+        assert_eq!(snapshot(), first);
+    }
+
+    // Joins appear in the order that their paths are first mentioned
+    let sql = &first[0];
+    let position = |table: &str| sql.find(&format!(r#"LEFT JOIN "{table}""#)).unwrap();
+    assert!(position("users") < position("teams"));
+    assert!(position("teams") < position("projects"));
+    assert!(position("projects") < position("products"));
+
+    assert!(first[1].ends_with(";\n"));
+    assert!(first[..4].iter().all(|s| !s.starts_with("-- Error: ")));
+    assert!(first[4].starts_with("-- Error: "));
+}
@@ -0,0 +1,29 @@
+#[test]
+fn test_sql_functions() {
+    use crate::options::Options;
+    use crate::{Compiler, DataType};
+
+    use super::get_test_resource;
+
+    let mut options = Options::default();
+    options.add_sql_function("geo_distance", 2, DataType::Numeric);
+    let schema_json = get_test_resource("issue_schema.json");
+    let compiler = Compiler::new(&schema_json, options).unwrap();
+    let compile = |input: &str| compiler.compile(input.to_owned());
+
+    assert_eq!(
+        compile("#issues $id|geo_distance(project)"),
+        Ok(
+            "SELECT\n  geo_distance(\"issues\".\"id\", \"issues\".\"project\")\nFROM \"issues\";"
+                .to_owned()
+        )
+    );
+    assert_eq!(
+        compile("#issues $id|geo_distance"),
+        Err("Function `geo_distance` expects exactly 2 arguments.".to_owned())
+    );
+    assert_eq!(
+        compile("#issues $id|geo_bearing(project)"),
+        Err("Scalar function `geo_bearing` does not exist.".to_owned())
+    );
+}
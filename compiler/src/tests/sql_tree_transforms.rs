@@ -0,0 +1,89 @@
+#[test]
+fn test_sql_tree_transforms() {
+    use crate::options::Options;
+    use crate::sql_tree::{Select, SqlTreeTransform};
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    struct NoOp;
+
+    impl SqlTreeTransform for NoOp {
+        fn transform(&self, _: &mut Select) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    /// Renames the first CTE, along with every reference to it
+    struct RenameCte(&'static str);
+
+    impl SqlTreeTransform for RenameCte {
+        fn transform(&self, select: &mut Select) -> Result<(), String> {
+            let cte = select.ctes.first_mut().ok_or("No CTE to rename")?;
+            let old_alias = std::mem::replace(&mut cte.alias, self.0.to_owned());
+            let rename = |content: &str| {
+                content.replace(&format!("\"{old_alias}\""), &format!("\"{}\"", self.0))
+            };
+            for join in select.joins.iter_mut().filter(|j| j.table == old_alias) {
+                join.table = self.0.to_owned();
+                join.alias = self.0.to_owned();
+                join.conditions.content = rename(&join.conditions.content);
+            }
+            for column in select.columns.iter_mut() {
+                column.expr.content = rename(&column.expr.content);
+            }
+            Ok(())
+        }
+    }
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let compile = |options: Options, input: &str| {
+        let compiler = Compiler::new(&schema_json, options).unwrap();
+        compiler.compile_query(input.to_owned())
+    };
+    let input = "#issues $id $#comments.id%count->comments";
+
+    let untransformed = compile(Options::default(), input).unwrap();
+    let options = Options {
+        sql_tree_transforms: vec![Box::new(NoOp)],
+        ..Options::default()
+    };
+    assert_eq!(compile(options, input).unwrap().sql, untransformed.sql);
+
+    let options = Options {
+        sql_tree_transforms: vec![Box::new(NoOp), Box::new(RenameCte("comment_counts"))],
+        ..Options::default()
+    };
+    let compiled = compile(options, input).unwrap();
+    assert_eq!(
+        compiled.sql,
+        [
+            "WITH",
+            "  \"comment_counts\" AS (",
+            "    SELECT",
+            "      \"comments\".\"issue\" AS \"pk\",",
+            "      count(\"comments\".\"id\") AS \"v1\"",
+            "    FROM \"comments\"",
+            "    GROUP BY \"comments\".\"issue\"",
+            "  )",
+            "SELECT",
+            "  \"issues\".\"id\",",
+            "  \"comment_counts\".\"v1\" AS \"comments\"",
+            "FROM \"issues\"",
+            "LEFT JOIN \"comment_counts\" ON",
+            "  \"issues\".\"id\" = \"comment_counts\".\"pk\";",
+        ]
+        .join("\n")
+    );
+    assert_eq!(compiled.ctes[0].alias, "comment_counts");
+
+    // Errors from a transform abort compilation
+    let options = Options {
+        sql_tree_transforms: vec![Box::new(RenameCte("unused"))],
+        ..Options::default()
+    };
+    assert_eq!(
+        compile(options, "#issues $id").map(|c| c.sql),
+        Err("No CTE to rename".to_owned())
+    );
+}
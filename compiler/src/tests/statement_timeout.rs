@@ -0,0 +1,65 @@
+#[test]
+fn test_statement_timeout() {
+    use crate::options::Options;
+    use crate::{Compiler, SqlServer, Sqlite};
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let compile = |options: Options| {
+        let compiler = Compiler::new(&schema_json, options).unwrap();
+        compiler.compile_query("#issues $id".to_owned())
+    };
+    let timeout = |timeout: &str| {
+        let options = Options {
+            statement_timeout: Some(timeout.to_owned()),
+            ..Options::default()
+        };
+        compile(options).map(|compiled| compiled.prelude)
+    };
+
+    assert_eq!(
+        timeout("5s"),
+        Ok("SET LOCAL statement_timeout = '5s';".to_owned())
+    );
+    assert_eq!(
+        timeout("1500 ms"),
+        Ok("SET LOCAL statement_timeout = '1500ms';".to_owned())
+    );
+    assert_eq!(
+        timeout("120s"),
+        Ok("SET LOCAL statement_timeout = '2min';".to_owned())
+    );
+    assert_eq!(
+        timeout("1h"),
+        Ok("SET LOCAL statement_timeout = '1h';".to_owned())
+    );
+    let compiled = compile(Options::default()).unwrap();
+    assert_eq!(compiled.prelude, "");
+    assert_eq!(
+        compiled.sql,
+        "SELECT\n  \"issues\".\"id\"\nFROM \"issues\";"
+    );
+
+    assert!(timeout("5").is_err());
+    assert!(timeout("0s").is_err());
+    assert!(timeout("-5s").is_err());
+    assert!(timeout("1.5s").is_err());
+    assert!(timeout("5 seconds").is_err());
+    assert!(timeout("99999999999999999999h").is_err());
+
+    for options in [
+        Options {
+            dialect: Box::new(SqlServer()),
+            statement_timeout: Some("5s".to_owned()),
+            ..Options::default()
+        },
+        Options {
+            dialect: Box::new(Sqlite()),
+            statement_timeout: Some("5s".to_owned()),
+            ..Options::default()
+        },
+    ] {
+        assert!(compile(options).is_err());
+    }
+}
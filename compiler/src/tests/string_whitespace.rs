@@ -0,0 +1,35 @@
+#[test]
+fn test_string_whitespace() {
+    use crate::options::Options;
+    use crate::{Compiler, StringWhitespace};
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let compile = |string_whitespace: StringWhitespace, input: &str| {
+        let options = Options {
+            string_whitespace,
+            ..Options::default()
+        };
+        let compiler = Compiler::new(&schema_json, options).unwrap();
+        compiler.compile(input.to_owned()).unwrap()
+    };
+    let input = r#"#issues title:"  fix   typo " $id"#;
+    let sql = |title: &str| {
+        [
+            r#"SELECT"#,
+            r#"  "issues"."id""#,
+            r#"FROM "issues""#,
+            r#"WHERE"#,
+            &format!(r#"  "issues"."title" = '{title}';"#),
+        ]
+        .join("\n")
+    };
+
+    assert_eq!(
+        compile(StringWhitespace::Preserve, input),
+        sql("  fix   typo ")
+    );
+    assert_eq!(compile(StringWhitespace::Trim, input), sql("fix   typo"));
+    assert_eq!(compile(StringWhitespace::Collapse, input), sql("fix typo"));
+}
@@ -0,0 +1,69 @@
+#[test]
+fn test_tree_debug() {
+    use std::sync::{Arc, Mutex};
+
+    use crate::options::Options;
+    use crate::sql_tree::{Select, SqlTreeTransform};
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    /// Keeps the outline of the tree it's given
+    struct Capture(Arc<Mutex<String>>);
+
+    impl SqlTreeTransform for Capture {
+        fn transform(&self, select: &mut Select) -> Result<(), String> {
+            *self.0.lock().unwrap() = select.pretty_debug();
+            Ok(())
+        }
+    }
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let outline = Arc::new(Mutex::new(String::new()));
+    let options = Options {
+        sql_tree_transforms: vec![Box::new(Capture(outline.clone()))],
+        ..Options::default()
+    };
+    let compiler = Compiler::new(&schema_json, options).unwrap();
+    compiler
+        .compile("#issues ++#comments $id $#assignments.id%count->assignees".to_owned())
+        .unwrap();
+    assert_eq!(
+        *outline.lock().unwrap(),
+        [
+            r#"Select"#,
+            r#"  base table: issues"#,
+            r#"  columns:"#,
+            r#"    "issues"."id""#,
+            r#"    "cte0"."v1" AS assignees"#,
+            r#"  ctes:"#,
+            r#"    cte0 (AggregateValue)"#,
+            r#"      join column: id"#,
+            r#"      annotation: aggregation of assignments"#,
+            r#"      Select"#,
+            r#"        base table: assignments"#,
+            r#"        columns:"#,
+            r#"          "assignments"."issue" AS pk"#,
+            r#"          count("assignments"."id") AS v1"#,
+            r#"        grouping:"#,
+            r#"          "assignments"."issue""#,
+            r#"    cte1 (Inclusion)"#,
+            r#"      join column: id"#,
+            r#"      annotation: presence of comments"#,
+            r#"      Select"#,
+            r#"        base table: comments"#,
+            r#"        columns:"#,
+            r#"          "comments"."issue" AS pk"#,
+            r#"        grouping:"#,
+            r#"          "comments"."issue""#,
+            r#"  joins:"#,
+            r#"    Inner cte cte1 AS cte1"#,
+            r#"      on: "issues"."id" = "cte1"."pk""#,
+            r#"      annotation: presence of comments"#,
+            r#"    LeftOuter cte cte0 AS cte0"#,
+            r#"      on: "issues"."id" = "cte0"."pk""#,
+            r#"      annotation: aggregation of assignments"#,
+        ]
+        .join("\n")
+    );
+}
@@ -0,0 +1,36 @@
+#[test]
+fn test_unconditioned_joins() {
+    use crate::options::Options;
+    use crate::sql_tree::{Select, SqlExpr, SqlTreeTransform};
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    /// Simulates a bug which leaves a join without its conditions
+    struct DropJoinConditions;
+
+    impl SqlTreeTransform for DropJoinConditions {
+        fn transform(&self, select: &mut Select) -> Result<(), String> {
+            for join in select.joins.iter_mut() {
+                join.conditions = SqlExpr::empty();
+            }
+            Ok(())
+        }
+    }
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let options = Options {
+        sql_tree_transforms: vec![Box::new(DropJoinConditions)],
+        ..Options::default()
+    };
+    let compiler = Compiler::new(&schema_json, options).unwrap();
+    assert_eq!(
+        compiler.compile("#issues $id $author.username".to_owned()),
+        Err(
+            "The join of `users` has no condition, so it would produce a cartesian product."
+                .to_owned()
+        )
+    );
+    // Explicit cross joins have no conditions by design
+    assert!(compiler.compile("#issues * #users $id".to_owned()).is_ok());
+}
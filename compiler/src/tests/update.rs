@@ -0,0 +1,76 @@
+#[test]
+fn test_update() {
+    use crate::options::Options;
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("shop_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    let update = |assignments: &[(&str, &str)], conditions: &str| {
+        compiler.compile_update("customers", assignments, conditions)
+    };
+
+    assert_eq!(
+        update(&[("is_vip", "#orders.amount%sum:>1000")], "++#orders"),
+        Ok([
+            "UPDATE \"customers\"",
+            "SET",
+            "  \"is_vip\" = (",
+            "    SELECT",
+            "      sum(\"orders\".\"amount\") AS \"v1\"",
+            "    FROM \"orders\"",
+            "    WHERE",
+            "      \"orders\".\"customer\" = \"customers\".\"id\"",
+            "  ) > 1000",
+            "WHERE",
+            "  EXISTS (",
+            "    SELECT",
+            "      1",
+            "    FROM \"orders\"",
+            "    WHERE",
+            "      \"orders\".\"customer\" = \"customers\".\"id\"",
+            "  );",
+        ]
+        .join("\n"))
+    );
+    assert_eq!(
+        update(&[("name", "\"Anonymous\""), ("is_vip", "@false")], ""),
+        Ok([
+            "UPDATE \"customers\"",
+            "SET",
+            "  \"name\" = 'Anonymous',",
+            "  \"is_vip\" = FALSE;",
+        ]
+        .join("\n"))
+    );
+    assert_eq!(
+        update(&[], "id:1"),
+        Err("An update must assign a value to at least one column.".to_owned())
+    );
+    assert_eq!(
+        update(&[("vip", "@true")], ""),
+        Err("Column `vip` not found within table `customers`.".to_owned())
+    );
+    assert_eq!(
+        update(&[("1", "@true")], ""),
+        Err("`1` is not a column which can be assigned a value.".to_owned())
+    );
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    assert_eq!(
+        compiler.compile_update("issues", &[("author.username", "\"alice\"")], ""),
+        Err(
+            "`author.username` belongs to a related record, which can't be updated through a join."
+                .to_owned()
+        )
+    );
+    assert_eq!(
+        compiler.compile_update("issues", &[("title", "author.username")], ""),
+        Err(
+            "This statement can't join other tables, so it can't refer to single related records."
+                .to_owned()
+        )
+    );
+}
@@ -0,0 +1,39 @@
+#[test]
+fn test_validate() {
+    use crate::options::Options;
+    use crate::Compiler;
+
+    use super::get_test_resource;
+
+    let schema_json = get_test_resource("issue_schema.json");
+    let compiler = Compiler::new(&schema_json, Options::default()).unwrap();
+    let validate = |input: &str| compiler.validate(input);
+
+    assert_eq!(
+        validate("#issues status:\"open\" $title $author.username"),
+        Ok(())
+    );
+    assert_eq!(
+        validate("#issues nope:1 $title $title^2"),
+        Err(vec![
+            "Column `nope` not found within table `issues`.".to_owned(),
+            "Both operands of `^` must be numeric.".to_owned(),
+        ])
+    );
+    // Later columns are checked alongside the valid ones before them
+    assert_eq!(
+        validate(r"#issues $status \g $title $id%count $nope"),
+        Err(vec![
+            "Column `title` must be grouped or contain an aggregate function because the query is grouped.".to_owned(),
+            "Column `nope` not found within table `issues`.".to_owned(),
+        ])
+    );
+    assert_eq!(
+        validate("#nope $title"),
+        Err(vec!["Base table `nope` does not exist.".to_owned()])
+    );
+    assert_eq!(
+        validate("#issues $"),
+        Err(vec!["Invalid querydown code".to_owned()])
+    );
+}
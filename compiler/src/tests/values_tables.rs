@@ -0,0 +1,20 @@
+#[test]
+fn test_values_table_row_length() {
+    use crate::options::Options;
+    use crate::Compiler;
+
+    let schema_json = r#"{
+        "tables": [
+            {
+                "name": "t",
+                "columns": [{ "name": "a" }, { "name": "b" }],
+                "values": [[1, 2], [3]]
+            }
+        ],
+        "links": []
+    }"#;
+    assert_eq!(
+        Compiler::new(schema_json, Options::default()).err(),
+        Some("Each row of values in table `t` must have 2 values, one for each column.".to_owned())
+    );
+}
@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 fn ascii_alphanumeric(s: &str) -> impl Iterator<Item = u8> + '_ {
     s.chars()
         .filter(char::is_ascii_alphanumeric)
@@ -34,34 +32,34 @@ fn flex_eq(str_a: &str, str_b: &str) -> bool {
     )
 }
 
-pub trait FlexMap<T> {
-    fn flex_get(&self, key: &str) -> Option<&T>;
-}
+/// Find the name which matches the search key exactly, or else the only name which matches it
+/// flexibly, ignoring case and punctuation
+pub fn flex_find<'n>(
+    names: impl IntoIterator<Item = &'n str>,
+    search_key: &str,
+) -> Option<&'n str> {
+    let mut winning_name: Option<&str> = None;
+    let mut is_ambiguous = false;
 
-impl<T> FlexMap<T> for HashMap<String, T> {
-    fn flex_get(&self, search_key: &str) -> Option<&T> {
-        let mut winning_value: Option<&T> = self.get(search_key);
-        if winning_value.is_some() {
-            return winning_value;
+    for name in names {
+        if name == search_key {
+            return Some(name);
         }
-
-        for (key, value) in self.iter() {
-            if !flex_eq(key, search_key) {
-                // Ignore entries that do not match the search key
-                continue;
-            }
-            if winning_value.is_none() {
-                // If this is the first match we have found, we store it
-                winning_value = Some(value);
-            } else {
-                // If we have duplicate matches then we return None because we want to ensure that
-                // the map is unambiguous
-                return None;
-            }
+        if !flex_eq(name, search_key) {
+            // Ignore names that do not match the search key
+            continue;
+        }
+        if winning_name.is_none() {
+            // If this is the first match we have found, we store it
+            winning_name = Some(name);
+        } else {
+            // If we have duplicate matches then we return None because we want to ensure that
+            // the names are unambiguous. An exact match may still come later though.
+            is_ambiguous = true;
         }
-
-        winning_value
     }
+
+    winning_name.filter(|_| !is_ambiguous)
 }
 
 #[cfg(test)]
@@ -90,25 +88,21 @@ mod tests {
     }
 
     #[test]
-    fn test_flex_map() {
-        let mut map = HashMap::new();
-        map.insert("one".to_string(), 1);
-        map.insert("one two".to_string(), 12);
-        map.insert("Two three".to_string(), 23);
-        map.insert("two_three".to_string(), 23);
-        map.insert("FIVE SIX".to_string(), 56);
+    fn test_flex_find() {
+        let names = ["one", "one two", "Two three", "two_three", "FIVE SIX"];
+        let find = |search_key: &str| flex_find(names, search_key);
 
-        assert_eq!(map.flex_get("one"), Some(&1));
-        assert_eq!(map.flex_get("ONE"), Some(&1));
+        assert_eq!(find("one"), Some("one"));
+        assert_eq!(find("ONE"), Some("one"));
 
-        assert_eq!(map.flex_get("one two"), Some(&12));
-        assert_eq!(map.flex_get("one_two"), Some(&12));
-        assert_eq!(map.flex_get("oneTwo"), Some(&12));
+        assert_eq!(find("one two"), Some("one two"));
+        assert_eq!(find("one_two"), Some("one two"));
+        assert_eq!(find("oneTwo"), Some("one two"));
 
-        assert_eq!(map.flex_get("Two three"), Some(&23));
-        assert_eq!(map.flex_get("two_three"), Some(&23));
-        assert_eq!(map.flex_get("twoThree"), None);
+        assert_eq!(find("Two three"), Some("Two three"));
+        assert_eq!(find("two_three"), Some("two_three"));
+        assert_eq!(find("twoThree"), None);
 
-        assert_eq!(map.flex_get("nope"), None);
+        assert_eq!(find("nope"), None);
     }
 }
@@ -1,12 +1,60 @@
+pub use crate::position::Span;
 use crate::tokens::LITERAL_NULL;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Query {
-    pub base_table: String,
+    /// When `None`, the query can only select values which don't depend on any table
+    pub base_table: Option<String>,
+    /// Additional tables which are cross joined to the base table, in order of appearance
+    pub cross_joined_tables: Vec<String>,
     pub transformations: Vec<Transformation>,
 }
 
-#[derive(Debug, PartialEq, Default)]
+/// Builder methods for constructing a query in Rust instead of parsing Querydown code. They
+/// produce the same AST that the parser produces for the equivalent code, and they perform no
+/// validation. Errors are reported when the query is compiled.
+impl Query {
+    /// A query of the given base table, with no conditions and no result columns
+    pub fn new(base_table: &str) -> Self {
+        Self {
+            base_table: Some(base_table.to_string()),
+            cross_joined_tables: vec![],
+            transformations: vec![Transformation::default()],
+        }
+    }
+
+    /// Cross join another table to the base table
+    pub fn cross_join(mut self, table: &str) -> Self {
+        self.cross_joined_tables.push(table.to_string());
+        self
+    }
+
+    /// Add a condition, which is combined with any existing conditions via AND
+    pub fn filter(mut self, condition: Expr) -> Self {
+        self.last_transformation()
+            .conditions
+            .entries
+            .push(condition);
+        self
+    }
+
+    /// Add a result column. Sorting and grouping are specified on the column itself.
+    pub fn column(mut self, column: impl Into<ColumnSpec>) -> Self {
+        let statement = ResultColumnStatement::Spec(column.into());
+        self.last_transformation().result_columns.push(statement);
+        self
+    }
+
+    fn last_transformation(&mut self) -> &mut Transformation {
+        if self.transformations.is_empty() {
+            self.transformations.push(Transformation::default());
+        }
+        // This unwrap is safe because we just ensured that there is at least one transformation
+        self.transformations.last_mut().unwrap()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Transformation {
     pub conditions: ConditionSet,
     pub result_columns: Vec<ResultColumnStatement>,
@@ -14,30 +62,65 @@ pub struct Transformation {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
-    Number(String),
-    Date(Date),
-    Duration(Duration),
-    String(String),
+    Number(String, Span),
+    Date(Date, Span),
+    Duration(Duration, Span),
+    String(String, Span),
+    Bytes(Vec<u8>, Span),
+    /// A UUID in canonical form, with lowercase hex digits
+    Uuid(String, Span),
     Variable(String),
-    Path(Vec<PathPart>),
+    /// A value supplied when the compiled statement is executed
+    Placeholder(Placeholder),
+    /// A chain of links through related tables, which may end with a column
+    Path(Vec<PathPart>, Span),
     ConditionSet(ConditionSet),
     HasQuantity(HasQuantity),
     Call(Call),
     Product(Box<Expr>, Box<Expr>),
     Quotient(Box<Expr>, Box<Expr>),
+    Power(Box<Expr>, Box<Expr>),
     Sum(Box<Expr>, Box<Expr>),
     Difference(Box<Expr>, Box<Expr>),
     Comparison(Box<Comparison>),
+    /// The rows over which a window function is computed, to which the function is piped
+    Window(Window),
 }
 
 impl Expr {
+    /// A path to a column of the base table
+    pub fn column(name: &str) -> Self {
+        Expr::path(vec![PathPart::Column(name.to_string())])
+    }
+
+    pub fn path(parts: Vec<PathPart>) -> Self {
+        Expr::Path(parts, Span::Unknown)
+    }
+
+    pub fn number(number: &str) -> Self {
+        Expr::Number(number.to_string(), Span::Unknown)
+    }
+
+    pub fn string(string: &str) -> Self {
+        Expr::String(string.to_string(), Span::Unknown)
+    }
+
+    /// Compare this expression to another, as in `a:b`
+    pub fn compare(self, operator: Operator, right: Expr) -> Self {
+        Expr::Comparison(Box::new(Comparison {
+            left: ComparisonSide::Expr(self),
+            operator,
+            right: ComparisonSide::Expr(right),
+        }))
+    }
+
     pub fn zero() -> Self {
-        Expr::Number("0".to_string())
+        Expr::number("0")
     }
 
     pub fn is_zero(&self) -> bool {
         match self {
-            Expr::Number(ref num) => num == "0",
+            Expr::Number(ref num, _) => num == "0",
             _ => false,
         }
     }
@@ -174,6 +257,13 @@ pub enum Operator {
     NLike,
     Match,
     NMatch,
+    /// Case-insensitive equality
+    IEq,
+    NIEq,
+    /// Three-valued logic test against `@true`, `@false`, or `@null`, which unlike equality never
+    /// yields NULL
+    Is,
+    IsNot,
 }
 
 #[derive(Debug, Clone, Default, PartialEq)]
@@ -213,6 +303,7 @@ pub enum Conjunction {
 pub struct HasQuantity {
     pub quantity: Quantity,
     pub path_parts: Vec<PathPart>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -221,12 +312,29 @@ pub enum Quantity {
     Zero,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placeholder {
+    pub name: String,
+    /// The name of the parameter's type, if annotated, e.g. `int` in `:min::int`
+    pub type_name: Option<String>,
+}
+
+impl Placeholder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            type_name: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Call {
     pub name: String,
     pub dimension: FunctionDimension,
     pub args: Vec<Expr>,
     pub syntax: CallSyntax,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -241,32 +349,118 @@ pub enum FunctionDimension {
     Aggregate,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    /// Columns flagged with `\p` partition the rows, and columns flagged with `\s` sort them
+    pub specs: Vec<ColumnSpec>,
+    pub frame: Option<WindowFrame>,
+}
+
+/// The rows within a partition to which a window function applies, as offsets from the current
+/// row. Negative offsets refer to preceding rows and positive offsets to following rows.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WindowFrame {
+    /// `None` for the first row of the partition
+    pub start: Option<i64>,
+    /// `None` for the last row of the partition
+    pub end: Option<i64>,
+    /// Rows to leave out of the frame even though they fall within its offsets
+    pub exclude: Option<FrameExclusion>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameExclusion {
+    /// Only the current row itself
+    CurrentRow,
+    /// The current row along with any rows that sort equally to it
+    Group,
+    /// Rows that sort equally to the current row, but not the current row itself
+    Ties,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ResultColumnStatement {
     Spec(ColumnSpec),
     Glob(ColumnGlob),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ColumnSpec {
     pub expr: Expr,
     pub alias: Option<String>,
     pub column_control: ColumnControl,
 }
 
-#[derive(Debug, PartialEq, Default)]
+impl ColumnSpec {
+    pub fn new(expr: Expr) -> Self {
+        Self {
+            expr,
+            alias: None,
+            column_control: ColumnControl::default(),
+        }
+    }
+
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.alias = Some(alias.to_string());
+        self
+    }
+
+    /// Sort by this column, after any columns sorted before it
+    pub fn sort(mut self, direction: SortDirection) -> Self {
+        self.column_control.sort = Some(SortSpec {
+            direction,
+            ..SortSpec::default()
+        });
+        self
+    }
+
+    /// Group by this column
+    pub fn group(mut self) -> Self {
+        self.column_control.group = Some(GroupSpec::default());
+        self
+    }
+}
+
+impl From<Expr> for ColumnSpec {
+    fn from(expr: Expr) -> Self {
+        Self::new(expr)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct ColumnControl {
     pub sort: Option<SortSpec>,
     pub group: Option<GroupSpec>,
     pub is_partition_by: bool,
     pub is_hidden: bool,
+    /// Rows with equal values in all such columns are reduced to the first row, as sorted
+    pub is_distinct_on: bool,
 }
 
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct GroupSpec {
     /// A GroupSpec without an ordinal means that we'd like to group by the column, but we want to
     /// infer the ordinality from the ColumnSpec's position within the ColumnLayout.
+    ///
+    /// With `GroupingKind::Sets`, the ordinal instead holds the grouping sets to which the column
+    /// belongs, one per digit.
     pub ordinal: Option<u32>,
+    pub kind: GroupingKind,
+}
+
+/// How the grouped columns of a query are combined to produce subtotal rows. All grouped columns
+/// within a query must use the same kind.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum GroupingKind {
+    /// One row per distinct combination of grouped values, with no subtotals
+    #[default]
+    Plain,
+    /// Subtotals for each prefix of the grouped columns, plus a grand total
+    Rollup,
+    /// Subtotals for every combination of the grouped columns, plus a grand total
+    Cube,
+    /// Subtotals for explicitly numbered sets of grouped columns
+    Sets,
 }
 
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -275,7 +469,8 @@ pub struct SortSpec {
     /// infer the ordinality from the ColumnSpec's position within the ColumnLayout.
     pub ordinal: Option<u32>,
     pub direction: SortDirection,
-    pub nulls_sort: NullsSort,
+    /// `None` when the Querydown code doesn't say where nulls go, leaving it up to the compiler
+    pub nulls_sort: Option<NullsSort>,
 }
 
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -292,7 +487,7 @@ pub enum SortDirection {
     Desc,
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct ColumnGlob {
     pub head: Vec<PathPart>,
     pub specs: Vec<ColumnSpec>,
@@ -0,0 +1,142 @@
+use chumsky::{error::Simple, Parser};
+
+use crate::ast::Query;
+use crate::parser::{check_nesting_depth, query};
+use crate::position::Position;
+use crate::tokens::*;
+use crate::ParseOptions;
+
+/// The result of parsing input which may be incomplete, e.g. while a user is still typing it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialParse {
+    /// The query formed by the longest leading portion of the input which parses successfully.
+    /// When the whole input is valid, this is the whole query.
+    pub query: Option<Query>,
+    /// Where and why parsing stopped, or `None` if the whole input is valid
+    pub completion_context: Option<CompletionContext>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletionContext {
+    /// The index of the character at which parsing stopped
+    pub offset: usize,
+    pub expectation: Expectation,
+}
+
+impl CompletionContext {
+    /// The line and column at which parsing stopped within the given input
+    pub fn position(&self, input: &str, tab_width: usize) -> Position {
+        Position::locate(input, self.offset, tab_width)
+    }
+}
+
+/// The kind of code which could continue the input at the point where parsing stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expectation {
+    /// The name of a table, e.g. after `#`
+    TableName,
+    /// Any value, such as a literal or a path to a column, e.g. after `:`
+    Value,
+    /// The name of a function, e.g. after `|`
+    FunctionName,
+    /// The alias of a result column, e.g. after `->`
+    Alias,
+    /// Something else, e.g. a closing bracket
+    Other,
+}
+
+impl Expectation {
+    fn from_label(label: Option<&str>) -> Self {
+        match label {
+            Some(LABEL_TABLE_NAME) => Expectation::TableName,
+            Some(LABEL_VALUE) => Expectation::Value,
+            Some(LABEL_FUNCTION_NAME) => Expectation::FunctionName,
+            Some(LABEL_ALIAS) => Expectation::Alias,
+            _ => Expectation::Other,
+        }
+    }
+}
+
+/// Parse a query which may be incomplete, for tooling like autocompletion in an editor. Instead of
+/// failing on invalid input, this describes what was expected where parsing stopped.
+pub fn parse_partial(input: &str) -> Result<PartialParse, String> {
+    check_nesting_depth(input)?;
+    let parser = query(ParseOptions::default());
+    let errors = match parser.parse(input) {
+        Ok(query) => {
+            return Ok(PartialParse {
+                query: Some(query),
+                completion_context: None,
+            })
+        }
+        Err(errors) => errors,
+    };
+    // The error which got furthest through the input is the one the user is most likely typing
+    let context = errors
+        .into_iter()
+        .max_by_key(|e| e.span().start)
+        .map(|e: Simple<char>| CompletionContext {
+            offset: e.span().start,
+            expectation: Expectation::from_label(e.label()),
+        });
+    let chars = input.chars().collect::<Vec<_>>();
+    let end = context.as_ref().map_or(chars.len(), |c| c.offset);
+    // Back up to each preceding word boundary until we find a prefix which forms a valid query
+    let query = (1..end)
+        .rev()
+        .filter(|&i| chars[i].is_whitespace())
+        .find_map(|i| parser.parse(chars[..i].iter().collect::<String>()).ok());
+    Ok(PartialParse {
+        query,
+        completion_context: context,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(input: &str) -> (Option<Query>, Option<(usize, Expectation)>) {
+        let partial = parse_partial(input).unwrap();
+        let context = partial
+            .completion_context
+            .map(|c| (c.offset, c.expectation));
+        (partial.query, context)
+    }
+
+    #[test]
+    fn test_parse_partial() {
+        assert_eq!(
+            context("#users name:"),
+            (Some(Query::new("users")), Some((12, Expectation::Value)))
+        );
+        assert_eq!(
+            context("#users $"),
+            (Some(Query::new("users")), Some((8, Expectation::Value)))
+        );
+        assert_eq!(
+            context("#users #"),
+            (Some(Query::new("users")), Some((8, Expectation::TableName)))
+        );
+        assert_eq!(context("#").1, Some((1, Expectation::TableName)));
+        assert_eq!(
+            context("#users $name|").1,
+            Some((13, Expectation::FunctionName))
+        );
+        assert_eq!(context("#users $name->").1, Some((14, Expectation::Alias)));
+        assert_eq!(context("#users"), (Some(Query::new("users")), None));
+    }
+
+    #[test]
+    fn test_completion_position() {
+        let input = "#issues\n  title:\"Ünïcödé\"\n\t$id $author.";
+        let context = parse_partial(input).unwrap().completion_context.unwrap();
+        assert_eq!(
+            context.position(input, 4),
+            Position {
+                line: 3,
+                column: 17
+            }
+        );
+    }
+}
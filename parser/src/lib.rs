@@ -1,14 +1,238 @@
+mod completion;
+mod options;
 mod parser;
+mod position;
+mod spans;
 
 pub mod ast;
 pub mod tokens;
 
-use chumsky::Parser;
-use parser::query;
+use chumsky::{prelude::end, text::TextParser, Parser};
+use parser::{check_nesting_depth, expr, query, top_level_condition_set};
+use spans::locate_spans;
+
+pub use completion::{parse_partial, CompletionContext, Expectation, PartialParse};
+pub use options::{ParseOptions, StringWhitespace};
+pub use parser::MAX_NESTING_DEPTH;
+pub use position::Position;
 
 pub fn parse(input: &str) -> Result<ast::Query, String> {
-    query()
+    parse_with_options(input, ParseOptions::default())
+}
+
+pub fn parse_with_options(input: &str, options: ParseOptions) -> Result<ast::Query, String> {
+    check_nesting_depth(input)?;
+    let mut query = query(options)
+        .parse(input)
+        // TODO_ERR improve error handling
+        .map_err(|_| "Invalid querydown code".to_string())?;
+    locate_spans(&mut query, input, options.tab_width);
+    Ok(query)
+}
+
+/// Parse a set of conditions on their own, without a base table or result columns
+pub fn parse_conditions(input: &str) -> Result<ast::ConditionSet, String> {
+    parse_conditions_with_options(input, ParseOptions::default())
+}
+
+pub fn parse_conditions_with_options(
+    input: &str,
+    options: ParseOptions,
+) -> Result<ast::ConditionSet, String> {
+    check_nesting_depth(input)?;
+    let mut condition_set = top_level_condition_set(options)
+        .then_ignore(end())
         .parse(input)
         // TODO_ERR improve error handling
-        .map_err(|_| "Invalid querydown code".to_string())
+        .map_err(|_| "Invalid querydown code".to_string())?;
+    locate_spans(&mut condition_set, input, options.tab_width);
+    Ok(condition_set)
+}
+
+/// Parse a single expression, e.g. a value to be assigned to a column
+pub fn parse_expr(input: &str) -> Result<ast::Expr, String> {
+    parse_expr_with_options(input, ParseOptions::default())
+}
+
+pub fn parse_expr_with_options(input: &str, options: ParseOptions) -> Result<ast::Expr, String> {
+    check_nesting_depth(input)?;
+    let mut expr = expr(options)
+        .padded()
+        .then_ignore(end())
+        .parse(input)
+        // TODO_ERR improve error handling
+        .map_err(|_| "Invalid querydown code".to_string())?;
+    locate_spans(&mut expr, input, options.tab_width);
+    Ok(expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_builder() {
+        use ast::*;
+
+        let built = Query::new("issues")
+            .filter(Expr::column("status").compare(Operator::Eq, Expr::string("open")))
+            .column(ColumnSpec::new(Expr::column("title")).sort(SortDirection::Desc))
+            .column(Expr::column("id"));
+        assert_eq!(parse(r#"#issues status:"open" $title \sd $id"#), Ok(built));
+        assert_eq!(parse("#issues"), Ok(Query::new("issues")));
+    }
+
+    #[test]
+    fn test_parse_expr() {
+        use ast::*;
+
+        assert_eq!(
+            parse_expr(" status:\"open\" "),
+            Ok(Expr::column("status").compare(Operator::Eq, Expr::string("open")))
+        );
+        assert!(parse_expr("status:\"open\" id:1").is_err());
+    }
+
+    #[test]
+    fn test_function_call_syntax() {
+        use ast::*;
+
+        /// Mark every call as piped, since that's the only way the two syntaxes differ
+        fn piped(expr: Expr) -> Expr {
+            match expr {
+                Expr::Call(call) => Expr::Call(Call {
+                    args: call.args.into_iter().map(piped).collect(),
+                    syntax: CallSyntax::Piped,
+                    ..call
+                }),
+                _ => expr,
+            }
+        }
+        let options = ParseOptions {
+            function_call_syntax: true,
+            ..ParseOptions::default()
+        };
+        let p = |s: &str| parse_expr_with_options(s, options);
+
+        let nested = p("upper(trim(name))").unwrap();
+        let Expr::Call(call) = &nested else {
+            panic!("expected a call");
+        };
+        assert_eq!(call.syntax, CallSyntax::Standalone);
+        assert_eq!(piped(nested), p("name | trim | upper").unwrap());
+        assert_eq!(
+            piped(p("round( price 2 )").unwrap()),
+            p("price|round(2)").unwrap()
+        );
+        assert_eq!(
+            p("now()"),
+            Ok(Expr::Call(Call {
+                span: Span::Unknown,
+                name: "now".to_string(),
+                dimension: FunctionDimension::Scalar,
+                args: vec![],
+                syntax: CallSyntax::Standalone,
+            }))
+        );
+        assert_eq!(p("upper(name)|trim"), p("(upper(name))|trim"));
+
+        // Without the option, a column followed by a parenthetical is two expressions
+        assert!(parse_expr("upper(name)").is_err());
+        assert_eq!(parse_conditions("a(b)").unwrap().entries.len(), 2);
+        assert_eq!(
+            parse_conditions_with_options("a(b)", options)
+                .unwrap()
+                .entries
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_string_whitespace() {
+        use ast::*;
+
+        let p = |s: &str, string_whitespace: StringWhitespace| {
+            let options = ParseOptions {
+                string_whitespace,
+                ..ParseOptions::default()
+            };
+            parse_expr_with_options(s, options).unwrap()
+        };
+        let input = "' big  red\tdog '";
+        let string = |s: &str| Expr::string(s);
+        assert_eq!(
+            p(input, StringWhitespace::Preserve),
+            string(" big  red\tdog ")
+        );
+        assert_eq!(p(input, StringWhitespace::Trim), string("big  red\tdog"));
+        assert_eq!(p(input, StringWhitespace::Collapse), string("big red dog"));
+        // Quoted identifiers keep their whitespace
+        assert_eq!(
+            p("`a  b`", StringWhitespace::Collapse),
+            Expr::column("a  b")
+        );
+    }
+
+    #[test]
+    fn test_spans() {
+        use ast::*;
+
+        let options = ParseOptions {
+            tab_width: 4,
+            ..ParseOptions::default()
+        };
+        let query = parse_with_options("#cafes\nname:\"ü\"\n\t$\"ü\"->x $price|round(2)", options);
+        let position = |line, column| Position { line, column };
+        let located = |span: &Span| (span.start().unwrap(), span.end().unwrap());
+        let transformation = &query.unwrap().transformations[0];
+        let columns = transformation
+            .result_columns
+            .iter()
+            .map(|statement| match statement {
+                ResultColumnStatement::Spec(spec) => &spec.expr,
+                ResultColumnStatement::Glob(_) => panic!("expected a column spec"),
+            })
+            .collect::<Vec<_>>();
+
+        let Expr::Comparison(comparison) = &transformation.conditions.entries[0] else {
+            panic!("expected a comparison");
+        };
+        let ComparisonSide::Expr(Expr::Path(_, span)) = &comparison.left else {
+            panic!("expected a path");
+        };
+        assert_eq!(located(span), (position(2, 1), position(2, 5)));
+
+        // The tab advances to the next tab stop and each multi-byte character is one column
+        let Expr::String(_, span) = columns[0] else {
+            panic!("expected a string");
+        };
+        assert_eq!(located(span), (position(3, 6), position(3, 9)));
+        let Expr::Call(call) = columns[1] else {
+            panic!("expected a call");
+        };
+        assert_eq!(located(&call.span), (position(3, 14), position(3, 28)));
+        let [Expr::Path(_, price_span), Expr::Number(_, number_span)] = call.args.as_slice() else {
+            panic!("expected a path and a number");
+        };
+        assert_eq!(located(price_span), (position(3, 14), position(3, 19)));
+        assert_eq!(located(number_span), (position(3, 26), position(3, 27)));
+
+        // Nodes which are built rather than parsed have no span
+        assert_eq!(Expr::number("2"), parse_expr("2").unwrap());
+        let Expr::Number(_, span) = Expr::number("2") else {
+            unreachable!()
+        };
+        assert_eq!(span.start(), None);
+    }
+
+    #[test]
+    fn test_deeply_nested_input() {
+        let depth = 10_000;
+        let input = format!("#foo ${}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(parse(&input).unwrap_err().contains("nested too deeply"));
+        assert!(parse_conditions(&input[5..])
+            .unwrap_err()
+            .contains("nested too deeply"));
+    }
 }
@@ -0,0 +1,38 @@
+/// Settings which change how the parser reads its input
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// When true, scalar functions may also be called by name with their arguments in
+    /// parentheses, e.g. `upper(trim(name))` instead of `name|trim|upper`. This is off by default
+    /// because it changes the meaning of a column followed by a parenthetical, e.g. `{a(b)}`.
+    pub function_call_syntax: bool,
+    /// How whitespace within string literals is normalized. This changes the values of the
+    /// literals themselves, so it's off by default.
+    pub string_whitespace: StringWhitespace,
+    /// The number of columns between tab stops, for the positions within the spans of the syntax
+    /// tree. Zero counts each tab as a single column.
+    pub tab_width: usize,
+}
+
+/// How whitespace within string literals is normalized as they're parsed. Identifiers and other
+/// quoted names are never affected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StringWhitespace {
+    /// Keep string literals exactly as written
+    #[default]
+    Preserve,
+    /// Remove whitespace from the start and end of each string literal
+    Trim,
+    /// Trim, and also replace each run of whitespace within the string literal with a single
+    /// space, e.g. `"  big   red dog "` becomes `"big red dog"`
+    Collapse,
+}
+
+impl StringWhitespace {
+    pub fn apply(self, s: String) -> String {
+        match self {
+            Self::Preserve => s,
+            Self::Trim => s.trim().to_owned(),
+            Self::Collapse => s.split_whitespace().collect::<Vec<_>>().join(" "),
+        }
+    }
+}
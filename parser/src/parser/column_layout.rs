@@ -2,32 +2,33 @@ use chumsky::{prelude::*, text::*};
 
 use crate::ast::*;
 use crate::tokens::*;
+use crate::ParseOptions;
 
 use super::expr::{expr, path_to_one};
 use super::utils::*;
 
-pub fn result_columns() -> impl Psr<Vec<ResultColumnStatement>> {
-    result_column_statement()
+pub fn result_columns(options: ParseOptions) -> impl Psr<Vec<ResultColumnStatement>> {
+    result_column_statement(options)
         .then_ignore(whitespace())
         .repeated()
 }
 
-fn result_column_statement() -> impl Psr<ResultColumnStatement> {
+fn result_column_statement(options: ParseOptions) -> impl Psr<ResultColumnStatement> {
     just(COLUMN_SPEC_PREFIX)
         .then(whitespace())
         .ignore_then(choice((
-            column_glob().map(ResultColumnStatement::Glob),
-            column_spec().map(ResultColumnStatement::Spec),
+            column_glob(options).map(ResultColumnStatement::Glob),
+            column_spec(options).map(ResultColumnStatement::Spec),
         )))
 }
 
-fn column_glob() -> impl Psr<ColumnGlob> {
+fn column_glob(options: ParseOptions) -> impl Psr<ColumnGlob> {
     let head = path_to_one()
         .then_ignore(just(PATH_SEPARATOR))
         .or_not()
         .map(|p| p.unwrap_or_default());
 
-    let specs = column_spec()
+    let specs = column_spec(options)
         .padded()
         .repeated()
         .delimited_by(
@@ -42,29 +43,33 @@ fn column_glob() -> impl Psr<ColumnGlob> {
         .map(|(head, specs)| ColumnGlob { head, specs })
 }
 
-fn column_spec() -> impl Psr<ColumnSpec> {
-    expr()
-        .then(
-            whitespace()
-                .then(just(COLUMN_ALIAS_PREFIX))
-                .then(whitespace())
-                .ignore_then(db_identifier())
-                .or_not(),
-        )
-        .then(
-            whitespace()
-                .ignore_then(
-                    column_control()
-                        .or_not()
-                        .map(|v| v.unwrap_or(ColumnControl::default())),
-                )
-                .or_not(),
-        )
-        .map(|((expr, alias), ctrl)| ColumnSpec {
-            expr,
-            alias,
-            column_control: ctrl.unwrap_or_default(),
-        })
+fn column_spec(options: ParseOptions) -> impl Psr<ColumnSpec> {
+    column_spec_of(expr(options))
+}
+
+/// A column spec whose expression is parsed by `e`, for use within expressions themselves
+pub fn column_spec_of(e: impl Psr<Expr>) -> impl Psr<ColumnSpec> {
+    e.then(
+        whitespace()
+            .then(just(COLUMN_ALIAS_PREFIX))
+            .then(whitespace())
+            .ignore_then(db_identifier().labelled(LABEL_ALIAS))
+            .or_not(),
+    )
+    .then(
+        whitespace()
+            .ignore_then(
+                column_control()
+                    .or_not()
+                    .map(|v| v.unwrap_or(ColumnControl::default())),
+            )
+            .or_not(),
+    )
+    .map(|((expr, alias), ctrl)| ColumnSpec {
+        expr,
+        alias,
+        column_control: ctrl.unwrap_or_default(),
+    })
 }
 
 fn column_control() -> impl Psr<ColumnControl> {
@@ -74,9 +79,11 @@ fn column_control() -> impl Psr<ColumnControl> {
         Desc,
         Ordinal(u32),
         Group,
-        NullsFirst,
+        GroupingKind(GroupingKind),
+        Nulls(NullsSort),
         Hide,
         Partition,
+        DistinctOn,
     }
     enum Context {
         Sorting,
@@ -89,20 +96,27 @@ fn column_control() -> impl Psr<ColumnControl> {
         // TODO_ERR handle error if number is too large
         int(10).from_str().unwrapped().map(|v| Flag::Ordinal(v)),
         just(COLUMN_CONTROL_FLAG_GROUP).to(Flag::Group),
-        just(COLUMN_CONTROL_FLAG_NULLS_FIRST).to(Flag::NullsFirst),
+        just(COLUMN_CONTROL_FLAG_ROLLUP).to(Flag::GroupingKind(GroupingKind::Rollup)),
+        just(COLUMN_CONTROL_FLAG_CUBE).to(Flag::GroupingKind(GroupingKind::Cube)),
+        just(COLUMN_CONTROL_FLAG_GROUPING_SETS).to(Flag::GroupingKind(GroupingKind::Sets)),
+        just(COLUMN_CONTROL_FLAG_NULLS_FIRST).to(Flag::Nulls(NullsSort::First)),
+        just(COLUMN_CONTROL_FLAG_NULLS_LAST).to(Flag::Nulls(NullsSort::Last)),
         just(COLUMN_CONTROL_FLAG_HIDE).to(Flag::Hide),
         just(COLUMN_CONTROL_FLAG_PARTITION).to(Flag::Partition),
+        just(COLUMN_CONTROL_FLAG_DISTINCT_ON).to(Flag::DistinctOn),
     ));
     just(COLUMN_CONTROL_FLAGS_PREFIX).ignore_then(flag.repeated().at_least(1).map(|flags| {
         let mut context = Context::General;
         let mut sort = false;
         let mut sort_ordinal: Option<u32> = None;
         let mut sort_direction = SortDirection::default();
-        let mut sort_nulls = NullsSort::default();
+        let mut sort_nulls: Option<NullsSort> = None;
         let mut group = false;
         let mut group_ordinal: Option<u32> = None;
+        let mut grouping_kind = GroupingKind::default();
         let mut partition = false;
         let mut hide = false;
+        let mut distinct_on = false;
         let mut handle_ordinal = |o: u32, c: &Context| match c {
             Context::Sorting => sort_ordinal = Some(o),
             Context::Grouping => group_ordinal = Some(o),
@@ -120,9 +134,11 @@ fn column_control() -> impl Psr<ColumnControl> {
                     group = true;
                     context = Context::Grouping;
                 }
-                Flag::NullsFirst => sort_nulls = NullsSort::First,
+                Flag::GroupingKind(kind) => grouping_kind = kind,
+                Flag::Nulls(nulls_sort) => sort_nulls = Some(nulls_sort),
                 Flag::Hide => hide = true,
                 Flag::Partition => partition = true,
+                Flag::DistinctOn => distinct_on = true,
             }
         }
         ColumnControl {
@@ -138,12 +154,14 @@ fn column_control() -> impl Psr<ColumnControl> {
             group: if group {
                 Some(GroupSpec {
                     ordinal: group_ordinal,
+                    kind: grouping_kind,
                 })
             } else {
                 None
             },
             is_partition_by: partition,
             is_hidden: hide,
+            is_distinct_on: distinct_on,
         }
     }))
 }
@@ -160,11 +178,54 @@ mod tests {
                 sort: Some(SortSpec {
                     ordinal: Some(1),
                     direction: SortDirection::Desc,
-                    nulls_sort: NullsSort::default(),
+                    nulls_sort: None,
                 }),
                 group: None,
                 is_partition_by: false,
                 is_hidden: false,
+                is_distinct_on: false,
+            })
+        );
+        assert_eq!(
+            column_control().parse(r"\sdl"),
+            Ok(ColumnControl {
+                sort: Some(SortSpec {
+                    ordinal: None,
+                    direction: SortDirection::Desc,
+                    nulls_sort: Some(NullsSort::Last),
+                }),
+                ..ColumnControl::default()
+            })
+        );
+        assert_eq!(
+            column_control().parse(r"\gr2"),
+            Ok(ColumnControl {
+                group: Some(GroupSpec {
+                    ordinal: Some(2),
+                    kind: GroupingKind::Rollup,
+                }),
+                ..ColumnControl::default()
+            })
+        );
+        assert_eq!(
+            column_control().parse(r"\ge12"),
+            Ok(ColumnControl {
+                group: Some(GroupSpec {
+                    ordinal: Some(12),
+                    kind: GroupingKind::Sets,
+                }),
+                ..ColumnControl::default()
+            })
+        );
+        assert_eq!(
+            column_control().parse(r"\us1"),
+            Ok(ColumnControl {
+                sort: Some(SortSpec {
+                    ordinal: Some(1),
+                    ..SortSpec::default()
+                }),
+                is_distinct_on: true,
+                ..ColumnControl::default()
             })
         );
     }
@@ -172,27 +233,28 @@ mod tests {
     #[test]
     fn test_parse_column_spec() {
         assert_eq!(
-            column_spec().parse("8"),
+            column_spec(ParseOptions::default()).parse("8"),
             Ok(ColumnSpec {
                 column_control: ColumnControl::default(),
-                expr: Expr::Number("8".to_string()),
+                expr: Expr::number("8"),
                 alias: None,
             })
         );
         assert_eq!(
-            column_spec().parse(r"foo->bar\s1d"),
+            column_spec(ParseOptions::default()).parse(r"foo->bar\s1d"),
             Ok(ColumnSpec {
                 column_control: ColumnControl {
                     sort: Some(SortSpec {
                         ordinal: Some(1),
                         direction: SortDirection::Desc,
-                        nulls_sort: NullsSort::default(),
+                        nulls_sort: None,
                     }),
                     group: None,
                     is_partition_by: false,
                     is_hidden: false,
+                    is_distinct_on: false,
                 },
-                expr: Expr::Path(vec![PathPart::Column("foo".to_string())]),
+                expr: Expr::path(vec![PathPart::Column("foo".to_string())]),
                 alias: Some("bar".to_string()),
             })
         );
@@ -201,7 +263,7 @@ mod tests {
     #[test]
     fn test_parse_result_columns() {
         assert_eq!(
-            result_columns().parse(r"$* $a.b.*(c \h d\s) $foo $bar->B \g"),
+            result_columns(ParseOptions::default()).parse(r"$* $a.b.*(c \h d\s) $foo $bar->B \g"),
             Ok(vec![
                 ResultColumnStatement::Glob(ColumnGlob::default()),
                 ResultColumnStatement::Glob(ColumnGlob {
@@ -216,8 +278,9 @@ mod tests {
                                 group: None,
                                 is_partition_by: false,
                                 is_hidden: true,
+                                is_distinct_on: false,
                             },
-                            expr: Expr::Path(vec![PathPart::Column("c".to_string())]),
+                            expr: Expr::path(vec![PathPart::Column("c".to_string())]),
                             alias: None,
                         },
                         ColumnSpec {
@@ -225,30 +288,32 @@ mod tests {
                                 sort: Some(SortSpec {
                                     ordinal: None,
                                     direction: SortDirection::Asc,
-                                    nulls_sort: NullsSort::default(),
+                                    nulls_sort: None,
                                 }),
                                 group: None,
                                 is_partition_by: false,
                                 is_hidden: false,
+                                is_distinct_on: false,
                             },
-                            expr: Expr::Path(vec![PathPart::Column("d".to_string())]),
+                            expr: Expr::path(vec![PathPart::Column("d".to_string())]),
                             alias: None,
                         },
                     ]
                 }),
                 ResultColumnStatement::Spec(ColumnSpec {
                     column_control: ColumnControl::default(),
-                    expr: Expr::Path(vec![PathPart::Column("foo".to_string())]),
+                    expr: Expr::path(vec![PathPart::Column("foo".to_string())]),
                     alias: None,
                 }),
                 ResultColumnStatement::Spec(ColumnSpec {
                     column_control: ColumnControl {
                         sort: None,
-                        group: Some(GroupSpec { ordinal: None }),
+                        group: Some(GroupSpec::default()),
                         is_partition_by: false,
                         is_hidden: false,
+                        is_distinct_on: false,
                     },
-                    expr: Expr::Path(vec![PathPart::Column("bar".to_string())]),
+                    expr: Expr::path(vec![PathPart::Column("bar".to_string())]),
                     alias: Some("B".to_string()),
                 }),
             ])
@@ -0,0 +1,48 @@
+use chumsky::prelude::*;
+
+use crate::parser::utils::*;
+use crate::tokens::*;
+
+pub fn bytes() -> impl Psr<Vec<u8>> {
+    exactly(BYTES_PREFIX).ignore_then(
+        filter(char::is_ascii_hexdigit)
+            .repeated()
+            .at_least(1)
+            .collect::<String>()
+            .try_map(|hex, span| {
+                decode_hex(&hex).ok_or_else(|| {
+                    Simple::custom(
+                        span,
+                        "A byte literal must have an even number of hex digits",
+                    )
+                })
+            })
+            .labelled("bytes"),
+    )
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes() {
+        assert_eq!(
+            bytes().parse("\\xDEADbeef"),
+            Ok(vec![0xde, 0xad, 0xbe, 0xef])
+        );
+        assert_eq!(bytes().parse("\\x00"), Ok(vec![0]));
+        assert!(bytes().parse("\\xABC").is_err());
+        assert!(bytes().parse("\\x").is_err());
+    }
+}
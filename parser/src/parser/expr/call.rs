@@ -0,0 +1,33 @@
+use chumsky::{prelude::*, text::*};
+
+use crate::ast::*;
+use crate::parser::utils::*;
+use crate::tokens::*;
+
+/// A scalar function called by name, e.g. `upper(trim(name))`. This is the same call that `pipe`
+/// parses from `name|trim|upper`, except that the first argument is written within the
+/// parentheses. It only matches when `enabled`.
+pub fn call(expr: impl Psr<Expr>, enabled: bool) -> impl Psr<Expr> {
+    let gate = empty().try_map(move |_, span| {
+        if enabled {
+            Ok(())
+        } else {
+            Err(Simple::custom(span, "function call syntax is disabled"))
+        }
+    });
+    let args = expr.padded().repeated().delimited_by(
+        just(COMPOSITION_ARGUMENT_BRACE_L),
+        just(COMPOSITION_ARGUMENT_BRACE_R),
+    );
+    gate.ignore_then(ident())
+        .then(args)
+        .map_with_span(|(name, args), span| {
+            Expr::Call(Call {
+                name,
+                dimension: FunctionDimension::Scalar,
+                syntax: CallSyntax::Standalone,
+                args,
+                span: span.into(),
+            })
+        })
+}
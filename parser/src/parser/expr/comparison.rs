@@ -63,6 +63,10 @@ fn operator() -> impl Psr<Operator> {
     choice((
         // Three character
         exactly(COMPARE_NOT_LIKE).to(Operator::NLike),
+        exactly(COMPARE_EQ_CASE_INSENSITIVE).to(Operator::IEq),
+        exactly(COMPARE_NEQ_CASE_INSENSITIVE).to(Operator::NIEq),
+        exactly(COMPARE_IS).to(Operator::Is),
+        exactly(COMPARE_IS_NOT).to(Operator::IsNot),
         exactly(COMPARE_GTE).to(Operator::Gte),
         exactly(COMPARE_LTE).to(Operator::Lte),
         exactly(COMPARE_LIKE).to(Operator::Like),
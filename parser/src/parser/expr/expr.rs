@@ -3,13 +3,15 @@ use chumsky::{prelude::*, text::*};
 use crate::ast::*;
 use crate::parser::utils::*;
 use crate::tokens::*;
+use crate::ParseOptions;
 
 use super::{
-    comparison::comparison, condition_set::condition_set, date::date, duration::duration,
-    has_quantity::has_quantity, number::number, path::path, pipe::pipe,
+    bytes::bytes, call::call, comparison::comparison, condition_set::condition_set, date::date,
+    duration::duration, has_quantity::has_quantity, number::number, path::path, pipe::pipe,
+    uuid::uuid, window::window,
 };
 
-pub fn expr() -> impl Psr<Expr> {
+pub fn expr(options: ParseOptions) -> impl Psr<Expr> {
     // A bit about how this works:
     //
     // - `prec` is short for "precedence".
@@ -34,20 +36,32 @@ pub fn expr() -> impl Psr<Expr> {
 
     recursive(|prec_comparison| {
         let prec_atom = choice((
-            number().map(Expr::Number),
-            date().map(Expr::Date),
-            duration().map(Expr::Duration),
-            string().map(Expr::String),
+            number().map_with_span(|n, span| Expr::Number(n, span.into())),
+            date().map_with_span(|d, span| Expr::Date(d, span.into())),
+            duration().map_with_span(|d, span| Expr::Duration(d, span.into())),
+            string()
+                .map(move |s| options.string_whitespace.apply(s))
+                .map_with_span(|s, span| Expr::String(s, span.into())),
+            bytes().map_with_span(|b, span| Expr::Bytes(b, span.into())),
+            uuid().map_with_span(|u, span| Expr::Uuid(u, span.into())),
             variable().map(Expr::Variable),
-            path(prec_comparison.clone()).map(Expr::Path),
+            placeholder().map(Expr::Placeholder),
+            // Before paths, since a function name alone would parse as a path to a column
+            call(prec_comparison.clone(), options.function_call_syntax),
+            path(prec_comparison.clone()).map_with_span(|p, span| Expr::Path(p, span.into())),
             has_quantity(prec_comparison.clone()).map(Expr::HasQuantity),
             condition_set(prec_comparison.clone()).map(Expr::ConditionSet),
+            window(prec_comparison.clone()).map(Expr::Window),
             parenthetical(prec_comparison.clone()),
-        ));
+        ))
+        .labelled(LABEL_VALUE);
 
         let prec_pipe = pipe(prec_atom.clone(), prec_comparison.clone());
 
-        let prec_multiplication = multiplication(prec_pipe);
+        // Boxing here keeps the parser's type from growing too large for the compiler to handle.
+        let prec_exponentiation = exponentiation(prec_pipe).boxed();
+
+        let prec_multiplication = multiplication(prec_exponentiation);
 
         let prec_addition = addition(prec_multiplication);
 
@@ -68,6 +82,17 @@ fn variable() -> impl Psr<String> {
     just(CONST_SIGIL).ignore_then(ident())
 }
 
+fn placeholder() -> impl Psr<Placeholder> {
+    just(PLACEHOLDER_SIGIL)
+        .ignore_then(ident())
+        .then(
+            exactly(PLACEHOLDER_TYPE_PREFIX)
+                .ignore_then(ident())
+                .or_not(),
+        )
+        .map(|(name, type_name)| Placeholder { name, type_name })
+}
+
 fn string() -> impl Psr<String> {
     quoted(STRING_QUOTE_SINGLE).or(quoted(STRING_QUOTE_DOUBLE))
 }
@@ -77,6 +102,22 @@ fn parenthetical(e: impl Psr<Expr>) -> impl Psr<Expr> {
         .delimited_by(just(EXPR_PAREN_L), just(EXPR_PAREN_R))
 }
 
+/// Exponentiation is right-associative, so `2^3^2` means `2^(3^2)`.
+fn exponentiation(e: impl Psr<Expr>) -> impl Psr<Expr> {
+    // Each operand is parsed only once so that nested expressions don't require backtracking.
+    e.clone()
+        .then(just(EXPR_POWER).padded().ignore_then(e).repeated())
+        .map(|(first, rest)| {
+            let mut operands = rest;
+            operands.insert(0, first);
+            let last = operands.pop().unwrap();
+            operands
+                .into_iter()
+                .rev()
+                .fold(last, |rhs, lhs| Expr::Power(Box::new(lhs), Box::new(rhs)))
+        })
+}
+
 fn multiplication(e: impl Psr<Expr>) -> impl Psr<Expr> {
     let op = choice((
         operator(EXPR_TIMES, Expr::Product),
@@ -87,10 +128,26 @@ fn multiplication(e: impl Psr<Expr>) -> impl Psr<Expr> {
         .foldl(|lhs, (f, rhs)| f(Box::new(lhs), Box::new(rhs)))
 }
 
+/// A sign with whitespace before it but not after it begins a signed number instead, so that
+/// `[-5 -3]` holds two numbers while `a - 5` and `a-5` are both subtractions.
+fn additive_operator(
+    c: char,
+    expr_enum_constructor: fn(Box<Expr>, Box<Expr>) -> Expr,
+) -> impl Psr<fn(Box<Expr>, Box<Expr>) -> Expr> {
+    let spaced = filter(|c: &char| c.is_whitespace()).repeated().at_least(1);
+    let not_before_digit = filter(|c: &char| !c.is_ascii_digit()).rewind();
+    choice((
+        just(c).ignored(),
+        spaced.then(just(c)).then(not_before_digit).ignored(),
+    ))
+    .then_ignore(whitespace())
+    .to(expr_enum_constructor)
+}
+
 fn addition(e: impl Psr<Expr>) -> impl Psr<Expr> {
     let op = choice((
-        operator(EXPR_PLUS, Expr::Sum),
-        operator(EXPR_MINUS, Expr::Difference),
+        additive_operator(EXPR_PLUS, Expr::Sum),
+        additive_operator(EXPR_MINUS, Expr::Difference),
     ));
     e.clone()
         .then(op.then(e).repeated())
@@ -100,42 +157,63 @@ fn addition(e: impl Psr<Expr>) -> impl Psr<Expr> {
 #[cfg(test)]
 mod tests {
     use crate::ast::*;
+    use crate::ParseOptions;
     use chumsky::prelude::*;
 
     use super::expr;
 
     #[test]
     fn test_parse_expr() {
-        let parser = expr().then_ignore(end());
+        let parser = expr(ParseOptions::default()).then_ignore(end());
         let p = |s: &str| parser.parse(s);
 
-        assert_eq!(p("8"), Ok(Expr::Number("8".to_string())));
+        assert_eq!(p("8"), Ok(Expr::number("8")));
         assert_eq!(
             p("@2000-01-01"),
-            Ok(Expr::Date(Date {
-                year: 2000,
-                day: 1,
-                month: 1
-            }))
+            Ok(Expr::Date(
+                Date {
+                    year: 2000,
+                    day: 1,
+                    month: 1
+                },
+                Span::Unknown
+            ))
         );
         assert_eq!(
             p("@1Y"),
-            Ok(Expr::Duration(Duration {
-                years: 1.0,
-                ..Default::default()
-            }))
+            Ok(Expr::Duration(
+                Duration {
+                    years: 1.0,
+                    ..Default::default()
+                },
+                Span::Unknown
+            ))
         );
-        assert_eq!(p("'foo'"), Ok(Expr::String("foo".to_string())));
-        assert_eq!(p("\"foo\""), Ok(Expr::String("foo".to_string())));
+        assert_eq!(p("'foo'"), Ok(Expr::string("foo")));
+        assert_eq!(p("\"foo\""), Ok(Expr::string("foo")));
         assert_eq!(p("@foo"), Ok(Expr::Variable("foo".to_string())));
         assert_eq!(p("@null"), Ok(Expr::Variable("null".to_string())));
+        assert_eq!(p(":foo"), Ok(Expr::Placeholder(Placeholder::new("foo"))));
+        assert_eq!(
+            p("foo::bar"),
+            Ok(Expr::column("foo")
+                .compare(Operator::Eq, Expr::Placeholder(Placeholder::new("bar"))))
+        );
+        assert_eq!(
+            p(":min::int"),
+            Ok(Expr::Placeholder(Placeholder {
+                name: "min".to_string(),
+                type_name: Some("int".to_string()),
+            }))
+        );
         assert_eq!(
             p("foo"),
-            Ok(Expr::Path(vec![PathPart::Column("foo".to_string())]))
+            Ok(Expr::path(vec![PathPart::Column("foo".to_string())]))
         );
         assert_eq!(
             p("++#foo"),
             Ok(Expr::HasQuantity(HasQuantity {
+                span: Span::Unknown,
                 quantity: Quantity::AtLeastOne,
                 path_parts: vec![PathPart::TableWithMany(TableWithMany {
                     table: "foo".to_string(),
@@ -147,17 +225,18 @@ mod tests {
         assert_eq!(
             p("++#foo{a:2}"),
             Ok(Expr::HasQuantity(HasQuantity {
+                span: Span::Unknown,
                 quantity: Quantity::AtLeastOne,
                 path_parts: vec![PathPart::TableWithMany(TableWithMany {
                     table: "foo".to_string(),
                     condition_set: ConditionSet {
                         conjunction: Conjunction::And,
                         entries: vec![Expr::Comparison(Box::new(Comparison {
-                            left: ComparisonSide::Expr(Expr::Path(vec![PathPart::Column(
+                            left: ComparisonSide::Expr(Expr::path(vec![PathPart::Column(
                                 "a".to_string()
                             )])),
                             operator: Operator::Eq,
-                            right: ComparisonSide::Expr(Expr::Number("2".to_string())),
+                            right: ComparisonSide::Expr(Expr::number("2")),
                         }))]
                     },
                     linking_column: None
@@ -169,8 +248,8 @@ mod tests {
             Ok(Expr::ConditionSet(ConditionSet {
                 conjunction: Conjunction::Or,
                 entries: vec![
-                    Expr::Path(vec![PathPart::Column("a".to_string())]),
-                    Expr::Path(vec![PathPart::Column("b".to_string())]),
+                    Expr::path(vec![PathPart::Column("a".to_string())]),
+                    Expr::path(vec![PathPart::Column("b".to_string())]),
                 ]
             }))
         );
@@ -179,8 +258,8 @@ mod tests {
             Ok(Expr::ConditionSet(ConditionSet {
                 conjunction: Conjunction::And,
                 entries: vec![
-                    Expr::Path(vec![PathPart::Column("a".to_string())]),
-                    Expr::Path(vec![PathPart::Column("b".to_string())]),
+                    Expr::path(vec![PathPart::Column("a".to_string())]),
+                    Expr::path(vec![PathPart::Column("b".to_string())]),
                 ]
             }))
         );
@@ -188,8 +267,8 @@ mod tests {
         assert_eq!(
             p("5*7"),
             Ok(Expr::Product(
-                Box::new(Expr::Number("5".to_string())),
-                Box::new(Expr::Number("7".to_string()))
+                Box::new(Expr::number("5")),
+                Box::new(Expr::number("7"))
             ))
         );
 
@@ -201,11 +280,33 @@ mod tests {
             ))
         );
 
+        assert_eq!(
+            p("2^3^2"),
+            Ok(Expr::Power(
+                Box::new(Expr::number("2")),
+                Box::new(Expr::Power(
+                    Box::new(Expr::number("3")),
+                    Box::new(Expr::number("2"))
+                ))
+            ))
+        );
+
+        assert_eq!(
+            p("2*3 ^ 2"),
+            Ok(Expr::Product(
+                Box::new(Expr::number("2")),
+                Box::new(Expr::Power(
+                    Box::new(Expr::number("3")),
+                    Box::new(Expr::number("2"))
+                ))
+            ))
+        );
+
         assert_eq!(
             p("5+7"),
             Ok(Expr::Sum(
-                Box::new(Expr::Number("5".to_string())),
-                Box::new(Expr::Number("7".to_string()))
+                Box::new(Expr::number("5")),
+                Box::new(Expr::number("7"))
             ))
         );
 
@@ -220,19 +321,50 @@ mod tests {
         assert_eq!(
             p("5 - 7"),
             Ok(Expr::Difference(
-                Box::new(Expr::Number("5".to_string())),
-                Box::new(Expr::Number("7".to_string()))
+                Box::new(Expr::number("5")),
+                Box::new(Expr::number("7"))
             ))
         );
 
         assert_eq!(
-            p("5 -7"),
+            p("5-7"),
             Ok(Expr::Difference(
-                Box::new(Expr::Number("5".to_string())),
-                Box::new(Expr::Number("7".to_string()))
+                Box::new(Expr::number("5")),
+                Box::new(Expr::number("7"))
             ))
         );
 
+        assert_eq!(
+            p("@a - -7"),
+            Ok(Expr::Difference(
+                Box::new(Expr::Variable("a".to_string())),
+                Box::new(Expr::number("-7"))
+            ))
+        );
+
+        assert_eq!(
+            p("@a -(7)"),
+            Ok(Expr::Difference(
+                Box::new(Expr::Variable("a".to_string())),
+                Box::new(Expr::number("7"))
+            ))
+        );
+
+        assert_eq!(p("-5"), Ok(Expr::number("-5")));
+        assert_eq!(p("+3.2"), Ok(Expr::number("3.2")));
+
+        assert_eq!(
+            p("[-5 -3 +2]"),
+            Ok(Expr::ConditionSet(ConditionSet {
+                conjunction: Conjunction::Or,
+                entries: vec![Expr::number("-5"), Expr::number("-3"), Expr::number("2"),]
+            }))
+        );
+
+        // These are two expressions, the second being a signed number.
+        assert!(p("5 -7").is_err());
+        assert!(p("5 +7").is_err());
+
         // This is two expressions, not one.
         assert!(p("5 (-7)").is_err());
 
@@ -240,20 +372,20 @@ mod tests {
             p("5*7+3"),
             Ok(Expr::Sum(
                 Box::new(Expr::Product(
-                    Box::new(Expr::Number("5".to_string())),
-                    Box::new(Expr::Number("7".to_string()))
+                    Box::new(Expr::number("5")),
+                    Box::new(Expr::number("7"))
                 )),
-                Box::new(Expr::Number("3".to_string()))
+                Box::new(Expr::number("3"))
             ))
         );
 
         assert_eq!(
             p("3+5*7"),
             Ok(Expr::Sum(
-                Box::new(Expr::Number("3".to_string())),
+                Box::new(Expr::number("3")),
                 Box::new(Expr::Product(
-                    Box::new(Expr::Number("5".to_string())),
-                    Box::new(Expr::Number("7".to_string()))
+                    Box::new(Expr::number("5")),
+                    Box::new(Expr::number("7"))
                 ))
             ))
         );
@@ -261,8 +393,8 @@ mod tests {
         assert_eq!(
             p("( 3 + 5 )"),
             Ok(Expr::Sum(
-                Box::new(Expr::Number("3".to_string())),
-                Box::new(Expr::Number("5".to_string()))
+                Box::new(Expr::number("3")),
+                Box::new(Expr::number("5"))
             ))
         );
 
@@ -270,10 +402,10 @@ mod tests {
             p("(3+5)*7"),
             Ok(Expr::Product(
                 Box::new(Expr::Sum(
-                    Box::new(Expr::Number("3".to_string())),
-                    Box::new(Expr::Number("5".to_string()))
+                    Box::new(Expr::number("3")),
+                    Box::new(Expr::number("5"))
                 )),
-                Box::new(Expr::Number("7".to_string()))
+                Box::new(Expr::number("7"))
             ))
         );
 
@@ -285,19 +417,19 @@ mod tests {
             p("(1:2):3"),
             Ok(Expr::Comparison(Box::new(Comparison {
                 left: ComparisonSide::Expr(Expr::Comparison(Box::new(Comparison {
-                    left: ComparisonSide::Expr(Expr::Number("1".to_string())),
+                    left: ComparisonSide::Expr(Expr::number("1")),
                     operator: Operator::Eq,
-                    right: ComparisonSide::Expr(Expr::Number("2".to_string())),
+                    right: ComparisonSide::Expr(Expr::number("2")),
                 }))),
                 operator: Operator::Eq,
-                right: ComparisonSide::Expr(Expr::Number("3".to_string())),
+                right: ComparisonSide::Expr(Expr::number("3")),
             })))
         );
 
         assert_eq!(
             p("x:@a..@b"),
             Ok(Expr::Comparison(Box::new(Comparison {
-                left: ComparisonSide::Expr(Expr::Path(vec![PathPart::Column("x".to_string())])),
+                left: ComparisonSide::Expr(Expr::path(vec![PathPart::Column("x".to_string())])),
                 operator: Operator::Eq,
                 right: ComparisonSide::Range(Range {
                     lower: RangeBound {
@@ -315,7 +447,7 @@ mod tests {
         assert_eq!(
             p("x:@a<..<@b"),
             Ok(Expr::Comparison(Box::new(Comparison {
-                left: ComparisonSide::Expr(Expr::Path(vec![PathPart::Column("x".to_string())])),
+                left: ComparisonSide::Expr(Expr::path(vec![PathPart::Column("x".to_string())])),
                 operator: Operator::Eq,
                 right: ComparisonSide::Range(Range {
                     lower: RangeBound {
@@ -333,26 +465,29 @@ mod tests {
         assert_eq!(
             p("1|a|b(2)|c(3 4)"),
             Ok(Expr::Call(Call {
+                span: Span::Unknown,
                 name: "c".to_string(),
                 dimension: FunctionDimension::Scalar,
                 syntax: CallSyntax::Piped,
                 args: vec![
                     Expr::Call(Call {
+                        span: Span::Unknown,
                         name: "b".to_string(),
                         dimension: FunctionDimension::Scalar,
                         syntax: CallSyntax::Piped,
                         args: vec![
                             Expr::Call(Call {
+                                span: Span::Unknown,
                                 name: "a".to_string(),
                                 dimension: FunctionDimension::Scalar,
                                 syntax: CallSyntax::Piped,
-                                args: vec![Expr::Number("1".to_string())],
+                                args: vec![Expr::number("1")],
                             }),
-                            Expr::Number("2".to_string())
+                            Expr::number("2")
                         ],
                     }),
-                    Expr::Number("3".to_string()),
-                    Expr::Number("4".to_string()),
+                    Expr::number("3"),
+                    Expr::number("4"),
                 ],
             }))
         );
@@ -362,17 +497,18 @@ mod tests {
             Ok(Expr::Comparison(Box::new(Comparison {
                 left: ComparisonSide::Expansion(ConditionSet {
                     entries: vec![
-                        Expr::Path(vec![PathPart::Column("a".to_string())]),
-                        Expr::Path(vec![PathPart::Column("b".to_string())]),
+                        Expr::path(vec![PathPart::Column("a".to_string())]),
+                        Expr::path(vec![PathPart::Column("b".to_string())]),
                     ],
                     conjunction: Conjunction::Or,
                 }),
                 operator: Operator::Neq,
                 right: ComparisonSide::Expr(Expr::Sum(
-                    Box::new(Expr::Number("2".to_string())),
+                    Box::new(Expr::number("2")),
                     Box::new(Expr::Product(
-                        Box::new(Expr::Path(vec![PathPart::Column("foo".to_string())])),
+                        Box::new(Expr::path(vec![PathPart::Column("foo".to_string())])),
                         Box::new(Expr::Call(Call {
+                            span: Span::Unknown,
                             name: "baz".to_string(),
                             dimension: FunctionDimension::Scalar,
                             syntax: CallSyntax::Piped,
@@ -14,8 +14,9 @@ pub fn has_quantity(expr: impl Psr<Expr>) -> impl Psr<HasQuantity> {
     quantity
         .then_ignore(whitespace())
         .then(path(expr))
-        .map(|(quantity, path_parts)| HasQuantity {
+        .map_with_span(|(quantity, path_parts), span| HasQuantity {
             quantity,
             path_parts,
+            span: span.into(),
         })
 }
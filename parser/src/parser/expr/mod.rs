@@ -1,3 +1,5 @@
+mod bytes;
+mod call;
 mod comparison;
 mod condition_set;
 mod date;
@@ -7,6 +9,8 @@ mod has_quantity;
 mod number;
 mod path;
 mod pipe;
+mod uuid;
+mod window;
 
 pub use expr::expr;
 pub use path::path_to_one;
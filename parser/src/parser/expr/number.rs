@@ -2,9 +2,14 @@ use chumsky::{prelude::*, text::*};
 
 use crate::parser::utils::*;
 
+/// A number, optionally signed. A leading `+` is dropped, since it has no effect.
 pub fn number() -> impl Psr<String> {
-    just('-')
+    let exponent = one_of("eE")
+        .chain::<char, _, _>(one_of("+-").or_not())
+        .chain::<char, _, _>(digits::<char, Simple<char>>(10));
+    one_of("+-")
         .or_not()
+        .map(|sign| sign.filter(|&c| c == '-'))
         .chain::<char, _, _>(int(10))
         .chain::<char, _, _>(
             just('.')
@@ -12,6 +17,24 @@ pub fn number() -> impl Psr<String> {
                 .or_not()
                 .flatten(),
         )
+        .chain::<char, _, _>(exponent.or_not().flatten())
         .collect::<String>()
         .labelled("number")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number() {
+        assert_eq!(number().parse("42"), Ok("42".to_string()));
+        assert_eq!(number().parse("-0.5"), Ok("-0.5".to_string()));
+        assert_eq!(number().parse("+3.2"), Ok("3.2".to_string()));
+        assert_eq!(number().then_ignore(end()).parse("+-3").ok(), None);
+        assert_eq!(number().parse("1.5e10"), Ok("1.5e10".to_string()));
+        assert_eq!(number().parse("2.3E-4"), Ok("2.3E-4".to_string()));
+        assert_eq!(number().parse("7e+2"), Ok("7e+2".to_string()));
+        assert_eq!(number().then_ignore(end()).parse("1e").ok(), None);
+    }
+}
@@ -40,7 +40,7 @@ fn path_part_to_one() -> impl Psr<PathPart> {
 }
 
 fn table_with_one() -> impl Psr<String> {
-    exactly(PATH_TO_TABLE_WITH_ONE_PREFIX).ignore_then(db_identifier())
+    exactly(PATH_TO_TABLE_WITH_ONE_PREFIX).ignore_then(db_identifier().labelled(LABEL_TABLE_NAME))
 }
 
 fn table_with_many(expr: impl Psr<Expr>) -> impl Psr<TableWithMany> {
@@ -50,6 +50,7 @@ fn table_with_many(expr: impl Psr<Expr>) -> impl Psr<TableWithMany> {
     );
     just(TABLE_SIGIL).ignore_then(
         db_identifier()
+            .labelled(LABEL_TABLE_NAME)
             .then(column.or_not())
             .then(condition_set(expr).or_not())
             .map(|((table, column), cs)| TableWithMany {
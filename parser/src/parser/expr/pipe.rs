@@ -14,24 +14,31 @@ pub fn pipe(arg0_expr: impl Psr<Expr>, extra_args_expr: impl Psr<Expr>) -> impl
         just(COMPOSITION_PIPE_AGGREGATE).to(FunctionDimension::Aggregate),
     ));
 
+    // Each call spans from the start of its first argument to the end of its own pipe, so the
+    // start is carried along with the calls as they're folded
     arg0_expr
+        .map_with_span(|arg0, span: std::ops::Range<usize>| (arg0, span.start))
         .then(
             dimension
                 .padded()
-                .then(ident())
+                .then(ident().labelled(LABEL_FUNCTION_NAME))
                 .then(args.or_not())
+                .map_with_span(|pipe, span: std::ops::Range<usize>| (pipe, span.end))
                 .repeated(),
         )
-        .foldl(|arg0, ((dimension, name), extra_args)| {
+        .foldl(|(arg0, start), (((dimension, name), extra_args), end)| {
             let args = vec![arg0]
                 .into_iter()
                 .chain(extra_args.unwrap_or_default().into_iter())
                 .collect();
-            Expr::Call(Call {
+            let call = Expr::Call(Call {
                 name,
                 dimension,
                 syntax: CallSyntax::Piped,
                 args,
-            })
+                span: Span::Offsets(start, end),
+            });
+            (call, start)
         })
+        .map(|(call, _)| call)
 }
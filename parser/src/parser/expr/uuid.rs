@@ -0,0 +1,57 @@
+use chumsky::prelude::*;
+
+use crate::parser::utils::*;
+use crate::tokens::*;
+
+/// The number of hex digits within each hyphen-separated group of a UUID
+const UUID_GROUP_LENGTHS: [usize; 5] = [8, 4, 4, 4, 12];
+
+pub fn uuid() -> impl Psr<String> {
+    exactly(UUID_PREFIX).ignore_then(
+        filter(|c: &char| c.is_ascii_hexdigit() || *c == '-')
+            .repeated()
+            .at_least(1)
+            .collect::<String>()
+            .try_map(|uuid, span| {
+                if is_canonical_uuid(&uuid) {
+                    Ok(uuid.to_lowercase())
+                } else {
+                    Err(Simple::custom(
+                        span,
+                        "A UUID literal must have hyphen-separated groups of 8, 4, 4, 4, and 12 hex digits",
+                    ))
+                }
+            })
+            .labelled("uuid"),
+    )
+}
+
+fn is_canonical_uuid(uuid: &str) -> bool {
+    let groups = uuid.split('-').collect::<Vec<_>>();
+    groups.len() == UUID_GROUP_LENGTHS.len()
+        && groups
+            .iter()
+            .zip(UUID_GROUP_LENGTHS)
+            .all(|(group, length)| group.len() == length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uuid() {
+        assert_eq!(
+            uuid().parse("\\u123E4567-e89b-12d3-a456-426614174000"),
+            Ok("123e4567-e89b-12d3-a456-426614174000".to_owned())
+        );
+        assert!(uuid().parse("\\u123e4567e89b12d3a456426614174000").is_err());
+        assert!(uuid()
+            .parse("\\u123e4567-e89b-12d3-a456-42661417400")
+            .is_err());
+        assert!(uuid()
+            .parse("\\u123e4567-e89b-12d3-a456--426614174000")
+            .is_err());
+        assert!(uuid().parse("\\u").is_err());
+    }
+}
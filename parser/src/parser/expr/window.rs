@@ -0,0 +1,143 @@
+use chumsky::{prelude::*, text::*};
+
+use crate::ast::*;
+use crate::parser::column_layout::column_spec_of;
+use crate::parser::utils::*;
+use crate::tokens::*;
+
+pub fn window(expr: impl Psr<Expr>) -> impl Psr<Window> {
+    #[derive(Clone)]
+    enum Entry {
+        Spec(ColumnSpec),
+        Frame(WindowFrame),
+    }
+    // The frame is tried first so that its leading offset isn't parsed as a column spec
+    let entry = choice((
+        frame().map(Entry::Frame),
+        column_spec_of(expr).map(Entry::Spec),
+    ));
+    exactly(WINDOW_DEFINITION_PREFIX)
+        .ignore_then(entry.padded().repeated().delimited_by(
+            just(WINDOW_DEFINITION_BRACE_L),
+            just(WINDOW_DEFINITION_BRACE_R),
+        ))
+        .try_map(|entries, span| {
+            let mut window = Window {
+                specs: vec![],
+                frame: None,
+            };
+            for entry in entries {
+                match entry {
+                    Entry::Spec(spec) => window.specs.push(spec),
+                    Entry::Frame(_) if window.frame.is_some() => {
+                        return Err(Simple::custom(span, "a window can only have one frame"))
+                    }
+                    Entry::Frame(frame) => window.frame = Some(frame),
+                }
+            }
+            Ok(window)
+        })
+}
+
+/// A range of row offsets, e.g. `-2..0`, either end of which may be omitted. The range may be
+/// followed by an exclusion, e.g. `-2..2\xc` to leave out the current row.
+fn frame() -> impl Psr<WindowFrame> {
+    offset()
+        .or_not()
+        .then_ignore(exactly(WINDOW_FRAME_BOUND_SEPARATOR))
+        .then(offset().or_not())
+        .then(exclusion().or_not())
+        .map(|((start, end), exclude)| WindowFrame {
+            start,
+            end,
+            exclude,
+        })
+}
+
+fn exclusion() -> impl Psr<FrameExclusion> {
+    exactly(WINDOW_FRAME_EXCLUSION_PREFIX).ignore_then(choice((
+        just(WINDOW_FRAME_EXCLUDE_CURRENT_ROW).to(FrameExclusion::CurrentRow),
+        just(WINDOW_FRAME_EXCLUDE_GROUP).to(FrameExclusion::Group),
+        just(WINDOW_FRAME_EXCLUDE_TIES).to(FrameExclusion::Ties),
+    )))
+}
+
+fn offset() -> impl Psr<i64> {
+    just('-')
+        .or_not()
+        .chain::<char, _, _>(int(10))
+        .collect::<String>()
+        .try_map(|v, span| {
+            v.parse()
+                .map_err(|_| Simple::custom(span, "invalid row offset"))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::expr;
+    use crate::ParseOptions;
+
+    use super::*;
+
+    #[test]
+    fn test_window() {
+        let p = |s: &str| window(expr(ParseOptions::default())).parse(s);
+        let spec = |name: &str, column_control: ColumnControl| ColumnSpec {
+            column_control,
+            ..ColumnSpec::new(Expr::column(name))
+        };
+        let partition = ColumnControl {
+            is_partition_by: true,
+            ..Default::default()
+        };
+        let sort = ColumnControl {
+            sort: Some(SortSpec::default()),
+            ..Default::default()
+        };
+        assert_eq!(
+            p(r"%%(account\p created_at\s)"),
+            Ok(Window {
+                specs: vec![spec("account", partition), spec("created_at", sort.clone())],
+                frame: None,
+            })
+        );
+        assert_eq!(
+            p(r"%%(created_at\s -2..0)"),
+            Ok(Window {
+                specs: vec![spec("created_at", sort)],
+                frame: Some(WindowFrame {
+                    start: Some(-2),
+                    end: Some(0),
+                    exclude: None,
+                }),
+            })
+        );
+        assert_eq!(
+            p("%%(..)").map(|w| w.frame),
+            Ok(Some(WindowFrame::default()))
+        );
+        assert_eq!(
+            p("%%(3..)").map(|w| w.frame),
+            Ok(Some(WindowFrame {
+                start: Some(3),
+                end: None,
+                exclude: None,
+            }))
+        );
+        assert_eq!(
+            p(r"%%(-2..2\xc)").map(|w| w.frame),
+            Ok(Some(WindowFrame {
+                start: Some(-2),
+                end: Some(2),
+                exclude: Some(FrameExclusion::CurrentRow),
+            }))
+        );
+        assert_eq!(
+            p(r"%%(created_at\s ..\xt)").map(|w| w.frame.and_then(|f| f.exclude)),
+            Ok(Some(FrameExclusion::Ties))
+        );
+        assert!(p("%%(..0 ..0)").is_err());
+        assert!(p(r"%%(..0\xz)").is_err());
+    }
+}
@@ -1,6 +1,9 @@
-mod column_layout;
+pub(crate) mod column_layout;
 mod expr;
+mod nesting;
 mod query;
 mod utils;
 
-pub use query::query;
+pub use expr::expr;
+pub use nesting::{check_nesting_depth, MAX_NESTING_DEPTH};
+pub use query::{query, top_level_condition_set};
@@ -0,0 +1,60 @@
+use crate::tokens::{
+    CONDITION_SET_AND_BRACE_L, CONDITION_SET_AND_BRACE_R, CONDITION_SET_OR_BRACE_L,
+    CONDITION_SET_OR_BRACE_R, DB_IDENTIFIER_QUOTE, EXPR_PAREN_L, EXPR_PAREN_R,
+    STRING_ESCAPE_PREFIX, STRING_QUOTE_DOUBLE, STRING_QUOTE_SINGLE,
+};
+
+/// The deepest that brackets may be nested within Querydown code. The parser is recursive, so
+/// without a limit, deeply nested input could overflow the stack. Each level of nesting also
+/// multiplies the time spent backtracking, so legitimate queries never come close to this.
+pub const MAX_NESTING_DEPTH: usize = 16;
+
+/// Returns an error if the brackets in the input are nested more deeply than we're willing to
+/// parse. Brackets within strings and quoted identifiers don't count. This doesn't check that the
+/// brackets are balanced. We leave that to the parser.
+pub fn check_nesting_depth(input: &str) -> Result<(), String> {
+    let mut depth: usize = 0;
+    let mut quote: Option<char> = None;
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(_), STRING_ESCAPE_PREFIX) => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, STRING_QUOTE_SINGLE | STRING_QUOTE_DOUBLE | DB_IDENTIFIER_QUOTE) => {
+                quote = Some(c)
+            }
+            (None, EXPR_PAREN_L | CONDITION_SET_AND_BRACE_L | CONDITION_SET_OR_BRACE_L) => {
+                depth += 1;
+                if depth > MAX_NESTING_DEPTH {
+                    return Err(format!(
+                        "Querydown code is nested too deeply. Brackets may be nested at most {} levels deep.",
+                        MAX_NESTING_DEPTH
+                    ));
+                }
+            }
+            (None, EXPR_PAREN_R | CONDITION_SET_AND_BRACE_R | CONDITION_SET_OR_BRACE_R) => {
+                depth = depth.saturating_sub(1);
+            }
+            (None, _) => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_nesting_depth() {
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(check_nesting_depth(&nested(MAX_NESTING_DEPTH)).is_ok());
+        assert!(check_nesting_depth(&nested(MAX_NESTING_DEPTH + 1)).is_err());
+        assert!(check_nesting_depth(&format!("'{}'", "(".repeat(1000))).is_ok());
+        assert!(check_nesting_depth(&format!("'\\'{}'", "(".repeat(1000))).is_ok());
+        assert!(check_nesting_depth(&"[{(".repeat(100)).is_err());
+    }
+}
@@ -2,44 +2,62 @@ use chumsky::{prelude::*, text::*};
 
 use crate::ast::*;
 use crate::tokens::*;
+use crate::ParseOptions;
 
 use super::utils::*;
 use super::{column_layout::result_columns, expr::expr};
 
-pub fn query() -> impl Psr<Query> {
-    let base_table = just(TABLE_SIGIL).ignore_then(db_identifier());
-    let transformations = transformation().separated_by(
+pub fn query(options: ParseOptions) -> impl Psr<Query> {
+    let table = just(TABLE_SIGIL).ignore_then(db_identifier().labelled(LABEL_TABLE_NAME));
+    let cross_joined_table = whitespace()
+        .then(just(CROSS_JOIN_DELIMITER))
+        .then(whitespace())
+        .ignore_then(table.clone());
+    let tables = table
+        .then(cross_joined_table.repeated())
+        .or_not()
+        .map(|tables| match tables {
+            Some((base_table, cross_joined_tables)) => (Some(base_table), cross_joined_tables),
+            None => (None, vec![]),
+        });
+    let transformations = transformation(options).separated_by(
         whitespace()
             .then(exactly(TRANSFORMATION_DELIMITER))
             .then(whitespace()),
     );
     whitespace().ignore_then(
-        base_table
+        tables
             .then_ignore(whitespace())
             .then(transformations)
             .then_ignore(whitespace().then(end()))
-            .map(|(base_table, transformations)| Query {
-                base_table,
-                transformations,
-            }),
+            .map(
+                |((base_table, cross_joined_tables), transformations)| Query {
+                    base_table,
+                    cross_joined_tables,
+                    transformations,
+                },
+            ),
     )
 }
 
-fn transformation() -> impl Psr<Transformation> {
-    top_level_condition_set()
+fn transformation(options: ParseOptions) -> impl Psr<Transformation> {
+    top_level_condition_set(options)
         .then_ignore(whitespace())
-        .then(result_columns().or_not())
+        .then(result_columns(options).or_not())
         .map(|(conditions, cl)| Transformation {
             conditions,
             result_columns: cl.unwrap_or_default(),
         })
 }
 
-fn top_level_condition_set() -> impl Psr<ConditionSet> {
-    expr().padded().repeated().map(|entries| ConditionSet {
-        conjunction: Conjunction::And,
-        entries,
-    })
+pub fn top_level_condition_set(options: ParseOptions) -> impl Psr<ConditionSet> {
+    expr(options)
+        .padded()
+        .repeated()
+        .map(|entries| ConditionSet {
+            conjunction: Conjunction::And,
+            entries,
+        })
 }
 
 #[cfg(test)]
@@ -49,26 +67,27 @@ mod tests {
     #[test]
     fn test_parse_query() {
         assert_eq!(
-            query().parse("#foo a:1 b:2 $c"),
+            query(ParseOptions::default()).parse("#foo a:1 b:2 $c"),
             Ok(Query {
-                base_table: "foo".to_string(),
+                base_table: Some("foo".to_string()),
+                cross_joined_tables: vec![],
                 transformations: vec![Transformation {
                     conditions: ConditionSet {
                         conjunction: Conjunction::And,
                         entries: vec![
                             Expr::Comparison(Box::new(Comparison {
-                                left: ComparisonSide::Expr(Expr::Path(vec![PathPart::Column(
+                                left: ComparisonSide::Expr(Expr::path(vec![PathPart::Column(
                                     "a".to_string()
                                 )])),
                                 operator: Operator::Eq,
-                                right: ComparisonSide::Expr(Expr::Number("1".to_string())),
+                                right: ComparisonSide::Expr(Expr::number("1")),
                             })),
                             Expr::Comparison(Box::new(Comparison {
-                                left: ComparisonSide::Expr(Expr::Path(vec![PathPart::Column(
+                                left: ComparisonSide::Expr(Expr::path(vec![PathPart::Column(
                                     "b".to_string()
                                 )])),
                                 operator: Operator::Eq,
-                                right: ComparisonSide::Expr(Expr::Number("2".to_string())),
+                                right: ComparisonSide::Expr(Expr::number("2")),
                             })),
                         ],
                     },
@@ -78,12 +97,48 @@ mod tests {
                             sort: None,
                             group: None,
                             is_partition_by: false,
-                            is_hidden: false
+                            is_hidden: false,
+                            is_distinct_on: false,
                         },
-                        expr: Expr::Path(vec![PathPart::Column("c".to_string())])
+                        expr: Expr::path(vec![PathPart::Column("c".to_string())])
+                    })],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_query_without_base_table() {
+        assert_eq!(
+            query(ParseOptions::default()).parse("$1->x"),
+            Ok(Query {
+                base_table: None,
+                cross_joined_tables: vec![],
+                transformations: vec![Transformation {
+                    conditions: ConditionSet {
+                        conjunction: Conjunction::And,
+                        entries: vec![],
+                    },
+                    result_columns: vec![ResultColumnStatement::Spec(ColumnSpec {
+                        alias: Some("x".to_string()),
+                        column_control: ColumnControl::default(),
+                        expr: Expr::number("1"),
                     })],
                 }],
             })
         );
     }
+
+    #[test]
+    fn test_parse_query_with_cross_joined_tables() {
+        let query = query(ParseOptions::default())
+            .parse("#foo * #bar *#baz a:1")
+            .unwrap();
+        assert_eq!(query.base_table, Some("foo".to_string()));
+        assert_eq!(
+            query.cross_joined_tables,
+            vec!["bar".to_string(), "baz".to_string()]
+        );
+        assert_eq!(query.transformations[0].conditions.entries.len(), 1);
+    }
 }
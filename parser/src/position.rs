@@ -0,0 +1,105 @@
+/// A location within the input, as an editor would display it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// Counting from 1
+    pub line: usize,
+    /// Counting from 1, with each tab advancing to the next multiple of the tab width
+    pub column: usize,
+}
+
+impl Position {
+    /// Find the position of the character at the given index. Indices count characters rather
+    /// than bytes, as the offsets reported by the parser do, so multi-byte characters occupy one
+    /// column each. An index past the end of the input gives the position just after the end.
+    pub fn locate(input: &str, char_index: usize, tab_width: usize) -> Self {
+        let mut position = Position { line: 1, column: 1 };
+        for c in input.chars().take(char_index) {
+            match c {
+                '\n' => {
+                    position.line += 1;
+                    position.column = 1;
+                }
+                '\t' if tab_width > 0 => {
+                    position.column += tab_width - (position.column - 1) % tab_width
+                }
+                // A carriage return before a line feed doesn't take up a column
+                '\r' => {}
+                _ => position.column += 1,
+            }
+        }
+        position
+    }
+}
+
+/// Where a node of the syntax tree was written within the input.
+///
+/// Spans don't take part in comparisons, so that a parsed syntax tree equals the same tree built in
+/// Rust, or parsed from the same code laid out differently.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Span {
+    /// The node was built rather than parsed
+    #[default]
+    Unknown,
+    /// The character offsets of the node, which the parser records before it locates them
+    Offsets(usize, usize),
+    /// From the first character of the node up to the character after its last
+    Located { start: Position, end: Position },
+}
+
+impl Span {
+    pub fn start(&self) -> Option<Position> {
+        match self {
+            Span::Located { start, .. } => Some(*start),
+            _ => None,
+        }
+    }
+
+    pub fn end(&self) -> Option<Position> {
+        match self {
+            Span::Located { end, .. } => Some(*end),
+            _ => None,
+        }
+    }
+
+    /// Turn the character offsets recorded by the parser into positions
+    pub(crate) fn locate(&mut self, input: &str, tab_width: usize) {
+        if let Span::Offsets(start, end) = *self {
+            *self = Span::Located {
+                start: Position::locate(input, start, tab_width),
+                end: Position::locate(input, end, tab_width),
+            };
+        }
+    }
+}
+
+impl From<std::ops::Range<usize>> for Span {
+    fn from(range: std::ops::Range<usize>) -> Self {
+        Span::Offsets(range.start, range.end)
+    }
+}
+
+impl PartialEq for Span {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate() {
+        let p = |input: &str, index: usize| {
+            let position = Position::locate(input, index, 4);
+            (position.line, position.column)
+        };
+        assert_eq!(p("#users", 0), (1, 1));
+        assert_eq!(p("#users", 3), (1, 4));
+        assert_eq!(p("#users\r\nname:1", 8), (2, 1));
+        assert_eq!(p("#users\n\tname:1", 8), (2, 5));
+        assert_eq!(p("#users\nab\tc", 10), (2, 5));
+        assert_eq!(p("#cafés\nname:\"ü\" $id", 18), (2, 12));
+        assert_eq!(p("#users", 100), (1, 7));
+    }
+}
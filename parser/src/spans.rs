@@ -0,0 +1,101 @@
+use crate::ast::*;
+
+/// Turn the character offsets which the parser records for each span into positions
+pub(crate) fn locate_spans(node: &mut impl HasSpans, input: &str, tab_width: usize) {
+    node.visit_spans(&mut |span| span.locate(input, tab_width));
+}
+
+pub(crate) trait HasSpans {
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span));
+}
+
+impl HasSpans for Query {
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span)) {
+        for transformation in &mut self.transformations {
+            transformation.conditions.visit_spans(f);
+            for statement in &mut transformation.result_columns {
+                match statement {
+                    ResultColumnStatement::Spec(spec) => spec.expr.visit_spans(f),
+                    ResultColumnStatement::Glob(glob) => {
+                        glob.head.visit_spans(f);
+                        for spec in &mut glob.specs {
+                            spec.expr.visit_spans(f);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl HasSpans for ConditionSet {
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span)) {
+        for entry in &mut self.entries {
+            entry.visit_spans(f);
+        }
+    }
+}
+
+impl HasSpans for Vec<PathPart> {
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span)) {
+        for part in self {
+            if let PathPart::TableWithMany(table_with_many) = part {
+                table_with_many.condition_set.visit_spans(f);
+            }
+        }
+    }
+}
+
+impl HasSpans for Expr {
+    fn visit_spans(&mut self, f: &mut impl FnMut(&mut Span)) {
+        match self {
+            Expr::Number(_, span)
+            | Expr::Date(_, span)
+            | Expr::Duration(_, span)
+            | Expr::String(_, span)
+            | Expr::Bytes(_, span)
+            | Expr::Uuid(_, span) => f(span),
+            Expr::Variable(_) | Expr::Placeholder(_) => {}
+            Expr::Path(parts, span) => {
+                f(span);
+                parts.visit_spans(f);
+            }
+            Expr::ConditionSet(condition_set) => condition_set.visit_spans(f),
+            Expr::HasQuantity(has_quantity) => {
+                f(&mut has_quantity.span);
+                has_quantity.path_parts.visit_spans(f);
+            }
+            Expr::Call(call) => {
+                f(&mut call.span);
+                for arg in &mut call.args {
+                    arg.visit_spans(f);
+                }
+            }
+            Expr::Product(a, b)
+            | Expr::Quotient(a, b)
+            | Expr::Power(a, b)
+            | Expr::Sum(a, b)
+            | Expr::Difference(a, b) => {
+                a.visit_spans(f);
+                b.visit_spans(f);
+            }
+            Expr::Comparison(comparison) => {
+                for side in [&mut comparison.left, &mut comparison.right] {
+                    match side {
+                        ComparisonSide::Expr(expr) => expr.visit_spans(f),
+                        ComparisonSide::Expansion(condition_set) => condition_set.visit_spans(f),
+                        ComparisonSide::Range(range) => {
+                            range.lower.expr.visit_spans(f);
+                            range.upper.expr.visit_spans(f);
+                        }
+                    }
+                }
+            }
+            Expr::Window(window) => {
+                for spec in &mut window.specs {
+                    spec.expr.visit_spans(f);
+                }
+            }
+        }
+    }
+}
@@ -1,9 +1,15 @@
+pub(crate) const BYTES_PREFIX: &str = "\\x";
 pub(crate) const COLUMN_ALIAS_PREFIX: &str = "->";
+pub(crate) const COLUMN_CONTROL_FLAG_CUBE: char = 'c';
 pub(crate) const COLUMN_CONTROL_FLAG_DESC: char = 'd';
+pub(crate) const COLUMN_CONTROL_FLAG_DISTINCT_ON: char = 'u';
+pub(crate) const COLUMN_CONTROL_FLAG_GROUPING_SETS: char = 'e';
 pub(crate) const COLUMN_CONTROL_FLAG_GROUP: char = 'g';
 pub(crate) const COLUMN_CONTROL_FLAG_HIDE: char = 'h';
 pub(crate) const COLUMN_CONTROL_FLAG_NULLS_FIRST: char = 'n';
+pub(crate) const COLUMN_CONTROL_FLAG_NULLS_LAST: char = 'l';
 pub(crate) const COLUMN_CONTROL_FLAG_PARTITION: char = 'p';
+pub(crate) const COLUMN_CONTROL_FLAG_ROLLUP: char = 'r';
 pub(crate) const COLUMN_CONTROL_FLAG_SORT: char = 's';
 pub(crate) const COLUMN_CONTROL_FLAGS_PREFIX: char = '\\';
 pub(crate) const COLUMN_GLOB_ADJUSTMENT_BRACE_L: char = '(';
@@ -11,13 +17,17 @@ pub(crate) const COLUMN_GLOB_ADJUSTMENT_BRACE_R: char = ')';
 pub(crate) const COLUMN_GLOB: char = '*';
 pub(crate) const COLUMN_SPEC_PREFIX: char = '$';
 pub(crate) const COMPARE_EQ: &str = ":";
+pub(crate) const COMPARE_EQ_CASE_INSENSITIVE: &str = ":~=";
 pub(crate) const COMPARE_GT: &str = ":>";
 pub(crate) const COMPARE_GTE: &str = ":>=";
+pub(crate) const COMPARE_IS: &str = ":==";
+pub(crate) const COMPARE_IS_NOT: &str = "!==";
 pub(crate) const COMPARE_LIKE: &str = ":~~";
 pub(crate) const COMPARE_LT: &str = ":<";
 pub(crate) const COMPARE_LTE: &str = ":<=";
 pub(crate) const COMPARE_MATCH: &str = ":~";
 pub(crate) const COMPARE_NEQ: &str = "!";
+pub(crate) const COMPARE_NEQ_CASE_INSENSITIVE: &str = "!~=";
 pub(crate) const COMPARE_NOT_LIKE: &str = "!~~";
 pub(crate) const COMPARE_NOT_MATCH: &str = "!~";
 pub(crate) const COMPARISON_EXPAND: &str = "..";
@@ -32,6 +42,7 @@ pub(crate) const CONDITION_SET_AND_BRACE_R: char = '}';
 pub(crate) const CONDITION_SET_OR_BRACE_L: char = '[';
 pub(crate) const CONDITION_SET_OR_BRACE_R: char = ']';
 pub(crate) const CONST_SIGIL: char = '@';
+pub(crate) const CROSS_JOIN_DELIMITER: char = '*';
 pub(crate) const DB_IDENTIFIER_QUOTE: char = '`';
 pub(crate) const EXPR_PAREN_L: char = '(';
 pub(crate) const EXPR_PAREN_R: char = ')';
@@ -39,10 +50,13 @@ pub(crate) const EXPR_DIVIDE: char = '/';
 pub(crate) const EXPR_TIMES: char = '*';
 pub(crate) const EXPR_PLUS: char = '+';
 pub(crate) const EXPR_MINUS: char = '-';
+pub(crate) const EXPR_POWER: char = '^';
 pub(crate) const HAS_QUANTITY_AT_LEAST_ONE: &str = "++";
 pub(crate) const HAS_QUANTITY_ZERO: &str = "--";
 pub(crate) const LITERAL_NULL: &str = "null";
 pub(crate) const PATH_SEPARATOR: char = '.';
+pub(crate) const PLACEHOLDER_SIGIL: char = ':';
+pub(crate) const PLACEHOLDER_TYPE_PREFIX: &str = "::";
 pub(crate) const PATH_TO_TABLE_WITH_ONE_PREFIX: &str = ">>";
 pub(crate) const STRING_ESCAPE_PREFIX: char = '\\';
 pub(crate) const STRING_QUOTE_DOUBLE: char = '"';
@@ -51,6 +65,16 @@ pub(crate) const TABLE_SIGIL: char = '#';
 pub(crate) const TABLE_WITH_MANY_COLUMN_BRACE_L: char = '(';
 pub(crate) const TABLE_WITH_MANY_COLUMN_BRACE_R: char = ')';
 pub(crate) const TRANSFORMATION_DELIMITER: &str = "~~~";
-// pub(crate) const WINDOW_DEFINITION_BRACE_L: char = '(';
-// pub(crate) const WINDOW_DEFINITION_BRACE_R: char = ')';
-// pub(crate) const WINDOW_DEFINITION_PREFIX: &str = "%%";
+pub(crate) const UUID_PREFIX: &str = "\\u";
+pub(crate) const WINDOW_DEFINITION_BRACE_L: char = '(';
+pub(crate) const WINDOW_DEFINITION_BRACE_R: char = ')';
+pub(crate) const WINDOW_DEFINITION_PREFIX: &str = "%%";
+pub(crate) const WINDOW_FRAME_BOUND_SEPARATOR: &str = "..";
+pub(crate) const WINDOW_FRAME_EXCLUDE_CURRENT_ROW: char = 'c';
+pub(crate) const WINDOW_FRAME_EXCLUDE_GROUP: char = 'g';
+pub(crate) const WINDOW_FRAME_EXCLUDE_TIES: char = 't';
+pub(crate) const WINDOW_FRAME_EXCLUSION_PREFIX: &str = "\\x";
+pub(crate) const LABEL_ALIAS: &str = "alias";
+pub(crate) const LABEL_FUNCTION_NAME: &str = "function name";
+pub(crate) const LABEL_TABLE_NAME: &str = "table name";
+pub(crate) const LABEL_VALUE: &str = "value";